@@ -1,7 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use pathforge::algorithms::astar::{astar, AStarConfig};
-use pathforge::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
-use pathforge::heuristics::Diagonal;
+use std::collections::HashSet;
+
+use pathforge::algorithms::astar::{astar, astar_precomputed, AStarConfig};
+use pathforge::algorithms::jps::jps;
+use pathforge::algorithms::flowfield::DistanceField;
+use pathforge::graphs::grid2d::{Grid2D, GridPattern, GridPos, DiagonalMode};
+use pathforge::heuristics::{AltHeuristic, Diagonal};
 
 fn bench_astar_empty(c: &mut Criterion) {
     let width = 128;
@@ -64,14 +68,10 @@ fn bench_astar_empty_100(c: &mut Criterion) {
 }
 
 fn bench_astar_maze(c: &mut Criterion) {
-    let width = 64;
-    let height = 64;
-    let mut grid = Grid2D::new(width, height, DiagonalMode::Always);
     // Simple maze: vertical walls every 4 units with gaps
-    for x in (4..width).step_by(4) {
-        grid.set_region_blocked((x, 0, 1, height - 5), true);
-    }
-    
+    let pattern = GridPattern::VerticalWalls { spacing: 4, gap_height: 5 };
+    let grid = Grid2D::from_pattern(64, 64, pattern, DiagonalMode::Always);
+
     let heuristic = Diagonal::default();
     let start = GridPos { x: 1, y: 1 };
     let goal = GridPos { x: 60, y: 60 };
@@ -98,6 +98,34 @@ fn bench_astar_maze(c: &mut Criterion) {
     });
 }
 
+fn bench_astar_patterns(c: &mut Criterion) {
+    let heuristic = Diagonal::default();
+    let cases = [
+        // Rings every 8 cells with doors on alternating sides: a long spiral out of the middle
+        ("astar_concentric_128x128", GridPattern::Concentric { ring_spacing: 8 }, GridPos { x: 64, y: 64 }),
+        // 4x4 blocks touching at corners; diagonal steps squeeze between them
+        ("astar_checkerboard_128x128", GridPattern::Checkerboard { cell_size: 4 }, GridPos { x: 1, y: 1 }),
+    ];
+    for (name, pattern, start) in cases {
+        let grid = Grid2D::from_pattern(128, 128, pattern, DiagonalMode::Always);
+        let goal = GridPos { x: 126, y: 126 };
+        let sample = astar(&grid, &heuristic, start, goal, AStarConfig::default());
+        println!("nodes_expanded ({name}): {}", sample.nodes_expanded);
+
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                astar(
+                    black_box(&grid),
+                    black_box(&heuristic),
+                    black_box(start),
+                    black_box(goal),
+                    black_box(AStarConfig::default()),
+                )
+            })
+        });
+    }
+}
+
 fn bench_astar_empty_1024(c: &mut Criterion) {
     let width = 1024;
     let height = 1024;
@@ -128,11 +156,63 @@ fn bench_astar_empty_1024(c: &mut Criterion) {
     });
 }
 
+// Masking borrows the grid, so its cost must not scale with the map size
+fn bench_masked_grid_1024(c: &mut Criterion) {
+    let grid = Grid2D::new(1024, 1024, DiagonalMode::Always);
+    let extra: HashSet<GridPos> = (0..10).map(|i| GridPos { x: 500, y: 495 + i }).collect();
+    let start = GridPos { x: 490, y: 500 };
+    let goal = GridPos { x: 510, y: 500 };
+
+    c.bench_function("masked_grid_1024x1024_10_cells", |b| {
+        b.iter(|| black_box(&grid).masked(black_box(&extra)).is_blocked(500, 500))
+    });
+
+    c.bench_function("jps_masked_1024x1024_10_cells", |b| {
+        b.iter(|| {
+            let masked = grid.masked(&extra);
+            jps(
+                black_box(&masked),
+                black_box(&Diagonal::default()),
+                black_box(start),
+                black_box(goal),
+                black_box(AStarConfig::default()),
+            )
+        })
+    });
+}
+
+fn bench_alt_precomputed(c: &mut Criterion) {
+    let mut grid = Grid2D::new(128, 128, DiagonalMode::Always);
+    for x in (8..120).step_by(8) {
+        grid.set_region_blocked((x, 0, 1, 120), true);
+    }
+    let corners = [
+        GridPos { x: 0, y: 0 },
+        GridPos { x: 127, y: 0 },
+        GridPos { x: 0, y: 127 },
+        GridPos { x: 127, y: 127 },
+    ];
+    let landmarks: Vec<_> = corners.iter().map(|&c| DistanceField::landmark(&grid, c)).collect();
+    let alt = AltHeuristic::new(&landmarks).unwrap();
+    let start = GridPos { x: 1, y: 1 };
+    let goal = GridPos { x: 126, y: 1 };
+
+    c.bench_function("astar_alt_128x128", |b| {
+        b.iter(|| astar(black_box(&grid), black_box(&alt), start, goal, AStarConfig::default()))
+    });
+    c.bench_function("astar_alt_precomputed_128x128", |b| {
+        b.iter(|| astar_precomputed(black_box(&grid), black_box(&alt), start, goal, AStarConfig::default()))
+    });
+}
+
 criterion_group!(
     benches,
     bench_astar_empty,
     bench_astar_empty_100,
     bench_astar_empty_1024,
-    bench_astar_maze
+    bench_astar_maze,
+    bench_astar_patterns,
+    bench_masked_grid_1024,
+    bench_alt_precomputed
 );
 criterion_main!(benches);
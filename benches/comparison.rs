@@ -1,8 +1,15 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use pathforge::algorithms::astar::{astar, AStarConfig};
+use pathforge::algorithms::flowfield::FlowField;
 use pathforge::algorithms::jps::jps;
+use pathforge::algorithms::theta::theta_star;
+use pathforge::cache::PathCache;
 use pathforge::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
-use pathforge::heuristics::Diagonal;
+use pathforge::graphs::hierarchical::HierarchicalGrid;
+use pathforge::graphs::visibility::VisibilityGraph;
+use pathforge::heuristics::{Diagonal, Euclidean};
+use pathforge::traits::{Graph, PathResult, PathStatus};
+use std::time::Duration;
 
 fn bench_jps_vs_astar(c: &mut Criterion) {
     let width = 128;
@@ -113,5 +120,188 @@ fn bench_jps_vs_astar(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_jps_vs_astar);
+fn bench_grouped_vs_loop(c: &mut Criterion) {
+    // 10 units in one 16x16 cluster, all heading across a 256x256 grid with wall segments
+    let mut grid = Grid2D::new(256, 256, DiagonalMode::Always);
+    for i in 0..12 {
+        let x = 20 + i * 19;
+        let gap = (i * 37) % 200 + 20;
+        grid.set_region_blocked((x, 0, 1, gap), true);
+        grid.set_region_blocked((x, gap + 12, 1, 256 - gap - 12), true);
+    }
+    let hp = HierarchicalGrid::new(grid, 16);
+    let starts: Vec<GridPos> = (0..10).map(|i| GridPos { x: 2 + i, y: 3 + i }).collect();
+    let goal = GridPos { x: 250, y: 240 };
+
+    let mut group = c.benchmark_group("Hierarchical Squad 10 Units");
+
+    group.bench_function("find_path loop", |b| {
+        b.iter(|| {
+            starts
+                .iter()
+                .map(|&start| hp.find_path(black_box(start), black_box(goal)))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    group.bench_function("find_paths_grouped", |b| {
+        b.iter(|| hp.find_paths_grouped(black_box(&starts), black_box(goal), AStarConfig::default()))
+    });
+
+    group.finish();
+}
+
+// Per-cell square scan, the obvious way to erode free space
+fn erode_naive(grid: &Grid2D, radius: i32) -> Vec<bool> {
+    let (w, h) = (grid.width as i32, grid.height as i32);
+    let mut out = vec![false; grid.width * grid.height];
+    for y in 0..h {
+        for x in 0..w {
+            out[(y * w + x) as usize] =
+                (-radius..=radius).any(|dy| (-radius..=radius).any(|dx| grid.is_blocked(x + dx, y + dy)));
+        }
+    }
+    out
+}
+
+fn bench_erode_vs_naive(c: &mut Criterion) {
+    let mut grid = Grid2D::new(2048, 2048, DiagonalMode::Always);
+    for i in 0..40_000usize {
+        grid.set_blocked((i * 7919) % 2048, (i * 104_729) % 2048, true);
+    }
+
+    let mut group = c.benchmark_group("Erode 2048x2048 r5");
+    group.sample_size(10);
+    group.bench_function("naive", |b| b.iter(|| erode_naive(black_box(&grid), 5)));
+    group.bench_function("erode_passable", |b| b.iter(|| black_box(&grid).erode_passable(5)));
+    group.finish();
+}
+
+fn bench_invalidate_rect_vs_region(c: &mut Criterion) {
+    // 1000 cached 500-cell paths spread over a large map; the edit touches only a few of them
+    let build = || {
+        let mut cache = PathCache::new(1000, Duration::from_secs(600));
+        // Bounding boxes are computed by `insert` from here on, outside the measured part
+        cache.track_bounds();
+        for i in 0..1000 {
+            let (x0, y) = ((i % 10) * 500, i / 10 * 3);
+            let path: Vec<GridPos> = (0..500).map(|x| GridPos { x: x0 + x, y }).collect();
+            let result = PathResult {
+                cost: 499.0,
+                length: 499.0,
+                cumulative_costs: Vec::new(),
+                nodes_expanded: 500,
+                best_g_score: 499.0,
+                nodes_in_open_set: 0,
+                status: PathStatus::Found,
+                elapsed: None,
+                path: path.clone(),
+            };
+            cache.insert(path[0], path[499], result);
+        }
+        cache
+    };
+    let rect = (1200, 30, 4, 4);
+    let inside = |p: &GridPos| p.x >= 1200 && p.x < 1204 && p.y >= 30 && p.y < 34;
+
+    let mut group = c.benchmark_group("Invalidate 1000 Paths");
+    group.bench_function("invalidate_region", |b| {
+        b.iter_batched(build, |mut cache| cache.invalidate_region(inside), BatchSize::LargeInput)
+    });
+    group.bench_function("invalidate_rect", |b| {
+        b.iter_batched(build, |mut cache| cache.invalidate_rect(black_box(rect)), BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+fn bench_flow_field_batch(c: &mut Criterion) {
+    // 16 capture points on a 512x512 map with staggered walls; the batch scales with cores
+    let mut grid = Grid2D::new(512, 512, DiagonalMode::IfNoObstacle);
+    for i in 0..16 {
+        let x = 16 + i * 31;
+        let gap = (i * 97) % 400 + 40;
+        grid.set_region_blocked((x, 0, 1, gap), true);
+        grid.set_region_blocked((x, gap + 16, 1, 512 - gap - 16), true);
+    }
+    let goals: Vec<GridPos> = (0..16).map(|i| GridPos { x: 8 + i * 31, y: (i * 151) % 500 + 6 }).collect();
+
+    let mut group = c.benchmark_group("Flow Fields 512x512 16 Goals");
+    group.sample_size(10);
+    group.bench_function("compute loop", |b| {
+        b.iter(|| goals.iter().map(|&goal| FlowField::compute(black_box(&grid), goal)).collect::<Vec<_>>())
+    });
+    group.bench_function("compute_batch", |b| b.iter(|| FlowField::compute_batch(black_box(&grid), &goals)));
+    group.finish();
+}
+
+fn bench_visibility_graph_vs_theta(c: &mut Criterion) {
+    // Rooms joined by doorways: few corners, long any-angle queries across the map
+    let (grid, rooms) = Grid2D::generate_room_layout(128, 128, 12, 24, 11, DiagonalMode::Always);
+    let center = |r: &(usize, usize, usize, usize)| GridPos { x: (r.0 + r.2 / 2) as i32, y: (r.1 + r.3 / 2) as i32 };
+    let (start, goal) = (center(&rooms[0]), center(&rooms[rooms.len() - 1]));
+
+    let mut group = c.benchmark_group("Visibility Graph 128x128 Rooms");
+    group.sample_size(10);
+    group.bench_function("build", |b| b.iter(|| VisibilityGraph::build(black_box(&grid))));
+    let visibility = VisibilityGraph::build(&grid);
+    group.bench_function("find_path", |b| b.iter(|| visibility.find_path(black_box(start), black_box(goal))));
+    group.bench_function("theta_star", |b| {
+        b.iter(|| theta_star(&grid, &Euclidean, black_box(start), black_box(goal), AStarConfig::default()))
+    });
+    group.finish();
+}
+
+// Grid2D without its node count hint, so searches start from empty maps and rehash as they grow
+struct UnhintedGrid<'a>(&'a Grid2D);
+
+impl Graph for UnhintedGrid<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &GridPos) -> bool {
+        self.0.is_passable(node)
+    }
+
+    fn neighbors<F: FnMut(GridPos, f32)>(&self, node: &GridPos, visit: F) {
+        self.0.neighbors(node, visit);
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+fn bench_node_count_hint(c: &mut Criterion) {
+    // Long search across a 1024x1024 map with staggered walls, so the maps grow large
+    let mut grid = Grid2D::new(1024, 1024, DiagonalMode::IfNoObstacle);
+    for i in 0..8 {
+        let x = 100 + i * 110;
+        let gap = if i % 2 == 0 { 900 } else { 100 };
+        grid.set_region_blocked((x, 0, 2, gap), true);
+        grid.set_region_blocked((x, gap + 24, 2, 1024 - gap - 24), true);
+    }
+    let (start, goal) = (GridPos { x: 10, y: 512 }, GridPos { x: 1010, y: 512 });
+    let heuristic = Diagonal::default();
+    let unhinted = UnhintedGrid(&grid);
+
+    let mut group = c.benchmark_group("Node Count Hint 1024x1024");
+    group.sample_size(10);
+    group.bench_function("astar with hint", |b| {
+        b.iter(|| astar(black_box(&grid), &heuristic, black_box(start), black_box(goal), AStarConfig::default()))
+    });
+    group.bench_function("astar without hint", |b| {
+        b.iter(|| astar(black_box(&unhinted), &heuristic, black_box(start), black_box(goal), AStarConfig::default()))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_jps_vs_astar,
+    bench_grouped_vs_loop,
+    bench_erode_vs_naive,
+    bench_invalidate_rect_vs_region,
+    bench_flow_field_batch,
+    bench_visibility_graph_vs_theta,
+    bench_node_count_hint
+);
 criterion_main!(benches);
@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pathforge::graphs::navmesh::NavMesh;
+
+// Strip of 2 * quads triangles along +X in the XZ plane
+fn strip(quads: u32) -> NavMesh {
+    let mut vertices = Vec::new();
+    for i in 0..=quads {
+        vertices.extend_from_slice(&[i as f32, 0.0, 0.0]);
+        vertices.extend_from_slice(&[i as f32, 0.0, 1.0]);
+    }
+    let mut polygons = Vec::new();
+    let mut neighbors = Vec::new();
+    for k in 0..quads {
+        let (b0, t0, b1, t1) = (2 * k, 2 * k + 1, 2 * k + 2, 2 * k + 3);
+        let (lower, upper) = (2 * k as i32, 2 * k as i32 + 1);
+        polygons.extend_from_slice(&[b0, b1, t0]);
+        neighbors.extend_from_slice(&[-1, upper, if k == 0 { -1 } else { lower - 1 }]);
+        polygons.extend_from_slice(&[b1, t1, t0]);
+        neighbors.extend_from_slice(&[if k + 1 == quads { -1 } else { upper + 1 }, -1, lower]);
+    }
+    NavMesh::new(vertices, polygons, neighbors)
+}
+
+fn bench_get_portals(c: &mut Criterion) {
+    // 1000 triangles, path over 200 of them
+    let mut mesh = strip(500);
+    let path: Vec<u32> = (300..500).collect();
+    let start = [150.2, 0.0, 0.2];
+    let end = [249.8, 0.0, 0.8];
+
+    let mut group = c.benchmark_group("navmesh_get_portals");
+
+    group.bench_function("uncached", |b| {
+        b.iter(|| mesh.get_portals(black_box(&path), black_box(start), black_box(end)))
+    });
+
+    mesh.compute_portal_cache();
+    group.bench_function("cached", |b| {
+        b.iter(|| mesh.get_portals(black_box(&path), black_box(start), black_box(end)))
+    });
+
+    group.finish();
+}
+
+fn bench_raycast(c: &mut Criterion) {
+    // 100 rays, each crossing up to ~400 triangles of the strip
+    let mesh = strip(500);
+    let rays: Vec<([f32; 3], [f32; 3], f32)> = (0..100)
+        .map(|i| ([0.5 + i as f32, 0.0, 0.1 + i as f32 * 0.008], [1.0, 0.0, 0.001], 200.0))
+        .collect();
+
+    let mut group = c.benchmark_group("navmesh_raycast_100");
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| rays.iter().map(|&(o, d, m)| mesh.raycast(o, d, m)).collect::<Vec<_>>())
+    });
+
+    group.bench_function("batch", |b| b.iter(|| mesh.raycast_batch(black_box(&rays))));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_portals, bench_raycast);
+criterion_main!(benches);
@@ -85,7 +85,7 @@ fn main() {
     assert_eq!(path_result.path, vec![0, 1]);
 
     // 2. Get Portals
-    let portals = mesh.get_portals(&path_result.path, start_pos, end_pos);
+    let portals = mesh.get_portals(&path_result.path, start_pos, end_pos).unwrap();
     println!("Portals: {:?}", portals);
     
     // 3. String Pulling
@@ -104,5 +104,11 @@ fn main() {
     assert!((p1[0] - end_pos[0]).abs() < 0.001);
     assert!((p1[2] - end_pos[2]).abs() < 0.001);
     
+    // The same pipeline in one call
+    let result = mesh.find_path(start_pos, end_pos, AStarConfig::default());
+    println!("find_path: {:?}, {} points, length {:.3}", result.status, result.points.len(), result.length);
+    assert_eq!(result.corridor, path_result.path);
+    assert_eq!(result.points, smoothed_path);
+
     println!("Test Passed!");
 }
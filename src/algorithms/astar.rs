@@ -1,14 +1,35 @@
 use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
 use std::time::{Duration, Instant};
-use std::hash::Hash;
-use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
+use std::hash::{Hash, Hasher};
+use std::fmt;
+use crate::cost::{Cost, CostGraph, CostHeuristic};
+use crate::error::ConfigError;
+use crate::rng::{Fnv1a, SplitMix64};
+use crate::traits::{
+    path_length, search_capacity, GoalBoundHeuristic, Graph, GraphWithHeuristic, Heuristic, PathResult, PathResultStream, PathStatus,
+    SearchStats,
+};
 
-#[derive(Clone, Copy)]
+/// Search settings. Outside this crate, build one with `AStarConfig::builder()` or by
+/// changing the fields of `AStarConfig::default()`, so new settings don't break callers.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
 pub struct AStarConfig {
     pub max_iterations: Option<usize>,
     pub timeout: Option<Duration>,
+    /// Absolute time limit; takes precedence over `timeout` when set. Lets several searches
+    /// share one budget (see `pin_deadline`). Not serialized, since an `Instant` is process-local.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub deadline: Option<Instant>,
+    /// Highest path cost worth searching. Nodes costing more to reach are never queued, so a
+    /// goal only reachable above it gives `NotFound`. Honored by `astar` and the searches run
+    /// on it (`astar_stream`, `astar_int`, both levels of HPA); other algorithms ignore it.
+    pub max_cost: Option<f32>,
     pub tie_breaking: TieBreaking,
+    pub heuristic_weight: f32, // > 1.0 trades optimality for speed (weighted A*)
 }
 
 impl Default for AStarConfig {
@@ -16,34 +37,164 @@ impl Default for AStarConfig {
         Self {
             max_iterations: None,
             timeout: None,
+            deadline: None,
+            max_cost: None,
             tie_breaking: TieBreaking::PreferHigherG, // Defaulting to standard best practice
+            heuristic_weight: 1.0,
+        }
+    }
+}
+
+impl AStarConfig {
+    pub fn builder() -> AStarConfigBuilder {
+        AStarConfigBuilder::default()
+    }
+
+    /// Checks value ranges. Useful for configs loaded from data files.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_iterations == Some(0) {
+            return Err(ConfigError::ZeroMaxIterations);
+        }
+        if self.timeout == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroTimeout);
+        }
+        if !self.heuristic_weight.is_finite() || self.heuristic_weight < 0.0 {
+            return Err(ConfigError::InvalidHeuristicWeight);
+        }
+        if self.max_cost.is_some_and(|c| c.is_nan() || c < 0.0) {
+            return Err(ConfigError::InvalidMaxCost);
+        }
+        Ok(())
+    }
+
+    /// When a search started at `started` runs out of time: the deadline if set, otherwise
+    /// `started + timeout`. Searches only read the clock for `started` with the `timing`
+    /// feature; without it (None) the clock is read here, and only if there's a timeout.
+    pub(crate) fn deadline_from(&self, started: Option<Instant>) -> Option<Instant> {
+        self.deadline.or_else(|| self.timeout.and_then(|t| started.unwrap_or_else(Instant::now).checked_add(t)))
+    }
+
+    /// Turns `timeout` into a deadline counted from now (keeping an existing deadline), so
+    /// every search run with the returned config shares one time limit. Composite operations
+    /// do this before their first internal search.
+    pub fn pin_deadline(mut self) -> Self {
+        self.deadline = self.deadline_from(None);
+        self
+    }
+}
+
+impl fmt::Display for AStarConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max_iterations {
+            Some(n) => write!(f, "max_iterations={}", n)?,
+            None => write!(f, "max_iterations=unlimited")?,
+        }
+        match self.timeout {
+            Some(t) => write!(f, ", timeout={:?}", t)?,
+            None => write!(f, ", timeout=none")?,
+        }
+        match self.max_cost {
+            Some(c) => write!(f, ", max_cost={}", c)?,
+            None => write!(f, ", max_cost=none")?,
+        }
+        write!(f, ", tie_breaking={:?}, heuristic_weight={}", self.tie_breaking, self.heuristic_weight)
+    }
+}
+
+/// Fluent construction of an `AStarConfig`, validated by `build`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AStarConfigBuilder {
+    config: AStarConfig,
+}
+
+impl AStarConfigBuilder {
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.config.max_iterations = Some(max_iterations);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_cost(mut self, max_cost: f32) -> Self {
+        self.config.max_cost = Some(max_cost);
+        self
+    }
+
+    pub fn tie_breaking(mut self, tie_breaking: TieBreaking) -> Self {
+        self.config.tie_breaking = tie_breaking;
+        self
+    }
+
+    pub fn heuristic_weight(mut self, weight: f32) -> Self {
+        self.config.heuristic_weight = weight;
+        self
+    }
+
+    /// Validates the settings, and also rejects a `timeout` combined with a `deadline`. A
+    /// config built field by field (or by `pin_deadline`) may carry both; the deadline wins.
+    pub fn build(self) -> Result<AStarConfig, ConfigError> {
+        if self.config.timeout.is_some() && self.config.deadline.is_some() {
+            return Err(ConfigError::TimeoutAndDeadline);
         }
+        self.config.validate()?;
+        Ok(self.config)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TieBreaking {
     None,
     PreferHigherG,   // Prefer paths further from start (closer to goal usually)
     PreferLowerG,    // Prefer paths closer to start
     CrossProduct,    // Break ties toward goal direction (Not implemented in heap sort yet)
+    /// Orders equal-f nodes by a hash of the node and seed, so different seeds settle on
+    /// different equal-cost paths (route variety for units sharing endpoints) while a seed
+    /// always reproduces its path, on any platform and Rust release. Path cost is unaffected:
+    /// only ties are reordered.
+    SeededRandom(u64),
+}
+
+impl TieBreaking {
+    // Secondary open-set key for a node reached with cost `g`; higher pops first
+    pub(crate) fn key<N: Hash>(self, node: &N, g: f32) -> f32 {
+        match self {
+            TieBreaking::None | TieBreaking::CrossProduct => 0.0,
+            TieBreaking::PreferHigherG => g,
+            TieBreaking::PreferLowerG => -g,
+            TieBreaking::SeededRandom(seed) => {
+                let mut hasher = Fnv1a::default();
+                (seed, node).hash(&mut hasher);
+                // FNV's high bits mix poorly on short inputs
+                (SplitMix64(hasher.finish()).next_u64() >> 40) as f32
+            }
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
-struct State<N> {
+struct State<N, C> {
     node: N,
-    cost: f32, // f_score
-    g_score: f32, // Actual cost from start
+    cost: C, // f_score
+    g_score: C, // Actual cost from start
     tie_breaker: f32, // Secondary sort key derived from strategy
 }
 
-impl<N: Eq> PartialEq for State<N> {
+impl<N: Eq, C: Cost> PartialEq for State<N, C> {
     fn eq(&self, other: &Self) -> bool {
-        self.cost == other.cost && self.tie_breaker == other.tie_breaker
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl<N: Eq> Eq for State<N> {}
+impl<N: Eq, C: Cost> Eq for State<N, C> {}
 
 // BinaryHeap is max-heap. We want MIN-heap for cost.
 // So we invert the comparison for cost.
@@ -57,32 +208,46 @@ impl<N: Eq> Eq for State<N> {}
 // If self.cost == other.cost:
 // We want the one with Higher G to float up.
 // So if self.tie_breaker > other.tie_breaker => Greater.
-impl<N: Eq> Ord for State<N> {
+impl<N: Eq, C: Cost> Ord for State<N, C> {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.cost < other.cost {
-            return Ordering::Greater;
-        } else if self.cost > other.cost {
-            return Ordering::Less;
-        }
-        
-        // Costs are equal, check tie breaker.
-        // We want higher tie_breaker to be "Greater" so it is popped first.
-        if self.tie_breaker > other.tie_breaker {
-            return Ordering::Greater;
-        } else if self.tie_breaker < other.tie_breaker {
-            return Ordering::Less;
-        }
-        
-        Ordering::Equal
+        // Lower f first; on equal f, the higher tie_breaker is "Greater" so it is popped first
+        other.cost.cmp_cost(&self.cost).then(self.tie_breaker.total_cmp(&other.tie_breaker))
     }
 }
 
-impl<N: Eq> PartialOrd for State<N> {
+impl<N: Eq, C: Cost> PartialOrd for State<N, C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// `astar` with the heuristic's per-goal setup done once up front (see `GoalBoundHeuristic`).
+/// Wrap heuristics without such setup in `NoopPrecomputed`.
+pub fn astar_precomputed<G, H>(
+    graph: &G,
+    heuristic: &H,
+    start: G::Node,
+    goal: G::Node,
+    config: AStarConfig,
+) -> PathResult<G::Node>
+where
+    G: Graph,
+    H: GoalBoundHeuristic<G::Node>,
+{
+    let precomputed = heuristic.precompute(&goal);
+    astar(graph, &precomputed, start, goal, config)
+}
+
+/// `astar` with the graph's own `default_heuristic`.
+pub fn find_path_default<G: GraphWithHeuristic>(
+    graph: &G,
+    start: G::Node,
+    goal: G::Node,
+    config: AStarConfig,
+) -> PathResult<G::Node> {
+    astar(graph, &graph.default_heuristic(), start, goal, config)
+}
+
 pub fn astar<G, H>(
     graph: &G,
     heuristic: &H,
@@ -94,22 +259,134 @@ where
     G: Graph,
     H: Heuristic<G::Node>,
 {
-    let start_time = Instant::now();
-    let mut open_set = BinaryHeap::new();
-    let mut g_scores = HashMap::new();
-    let mut came_from = HashMap::new();
+    let outcome = astar_search(graph, heuristic, start, goal, config);
+    let stats = outcome.stats;
+    let result = match outcome.end {
+        Some((last, cost)) => reconstruct_path(graph, last, &outcome.came_from, &outcome.g_scores, cost, stats, outcome.status),
+        None => PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: stats.nodes_expanded,
+            best_g_score: stats.best_g_score,
+            nodes_in_open_set: 0,
+            status: outcome.status,
+            elapsed: None,
+        },
+    };
+    result.timed(outcome.started)
+}
+
+/// `astar` handing the path out lazily: the search's parent links are kept rather than
+/// copied into a `Vec`, and nodes are produced as the stream is iterated. Saves the up-front
+/// allocation for very long paths, e.g. agents that only look a few nodes ahead.
+pub fn astar_stream<G, H>(
+    graph: &G,
+    heuristic: &H,
+    start: G::Node,
+    goal: G::Node,
+    config: AStarConfig,
+) -> PathResultStream<G::Node>
+where
+    G: Graph,
+    H: Heuristic<G::Node>,
+{
+    let outcome = astar_search(graph, heuristic, start, goal, config);
+    let (last, cost) = outcome.end.map_or((None, 0.0), |(last, cost)| (Some(last), cost));
+    PathResultStream::new(last, outcome.came_from, cost, outcome.stats, outcome.status)
+}
+
+/// `astar_int`'s result: the path with its exact cost, and the cost reached at each node,
+/// in the search's cost type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostPathResult<N, C> {
+    pub path: Vec<N>,
+    pub cost: C,
+    pub cumulative_costs: Vec<C>,
+    pub nodes_expanded: usize,
+    pub status: PathStatus,
+}
+
+/// `astar` in any `Cost` type. With `Milli` (e.g. an `IntGrid2D` and `IntDiagonal`) every sum
+/// and comparison is integer, so a query gives bit-identical costs and paths on every
+/// platform; costs saturate at `Cost::MAX` instead of overflowing. The search loop is the
+/// one `astar` runs, so limits and `tie_breaking` behave the same (`CrossProduct` is a no-op
+/// here as there). `heuristic_weight` is ignored for `Milli`, since scaling would bring
+/// floats back.
+pub fn astar_int<G, H, C>(
+    graph: &G,
+    heuristic: &H,
+    start: G::Node,
+    goal: G::Node,
+    config: AStarConfig,
+) -> CostPathResult<G::Node, C>
+where
+    G: CostGraph<C>,
+    H: CostHeuristic<G::Node, C>,
+    C: Cost,
+{
+    let outcome = astar_search(graph, heuristic, start, goal, config);
+    let nodes_expanded = outcome.stats.nodes_expanded;
+    let Some((last, cost)) = outcome.end else {
+        let status = outcome.status;
+        return CostPathResult { path: vec![], cost: C::ZERO, cumulative_costs: vec![], nodes_expanded, status };
+    };
+    let mut path = vec![last];
+    while let Some(parent) = outcome.came_from.get(&path[path.len() - 1]) {
+        path.push(parent.clone());
+    }
+    path.reverse();
+    let g_scores = &outcome.g_scores;
+    let mut cumulative_costs: Vec<C> = path.iter().map(|n| g_scores.get(n).copied().unwrap_or(C::ZERO)).collect();
+    if let Some(last) = cumulative_costs.last_mut() {
+        *last = cost;
+    }
+    CostPathResult { path, cost, cumulative_costs, nodes_expanded, status: outcome.status }
+}
+
+// Where a search stopped, with the maps its path is rebuilt from
+struct SearchOutcome<N, C> {
+    end: Option<(N, C)>, // Last node of the (possibly partial) path and its cost; None if not found
+    came_from: HashMap<N, N>,
+    g_scores: HashMap<N, C>,
+    stats: SearchStats,
+    status: PathStatus,
+    started: Option<Instant>, // What the limits are counted from, reused for `PathResult::elapsed`
+}
+
+// The A* loop behind `astar`, `astar_stream` and `astar_int`
+fn astar_search<G, H, C>(
+    graph: &G,
+    heuristic: &H,
+    start: G::Node,
+    goal: G::Node,
+    config: AStarConfig,
+) -> SearchOutcome<G::Node, C>
+where
+    G: CostGraph<C>,
+    H: CostHeuristic<G::Node, C>,
+    C: Cost,
+{
+    let started = cfg!(feature = "timing").then(Instant::now);
+    let deadline = config.deadline_from(started);
+    let h_start = heuristic.estimate_cost(&start, &goal).weighted(config.heuristic_weight);
+    let capacity = search_capacity(graph.node_count_hint(), h_start.to_f32());
+    let mut open_set = BinaryHeap::with_capacity(capacity);
+    let mut g_scores = HashMap::with_capacity(capacity);
+    let mut came_from = HashMap::with_capacity(capacity);
     
-    g_scores.insert(start.clone(), 0.0);
+    g_scores.insert(start.clone(), C::ZERO);
     
-    let h_start = heuristic.estimate(&start, &goal);
     open_set.push(State {
         node: start.clone(),
         cost: h_start,
-        g_score: 0.0,
+        g_score: C::ZERO,
         tie_breaker: 0.0, // Start node tie breaking irrelevant usually
     });
     
     let mut nodes_expanded = 0;
+    let mut furthest_g: f32 = 0.0;
     let mut iterations = 0;
     
     while let Some(State { node: current, cost: _f_score, g_score: current_g, tie_breaker: _ }) = open_set.pop() {
@@ -118,35 +395,41 @@ where
         // Check limits
         if let Some(max_iter) = config.max_iterations {
             if iterations > max_iter {
-                 return reconstruct_partial(current, &came_from, current_g, nodes_expanded, PathStatus::PartialMaxIter);
+                let stats = SearchStats::new(nodes_expanded, furthest_g, open_set.len());
+                return SearchOutcome { end: Some((current, current_g)), came_from, g_scores, stats, status: PathStatus::PartialMaxIter, started };
             }
         }
-        if let Some(timeout) = config.timeout {
-            if start_time.elapsed() > timeout {
-                return reconstruct_partial(current, &came_from, current_g, nodes_expanded, PathStatus::PartialTimeout);
-            }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            let stats = SearchStats::new(nodes_expanded, furthest_g, open_set.len());
+            return SearchOutcome { end: Some((current, current_g)), came_from, g_scores, stats, status: PathStatus::PartialTimeout, started };
         }
         
         if current == goal {
-            return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::Found);
+            furthest_g = furthest_g.max(current_g.to_f32());
+            let stats = SearchStats::new(nodes_expanded, furthest_g, open_set.len());
+            return SearchOutcome { end: Some((current, current_g)), came_from, g_scores, stats, status: PathStatus::Found, started };
         }
         
         // Optimization: Check if we found a better path to this node already
-        if let Some(&best_g) = g_scores.get(&current) {
+        if let Some(best_g) = g_scores.get(&current) {
             // If the popped node has a worse (higher) g_score than what is known, it is stale.
             // Using strict inequality > because float equality is tricky, but mostly if we have a strictly better path, this one is >.
-            if current_g > best_g {
+            if current_g.cmp_cost(best_g) == Ordering::Greater {
                 continue;
             }
         }
         
         nodes_expanded += 1;
+        furthest_g = furthest_g.max(current_g.to_f32());
 
-        graph.neighbors(&current, |neighbor, edge_cost| {
-            let tentative_g = current_g + edge_cost;
+        graph.neighbors_with_cost(&current, |neighbor, edge_cost| {
+            let tentative_g = current_g.saturating_add(edge_cost);
+            if config.max_cost.is_some_and(|max| tentative_g.to_f32() > max) {
+                return;
+            }
             
-            if let Some(&existing_g) = g_scores.get(&neighbor) {
-                if tentative_g >= existing_g {
+            if let Some(existing_g) = g_scores.get(&neighbor) {
+                if tentative_g.cmp_cost(existing_g) != Ordering::Less {
                     return;
                 }
             }
@@ -154,15 +437,10 @@ where
             came_from.insert(neighbor.clone(), current.clone());
             g_scores.insert(neighbor.clone(), tentative_g);
             
-            let h = heuristic.estimate(&neighbor, &goal);
-            let f = tentative_g + h;
+            let h = heuristic.estimate_cost(&neighbor, &goal).weighted(config.heuristic_weight);
+            let f = tentative_g.saturating_add(h);
             
-            let tb = match config.tie_breaking {
-                TieBreaking::None => 0.0,
-                TieBreaking::PreferHigherG => tentative_g,
-                TieBreaking::PreferLowerG => -tentative_g,
-                TieBreaking::CrossProduct => 0.0,
-            };
+            let tb = config.tie_breaking.key(&neighbor, tentative_g.to_f32());
             
             open_set.push(State {
                 node: neighbor,
@@ -173,21 +451,19 @@ where
         });
     }
     
-    PathResult {
-        path: vec![],
-        cost: 0.0,
-        nodes_expanded,
-        status: PathStatus::NotFound,
-    }
+    let stats = SearchStats::new(nodes_expanded, furthest_g, 0);
+    SearchOutcome { end: None, came_from, g_scores, stats, status: PathStatus::NotFound, started }
 }
 
-fn reconstruct_path<N: Clone + Eq + Hash>(
-    current: N,
-    came_from: &HashMap<N, N>,
+pub(crate) fn reconstruct_path<G: Graph>(
+    graph: &G,
+    current: G::Node,
+    came_from: &HashMap<G::Node, G::Node>,
+    g_scores: &HashMap<G::Node, f32>,
     cost: f32,
-    nodes_expanded: usize,
+    stats: SearchStats,
     status: PathStatus
-) -> PathResult<N> {
+) -> PathResult<G::Node> {
     let mut path = vec![current.clone()];
     let mut cur = current;
     while let Some(parent) = came_from.get(&cur) {
@@ -195,20 +471,377 @@ fn reconstruct_path<N: Clone + Eq + Hash>(
         cur = parent.clone();
     }
     path.reverse();
+    let mut cumulative_costs: Vec<f32> = path.iter()
+        .map(|n| g_scores.get(n).copied().unwrap_or(0.0))
+        .collect();
+    if let Some(last) = cumulative_costs.last_mut() {
+        *last = cost;
+    }
     PathResult {
+        length: path_length(graph, &path),
         path,
         cost,
-        nodes_expanded,
+        cumulative_costs,
+        nodes_expanded: stats.nodes_expanded,
+        best_g_score: stats.best_g_score,
+        nodes_in_open_set: stats.nodes_in_open_set,
+        elapsed: None,
         status,
     }
 }
 
-fn reconstruct_partial<N: Clone + Eq + Hash>(
-    current: N,
-    came_from: &HashMap<N, N>,
-    cost: f32,
-    nodes_expanded: usize,
-    status: PathStatus
-) -> PathResult<N> {
-    reconstruct_path(current, came_from, cost, nodes_expanded, status)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{IntDiagonal, Milli};
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos, IntGrid2D};
+
+    #[test]
+    fn integer_costs_agree_across_tie_breaking() {
+        // Muddy patches with fractional costs, where float sums would pick up rounding
+        let mut grid = Grid2D::new(30, 30, DiagonalMode::IfNoObstacle);
+        for i in 0..200 {
+            grid.set_cost((i * 37 + 3) % 30, (i * 53 + 11) % 30, 1.1 + (i % 7) as f32 * 0.3);
+        }
+        grid.set_region_blocked((8, 4, 1, 20), true);
+        let int_grid = IntGrid2D::new(&grid, Milli(10), Milli(14));
+        let heuristic = IntDiagonal { cardinal: Milli(10), diagonal: Milli(14) };
+        let (start, goal) = (GridPos { x: 1, y: 14 }, GridPos { x: 28, y: 2 });
+
+        let results: Vec<CostPathResult<GridPos, Milli>> =
+            [TieBreaking::None, TieBreaking::PreferHigherG, TieBreaking::PreferLowerG, TieBreaking::SeededRandom(7)]
+                .into_iter()
+                .map(|tie_breaking| AStarConfig { tie_breaking, ..AStarConfig::default() })
+                .map(|config| astar_int(&int_grid, &heuristic, start, goal, config))
+                .collect();
+        for result in &results {
+            assert_eq!(result.status, PathStatus::Found);
+            assert_eq!(result.cost, results[0].cost);
+            let steps = result.path.windows(2).map(|w| int_grid.step_cost(w[0], w[1]));
+            let summed = steps.fold(Milli(0), Milli::saturating_add);
+            assert_eq!(summed, result.cost);
+            assert_eq!(result.cumulative_costs.last(), Some(&result.cost));
+        }
+        // The same prices searched as floats find the same cost
+        let float = astar_int(&int_grid, &crate::heuristics::Zero, start, goal, AStarConfig::default());
+        assert!((float.cost - results[0].cost.to_f32()).abs() < 1e-3);
+
+        // Sums saturate instead of wrapping
+        let corridor = Grid2D::new(4, 1, DiagonalMode::Never);
+        let huge = IntGrid2D::new(&corridor, Milli(u32::MAX / 2), Milli(u32::MAX));
+        let zero = IntDiagonal { cardinal: Milli(0), diagonal: Milli(0) };
+        let far = astar_int(&huge, &zero, GridPos { x: 0, y: 0 }, GridPos { x: 3, y: 0 }, AStarConfig::default());
+        assert_eq!((far.status, far.cost, far.path.len()), (PathStatus::Found, Milli::MAX, 4));
+    }
+
+    #[test]
+    fn builder_rejects_invalid_settings() {
+        assert_eq!(AStarConfig::builder().max_iterations(0).build().unwrap_err(), ConfigError::ZeroMaxIterations);
+        assert_eq!(AStarConfig::builder().timeout(Duration::ZERO).build().unwrap_err(), ConfigError::ZeroTimeout);
+        assert_eq!(AStarConfig::builder().heuristic_weight(-1.0).build().unwrap_err(), ConfigError::InvalidHeuristicWeight);
+        assert_eq!(AStarConfig::builder().heuristic_weight(f32::NAN).build().unwrap_err(), ConfigError::InvalidHeuristicWeight);
+        assert_eq!(AStarConfig::builder().max_cost(-1.0).build().unwrap_err(), ConfigError::InvalidMaxCost);
+        let both = AStarConfig::builder().timeout(Duration::from_millis(5)).deadline(Instant::now());
+        assert_eq!(both.build().unwrap_err(), ConfigError::TimeoutAndDeadline);
+
+        let config = AStarConfig::builder()
+            .max_iterations(500)
+            .timeout(Duration::from_millis(2))
+            .tie_breaking(TieBreaking::PreferLowerG)
+            .heuristic_weight(1.5)
+            .build()
+            .unwrap();
+        assert_eq!(config.max_iterations, Some(500));
+        assert_eq!(config.tie_breaking, TieBreaking::PreferLowerG);
+        assert_eq!(
+            config.to_string(),
+            "max_iterations=500, timeout=2ms, max_cost=none, tie_breaking=PreferLowerG, heuristic_weight=1.5"
+        );
+        assert!(AStarConfig::default().validate().is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_json_round_trip() {
+        let config = AStarConfig::builder()
+            .max_iterations(1_000)
+            .timeout(Duration::from_millis(5))
+            .heuristic_weight(2.0)
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(&config).unwrap();
+        let back: AStarConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.max_iterations, config.max_iterations);
+        assert_eq!(back.timeout, config.timeout);
+        assert_eq!(back.tie_breaking, config.tie_breaking);
+        assert_eq!(back.heuristic_weight, config.heuristic_weight);
+
+        // Missing fields fall back to the defaults
+        let partial: AStarConfig = serde_json::from_str(r#"{"max_iterations": 10}"#).unwrap();
+        assert_eq!(partial.max_iterations, Some(10));
+        assert_eq!(partial.heuristic_weight, 1.0);
+    }
+
+    #[test]
+    fn find_path_default_uses_graph_heuristic() {
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::graphs::grid3d::{Grid3D, GridPos3D};
+        use crate::heuristics::{Diagonal, Euclidean};
+
+        let mut grid = Grid2D::new(20, 20, DiagonalMode::Always);
+        grid.set_region_blocked((5, 2, 1, 15), true);
+        grid.set_cost(8, 8, 4.0);
+        let (start, goal) = (GridPos { x: 1, y: 10 }, GridPos { x: 17, y: 6 });
+        let default = find_path_default(&grid, start, goal, AStarConfig::default());
+        let explicit = astar(&grid, &Diagonal::default(), start, goal, AStarConfig::default());
+        assert_eq!(default.status, PathStatus::Found);
+        assert_eq!(default.path, explicit.path);
+        assert_eq!(default.cost, explicit.cost);
+        assert_eq!(default.nodes_expanded, explicit.nodes_expanded);
+
+        let mut voxels = Grid3D::new(6, 6, 6);
+        voxels.set_blocked(2, 2, 2, true);
+        let (a, b) = (GridPos3D { x: 0, y: 0, z: 0 }, GridPos3D { x: 5, y: 5, z: 5 });
+        let default = find_path_default(&voxels, a, b, AStarConfig::default());
+        let explicit = astar(&voxels, &Euclidean, a, b, AStarConfig::default());
+        assert_eq!((default.path, default.cost), (explicit.path, explicit.cost));
+    }
+
+    #[test]
+    fn max_cost_bounds_the_search() {
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::Manhattan;
+
+        let grid = Grid2D::new(20, 20, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 10, y: 0 });
+        let within = AStarConfig { max_cost: Some(10.0), ..AStarConfig::default() };
+        assert_eq!(astar(&grid, &Manhattan, start, goal, within).cost, 10.0);
+
+        let capped = AStarConfig { max_cost: Some(9.5), ..AStarConfig::default() };
+        let res = astar(&grid, &Manhattan, start, goal, capped);
+        assert_eq!((res.status, res.path.is_empty()), (PathStatus::NotFound, true));
+        // Only the 55 cells within 9 steps of the corner were expanded
+        assert_eq!(res.nodes_expanded, 55);
+    }
+
+    #[test]
+    fn deadline_takes_precedence_over_timeout() {
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::Diagonal;
+
+        let grid = Grid2D::new(30, 30, DiagonalMode::Always);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 29, y: 29 });
+        let passed = Instant::now();
+        let timeout = Some(Duration::from_secs(60));
+        let config = AStarConfig { timeout, deadline: Some(passed), ..AStarConfig::default() };
+        assert_eq!(astar(&grid, &Diagonal::default(), start, goal, config).status, PathStatus::PartialTimeout);
+
+        // Pinning keeps a set deadline and otherwise converts the timeout
+        assert_eq!(config.pin_deadline().deadline, Some(passed));
+        let pinned = AStarConfig { timeout: Some(Duration::from_secs(60)), ..AStarConfig::default() }.pin_deadline();
+        assert!(pinned.deadline.is_some_and(|d| d > Instant::now() + Duration::from_secs(59)));
+        assert_eq!(astar(&grid, &Diagonal::default(), start, goal, pinned).status, PathStatus::Found);
+    }
+
+    #[test]
+    fn precomputed_alt_matches_plain_alt() {
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::algorithms::flowfield::DistanceField;
+        use crate::heuristics::{AltHeuristic, Diagonal, NoopPrecomputed};
+
+        let mut grid = Grid2D::new(48, 48, DiagonalMode::Always);
+        for x in (4..44).step_by(8) {
+            grid.set_region_blocked((x, 0, 1, 40), true);
+            grid.set_region_blocked((x + 4, 8, 1, 40), true);
+        }
+        let corners = [GridPos { x: 0, y: 0 }, GridPos { x: 47, y: 0 }, GridPos { x: 0, y: 47 }, GridPos { x: 47, y: 47 }];
+        let landmarks: Vec<_> = corners.iter().map(|&c| DistanceField::landmark(&grid, c)).collect();
+        let alt = AltHeuristic::new(&landmarks).unwrap();
+        let octile = Diagonal::for_grid(&grid);
+
+        for (start, goal) in [(GridPos { x: 1, y: 1 }, GridPos { x: 46, y: 20 }), (GridPos { x: 30, y: 45 }, GridPos { x: 2, y: 10 })] {
+            let plain = astar(&grid, &alt, start, goal, AStarConfig::default());
+            let precomputed = astar_precomputed(&grid, &alt, start, goal, AStarConfig::default());
+            assert_eq!(precomputed.status, PathStatus::Found);
+            assert_eq!(precomputed.path, plain.path);
+            assert_eq!(precomputed.nodes_expanded, plain.nodes_expanded);
+
+            // Admissible, and better informed than octile distance around the walls
+            let baseline = astar(&grid, &octile, start, goal, AStarConfig::default());
+            assert!((precomputed.cost - baseline.cost).abs() < 1e-3);
+            assert!(precomputed.nodes_expanded < baseline.nodes_expanded);
+
+            let noop = astar_precomputed(&grid, &NoopPrecomputed(octile), start, goal, AStarConfig::default());
+            assert_eq!((noop.path, noop.nodes_expanded), (baseline.path, baseline.nodes_expanded));
+        }
+    }
+
+    #[test]
+    fn alt_bounds_asymmetric_costs_from_both_sides() {
+        use crate::algorithms::flowfield::DistanceField;
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::AltHeuristic;
+
+        // Heading west costs three times as much as heading east
+        let mut grid = Grid2D::new(12, 12, DiagonalMode::Always);
+        grid.set_directional_bias([1.0, 1.0, 1.0, 1.0, 1.0, 3.0, 3.0, 3.0]);
+        grid.set_region_blocked((6, 2, 1, 8), true);
+        let landmark = GridPos { x: 0, y: 5 };
+        let alt = AltHeuristic::new(&[DistanceField::landmark(&grid, landmark)]).unwrap();
+
+        // Out of the landmark only `d(L, to) - d(L, from)` says anything, and it's exact there
+        let east = GridPos { x: 10, y: 5 };
+        let exact = astar(&grid, &crate::heuristics::Zero, landmark, east, AStarConfig::default()).cost;
+        assert!((alt.estimate(&landmark, &east) - exact).abs() < 1e-3, "{} vs {exact}", alt.estimate(&landmark, &east));
+
+        // Admissible both ways between a spread of cells
+        let cells: Vec<GridPos> =
+            (0..12).step_by(3).flat_map(|x| (0..12).step_by(4).map(move |y| GridPos { x, y })).collect();
+        for &from in &cells {
+            for &to in &cells {
+                let cost = astar(&grid, &crate::heuristics::Zero, from, to, AStarConfig::default()).cost;
+                assert!(alt.estimate(&from, &to) <= cost + 1e-3, "{from:?} -> {to:?}");
+            }
+        }
+
+        let small = DistanceField::landmark(&Grid2D::new(4, 4, DiagonalMode::Always), landmark);
+        let mismatched = AltHeuristic::new(&[DistanceField::landmark(&grid, landmark), small]);
+        assert_eq!(mismatched.unwrap_err(), crate::error::GridError::DimensionMismatch);
+    }
+
+    #[test]
+    fn cached_heuristic_estimates_each_node_once_per_goal() {
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::{CachedHeuristic, Diagonal};
+
+        // Octile distance that counts its calls per (from, to)
+        struct Counting {
+            octile: Diagonal,
+            calls: RefCell<HashMap<(GridPos, GridPos), usize>>,
+        }
+        impl Heuristic<GridPos> for Counting {
+            fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+                *self.calls.borrow_mut().entry((*from, *to)).or_default() += 1;
+                self.octile.estimate(from, to)
+            }
+        }
+
+        let mut grid = Grid2D::new(32, 32, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((16, 0, 1, 28), true);
+        let counting = Counting { octile: Diagonal::for_grid(&grid), calls: RefCell::default() };
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 30, y: 3 });
+        let plain = astar(&grid, &counting, start, goal, AStarConfig::default());
+        let uncached_calls: usize = counting.calls.take().values().sum();
+
+        let cached = CachedHeuristic::new(counting);
+        let result = astar(&grid, &cached, start, goal, AStarConfig::default());
+        assert_eq!((&result.path, result.nodes_expanded), (&plain.path, plain.nodes_expanded));
+        let calls = cached.inner().calls.take();
+        assert!(calls.values().all(|&n| n == 1));
+        assert_eq!(calls.len(), cached.cached_len());
+        assert!(calls.len() < uncached_calls, "{} vs {uncached_calls}", calls.len());
+
+        // A new goal starts a fresh memo, so no estimate toward the old goal leaks in
+        let back = astar(&grid, &cached, goal, start, AStarConfig::default());
+        assert!((back.cost - plain.cost).abs() < 1e-4);
+        let calls = cached.inner().calls.take();
+        assert!(calls.keys().all(|&(_, to)| to == start) && calls.values().all(|&n| n == 1));
+        assert_eq!(calls.len(), cached.cached_len());
+        cached.reset();
+        assert_eq!(cached.cached_len(), 0);
+    }
+
+    #[test]
+    fn stream_yields_the_collected_path() {
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::Diagonal;
+
+        let mut grid = Grid2D::new(64, 64, DiagonalMode::Always);
+        grid.set_region_blocked((20, 0, 2, 50), true);
+        grid.set_region_blocked((40, 14, 2, 50), true);
+        let (start, goal) = (GridPos { x: 2, y: 30 }, GridPos { x: 60, y: 30 });
+        let h = Diagonal::for_grid(&grid);
+        let collected = astar(&grid, &h, start, goal, AStarConfig::default());
+
+        let stream = astar_stream(&grid, &h, start, goal, AStarConfig::default());
+        assert_eq!((stream.status, stream.cost, stream.nodes_expanded), (PathStatus::Found, collected.cost, collected.nodes_expanded));
+        assert_eq!(stream.collect_path(), collected.path);
+        let mut ahead = astar_stream(&grid, &h, start, goal, AStarConfig::default()).skip_nodes(10);
+        assert_eq!(ahead.next(), Some(collected.path[10]));
+        assert_eq!(ahead.collect_path(), collected.path[11..]);
+        let past_the_end = astar_stream(&grid, &h, start, goal, AStarConfig::default()).skip_nodes(1000);
+        assert!(past_the_end.collect_path().is_empty());
+
+        // Partial results stream the partial path; failures stream nothing
+        let limited = AStarConfig { max_iterations: Some(40), ..AStarConfig::default() };
+        let partial = astar(&grid, &h, start, goal, limited);
+        assert_eq!(astar_stream(&grid, &h, start, goal, limited).collect_path(), partial.path);
+        grid.set_region_blocked((59, 29, 3, 3), true);
+        grid.set_blocked(60, 30, false);
+        let walled_in = astar_stream(&grid, &h, start, goal, AStarConfig::default());
+        assert_eq!(walled_in.status, PathStatus::NotFound);
+        assert_eq!(walled_in.count(), 0);
+    }
+
+    #[test]
+    fn seeded_ties_vary_paths_without_changing_cost() {
+        use std::collections::HashSet;
+        use crate::algorithms::{jps::jps, theta::theta_star};
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::{Diagonal, Euclidean, Manhattan};
+
+        // Every monotone staircase between the corners costs the same
+        let grid = Grid2D::new(10, 10, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 1, y: 1 }, GridPos { x: 8, y: 8 });
+        let seeded = |seed| AStarConfig { tie_breaking: TieBreaking::SeededRandom(seed), ..AStarConfig::default() };
+        let paths: Vec<PathResult<GridPos>> = (0..10).map(|seed| astar(&grid, &Manhattan, start, goal, seeded(seed))).collect();
+        assert!(paths.iter().all(|r| r.status == PathStatus::Found && r.cost == 14.0));
+        assert!(paths.iter().map(|r| &r.path).collect::<HashSet<_>>().len() >= 3);
+        assert_eq!(astar(&grid, &Manhattan, start, goal, seeded(4)).path, paths[4].path);
+        // Pinned: the key must not depend on the toolchain's DefaultHasher
+        assert_eq!(TieBreaking::SeededRandom(7).key(&GridPos { x: 1, y: 2 }, 0.0), 869502.0);
+
+        let grid = Grid2D::new(12, 12, DiagonalMode::Always);
+        let (start, goal) = (GridPos { x: 0, y: 2 }, GridPos { x: 11, y: 7 });
+        let octile = Diagonal::for_grid(&grid);
+        let jps_cost = jps(&grid, &octile, start, goal, AStarConfig::default()).cost;
+        let theta_cost = theta_star(&grid, &Euclidean, start, goal, AStarConfig::default()).cost;
+        for seed in 0..10 {
+            assert!((jps(&grid, &octile, start, goal, seeded(seed)).cost - jps_cost).abs() < 1e-4);
+            assert!((theta_star(&grid, &Euclidean, start, goal, seeded(seed)).cost - theta_cost).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn length_matches_cost_only_on_uniform_terrain() {
+        use crate::algorithms::{jps::jps, theta::theta_star};
+        use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
+        use crate::heuristics::{Diagonal, Euclidean};
+        use crate::smoothing::{postprocess, PostProcessOptions, SmoothingMethod};
+
+        let mut grid = Grid2D::new(16, 16, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((8, 0, 1, 12), true);
+        let (start, goal) = (GridPos { x: 1, y: 3 }, GridPos { x: 14, y: 5 });
+        let octile = Diagonal::for_grid(&grid);
+        let found = astar(&grid, &octile, start, goal, AStarConfig::default());
+        assert!((found.length - found.cost).abs() < 1e-4, "{} vs {}", found.length, found.cost);
+        let jumped = jps(&grid, &octile, start, goal, AStarConfig::default());
+        assert!((jumped.length - found.length).abs() < 1e-4);
+        let any_angle = theta_star(&grid, &Euclidean, start, goal, AStarConfig::default());
+        assert!((any_angle.length - any_angle.cost).abs() < 1e-4 && any_angle.length < found.length);
+        assert_eq!(astar(&grid, &octile, start, GridPos { x: 8, y: 3 }, AStarConfig::default()).length, 0.0);
+
+        let smoothed = postprocess(&grid, found.clone(), PostProcessOptions { smoothing: SmoothingMethod::Funnel, ..Default::default() });
+        assert!(smoothed.length <= found.length + 1e-4);
+
+        // A swamp along the direct route: the path stays no shorter, its cost climbs
+        for (x, y) in (2..7).flat_map(|x| (0..16).map(move |y| (x, y))) {
+            grid.set_cost(x, y, 3.0);
+        }
+        let muddy = astar(&grid, &octile, start, goal, AStarConfig::default());
+        assert!(muddy.length >= found.length - 1e-4 && muddy.length < muddy.cost);
+    }
 }
@@ -0,0 +1,335 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+use crate::algorithms::astar::AStarConfig;
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::traits::Graph;
+
+/// Constraint-tree expansions allowed when `AStarConfig::max_iterations` is unset.
+/// CBS never terminates on unsolvable instances without a cap.
+const DEFAULT_MAX_CT_NODES: usize = 10_000;
+
+/// A timed path: the agent is at `pos` at time step `t`. Waits repeat the position.
+pub type TimedPath = Vec<(GridPos, u32)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Both agents occupy the cell at the conflict time.
+    Vertex(GridPos),
+    /// The agents swap cells; `agent_a` moves `from -> to`, `agent_b` moves `to -> from`.
+    Edge { from: GridPos, to: GridPos },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub agent_a: usize,
+    pub agent_b: usize,
+    /// Time step at which the agents collide (arrival time for edge conflicts).
+    pub time: u32,
+    pub kind: ConflictKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constraint {
+    Vertex { pos: GridPos, time: u32 },
+    Edge { from: GridPos, to: GridPos, time: u32 },
+}
+
+/// Position of an agent at time `t`; agents wait at their last position once done.
+fn position_at(path: &[(GridPos, u32)], t: u32) -> Option<GridPos> {
+    let last = path.last()?;
+    if t >= last.1 {
+        return Some(last.0);
+    }
+    Some(path[t as usize].0)
+}
+
+/// Finds the earliest vertex or edge (swap) conflict between any two timed paths.
+pub fn find_first_conflict(paths: &[TimedPath]) -> Option<Conflict> {
+    let horizon = paths.iter().filter_map(|p| p.last()).map(|l| l.1).max()?;
+    for t in 0..=horizon {
+        for a in 0..paths.len() {
+            for b in (a + 1)..paths.len() {
+                let (Some(pa), Some(pb)) = (position_at(&paths[a], t), position_at(&paths[b], t)) else {
+                    continue;
+                };
+                if pa == pb {
+                    return Some(Conflict { agent_a: a, agent_b: b, time: t, kind: ConflictKind::Vertex(pa) });
+                }
+                if t > 0 {
+                    let prev_a = position_at(&paths[a], t - 1).unwrap();
+                    let prev_b = position_at(&paths[b], t - 1).unwrap();
+                    if prev_a == pb && prev_b == pa {
+                        return Some(Conflict {
+                            agent_a: a,
+                            agent_b: b,
+                            time: t,
+                            kind: ConflictKind::Edge { from: prev_a, to: pa },
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Conflict-Based Search: jointly conflict-free, sum-of-costs optimal timed paths.
+///
+/// Every move (including waiting) takes one time step; connectivity follows the grid's
+/// `DiagonalMode`. Returns `None` if no solution is found within the constraint-tree cap
+/// (`config.max_iterations`, default 10 000) or the time limit (`config.deadline`/`timeout`).
+pub fn cbs(grid: &Grid2D, agents: &[(GridPos, GridPos)], config: AStarConfig) -> Option<Vec<TimedPath>> {
+    let deadline = config.deadline_from(None);
+    let max_nodes = config.max_iterations.unwrap_or(DEFAULT_MAX_CT_NODES);
+
+    // Perfect per-agent heuristics (unconstrained step distance to goal)
+    let dist_maps: Vec<Vec<u32>> = agents.iter().map(|&(_, goal)| step_distances(grid, goal)).collect();
+
+    let mut root_paths = Vec::with_capacity(agents.len());
+    for (i, &(start, goal)) in agents.iter().enumerate() {
+        root_paths.push(low_level(grid, start, goal, &[], &dist_maps[i])?);
+    }
+
+    struct CtNode {
+        constraints: Vec<Vec<Constraint>>, // per agent
+        paths: Vec<TimedPath>,
+        cost: u32,
+    }
+
+    let mut tree = vec![CtNode {
+        cost: sum_of_costs(&root_paths),
+        constraints: vec![Vec::new(); agents.len()],
+        paths: root_paths,
+    }];
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((tree[0].cost, 0usize)));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, idx))) = open.pop() {
+        expanded += 1;
+        if expanded > max_nodes {
+            return None;
+        }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            return None;
+        }
+
+        let Some(conflict) = find_first_conflict(&tree[idx].paths) else {
+            return Some(std::mem::take(&mut tree[idx].paths));
+        };
+
+        let branches = match conflict.kind {
+            ConflictKind::Vertex(pos) => [
+                (conflict.agent_a, Constraint::Vertex { pos, time: conflict.time }),
+                (conflict.agent_b, Constraint::Vertex { pos, time: conflict.time }),
+            ],
+            ConflictKind::Edge { from, to } => [
+                (conflict.agent_a, Constraint::Edge { from, to, time: conflict.time }),
+                (conflict.agent_b, Constraint::Edge { from: to, to: from, time: conflict.time }),
+            ],
+        };
+
+        for (agent, constraint) in branches {
+            let mut constraints = tree[idx].constraints.clone();
+            constraints[agent].push(constraint);
+            let (start, goal) = agents[agent];
+            if let Some(path) = low_level(grid, start, goal, &constraints[agent], &dist_maps[agent]) {
+                let mut paths = tree[idx].paths.clone();
+                paths[agent] = path;
+                let cost = sum_of_costs(&paths);
+                tree.push(CtNode { constraints, paths, cost });
+                open.push(Reverse((cost, tree.len() - 1)));
+            }
+        }
+    }
+
+    None
+}
+
+fn sum_of_costs(paths: &[TimedPath]) -> u32 {
+    paths.iter().filter_map(|p| p.last()).map(|l| l.1).sum()
+}
+
+/// Breadth-first step counts from `goal` (u32::MAX = unreachable).
+fn step_distances(grid: &Grid2D, goal: GridPos) -> Vec<u32> {
+    let mut dist = vec![u32::MAX; grid.width * grid.height];
+    if grid.is_blocked(goal.x, goal.y) {
+        return dist;
+    }
+    let idx = |p: GridPos| p.y as usize * grid.width + p.x as usize;
+    dist[idx(goal)] = 0;
+    let mut queue = VecDeque::from([goal]);
+    while let Some(p) = queue.pop_front() {
+        let d = dist[idx(p)];
+        grid.neighbors(&p, |n, _| {
+            if dist[idx(n)] == u32::MAX {
+                dist[idx(n)] = d + 1;
+                queue.push_back(n);
+            }
+        });
+    }
+    dist
+}
+
+/// Space-time A* for one agent under its constraints.
+fn low_level(
+    grid: &Grid2D,
+    start: GridPos,
+    goal: GridPos,
+    constraints: &[Constraint],
+    dist: &[u32],
+) -> Option<TimedPath> {
+    let h = |p: GridPos| dist[p.y as usize * grid.width + p.x as usize];
+    if grid.is_blocked(start.x, start.y) || h(start) == u32::MAX {
+        return None;
+    }
+
+    let vertex_blocked: HashSet<(GridPos, u32)> = constraints
+        .iter()
+        .filter_map(|c| match *c {
+            Constraint::Vertex { pos, time } => Some((pos, time)),
+            _ => None,
+        })
+        .collect();
+    let edge_blocked: HashSet<(GridPos, GridPos, u32)> = constraints
+        .iter()
+        .filter_map(|c| match *c {
+            Constraint::Edge { from, to, time } => Some((from, to, time)),
+            _ => None,
+        })
+        .collect();
+    // The agent can only stop at its goal once no later constraint forbids standing there
+    let goal_free_after = constraints
+        .iter()
+        .filter_map(|c| match *c {
+            Constraint::Vertex { pos, time } if pos == goal => Some(time),
+            _ => None,
+        })
+        .max();
+    let latest = constraints
+        .iter()
+        .map(|c| match *c {
+            Constraint::Vertex { time, .. } | Constraint::Edge { time, .. } => time,
+        })
+        .max()
+        .unwrap_or(0);
+    let horizon = latest + (grid.width * grid.height) as u32;
+
+    if vertex_blocked.contains(&(start, 0)) {
+        return None;
+    }
+
+    // Max-heap on (-f, t): lowest f first, deeper states first on ties
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(GridPos, u32), GridPos> = HashMap::new();
+    let mut closed: HashSet<(GridPos, u32)> = HashSet::new();
+    open.push((Reverse(h(start)), 0u32, start.x, start.y));
+
+    while let Some((_, t, x, y)) = open.pop() {
+        let pos = GridPos { x, y };
+        if !closed.insert((pos, t)) {
+            continue;
+        }
+        if pos == goal && goal_free_after.is_none_or(|ft| t > ft) {
+            let mut path = vec![(pos, t)];
+            let mut cur = (pos, t);
+            while cur.1 > 0 {
+                let prev = came_from[&cur];
+                cur = (prev, cur.1 - 1);
+                path.push(cur);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if t >= horizon {
+            continue;
+        }
+
+        let nt = t + 1;
+        let mut push = |next: GridPos| {
+            if closed.contains(&(next, nt))
+                || vertex_blocked.contains(&(next, nt))
+                || edge_blocked.contains(&(pos, next, nt))
+            {
+                return;
+            }
+            let hn = h(next);
+            if hn == u32::MAX {
+                return;
+            }
+            came_from.entry((next, nt)).or_insert(pos);
+            open.push((Reverse(nt + hn), nt, next.x, next.y));
+        };
+        push(pos); // wait
+        grid.neighbors(&pos, |n, _| push(n));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::DiagonalMode;
+
+    #[test]
+    fn corridor_swap_uses_alcove() {
+        // 5-long corridor with a single alcove below the middle cell
+        let mut grid = Grid2D::new(5, 2, DiagonalMode::Never);
+        grid.set_region_blocked((0, 1, 5, 1), true);
+        grid.set_blocked(2, 1, false);
+
+        let agents = [
+            (GridPos { x: 0, y: 0 }, GridPos { x: 4, y: 0 }),
+            (GridPos { x: 4, y: 0 }, GridPos { x: 0, y: 0 }),
+        ];
+        let paths = cbs(&grid, &agents, AStarConfig::default()).expect("solvable");
+
+        assert!(find_first_conflict(&paths).is_none());
+        assert_eq!(sum_of_costs(&paths), 11);
+        let alcove = GridPos { x: 2, y: 1 };
+        assert_eq!(paths.iter().filter(|p| p.iter().any(|&(pos, _)| pos == alcove)).count(), 1);
+        for (path, &(start, goal)) in paths.iter().zip(agents.iter()) {
+            assert_eq!(path.first().unwrap().0, start);
+            assert_eq!(path.last().unwrap().0, goal);
+        }
+    }
+
+    #[test]
+    fn four_agent_crossing_completes() {
+        let grid = Grid2D::new(5, 5, DiagonalMode::Never);
+        let agents = [
+            (GridPos { x: 0, y: 2 }, GridPos { x: 4, y: 2 }),
+            (GridPos { x: 4, y: 2 }, GridPos { x: 0, y: 2 }),
+            (GridPos { x: 2, y: 0 }, GridPos { x: 2, y: 4 }),
+            (GridPos { x: 2, y: 4 }, GridPos { x: 2, y: 0 }),
+        ];
+        let config = AStarConfig { max_iterations: Some(2_000), ..Default::default() };
+        let paths = cbs(&grid, &agents, config).expect("should finish under the cap");
+
+        assert!(find_first_conflict(&paths).is_none());
+        assert_eq!(paths.len(), 4);
+    }
+
+    #[test]
+    fn detects_vertex_and_swap_conflicts() {
+        let a = GridPos { x: 0, y: 0 };
+        let b = GridPos { x: 1, y: 0 };
+        let c = GridPos { x: 2, y: 0 };
+
+        let swap = vec![vec![(a, 0), (b, 1)], vec![(b, 0), (a, 1)]];
+        assert_eq!(
+            find_first_conflict(&swap),
+            Some(Conflict { agent_a: 0, agent_b: 1, time: 1, kind: ConflictKind::Edge { from: a, to: b } })
+        );
+
+        // Agent 1 arrives where agent 0 has finished and is waiting
+        let vertex = vec![vec![(b, 0)], vec![(a, 0), (a, 1), (b, 2), (c, 3)]];
+        assert_eq!(
+            find_first_conflict(&vertex),
+            Some(Conflict { agent_a: 0, agent_b: 1, time: 2, kind: ConflictKind::Vertex(b) })
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::algorithms::astar::{astar, AStarConfig};
+use crate::algorithms::jps::jps;
+use crate::algorithms::theta::theta_star;
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::heuristics::Zero;
+use crate::traits::{Heuristic, PathResult, PathStatus};
+
+// Heuristic weight for greedy best-first: large enough that the estimate alone orders the
+// open list, with path cost only breaking ties
+const GREEDY_WEIGHT: f32 = 1000.0;
+
+/// Grid search algorithm picked at runtime, e.g. from a data file. Run with `find_path_with`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    AStar,
+    /// Uniform-cost grids moving diagonally in `Always` or `IfNoObstacle` mode only (its
+    /// pruning assumes diagonals can cut corners); `UnsupportedGraph` otherwise.
+    Jps,
+    /// Any-angle. Shortcuts are priced with the heuristic, so pass `Euclidean`. Uniform-cost
+    /// grids only; `UnsupportedGraph` otherwise.
+    ThetaStar,
+    /// A* with the heuristic ignored.
+    Dijkstra,
+    /// Expands whatever looks closest to the goal: fast, but paths aren't optimal.
+    GreedyBestFirst,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] =
+        [Algorithm::AStar, Algorithm::Jps, Algorithm::ThetaStar, Algorithm::Dijkstra, Algorithm::GreedyBestFirst];
+}
+
+/// Object-safe form of `Heuristic<GridPos>`, so a heuristic chosen at runtime can be passed
+/// as `&dyn ErasedHeuristic`. Every grid heuristic implements it.
+pub trait ErasedHeuristic {
+    fn estimate_erased(&self, from: &GridPos, to: &GridPos) -> f32;
+    fn is_admissible_erased(&self) -> bool;
+}
+
+impl<H: Heuristic<GridPos>> ErasedHeuristic for H {
+    fn estimate_erased(&self, from: &GridPos, to: &GridPos) -> f32 {
+        self.estimate(from, to)
+    }
+
+    fn is_admissible_erased(&self) -> bool {
+        self.is_admissible()
+    }
+}
+
+// Back to a sized `Heuristic` for the generic searches
+struct Erased<'a>(&'a dyn ErasedHeuristic);
+
+impl Heuristic<GridPos> for Erased<'_> {
+    fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+        self.0.estimate_erased(from, to)
+    }
+
+    fn is_admissible(&self) -> bool {
+        self.0.is_admissible_erased()
+    }
+}
+
+/// Runs `algo` on `grid`. Algorithms whose preconditions `grid` breaks (weighted cells or
+/// directional costs for `Jps` and `ThetaStar`, strict or no diagonals for `Jps`) return
+/// `PathStatus::UnsupportedGraph` without searching. This is where the weighted-cell check
+/// lives, for both: it scans the whole grid, so only those two arms pay for it, and `jps`
+/// and `theta_star` called directly skip it. `GreedyBestFirst` overrides `config.heuristic_weight`.
+pub fn find_path_with(
+    algo: Algorithm,
+    grid: &Grid2D,
+    heuristic: &dyn ErasedHeuristic,
+    start: GridPos,
+    goal: GridPos,
+    config: AStarConfig,
+) -> PathResult<GridPos> {
+    let heuristic = Erased(heuristic);
+    let uniform = || grid.is_uniform() && !grid.has_directional_costs();
+    match algo {
+        Algorithm::AStar => astar(grid, &heuristic, start, goal, config),
+        Algorithm::Jps if uniform() => jps(grid, &heuristic, start, goal, config),
+        Algorithm::ThetaStar if uniform() => theta_star(grid, &heuristic, start, goal, config),
+        Algorithm::Jps | Algorithm::ThetaStar => PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: 0,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::UnsupportedGraph,
+            elapsed: None,
+        },
+        Algorithm::Dijkstra => astar(grid, &Zero, start, goal, config),
+        Algorithm::GreedyBestFirst => {
+            astar(grid, &heuristic, start, goal, AStarConfig { heuristic_weight: GREEDY_WEIGHT, ..config })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::{Euclidean, Octile};
+
+    #[test]
+    fn every_algorithm_over_one_fixture() {
+        // A wall with a gap at the bottom between start and goal
+        let mut grid = Grid2D::new(20, 20, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((10, 0, 1, 16), true);
+        let (start, goal) = (GridPos { x: 2, y: 3 }, GridPos { x: 17, y: 4 });
+        let optimal = astar(&grid, &Octile, start, goal, AStarConfig::default()).cost;
+
+        for algo in Algorithm::ALL {
+            let found = find_path_with(algo, &grid, &Euclidean, start, goal, AStarConfig::default());
+            assert_eq!(found.status, PathStatus::Found, "{algo:?}");
+            assert_eq!((found.path[0], *found.path.last().unwrap()), (start, goal), "{algo:?}");
+            match algo {
+                Algorithm::AStar | Algorithm::Dijkstra | Algorithm::Jps => {
+                    assert!((found.cost - optimal).abs() < 1e-3, "{algo:?}: {} vs {optimal}", found.cost)
+                }
+                Algorithm::ThetaStar => assert!(found.cost < optimal, "{} vs {optimal}", found.cost),
+                Algorithm::GreedyBestFirst => assert!(found.cost >= optimal - 1e-3),
+            }
+        }
+
+        // Greedy expands fewer nodes than Dijkstra on the same query
+        let run = |grid: &Grid2D, algo| find_path_with(algo, grid, &Octile, start, goal, AStarConfig::default());
+        assert!(run(&grid, Algorithm::GreedyBestFirst).nodes_expanded < run(&grid, Algorithm::Dijkstra).nodes_expanded);
+
+        // JPS pruning needs corner cutting
+        grid.diagonal_movement = DiagonalMode::OnlyIfBothOpen;
+        assert_eq!(run(&grid, Algorithm::Jps).status, PathStatus::UnsupportedGraph);
+        assert_eq!(run(&grid, Algorithm::ThetaStar).status, PathStatus::Found);
+        grid.diagonal_movement = DiagonalMode::IfNoObstacle;
+
+        // Weighted cells break the JPS and Theta* preconditions
+        grid.set_cost(5, 5, 3.0);
+        let weighted = astar(&grid, &Octile, start, goal, AStarConfig::default()).cost;
+        for algo in Algorithm::ALL {
+            let found = run(&grid, algo);
+            match algo {
+                Algorithm::Jps | Algorithm::ThetaStar => {
+                    assert_eq!(found.status, PathStatus::UnsupportedGraph, "{algo:?}");
+                    assert!(found.path.is_empty());
+                }
+                _ => assert_eq!(found.status, PathStatus::Found, "{algo:?}"),
+            }
+        }
+        assert!((run(&grid, Algorithm::Dijkstra).cost - weighted).abs() < 1e-3);
+    }
+}
@@ -1,11 +1,20 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
 
-use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
-use crate::traits::Graph;
+use rayon::prelude::*;
 
+pub use crate::graphs::grid2d::Direction;
+use crate::graphs::grid2d::{CellIndex, DiagonalMode, Grid2D, GridPos};
+use crate::graphs::grid3d::{Grid3D, GridPos3D};
+use crate::heuristics::{Diagonal, Landmark};
+use crate::traits::{Graph, Heuristic};
+
+/// Step toward the goal in a `FlowField3D`: the eight compass directions of `Direction`
+/// in the same layer, straight `Up` (+z) or `Down` (-z), and the compass directions one
+/// layer up or down.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-pub enum Direction {
+pub enum Direction3D {
     #[default]
     None,
     N,
@@ -16,155 +25,354 @@ pub enum Direction {
     SW,
     W,
     NW,
+    Up,
+    UpN,
+    UpNE,
+    UpE,
+    UpSE,
+    UpS,
+    UpSW,
+    UpW,
+    UpNW,
+    Down,
+    DownN,
+    DownNE,
+    DownE,
+    DownSE,
+    DownS,
+    DownSW,
+    DownW,
+    DownNW,
 }
 
-impl Direction {
-    fn to_vec2(self) -> (f32, f32) {
-        match self {
-            Direction::None => (0.0, 0.0),
-            Direction::N => (0.0, -1.0),
-            Direction::NE => (1.0, -1.0),
-            Direction::E => (1.0, 0.0),
-            Direction::SE => (1.0, 1.0),
-            Direction::S => (0.0, 1.0),
-            Direction::SW => (-1.0, 1.0),
-            Direction::W => (-1.0, 0.0),
-            Direction::NW => (-1.0, -1.0),
-        }
+impl Direction3D {
+    // Every direction with its voxel offset, same-layer first
+    const ALL: [(i32, i32, i32, Direction3D); 26] = [
+        (0, -1, 0, Direction3D::N),
+        (1, -1, 0, Direction3D::NE),
+        (1, 0, 0, Direction3D::E),
+        (1, 1, 0, Direction3D::SE),
+        (0, 1, 0, Direction3D::S),
+        (-1, 1, 0, Direction3D::SW),
+        (-1, 0, 0, Direction3D::W),
+        (-1, -1, 0, Direction3D::NW),
+        (0, 0, 1, Direction3D::Up),
+        (0, -1, 1, Direction3D::UpN),
+        (1, -1, 1, Direction3D::UpNE),
+        (1, 0, 1, Direction3D::UpE),
+        (1, 1, 1, Direction3D::UpSE),
+        (0, 1, 1, Direction3D::UpS),
+        (-1, 1, 1, Direction3D::UpSW),
+        (-1, 0, 1, Direction3D::UpW),
+        (-1, -1, 1, Direction3D::UpNW),
+        (0, 0, -1, Direction3D::Down),
+        (0, -1, -1, Direction3D::DownN),
+        (1, -1, -1, Direction3D::DownNE),
+        (1, 0, -1, Direction3D::DownE),
+        (1, 1, -1, Direction3D::DownSE),
+        (0, 1, -1, Direction3D::DownS),
+        (-1, 1, -1, Direction3D::DownSW),
+        (-1, 0, -1, Direction3D::DownW),
+        (-1, -1, -1, Direction3D::DownNW),
+    ];
+
+    /// Voxel offset of one step, like `Direction::to_vec2`: not normalized.
+    pub fn to_vec3(self) -> (f32, f32, f32) {
+        Self::ALL
+            .iter()
+            .find(|d| d.3 == self)
+            .map_or((0.0, 0.0, 0.0), |&(dx, dy, dz, _)| (dx as f32, dy as f32, dz as f32))
     }
 }
 
-#[derive(Clone)]
-pub struct FlowField {
+/// Cost of the cheapest walk from every cell to the nearest source (multi-source Dijkstra).
+/// Blocked and unreachable cells are `f32::INFINITY`.
+#[derive(Clone, Debug)]
+pub struct DistanceField {
     pub width: usize,
     pub height: usize,
-    pub integration: Vec<f32>, // cost-to-goal
-    pub flow: Vec<Direction>,  // best step toward goal
+    pub distances: Vec<f32>,
+    diagonal_movement: DiagonalMode,
 }
 
-impl FlowField {
-    pub fn compute(grid: &Grid2D, goal: GridPos) -> Self {
-        let width = grid.width;
-        let height = grid.height;
-        let len = width * height;
-        let mut integration = vec![f32::INFINITY; len];
-        let mut flow = vec![Direction::None; len];
-        let mut visited = vec![false; len];
+impl DistanceField {
+    /// Sources outside the grid or on blocked cells are ignored.
+    pub fn compute(grid: &Grid2D, sources: &[GridPos]) -> Self {
+        // `is_blocked` also rejects out-of-bounds positions
+        let sources = sources.iter().copied().filter(|s| !grid.is_blocked(s.x, s.y));
+        let capacity = grid.node_count_hint().unwrap_or(0);
+        let distances = integrate(grid.width, grid.height, capacity, sources, |pos, relax| {
+            grid.neighbors(&pos, |n, _| relax(n, grid.entry_cost(&pos, pos.x - n.x, pos.y - n.y)));
+        });
+        Self {
+            width: grid.width,
+            height: grid.height,
+            distances,
+            diagonal_movement: grid.diagonal_movement,
+        }
+    }
 
-        if goal.x < 0
-            || goal.y < 0
-            || goal.x as usize >= width
-            || goal.y as usize >= height
-            || grid.is_blocked(goal.x, goal.y)
-        {
-            return Self {
-                width,
-                height,
-                integration,
-                flow,
-            };
+    /// Costs to and from `landmark` for every cell of `grid`, for `AltHeuristic`: the
+    /// distance field toward it, and one Dijkstra outward from it.
+    pub fn landmark(grid: &Grid2D, landmark: GridPos) -> Landmark {
+        let to_landmark = Self::compute(grid, &[landmark]).distances;
+        let source = (!grid.is_blocked(landmark.x, landmark.y)).then_some(landmark);
+        let capacity = grid.node_count_hint().unwrap_or(0);
+        let from_landmark = integrate(grid.width, grid.height, capacity, source.into_iter(), |pos, relax| {
+            grid.neighbors(&pos, relax);
+        });
+        Landmark { width: grid.width, height: grid.height, to_landmark, from_landmark }
+    }
+
+    /// `compute` over a snapshot of the grid, with identical results.
+    pub fn compute_from_snapshot(snapshot: &GridSnapshot, sources: &[GridPos]) -> Self {
+        let width = snapshot.width;
+        let sources = sources.iter().copied().filter(|s| snapshot.is_passable(*s));
+        let distances = integrate(width, snapshot.height, snapshot.passable.len(), sources, |pos, relax| {
+            let idx = FlowField::idx(width, pos.x as usize, pos.y as usize);
+            let (from, to) = (snapshot.offsets[idx] as usize, snapshot.offsets[idx + 1] as usize);
+            for &(n_idx, step) in &snapshot.steps[from..to] {
+                let n_idx = n_idx as usize;
+                relax(CellIndex(n_idx).to_pos(width), step);
+            }
+        });
+        Self {
+            width,
+            height: snapshot.height,
+            distances,
+            diagonal_movement: snapshot.diagonal_movement,
         }
+    }
 
-        let mut frontier = BinaryHeap::new();
-        let goal_idx = Self::idx(width, goal.x as usize, goal.y as usize);
-        integration[goal_idx] = 0.0;
+    /// Distance to the nearest source; `f32::INFINITY` outside the grid.
+    #[inline]
+    pub fn get(&self, pos: GridPos) -> f32 {
+        CellIndex::of(pos, self.width, self.height).map_or(f32::INFINITY, |cell| self.distances[cell.0])
+    }
+
+    /// Neighbor with the lowest distance, if it is lower than the distance at `pos`.
+    /// None at a source or on unreachable cells.
+    pub fn argmin_neighbor(&self, pos: GridPos) -> Option<GridPos> {
+        self.argmin_step(pos).map(|(n, _)| n)
+    }
+
+    // `argmin_neighbor` plus the direction of the step
+    fn argmin_step(&self, pos: GridPos) -> Option<(GridPos, Direction)> {
+        let mut best = None;
+        let mut best_cost = self.get(pos);
+        if best_cost.is_infinite() {
+            return None;
+        }
+        for &(dx, dy, dir) in FlowField::neighbor_dirs(self.diagonal_movement) {
+            let n = GridPos { x: pos.x + dx, y: pos.y + dy };
+            let n_cost = self.get(n);
+            if n_cost < best_cost {
+                best_cost = n_cost;
+                best = Some((n, dir));
+            }
+        }
+        best
+    }
+
+    /// Exact cost-to-go heuristic for searches toward `goal`, which should be the field's
+    /// only source. Admissible and consistent; estimates toward any other node are 0.
+    pub fn into_heuristic(self, goal: GridPos) -> DistanceHeuristic {
+        DistanceHeuristic { field: self, goal }
+    }
+}
+
+// Dijkstra outward from `sources` over a `width` x `height` grid. `steps_into(pos, relax)`
+// calls `relax(n, cost)` for each neighbor `n` of `pos` with the cost of stepping from `n`
+// into `pos`; neighbors are symmetric on a grid, so this is the cost to reach a source.
+fn integrate<I, F>(width: usize, height: usize, cells: usize, sources: I, mut steps_into: F) -> Vec<f32>
+where
+    I: Iterator<Item = GridPos>,
+    F: FnMut(GridPos, &mut dyn FnMut(GridPos, f32)),
+{
+    let len = width * height;
+    let mut distances = vec![f32::INFINITY; len];
+    let mut visited = vec![false; len];
+    // The frontier of a grid Dijkstra stays around the perimeter of the explored area
+    let mut frontier = BinaryHeap::with_capacity(cells.min(4 * (width + height)));
+
+    for source in sources {
+        distances[FlowField::idx(width, source.x as usize, source.y as usize)] = 0.0;
         frontier.push(State {
             cost: 0.0,
-            pos: goal,
+            pos: source,
         });
+    }
 
-        // Dijkstra from goal outward (integration field)
-        while let Some(State { cost, pos }) = frontier.pop() {
-            let idx = Self::idx(width, pos.x as usize, pos.y as usize);
-            if visited[idx] || cost > integration[idx] {
-                continue;
+    while let Some(State { cost, pos }) = frontier.pop() {
+        let idx = FlowField::idx(width, pos.x as usize, pos.y as usize);
+        if visited[idx] || cost > distances[idx] {
+            continue;
+        }
+        visited[idx] = true;
+
+        steps_into(pos, &mut |n, step| {
+            let next_cost = cost + step;
+            let n_idx = FlowField::idx(width, n.x as usize, n.y as usize);
+            if next_cost < distances[n_idx] {
+                distances[n_idx] = next_cost;
+                frontier.push(State {
+                    cost: next_cost,
+                    pos: n,
+                });
             }
-            visited[idx] = true;
+        });
+    }
+    distances
+}
 
-            grid.neighbors(&pos, |n, edge_cost| {
-                if grid.is_blocked(n.x, n.y) {
-                    return;
-                }
-                let next_cost = cost + edge_cost;
-                if n.x < 0
-                    || n.y < 0
-                    || n.x as usize >= width
-                    || n.y as usize >= height
-                {
-                    return;
-                }
-                let n_idx = Self::idx(width, n.x as usize, n.y as usize);
-                if next_cost < integration[n_idx] {
-                    integration[n_idx] = next_cost;
-                    frontier.push(State {
-                        cost: next_cost,
-                        pos: n,
-                    });
-                }
-            });
-        }
+/// Compact copy of a `Grid2D`'s passability and step costs, for computing many
+/// `DistanceField`s or `FlowField`s without re-reading the grid's cells, corner rules and
+/// directional costs each time. Later edits to the grid don't show up in the snapshot.
+#[derive(Clone, Debug)]
+pub struct GridSnapshot {
+    pub width: usize,
+    pub height: usize,
+    diagonal_movement: DiagonalMode,
+    passable: Vec<bool>,
+    // Cell `i`'s steps in are `steps[offsets[i]..offsets[i + 1]]`: (neighbor index, cost of
+    // stepping from the neighbor into `i`), in `Grid2D::neighbors` order
+    offsets: Vec<u32>,
+    steps: Vec<(u32, f32)>,
+}
 
-        // Flow pass: choose neighbor with lowest integration value
-        for y in 0..height {
-            for x in 0..width {
-                let idx = Self::idx(width, x, y);
-                if integration[idx].is_infinite() || grid.is_blocked(x as i32, y as i32) {
-                    continue;
-                }
-                let mut best_dir = Direction::None;
-                let mut best_cost = integration[idx];
-                for &(dx, dy, dir) in Self::neighbor_dirs(grid.diagonal_movement) {
-                    let nx = x as i32 + dx;
-                    let ny = y as i32 + dy;
-                    if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
-                        continue;
-                    }
-                    if grid.is_blocked(nx, ny) {
-                        continue;
-                    }
-                    let n_idx = Self::idx(width, nx as usize, ny as usize);
-                    let n_cost = integration[n_idx];
-                    if n_cost < best_cost {
-                        best_cost = n_cost;
-                        best_dir = dir;
-                    }
-                }
-                flow[idx] = best_dir;
+impl GridSnapshot {
+    pub fn new(grid: &Grid2D) -> Self {
+        let len = grid.width * grid.height;
+        let mut passable = Vec::with_capacity(len);
+        let mut offsets = Vec::with_capacity(len + 1);
+        let mut steps = Vec::with_capacity(len * 4);
+        offsets.push(0);
+        for idx in 0..len {
+            let pos = GridPos { x: (idx % grid.width) as i32, y: (idx / grid.width) as i32 };
+            let open = !grid.is_blocked(pos.x, pos.y);
+            passable.push(open);
+            if open {
+                grid.neighbors(&pos, |n, _| {
+                    let n_idx = FlowField::idx(grid.width, n.x as usize, n.y as usize);
+                    steps.push((n_idx as u32, grid.entry_cost(&pos, pos.x - n.x, pos.y - n.y)));
+                });
             }
+            offsets.push(steps.len() as u32);
+        }
+        Self {
+            width: grid.width,
+            height: grid.height,
+            diagonal_movement: grid.diagonal_movement,
+            passable,
+            offsets,
+            steps,
+        }
+    }
+
+    /// False outside the grid.
+    pub fn is_passable(&self, pos: GridPos) -> bool {
+        CellIndex::of(pos, self.width, self.height).is_some_and(|cell| self.passable[cell.0])
+    }
+}
+
+/// Heuristic backed by a `DistanceField`, see `DistanceField::into_heuristic`.
+#[derive(Clone, Debug)]
+pub struct DistanceHeuristic {
+    pub field: DistanceField,
+    pub goal: GridPos,
+}
+
+impl Heuristic<GridPos> for DistanceHeuristic {
+    fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+        if *to == self.goal {
+            self.field.get(*from)
+        } else {
+            0.0
         }
+    }
+}
+
+/// `cost_to_goal(from) - cost_to_goal(to)`, clamped at 0: by the triangle inequality a lower
+/// bound on the cost from `from` to `to` on the grid the field was computed on. Nearly exact
+/// when `to` lies between `from` and the field's goal. Where either cost is infinite
+/// (unreachable or out of bounds) `fallback` is used instead.
+#[derive(Clone, Copy)]
+pub struct FlowFieldHeuristic<'a> {
+    pub field: &'a FlowField,
+    pub fallback: Diagonal,
+}
+
+impl Heuristic<GridPos> for FlowFieldHeuristic<'_> {
+    fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+        let (a, b) = (self.field.get_cost_to_goal(*from), self.field.get_cost_to_goal(*to));
+        if a.is_finite() && b.is_finite() {
+            (a - b).max(0.0)
+        } else {
+            self.fallback.estimate(from, to)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FlowField {
+    pub width: usize,
+    pub height: usize,
+    pub integration: Vec<f32>, // cost-to-goal
+    pub flow: Vec<Direction>,  // best step toward goal
+    /// Time `compute` took, as for `PathResult::elapsed`; None without the `timing` feature.
+    pub elapsed: Option<Duration>,
+}
+
+impl FlowField {
+    pub fn compute(grid: &Grid2D, goal: GridPos) -> Self {
+        let started = cfg!(feature = "timing").then(Instant::now);
+        let field = Self::from_distances(DistanceField::compute(grid, &[goal]));
+        Self { elapsed: started.map(|s| s.elapsed()), ..field }
+    }
+
+    /// `compute` over a snapshot of the grid, with identical results.
+    pub fn compute_from_snapshot(snapshot: &GridSnapshot, goal: GridPos) -> Self {
+        let started = cfg!(feature = "timing").then(Instant::now);
+        let field = Self::from_distances(DistanceField::compute_from_snapshot(snapshot, &[goal]));
+        Self { elapsed: started.map(|s| s.elapsed()), ..field }
+    }
+
+    /// One field per goal, in order, computed in parallel over a single `GridSnapshot`
+    /// of the grid. Each equals `compute(grid, goal)`.
+    pub fn compute_batch(grid: &Grid2D, goals: &[GridPos]) -> Vec<FlowField> {
+        let snapshot = GridSnapshot::new(grid);
+        goals.par_iter().map(|&goal| Self::compute_from_snapshot(&snapshot, goal)).collect()
+    }
+
+    fn from_distances(field: DistanceField) -> Self {
+        // Flow pass: choose neighbor with lowest integration value
+        let flow = (0..field.width * field.height)
+            .map(|idx| field.argmin_step(CellIndex(idx).to_pos(field.width)).map_or(Direction::None, |(_, dir)| dir))
+            .collect();
 
         Self {
-            width,
-            height,
-            integration,
+            width: field.width,
+            height: field.height,
+            integration: field.distances,
             flow,
+            elapsed: None,
         }
     }
 
     #[inline]
     pub fn get_direction(&self, pos: GridPos) -> Direction {
-        if pos.x < 0
-            || pos.y < 0
-            || pos.x as usize >= self.width
-            || pos.y as usize >= self.height
-        {
-            return Direction::None;
-        }
-        let idx = Self::idx(self.width, pos.x as usize, pos.y as usize);
-        self.flow[idx]
+        CellIndex::of(pos, self.width, self.height).map_or(Direction::None, |cell| self.flow[cell.0])
     }
 
     #[inline]
     pub fn get_cost_to_goal(&self, pos: GridPos) -> f32 {
-        if pos.x < 0
-            || pos.y < 0
-            || pos.x as usize >= self.width
-            || pos.y as usize >= self.height
-        {
-            return f32::INFINITY;
-        }
-        let idx = Self::idx(self.width, pos.x as usize, pos.y as usize);
-        self.integration[idx]
+        CellIndex::of(pos, self.width, self.height).map_or(f32::INFINITY, |cell| self.integration[cell.0])
+    }
+
+    /// Differential heuristic for searches toward goals near this field's goal, e.g. many
+    /// units heading for spots around one stronghold. See `FlowFieldHeuristic`.
+    pub fn as_heuristic(&self) -> FlowFieldHeuristic<'_> {
+        FlowFieldHeuristic { field: self, fallback: Diagonal::default() }
     }
 
     /// Returns a smoothed flow vector using bilinear sampling of the 4 surrounding cells.
@@ -200,6 +408,176 @@ impl FlowField {
         (lerp(vx0, vx1, fy), lerp(vy0, vy1, fy))
     }
 
+    /// Repulsion from nearby agents for local avoidance.
+    ///
+    /// `agents` are the *other* agents' positions (exclude the querying agent). Each agent
+    /// within `separation_radius` of `query_pos` pushes away with strength inversely
+    /// proportional to its distance. Exactly coincident agents push along a fixed
+    /// per-index direction so stacked agents still spread out.
+    pub fn compute_separation_force(
+        &self,
+        agents: &[(f32, f32)],
+        query_pos: (f32, f32),
+        separation_radius: f32,
+    ) -> (f32, f32) {
+        // Avoid unbounded forces for (nearly) coincident agents
+        let min_dist = separation_radius * 0.01;
+        let mut force = (0.0, 0.0);
+        for (i, &(ax, ay)) in agents.iter().enumerate() {
+            let dx = query_pos.0 - ax;
+            let dy = query_pos.1 - ay;
+            let dist = (dx * dx + dy * dy).sqrt();
+            if dist >= separation_radius {
+                continue;
+            }
+            let (nx, ny) = if dist > f32::EPSILON {
+                (dx / dist, dy / dist)
+            } else {
+                let angle = i as f32 * GOLDEN_ANGLE;
+                (angle.cos(), angle.sin())
+            };
+            let strength = 1.0 / dist.max(min_dist);
+            force.0 += nx * strength;
+            force.1 += ny * strength;
+        }
+        force
+    }
+
+    /// Flow direction at `pos` (bilinear) plus `separation_weight` times the separation force.
+    pub fn combined_steering_force(
+        &self,
+        agents: &[(f32, f32)],
+        pos: (f32, f32),
+        separation_radius: f32,
+        separation_weight: f32,
+    ) -> (f32, f32) {
+        let flow = self.sample_bilinear(pos.0, pos.1);
+        let sep = self.compute_separation_force(agents, pos, separation_radius);
+        (flow.0 + sep.0 * separation_weight, flow.1 + sep.1 * separation_weight)
+    }
+
+    /// Indices into `path` of the cells whose flow turns by more than 45 degrees from the
+    /// previous cell's: where the field makes a routing decision (around a corner, through
+    /// a gap). Cells without a direction (the goal, blocked or unreachable cells) are skipped.
+    pub fn portals_along_path(&self, path: &[GridPos]) -> Vec<usize> {
+        let cos_45 = std::f32::consts::FRAC_1_SQRT_2 - 1e-4;
+        (1..path.len())
+            .filter(|&i| {
+                let (a, b) = (self.unit_direction(path[i - 1]), self.unit_direction(path[i]));
+                a.zip(b).is_some_and(|(a, b)| a.0 * b.0 + a.1 * b.1 < cos_45)
+            })
+            .collect()
+    }
+
+    /// How closely `path` follows the field: the mean, over its steps, of the cosine between
+    /// the step and the flow at the cell it leaves. 1.0 goes with the flow all the way, -1.0
+    /// straight against it, 0.0 across it. Steps from cells without a direction don't count;
+    /// 0.0 if no step does.
+    pub fn path_alignment_score(&self, path: &[GridPos]) -> f32 {
+        let (sum, steps) = path
+            .windows(2)
+            .filter_map(|w| {
+                let (fx, fy) = self.unit_direction(w[0])?;
+                let (dx, dy) = ((w[1].x - w[0].x) as f32, (w[1].y - w[0].y) as f32);
+                let len = (dx * dx + dy * dy).sqrt();
+                (len > 0.0).then(|| (dx * fx + dy * fy) / len)
+            })
+            .fold((0.0, 0), |(sum, n), cos| (sum + cos, n + 1));
+        if steps == 0 { 0.0 } else { sum / steps as f32 }
+    }
+
+    /// Curl `dFy/dx - dFx/dy` of the unit flow vectors at every cell (row-major), by central
+    /// differences, or one-sided ones beside walls and the map edge. Positive where the flow
+    /// turns clockwise as drawn with y pointing down. A field that flows straight to its
+    /// goal has little curl; large values mark vortices that can trap agents. 0 on cells
+    /// that can't reach the goal.
+    pub fn compute_curl(&self) -> Vec<f32> {
+        self.per_cell_derivative(|d_dx, d_dy| d_dx.1 - d_dy.0)
+    }
+
+    /// Cells whose curl (see `compute_curl`) exceeds `threshold` in magnitude, row-major.
+    pub fn find_vortex_cells(&self, threshold: f32) -> Vec<GridPos> {
+        let curl = self.compute_curl();
+        let vortex = |idx: &usize| curl[*idx].abs() > threshold;
+        (0..curl.len()).filter(vortex).map(|idx| CellIndex(idx).to_pos(self.width)).collect()
+    }
+
+    /// Divergence `dFx/dx + dFy/dy` of the unit flow vectors, differenced as in `compute_curl`.
+    /// Negative where flow converges (most of all around the goal), positive where it splits,
+    /// e.g. on the ridge behind an obstacle where routes around either side part ways.
+    pub fn divergence(&self) -> Vec<f32> {
+        self.per_cell_derivative(|d_dx, d_dy| d_dx.0 + d_dy.1)
+    }
+
+    // `combine(d/dx, d/dy)` of the flow at each cell; 0 on cells that can't reach the goal
+    fn per_cell_derivative<F>(&self, combine: F) -> Vec<f32>
+    where
+        F: Fn((f32, f32), (f32, f32)) -> f32,
+    {
+        (0..self.width * self.height)
+            .map(|idx| {
+                let pos = CellIndex(idx).to_pos(self.width);
+                if self.flow_sample(pos).is_none() {
+                    return 0.0;
+                }
+                combine(self.axis_derivative(pos, (1, 0)), self.axis_derivative(pos, (0, 1)))
+            })
+            .collect()
+    }
+
+    // Derivative of the flow along (dx, dy): central where both neighbors have a sample,
+    // one-sided where only one does
+    fn axis_derivative(&self, pos: GridPos, (dx, dy): (i32, i32)) -> (f32, f32) {
+        let before = self.flow_sample(GridPos { x: pos.x - dx, y: pos.y - dy });
+        let after = self.flow_sample(GridPos { x: pos.x + dx, y: pos.y + dy });
+        let here = self.flow_sample(pos).unwrap_or((0.0, 0.0));
+        match (before, after) {
+            (Some(a), Some(b)) => ((b.0 - a.0) / 2.0, (b.1 - a.1) / 2.0),
+            (Some(a), None) => (here.0 - a.0, here.1 - a.1),
+            (None, Some(b)) => (b.0 - here.0, b.1 - here.1),
+            (None, None) => (0.0, 0.0),
+        }
+    }
+
+    // Unit flow at `pos` (zero at the goal); None off the grid and where the goal is unreachable
+    fn flow_sample(&self, pos: GridPos) -> Option<(f32, f32)> {
+        self.get_cost_to_goal(pos).is_finite().then(|| self.unit_direction(pos).unwrap_or((0.0, 0.0)))
+    }
+
+    /// Blends fields over the same grid, e.g. toward an objective and away from threats:
+    /// per cell, the weighted sum of each field's unit flow direction and of its cost to
+    /// goal. A negative weight pushes away from that field's goal. Cells any field can't
+    /// reach count as blocked, with a zero vector and infinite potential.
+    ///
+    /// Panics if the fields differ in size.
+    pub fn combine(fields: &[(&FlowField, f32)]) -> CombinedFlow {
+        let (width, height) = fields.first().map_or((0, 0), |(f, _)| (f.width, f.height));
+        assert!(
+            fields.iter().all(|(f, _)| f.width == width && f.height == height),
+            "combined flow fields must share a grid size"
+        );
+        let (vectors, potential) = (0..width * height)
+            .map(|idx| {
+                if fields.iter().any(|(f, _)| f.integration[idx].is_infinite()) {
+                    return ((0.0, 0.0), f32::INFINITY);
+                }
+                fields.iter().fold(((0.0, 0.0), 0.0), |((vx, vy), p), &(field, w)| {
+                    let (x, y) = field.flow[idx].to_vec2();
+                    let len = (x * x + y * y).sqrt().max(1.0);
+                    ((vx + x / len * w, vy + y / len * w), p + field.integration[idx] * w)
+                })
+            })
+            .unzip();
+        CombinedFlow { width, height, vectors, potential }
+    }
+
+    // Normalized flow at `pos`; None where there is no direction
+    fn unit_direction(&self, pos: GridPos) -> Option<(f32, f32)> {
+        let (x, y) = self.get_direction(pos).to_vec2();
+        let len = (x * x + y * y).sqrt();
+        (len > 0.0).then(|| (x / len, y / len))
+    }
+
     #[inline]
     fn idx(width: usize, x: usize, y: usize) -> usize {
         y * width + x
@@ -227,33 +605,285 @@ impl FlowField {
     }
 }
 
+
+/// Independent flow fields over different grids, e.g. one per agent type
+/// (ground units vs. flyers that ignore ground obstacles).
+#[derive(Clone)]
+pub struct LayeredFlowField {
+    pub layers: Vec<FlowField>,
+    pub layer_names: Vec<String>, // Defaults to "layer0", "layer1", ...
+}
+
+impl LayeredFlowField {
+    /// Computes one field per (grid, goal) pair in parallel.
+    pub fn compute(layers: Vec<(&Grid2D, GridPos)>) -> Self {
+        let fields: Vec<FlowField> = layers
+            .par_iter()
+            .map(|&(grid, goal)| FlowField::compute(grid, goal))
+            .collect();
+        let layer_names = (0..fields.len()).map(|i| format!("layer{}", i)).collect();
+        Self {
+            layers: fields,
+            layer_names,
+        }
+    }
+
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layer_names.iter().position(|n| n == name)
+    }
+
+    /// `Direction::None` for an unknown layer.
+    pub fn get_direction(&self, layer: usize, pos: GridPos) -> Direction {
+        self.layers
+            .get(layer)
+            .map_or(Direction::None, |f| f.get_direction(pos))
+    }
+
+    /// Zero vector for an unknown layer.
+    pub fn sample_bilinear(&self, layer: usize, x: f32, y: f32) -> (f32, f32) {
+        self.layers
+            .get(layer)
+            .map_or((0.0, 0.0), |f| f.sample_bilinear(x, y))
+    }
+
+    /// Weighted average of the layers' directions at `pos`. Missing weights count as 0.
+    pub fn composite_direction(&self, pos: GridPos, weights: &[f32]) -> (f32, f32) {
+        let mut sum = (0.0, 0.0);
+        let mut total = 0.0;
+        for (field, &w) in self.layers.iter().zip(weights) {
+            let v = field.get_direction(pos).to_vec2();
+            sum.0 += v.0 * w;
+            sum.1 += v.1 * w;
+            total += w;
+        }
+        if total == 0.0 {
+            return (0.0, 0.0);
+        }
+        (sum.0 / total, sum.1 / total)
+    }
+}
+
+/// Weighted blend of several `FlowField`s, from `FlowField::combine`.
+#[derive(Clone, Debug)]
+pub struct CombinedFlow {
+    pub width: usize,
+    pub height: usize,
+    pub vectors: Vec<(f32, f32)>, // Weighted sum of unit flow directions
+    pub potential: Vec<f32>,      // Weighted sum of costs to goal; infinite where blocked
+}
+
+impl CombinedFlow {
+    /// Blended vector at `pos`; zero outside the grid and on blocked cells.
+    pub fn get_vector(&self, pos: GridPos) -> (f32, f32) {
+        self.index(pos).map_or((0.0, 0.0), |idx| self.vectors[idx])
+    }
+
+    /// Blended potential at `pos`; `f32::INFINITY` outside the grid and on blocked cells.
+    pub fn get_potential(&self, pos: GridPos) -> f32 {
+        self.index(pos).map_or(f32::INFINITY, |idx| self.potential[idx])
+    }
+
+    /// The compass direction closest to the blended vector at `pos` that leads to an open
+    /// cell; diagonals also need both cells beside them open. `Direction::None` where the
+    /// vector (nearly) cancels out, on blocked cells and where no step points within 90
+    /// degrees of it.
+    pub fn get_direction(&self, pos: GridPos) -> Direction {
+        let (vx, vy) = self.get_vector(pos);
+        if vx * vx + vy * vy < 1e-6 {
+            return Direction::None;
+        }
+        let open = |dx: i32, dy: i32| self.get_potential(GridPos { x: pos.x + dx, y: pos.y + dy }).is_finite();
+        let mut best = (Direction::None, 0.0);
+        for &(dx, dy, dir) in FlowField::neighbor_dirs(DiagonalMode::Always) {
+            if !open(dx, dy) || (dx != 0 && dy != 0 && !(open(dx, 0) && open(0, dy))) {
+                continue;
+            }
+            let (x, y) = dir.to_vec2();
+            let alignment = (x * vx + y * vy) / (x * x + y * y).sqrt();
+            if alignment > best.1 {
+                best = (dir, alignment);
+            }
+        }
+        best.0
+    }
+
+    /// Bilinear interpolation of the blended vectors of the 4 surrounding cells, as
+    /// `FlowField::sample_bilinear`.
+    pub fn sample_bilinear(&self, x: f32, y: f32) -> (f32, f32) {
+        if x < 0.0 || y < 0.0 {
+            return (0.0, 0.0);
+        }
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        if x0 + 1 >= self.width as i32 || y0 + 1 >= self.height as i32 {
+            return (0.0, 0.0);
+        }
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let v00 = self.get_vector(GridPos { x: x0, y: y0 });
+        let v10 = self.get_vector(GridPos { x: x0 + 1, y: y0 });
+        let v01 = self.get_vector(GridPos { x: x0, y: y0 + 1 });
+        let v11 = self.get_vector(GridPos { x: x0 + 1, y: y0 + 1 });
+        let top = (lerp(v00.0, v10.0, fx), lerp(v00.1, v10.1, fx));
+        let bottom = (lerp(v01.0, v11.0, fx), lerp(v01.1, v11.1, fx));
+        (lerp(top.0, bottom.0, fy), lerp(top.1, bottom.1, fy))
+    }
+
+    fn index(&self, pos: GridPos) -> Option<usize> {
+        CellIndex::of(pos, self.width, self.height).map(|cell| cell.0)
+    }
+}
+
+/// `FlowField` for a `Grid3D`, over all 26 neighbors rather than the grid's own 6. A step
+/// costs its length (1, sqrt 2 or sqrt 3) times the cost of the voxel it enters, and may
+/// not cut corners: every voxel in the box it spans must be passable.
+#[derive(Clone)]
+pub struct FlowField3D {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub integration: Vec<f32>,  // cost-to-goal
+    pub flow: Vec<Direction3D>, // best step toward goal
+}
+
+impl FlowField3D {
+    pub fn compute(grid: &Grid3D, goal: GridPos3D) -> Self {
+        let (width, height, depth) = (grid.width, grid.height, grid.depth);
+        let len = width * height * depth;
+        let mut integration = vec![f32::INFINITY; len];
+        let mut visited = vec![false; len];
+        let mut frontier = BinaryHeap::new();
+        let mut field = Self { width, height, depth, integration: Vec::new(), flow: Vec::new() };
+
+        if grid.is_passable(&goal) {
+            integration[field.idx(goal).unwrap()] = 0.0;
+            frontier.push(State { cost: 0.0, pos: goal });
+        }
+        // Steps are symmetric, so each neighbor `n` is relaxed with the cost of stepping into `pos`
+        while let Some(State { cost, pos }) = frontier.pop() {
+            let idx = field.idx(pos).unwrap();
+            if visited[idx] || cost > integration[idx] {
+                continue;
+            }
+            visited[idx] = true;
+            for &(dx, dy, dz, _) in &Direction3D::ALL {
+                let n = GridPos3D { x: pos.x + dx, y: pos.y + dy, z: pos.z + dz };
+                if !Self::can_step(grid, n, (-dx, -dy, -dz)) {
+                    continue;
+                }
+                let step = ((dx * dx + dy * dy + dz * dz) as f32).sqrt();
+                let next_cost = cost + step * grid.get_cost(pos.x, pos.y, pos.z);
+                let n_idx = field.idx(n).unwrap();
+                if next_cost < integration[n_idx] {
+                    integration[n_idx] = next_cost;
+                    frontier.push(State { cost: next_cost, pos: n });
+                }
+            }
+        }
+        field.integration = integration;
+
+        // Flow pass: the reachable neighbor with the lowest integration value
+        field.flow = (0..len)
+            .map(|idx| {
+                let pos = GridPos3D {
+                    x: (idx % width) as i32,
+                    y: (idx / width % height) as i32,
+                    z: (idx / (width * height)) as i32,
+                };
+                let mut best = (field.integration[idx], Direction3D::None);
+                for &(dx, dy, dz, dir) in &Direction3D::ALL {
+                    let n = GridPos3D { x: pos.x + dx, y: pos.y + dy, z: pos.z + dz };
+                    let n_cost = field.get_cost_to_goal(n);
+                    if n_cost < best.0 && Self::can_step(grid, pos, (dx, dy, dz)) {
+                        best = (n_cost, dir);
+                    }
+                }
+                best.1
+            })
+            .collect();
+        field
+    }
+
+    #[inline]
+    pub fn get_direction(&self, pos: GridPos3D) -> Direction3D {
+        self.idx(pos).map_or(Direction3D::None, |idx| self.flow[idx])
+    }
+
+    #[inline]
+    pub fn get_cost_to_goal(&self, pos: GridPos3D) -> f32 {
+        self.idx(pos).map_or(f32::INFINITY, |idx| self.integration[idx])
+    }
+
+    /// Smoothed flow vector from trilinear sampling of the 8 surrounding voxels.
+    pub fn sample_trilinear(&self, x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+        if x < 0.0 || y < 0.0 || z < 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let (x0, y0, z0) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        if x0 as usize + 1 >= self.width || y0 as usize + 1 >= self.height || z0 as usize + 1 >= self.depth {
+            return (0.0, 0.0, 0.0);
+        }
+        let (fx, fy, fz) = (x - x0 as f32, y - y0 as f32, z - z0 as f32);
+
+        let mut sum = (0.0, 0.0, 0.0);
+        for (dx, dy, dz) in (0..8).map(|corner| (corner & 1, (corner >> 1) & 1, corner >> 2)) {
+            let v = self.get_direction(GridPos3D { x: x0 + dx, y: y0 + dy, z: z0 + dz }).to_vec3();
+            let weight = lerp(1.0 - fx, fx, dx as f32) * lerp(1.0 - fy, fy, dy as f32) * lerp(1.0 - fz, fz, dz as f32);
+            sum.0 += v.0 * weight;
+            sum.1 += v.1 * weight;
+            sum.2 += v.2 * weight;
+        }
+        sum
+    }
+
+    fn idx(&self, pos: GridPos3D) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.z < 0 {
+            return None;
+        }
+        let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+        if x >= self.width || y >= self.height || z >= self.depth {
+            return None;
+        }
+        Some((z * self.height + y) * self.width + x)
+    }
+
+    // Whether `from` and every voxel of the box spanned by the step `(dx, dy, dz)` are passable
+    fn can_step(grid: &Grid3D, from: GridPos3D, (dx, dy, dz): (i32, i32, i32)) -> bool {
+        (0..8).all(|corner| {
+            let (ox, oy, oz) = (corner & 1, (corner >> 1) & 1, corner >> 2);
+            !grid.is_blocked(from.x + dx * ox, from.y + dy * oy, from.z + dz * oz)
+        })
+    }
+}
+
 #[derive(Copy, Clone)]
-struct State {
+struct State<P = GridPos> {
     cost: f32,
-    pos: GridPos,
+    pos: P,
 }
 
-impl PartialEq for State {
+impl<P> PartialEq for State<P> {
     fn eq(&self, other: &Self) -> bool {
         self.cost == other.cost
     }
 }
 
-impl Eq for State {}
+impl<P> Eq for State<P> {}
 
-impl Ord for State {
+impl<P> Ord for State<P> {
     fn cmp(&self, other: &Self) -> Ordering {
         // Reverse for min-heap behavior
         other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
     }
 }
 
-impl PartialOrd for State {
+impl<P> PartialOrd for State<P> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+// Spreads fallback directions for coincident agents evenly around the circle
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
@@ -261,7 +891,10 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig, TieBreaking};
     use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::{Diagonal, Manhattan, Zero};
+    use crate::traits::PathStatus;
 
     #[test]
     fn simple_cardinal_field_points_to_goal() {
@@ -274,10 +907,329 @@ mod tests {
         assert_eq!(ff.get_cost_to_goal(GridPos { x: 2, y: 0 }), 0.0);
     }
 
+    #[test]
+    fn flow_following_paths_score_one_and_turn_at_portals() {
+        // Wall from the top down to y = 4; the goal is behind it, top right
+        let mut grid = Grid2D::new(10, 7, DiagonalMode::Never);
+        grid.set_region_blocked((5, 0, 1, 5), true);
+        let goal = GridPos { x: 9, y: 0 };
+        let field = FlowField::compute(&grid, goal);
+
+        let mut path = vec![GridPos { x: 0, y: 0 }];
+        while *path.last().unwrap() != goal {
+            let at = *path.last().unwrap();
+            let (dx, dy) = field.get_direction(at).to_vec2();
+            path.push(GridPos { x: at.x + dx as i32, y: at.y + dy as i32 });
+        }
+        assert_eq!(field.path_alignment_score(&path), 1.0);
+        let backwards: Vec<GridPos> = path.iter().rev().copied().collect();
+        assert!(field.path_alignment_score(&backwards) < -0.5);
+
+        // With four directions every change of direction is a 90 degree turn
+        let portals = field.portals_along_path(&path);
+        let turns: Vec<usize> = (1..path.len() - 1)
+            .filter(|&i| field.get_direction(path[i]) != field.get_direction(path[i - 1]))
+            .collect();
+        assert!(turns.len() >= 2, "the path must go down and back up around the wall");
+        assert_eq!(portals, turns);
+        assert_eq!(field.path_alignment_score(&path[..1]), 0.0);
+    }
+
+    #[test]
+    fn diagonal_turns_of_45_degrees_are_not_portals() {
+        let grid = Grid2D::new(6, 6, DiagonalMode::Always);
+        let field = FlowField::compute(&grid, GridPos { x: 5, y: 3 });
+        // Flow runs SE from (0, 0) then E along the goal's row
+        let path: Vec<GridPos> = (0..6).map(|x| GridPos { x, y: x.min(3) }).collect();
+        assert_eq!(field.get_direction(path[0]), Direction::SE);
+        assert_eq!(field.get_direction(path[3]), Direction::E);
+        assert!(field.portals_along_path(&path).is_empty());
+        assert_eq!(field.path_alignment_score(&path), 1.0);
+
+        // Walking back along the goal's row, straight into the flow
+        let against: Vec<GridPos> = (0..5).rev().map(|x| GridPos { x, y: 3 }).collect();
+        assert_eq!(field.path_alignment_score(&against), -1.0);
+    }
+
     #[test]
     fn diagonal_field_prefers_shortcut() {
         let grid = Grid2D::new(3, 3, DiagonalMode::Always);
         let ff = FlowField::compute(&grid, GridPos { x: 2, y: 2 });
         assert_eq!(ff.get_direction(GridPos { x: 0, y: 0 }), Direction::SE);
     }
+
+    #[test]
+    fn integration_uses_grid_diagonal_cost() {
+        let grid = Grid2D::new(4, 4, DiagonalMode::Always).with_diagonal_cost(1.5);
+        let ff = FlowField::compute(&grid, GridPos { x: 3, y: 3 });
+        assert!((ff.get_cost_to_goal(GridPos { x: 0, y: 0 }) - 4.5).abs() < 1e-5);
+        assert!((ff.get_cost_to_goal(GridPos { x: 0, y: 3 }) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn distance_field_matches_astar_cost_to_goal() {
+        let mut grid = Grid2D::new(8, 6, DiagonalMode::Always);
+        grid.set_region_blocked((3, 0, 1, 4), true);
+        grid.set_cost(5, 3, 4.0);
+        grid.set_cost(2, 5, 0.5);
+        let goal = GridPos { x: 7, y: 0 };
+        let field = DistanceField::compute(&grid, &[goal]);
+
+        for y in 0..6 {
+            for x in 0..8 {
+                let pos = GridPos { x, y };
+                if grid.is_blocked(x, y) {
+                    assert_eq!(field.get(pos), f32::INFINITY);
+                    continue;
+                }
+                let expected = astar(&grid, &Zero, pos, goal, AStarConfig::default());
+                if expected.status == PathStatus::Found {
+                    assert!((field.get(pos) - expected.cost).abs() < 1e-4, "{pos:?}");
+                } else {
+                    assert_eq!(field.get(pos), f32::INFINITY);
+                }
+            }
+        }
+        assert_eq!(field.argmin_neighbor(goal), None);
+        assert_eq!(field.argmin_neighbor(GridPos { x: 3, y: 0 }), None, "blocked");
+        assert_eq!(field.argmin_neighbor(GridPos { x: 6, y: 1 }), Some(goal));
+    }
+
+    #[test]
+    fn distance_heuristic_expands_only_an_optimal_path() {
+        let mut grid = Grid2D::new(20, 20, DiagonalMode::Never);
+        grid.set_region_blocked((5, 0, 1, 15), true);
+        grid.set_region_blocked((12, 5, 1, 15), true);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 19, y: 19 });
+        let heuristic = DistanceField::compute(&grid, &[goal]).into_heuristic(goal);
+        let config = AStarConfig {
+            tie_breaking: TieBreaking::PreferHigherG,
+            ..AStarConfig::default()
+        };
+
+        let guided = astar(&grid, &heuristic, start, goal, config);
+        let baseline = astar(&grid, &Manhattan, start, goal, config);
+        assert_eq!(guided.status, PathStatus::Found);
+        assert!((guided.cost - baseline.cost).abs() < 1e-4);
+        // Every expanded node lies on the returned path; ties are broken toward the goal
+        assert!(guided.nodes_expanded <= guided.path.len(), "{}", guided.nodes_expanded);
+        assert!(baseline.nodes_expanded > guided.nodes_expanded);
+    }
+
+    #[test]
+    fn separation_pushes_close_agents_apart() {
+        let grid = Grid2D::new(5, 5, DiagonalMode::Always);
+        let ff = FlowField::compute(&grid, GridPos { x: 4, y: 4 });
+        let a = (2.0, 2.0);
+        let b = (2.1, 2.0);
+
+        let on_a = ff.compute_separation_force(&[b], a, 1.0);
+        let on_b = ff.compute_separation_force(&[a], b, 1.0);
+        assert!(on_a.0 < 0.0 && on_b.0 > 0.0);
+        assert!((on_a.0 + on_b.0).abs() < 1e-4 && on_a.1.abs() < 1e-6);
+
+        // Exactly stacked agents still get a non-zero push
+        let stacked = ff.compute_separation_force(&[a], a, 1.0);
+        assert!(stacked.0.abs() + stacked.1.abs() > 0.0);
+
+        // Nobody within the radius: no force, steering is the plain flow
+        let isolated = ff.compute_separation_force(&[(0.0, 0.0)], a, 1.0);
+        assert_eq!(isolated, (0.0, 0.0));
+        let steer = ff.combined_steering_force(&[(0.0, 0.0)], (1.5, 1.5), 1.0, 2.0);
+        assert_eq!(steer, ff.sample_bilinear(1.5, 1.5));
+    }
+
+    #[test]
+    fn layered_fields_are_independent() {
+        // Ground units must detour around the wall, flyers go straight over it
+        let mut ground = Grid2D::new(7, 5, DiagonalMode::Never);
+        ground.set_region_blocked((3, 0, 1, 4), true);
+        let air = Grid2D::new(7, 5, DiagonalMode::Never);
+        let goal = GridPos { x: 6, y: 0 };
+
+        let layered = LayeredFlowField::compute(vec![(&ground, goal), (&air, goal)]);
+        assert_eq!(layered.layers.len(), 2);
+        assert_eq!(layered.layer_index("layer1"), Some(1));
+
+        let pos = GridPos { x: 2, y: 0 };
+        assert_eq!(layered.get_direction(0, pos), Direction::S);
+        assert_eq!(layered.get_direction(1, pos), Direction::E);
+        assert_eq!(layered.get_direction(2, pos), Direction::None);
+
+        assert_eq!(layered.composite_direction(pos, &[1.0, 0.0]), (0.0, 1.0));
+        assert_eq!(layered.composite_direction(pos, &[1.0, 1.0]), (0.5, 0.5));
+        assert_eq!(layered.composite_direction(pos, &[]), (0.0, 0.0));
+        assert_eq!(layered.sample_bilinear(1, 1.0, 1.0), layered.layers[1].sample_bilinear(1.0, 1.0));
+    }
+
+    #[test]
+    fn flow_field_heuristic_guides_searches_to_nearby_goals() {
+        // A cup open toward the start with the stronghold behind it: octile estimates lure
+        // the search into the cup, the field knows to go around
+        let mut grid = Grid2D::new(40, 40, DiagonalMode::Always);
+        grid.set_region_blocked((30, 5, 1, 31), true);
+        grid.set_region_blocked((15, 5, 15, 1), true);
+        grid.set_region_blocked((15, 35, 15, 1), true);
+        let stronghold = GridPos { x: 35, y: 20 };
+        let field = FlowField::compute(&grid, stronghold);
+        let heuristic = field.as_heuristic();
+        let start = GridPos { x: 5, y: 20 };
+
+        for goal in [GridPos { x: 34, y: 20 }, GridPos { x: 34, y: 21 }, GridPos { x: 35, y: 19 }] {
+            let guided = astar(&grid, &heuristic, start, goal, AStarConfig::default());
+            let octile = astar(&grid, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default());
+            assert_eq!(guided.status, PathStatus::Found);
+            assert!((guided.cost - octile.cost).abs() < 1e-3, "{goal:?}");
+            assert!(guided.nodes_expanded * 2 < octile.nodes_expanded, "{} vs {}", guided.nodes_expanded, octile.nodes_expanded);
+        }
+
+        // Blocked and out-of-bounds cells fall back to the octile estimate
+        let wall = GridPos { x: 30, y: 20 };
+        assert_eq!(heuristic.estimate(&start, &wall), Diagonal::default().estimate(&start, &wall));
+        let outside = GridPos { x: -3, y: 20 };
+        assert_eq!(heuristic.estimate(&outside, &start), 8.0);
+        // Moving away from the stronghold has no differential bound
+        assert_eq!(heuristic.estimate(&stronghold, &start), 0.0);
+    }
+
+    #[test]
+    fn voxel_field_points_through_the_cube_toward_the_goal_corner() {
+        let mut grid = Grid3D::new(3, 3, 3);
+        let goal = GridPos3D { x: 2, y: 2, z: 2 };
+        let center = GridPos3D { x: 1, y: 1, z: 1 };
+        let field = FlowField3D::compute(&grid, goal);
+        assert_eq!(field.get_direction(center), Direction3D::UpSE);
+        assert!((field.get_cost_to_goal(center) - 3f32.sqrt()).abs() < 1e-5);
+        assert_eq!(field.get_direction(goal), Direction3D::None);
+        assert_eq!(field.sample_trilinear(1.0, 1.0, 1.0), (1.0, 1.0, 1.0));
+        assert_eq!(field.sample_trilinear(2.5, 1.0, 1.0), (0.0, 0.0, 0.0));
+
+        // Blocking a voxel beside the diagonal forbids cutting its corner
+        grid.set_blocked(2, 1, 1, true);
+        let field = FlowField3D::compute(&grid, goal);
+        assert_ne!(field.get_direction(center), Direction3D::UpSE);
+        assert!(field.get_cost_to_goal(center) > 3f32.sqrt());
+        assert_eq!(field.get_cost_to_goal(GridPos3D { x: 2, y: 1, z: 1 }), f32::INFINITY);
+        assert_eq!(field.get_direction(GridPos3D { x: 3, y: 0, z: 0 }), Direction3D::None);
+    }
+
+    #[test]
+    fn combined_fields_cancel_between_equal_pulls_and_steer_around_threats() {
+        // Equal pulls toward opposite ends cancel along the line between the goals, and
+        // across it on the midline
+        let grid = Grid2D::new(21, 11, DiagonalMode::Always);
+        let west = FlowField::compute(&grid, GridPos { x: 0, y: 5 });
+        let east = FlowField::compute(&grid, GridPos { x: 20, y: 5 });
+        let tug = FlowField::combine(&[(&west, 1.0), (&east, 1.0)]);
+        for x in 1..20 {
+            let (vx, vy) = tug.get_vector(GridPos { x, y: 5 });
+            assert!(vx.abs() < 1e-6 && vy.abs() < 1e-6);
+            assert_eq!(tug.get_direction(GridPos { x, y: 5 }), Direction::None);
+        }
+        for y in 0..11 {
+            assert!(tug.get_vector(GridPos { x: 10, y }).0.abs() < 1e-6);
+        }
+        // Repelling from the east goal reinforces the pull west
+        let flee = FlowField::combine(&[(&west, 1.0), (&east, -1.0)]);
+        assert_eq!(flee.get_vector(GridPos { x: 10, y: 5 }), (-2.0, 0.0));
+        assert_eq!(flee.get_potential(GridPos { x: 10, y: 5 }), 0.0);
+
+        // Objective in the west room, a threat in the east room; the rooms meet at the bottom
+        let mut grid = Grid2D::new(30, 15, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((15, 0, 1, 11), true);
+        let objective = GridPos { x: 2, y: 2 };
+        let attract = FlowField::compute(&grid, objective);
+        let threat = FlowField::compute(&grid, GridPos { x: 25, y: 2 });
+        let combined = FlowField::combine(&[(&attract, 1.0), (&threat, -0.3)]);
+        assert_eq!(combined.get_vector(GridPos { x: 15, y: 3 }), (0.0, 0.0));
+        assert_eq!(combined.get_potential(GridPos { x: 15, y: 3 }), f32::INFINITY);
+        assert_eq!(combined.sample_bilinear(3.0, 3.0), combined.get_vector(GridPos { x: 3, y: 3 }));
+        // The threat only tips the balance where two steps were nearly as good
+        let mut cells = (0..15).flat_map(|y| (0..30).map(move |x| GridPos { x, y }));
+        assert!(cells.any(|p| combined.get_direction(p) != attract.get_direction(p)));
+
+        for start in [GridPos { x: 13, y: 1 }, GridPos { x: 14, y: 14 }, GridPos { x: 5, y: 12 }, GridPos { x: 1, y: 7 }] {
+            let mut pos = start;
+            for _ in 0..60 {
+                if pos == objective {
+                    break;
+                }
+                let (dx, dy) = combined.get_direction(pos).to_vec2();
+                pos = GridPos { x: pos.x + dx as i32, y: pos.y + dy as i32 };
+                assert!(!grid.is_blocked(pos.x, pos.y), "{start:?} walked into a wall at {pos:?}");
+            }
+            assert_eq!(pos, objective, "from {start:?}");
+        }
+    }
+
+    #[test]
+    fn curl_finds_vortices_and_divergence_finds_the_goal() {
+        // Straight to the goal: no vortex anywhere, and the flow converges on the goal
+        let grid = Grid2D::new(21, 21, DiagonalMode::Always);
+        let goal = GridPos { x: 10, y: 10 };
+        let field = FlowField::compute(&grid, goal);
+        let curl = field.compute_curl();
+        assert_eq!(curl.len(), 21 * 21);
+        // Only the snapping to eight directions leaves any curl
+        assert!(curl.iter().all(|c| c.abs() < 0.5), "max curl {}", curl.iter().fold(0.0f32, |m, c| m.max(c.abs())));
+        assert!(field.find_vortex_cells(0.5).is_empty());
+        let divergence = field.divergence();
+        let goal_idx = 10 * 21 + 10;
+        assert!(divergence[goal_idx] < -1.0);
+        assert!(divergence.iter().all(|&d| d >= divergence[goal_idx]));
+        // Behind a wall, routes around either end part ways
+        let mut walled = Grid2D::new(21, 21, DiagonalMode::Always);
+        walled.set_region_blocked((5, 14, 11, 1), true);
+        let behind = FlowField::compute(&walled, goal).divergence();
+        assert!(behind[18 * 21 + 10] > 0.5, "{}", behind[18 * 21 + 10]);
+        assert_eq!(behind[14 * 21 + 10], 0.0);
+
+        // A 3x3 whirl (clockwise on screen) around a cell with no direction
+        let mut whirl = FlowField::compute(&Grid2D::new(3, 3, DiagonalMode::Always), GridPos { x: 0, y: 0 });
+        whirl.flow = vec![
+            Direction::E, Direction::E, Direction::S,
+            Direction::N, Direction::None, Direction::S,
+            Direction::N, Direction::W, Direction::W,
+        ];
+        let curl = whirl.compute_curl();
+        assert!(curl[4] > 1.5, "{curl:?}");
+        assert!(curl.iter().all(|&c| c >= 0.0));
+        assert!(whirl.find_vortex_cells(1.5).contains(&GridPos { x: 1, y: 1 }));
+    }
+
+    #[test]
+    fn batched_fields_match_individually_computed_ones() {
+        let mut grid = Grid2D::new(40, 30, DiagonalMode::IfNoObstacle);
+        grid.apply_noise_cost(7, 6.0, 1.0, 3.0);
+        grid.set_region_blocked((12, 0, 1, 24), true);
+        grid.set_region_blocked((25, 6, 1, 24), true);
+        grid.set_directional_cost(20, 10, Direction::NE, 5.0);
+        grid.set_directional_cost(13, 26, Direction::W, 0.5);
+        let goals = [
+            GridPos { x: 38, y: 2 },
+            GridPos { x: 0, y: 29 },
+            GridPos { x: 12, y: 5 },   // Blocked
+            GridPos { x: 40, y: 0 },   // Out of bounds
+            GridPos { x: 18, y: 15 },
+            GridPos { x: 38, y: 2 },
+        ];
+
+        let batch = FlowField::compute_batch(&grid, &goals);
+        assert_eq!(batch.len(), goals.len());
+        for (field, &goal) in batch.iter().zip(&goals) {
+            let single = FlowField::compute(&grid, goal);
+            assert_eq!((field.width, field.height), (single.width, single.height));
+            assert_eq!(field.integration, single.integration, "{goal:?}");
+            assert_eq!(field.flow, single.flow, "{goal:?}");
+        }
+        assert!(batch[2].integration.iter().all(|c| c.is_infinite()));
+        assert!(batch[4].get_cost_to_goal(GridPos { x: 0, y: 0 }).is_finite());
+
+        // A snapshot doesn't see later edits
+        let snapshot = GridSnapshot::new(&grid);
+        grid.set_blocked(18, 15, true);
+        assert!(snapshot.is_passable(GridPos { x: 18, y: 15 }));
+        assert!(!snapshot.is_passable(GridPos { x: 12, y: 5 }) && !snapshot.is_passable(GridPos { x: -1, y: 0 }));
+        assert_eq!(FlowField::compute_from_snapshot(&snapshot, GridPos { x: 18, y: 15 }).integration, batch[4].integration);
+    }
 }
@@ -23,15 +23,33 @@ fn tri_area_2d(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
 /// 
 /// `portals`: The sequence of edges including start (p, p) and end (goal, goal).
 pub fn string_pull(portals: &[Portal]) -> Vec<Vec3> {
+    pull(portals, portals)
+}
+
+/// String pulling for corridors that climb: the funnel runs on the corridor unfolded into
+/// a plane (each portal laid flat against the previous one by its 3D edge lengths)
+/// instead of on its XZ shadow. Needed where the shadow overlaps itself or collapses, as
+/// on a spiral ramp whose floors stack over each other; on a flat corridor the result is
+/// the same as `string_pull`. Corners are the original portal vertices.
+pub fn string_pull_3d(portals: &[Portal]) -> Vec<Vec3> {
+    pull(portals, &unfold(portals))
+}
+
+// Runs the funnel on `flat` and emits the matching vertices of `portals`
+fn pull(portals: &[Portal], flat: &[Portal]) -> Vec<Vec3> {
     let mut path = Vec::with_capacity(portals.len());
     if portals.is_empty() {
         return path;
     }
 
     // The "Apex" is the pivot point of the funnel (usually the last corner we turned).
-    let mut apex = portals[0].left; 
-    let mut portal_left = portals[0].left;
-    let mut portal_right = portals[0].right;
+    // Each `*_src` is the vertex of `portals` that the flat point stands for.
+    let mut apex = flat[0].left;
+    let mut portal_left = flat[0].left;
+    let mut portal_right = flat[0].right;
+    let mut apex_src = portals[0].left;
+    let mut left_src = portals[0].left;
+    let mut right_src = portals[0].right;
 
     // Indices to keep track of where the funnel sides are in the list
     // so we don't process old portals.
@@ -39,12 +57,12 @@ pub fn string_pull(portals: &[Portal]) -> Vec<Vec3> {
     let mut right_index = 0;
 
     // Add start point
-    path.push(apex);
+    path.push(apex_src);
 
     let mut i = 1;
     while i < portals.len() {
-        let left = portals[i].left;
-        let right = portals[i].right;
+        let left = flat[i].left;
+        let right = flat[i].right;
 
         // Update Right Leg
         // Check if the new right vertex tightens the funnel (is to the left of the current right leg)
@@ -54,16 +72,19 @@ pub fn string_pull(portals: &[Portal]) -> Vec<Vec3> {
             if apex == portal_right || tri_area_2d(&apex, &portal_left, &right) > 0.0 {
                 // Tighten the funnel
                 portal_right = right;
+                right_src = portals[i].right;
                 right_index = i;
             } else {
                 // CROSSOVER! The right leg crossed the left leg.
                 // We must turn a corner around the LEFT leg.
                 apex = portal_left;
-                path.push(apex);
+                apex_src = left_src;
+                path.push(apex_src);
                 
                 // Reset the funnel to the new apex
                 portal_left = apex;
                 portal_right = apex;
+                right_src = apex_src;
                 
                 // Restart scan from the portal that formed the corner
                 i = left_index + 1;
@@ -80,16 +101,19 @@ pub fn string_pull(portals: &[Portal]) -> Vec<Vec3> {
             if apex == portal_left || tri_area_2d(&apex, &portal_right, &left) < 0.0 {
                 // Tighten the funnel
                 portal_left = left;
+                left_src = portals[i].left;
                 left_index = i;
             } else {
                 // CROSSOVER! The left leg crossed the right leg.
                 // We must turn a corner around the RIGHT leg.
                 apex = portal_right;
-                path.push(apex);
+                apex_src = right_src;
+                path.push(apex_src);
                 
                 // Reset the funnel
                 portal_left = apex;
                 portal_right = apex;
+                left_src = apex_src;
                 
                 // Restart scan
                 i = right_index + 1;
@@ -112,3 +136,129 @@ pub fn string_pull(portals: &[Portal]) -> Vec<Vec3> {
 
     path
 }
+
+// Lays the corridor flat in the XZ plane of the result, portal by portal. A vertex shared
+// with the previous portal keeps its place; a new one goes where its 3D distances to the
+// previous portal's ends put it, ahead of that portal. Exact for triangle corridors.
+fn unfold(portals: &[Portal]) -> Vec<Portal> {
+    let mut flat: Vec<Portal> = Vec::with_capacity(portals.len());
+    for (i, portal) in portals.iter().enumerate() {
+        let Some(prev_flat) = i.checked_sub(1).map(|j| flat[j]) else {
+            let width = dist(&portal.left, &portal.right);
+            flat.push(Portal { left: [0.0; 3], right: [width, 0.0, 0.0] });
+            continue;
+        };
+        let prev = portals[i - 1];
+        let place = |p: &Vec3, placed_left: Option<Vec3>| -> Vec3 {
+            if *p == prev.left {
+                return prev_flat.left;
+            }
+            if *p == prev.right {
+                return prev_flat.right;
+            }
+            if prev.left != prev.right {
+                // Ahead of the previous portal, which is its negative side
+                let (da, db) = (dist(p, &prev.left), dist(p, &prev.right));
+                return triangulate(&prev_flat.left, &prev_flat.right, da, db, -1.0);
+            }
+            // Leaving a single point (the start), which is behind this portal
+            match placed_left {
+                None => {
+                    let [x, y, z] = prev_flat.left;
+                    [x + dist(p, &prev.left), y, z]
+                }
+                Some(left) if *p == portal.left => left,
+                Some(left) => {
+                    let (da, db) = (dist(p, &prev.left), dist(p, &portal.left));
+                    triangulate(&prev_flat.left, &left, da, db, 1.0)
+                }
+            }
+        };
+        let left = place(&portal.left, None);
+        let right = place(&portal.right, Some(left));
+        flat.push(Portal { left, right });
+    }
+    flat
+}
+
+fn dist(a: &Vec3, b: &Vec3) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2) + (b[2] - a[2]).powi(2)).sqrt()
+}
+
+// The point at `da` from `a` and `db` from `b` (in XZ) on the side of a -> b where
+// `tri_area_2d` has the sign of `side`
+fn triangulate(a: &Vec3, b: &Vec3, da: f32, db: f32, side: f32) -> Vec3 {
+    let base = dist(a, b);
+    if base == 0.0 {
+        return [a[0] + da, a[1], a[2]];
+    }
+    let (ex, ez) = ((b[0] - a[0]) / base, (b[2] - a[2]) / base);
+    let along = (da * da - db * db + base * base) / (2.0 * base);
+    let across = (da * da - along * along).max(0.0).sqrt() * side;
+    // (ez, -ex) is the positive side
+    [a[0] + ex * along + ez * across, a[1], a[2] + ez * along - ex * across]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::navmesh::NavMesh;
+
+    // Ramp winding 1.5 turns around a newel post of radius `inner`, rising 0.5 per eighth of
+    // a turn, so the last third lies 4 units over the first. Segment k is the quad between
+    // angles k and k + 1, split into triangles 2k and 2k + 1.
+    fn spiral(inner: f32) -> NavMesh {
+        let mut vertices = Vec::new();
+        for k in 0..=12 {
+            let (angle, y) = (k as f32 * std::f32::consts::FRAC_PI_4, k as f32 * 0.5);
+            for r in [inner, 3.0] {
+                vertices.extend([r * angle.cos(), y, r * angle.sin()]);
+            }
+        }
+        let (mut polygons, mut neighbors) = (Vec::new(), Vec::new());
+        for k in 0..12i32 {
+            let (i0, o0, i1, o1) = (2 * k as u32, 2 * k as u32 + 1, 2 * k as u32 + 2, 2 * k as u32 + 3);
+            let (a, b) = (2 * k, 2 * k + 1);
+            polygons.extend([i0, o0, o1, i0, o1, i1]);
+            neighbors.extend([if k == 0 { -1 } else { a - 1 }, -1, b]);
+            neighbors.extend([a, if k == 11 { -1 } else { b + 1 }, -1]);
+        }
+        NavMesh::new(vertices, polygons, neighbors)
+    }
+
+    // Through all 23 triangles, from the first segment to the last, 1.5 turns higher
+    fn climb(mesh: &NavMesh) -> Vec<Portal> {
+        let corridor: Vec<u32> = (0..23).collect();
+        mesh.get_portals(&corridor, [1.9, 0.1, 0.7], [-1.9, 5.6, 0.7]).unwrap()
+    }
+
+    #[test]
+    fn unfolded_funnel_agrees_where_the_shadow_only_overlaps() {
+        // The top half-turn hangs over the bottom one, but each step keeps its own place in XZ
+        let portals = climb(&spiral(1.0));
+        let pulled = string_pull_3d(&portals);
+        assert_eq!(pulled, string_pull(&portals));
+        // Around the post, one corner per step
+        assert_eq!(pulled.len(), 11);
+    }
+
+    #[test]
+    fn unfolded_funnel_climbs_a_newel_post_one_step_at_a_time() {
+        // Every step meets at the post, so all its vertices share one XZ position
+        let portals = climb(&spiral(0.0));
+        let on_post = |p: &Vec3| p[0] == 0.0 && p[2] == 0.0;
+
+        // The flat funnel can't tell them apart: it heads from the bottom step straight to
+        // the post four steps up, then visits every later step twice
+        let flat = string_pull(&portals);
+        assert_eq!(flat[1], [0.0, 2.0, 0.0]);
+        assert!(flat.windows(2).any(|w| w[0] == w[1]));
+
+        let pulled = string_pull_3d(&portals);
+        assert_eq!(pulled[0], [1.9, 0.1, 0.7]);
+        assert_eq!(pulled.last(), Some(&[-1.9, 5.6, 0.7]));
+        assert!(pulled[1..pulled.len() - 1].iter().all(on_post));
+        assert_eq!(pulled[1], [0.0, 1.0, 0.0]);
+        assert!(pulled.windows(2).all(|w| w[1][1] > w[0][1]), "{pulled:?}");
+    }
+}
@@ -1,6 +1,7 @@
-use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode};
-use crate::traits::{PathResult, PathStatus, Heuristic};
-use crate::algorithms::astar::{AStarConfig, TieBreaking};
+use crate::graphs::grid2d::{Grid2D, GridPos, DiagonalMode, MaskedGrid2D};
+use crate::traits::{PathResult, PathStatus, Heuristic, SearchStats};
+use crate::algorithms::astar::AStarConfig;
+use crate::heuristics::Euclidean;
 use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
 use std::time::Instant;
@@ -43,22 +44,49 @@ impl PartialOrd for State {
     }
 }
 
-pub fn jps<H>(
-    grid: &Grid2D,
+/// Jump point search: `astar`'s result on uniform-cost grids, expanding only jump points.
+///
+/// Grids with directional costs, and grids in `DiagonalMode::Never` or `OnlyIfBothOpen`,
+/// return `PathStatus::UnsupportedGraph` without searching. The forced-neighbor rules assume
+/// diagonals may pass a single blocked corner, so in those modes it used to miss paths that
+/// exist and report `NotFound`; run `astar` on such grids. Weighted cells are only caught by a
+/// debug assertion, since checking costs scans the whole grid. `dispatch::find_path_with`
+/// does that scan and returns `UnsupportedGraph` for them.
+pub fn jps<G, H>(
+    grid: &G,
     heuristic: &H,
     start: GridPos,
     goal: GridPos,
     config: AStarConfig,
 ) -> PathResult<GridPos>
 where
+    G: JpsGrid,
     H: Heuristic<GridPos>,
 {
+    // Jump pruning assumes symmetric step costs, and its forced-neighbor rules assume
+    // diagonals may pass a single blocked corner
+    let cuts_corners = matches!(grid.diagonal_movement(), DiagonalMode::Always | DiagonalMode::IfNoObstacle);
+    if grid.has_directional_costs() || !cuts_corners {
+        return PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: 0,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::UnsupportedGraph,
+            elapsed: None,
+        };
+    }
+
     debug_assert!(
         grid.is_uniform(),
         "JPS is defined for uniform-cost grids; ensure Grid2D costs are all 1.0"
     );
 
-    let start_time = Instant::now();
+    let started = cfg!(feature = "timing").then(Instant::now);
+    let deadline = config.deadline_from(started);
     let mut open_set = BinaryHeap::new();
     let mut g_scores = HashMap::new();
     let mut came_from = HashMap::new();
@@ -67,12 +95,13 @@ where
     
     open_set.push(State {
         node: start,
-        cost: heuristic.estimate(&start, &goal),
+        cost: heuristic.estimate(&start, &goal) * config.heuristic_weight,
         g_score: 0.0,
         tie_breaker: 0.0,
     });
     
     let mut nodes_expanded = 0;
+    let mut furthest_g: f32 = 0.0;
     let mut iterations = 0;
     
     while let Some(State { node: current, cost: _, g_score: current_g, tie_breaker: _ }) = open_set.pop() {
@@ -80,17 +109,16 @@ where
         
         if let Some(max_iter) = config.max_iterations {
             if iterations > max_iter {
-                 return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::PartialMaxIter);
+                 return reconstruct_path(current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::PartialMaxIter).timed(started);
             }
         }
-        if let Some(timeout) = config.timeout {
-            if start_time.elapsed() > timeout {
-                return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::PartialTimeout);
-            }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            return reconstruct_path(current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::PartialTimeout).timed(started);
         }
         
         if current == goal {
-            return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::Found);
+            furthest_g = furthest_g.max(current_g);
+            return reconstruct_path(current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::Found).timed(started);
         }
         
         if let Some(&best_g) = g_scores.get(&current) {
@@ -100,13 +128,14 @@ where
         }
         
         nodes_expanded += 1;
+        furthest_g = furthest_g.max(current_g);
         
         let parent = came_from.get(&current).cloned();
         
         let successors = find_successors(grid, current, parent, goal);
         
         for neighbor in successors {
-            let dist = distance(current, neighbor);
+            let dist = distance(grid, current, neighbor);
             let tentative_g = current_g + dist;
             
             if let Some(&existing_g) = g_scores.get(&neighbor) {
@@ -118,15 +147,10 @@ where
             came_from.insert(neighbor, current);
             g_scores.insert(neighbor, tentative_g);
             
-            let h = heuristic.estimate(&neighbor, &goal);
+            let h = heuristic.estimate(&neighbor, &goal) * config.heuristic_weight;
             let f = tentative_g + h;
             
-            let tb = match config.tie_breaking {
-                TieBreaking::None => 0.0,
-                TieBreaking::PreferHigherG => tentative_g,
-                TieBreaking::PreferLowerG => -tentative_g,
-                TieBreaking::CrossProduct => 0.0,
-            };
+            let tb = config.tie_breaking.key(&neighbor, tentative_g);
             
             open_set.push(State {
                 node: neighbor,
@@ -137,23 +161,30 @@ where
         }
     }
     
-    PathResult {
+    let not_found = PathResult {
         path: vec![],
         cost: 0.0,
+        length: 0.0,
+        cumulative_costs: vec![],
         nodes_expanded,
+        best_g_score: furthest_g,
+        nodes_in_open_set: 0,
         status: PathStatus::NotFound,
-    }
+        elapsed: None,
+    };
+    not_found.timed(started)
 }
 
-fn distance(a: GridPos, b: GridPos) -> f32 {
+// Octile distance using the grid's own diagonal cost, so JPS and A* agree
+fn distance<G: JpsGrid>(grid: &G, a: GridPos, b: GridPos) -> f32 {
     let dx = (a.x - b.x).abs() as f32;
     let dy = (a.y - b.y).abs() as f32;
     let min_d = dx.min(dy);
     let max_d = dx.max(dy);
-    (std::f32::consts::SQRT_2 - 1.0) * min_d + max_d
+    (grid.diagonal_cost() - 1.0) * min_d + max_d
 }
 
-fn find_successors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>, goal: GridPos) -> Vec<GridPos> {
+fn find_successors<G: JpsGrid>(grid: &G, current: GridPos, parent: Option<GridPos>, goal: GridPos) -> Vec<GridPos> {
     let mut successors = Vec::new();
     let neighbors = prune_neighbors(grid, current, parent);
     
@@ -168,7 +199,7 @@ fn find_successors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>, goa
     successors
 }
 
-fn prune_neighbors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>) -> Vec<GridPos> {
+fn prune_neighbors<G: JpsGrid>(grid: &G, current: GridPos, parent: Option<GridPos>) -> Vec<GridPos> {
     let mut neighbors = Vec::new();
     
     if parent.is_none() {
@@ -178,8 +209,8 @@ fn prune_neighbors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>) ->
         ];
         for (dx, dy) in dirs.iter() {
             if is_walkable(grid, current.x + dx, current.y + dy) {
-                 if *dx != 0 && *dy != 0 {
-                     if !grid.diagonal_movement_allowed(current, *dx, *dy) { continue; }
+                 if *dx != 0 && *dy != 0 && !grid.diagonal_movement_allowed(current, *dx, *dy) {
+                     continue;
                  }
                  neighbors.push(GridPos { x: current.x + dx, y: current.y + dy });
             }
@@ -192,10 +223,8 @@ fn prune_neighbors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>) ->
     let dy = (current.y - parent.y).signum();
     
     if dx != 0 && dy != 0 {
-        if is_walkable(grid, current.x + dx, current.y + dy) {
-             if grid.diagonal_movement_allowed(current, dx, dy) {
-                neighbors.push(GridPos { x: current.x + dx, y: current.y + dy });
-             }
+        if is_walkable(grid, current.x + dx, current.y + dy) && grid.diagonal_movement_allowed(current, dx, dy) {
+            neighbors.push(GridPos { x: current.x + dx, y: current.y + dy });
         }
         if is_walkable(grid, current.x + dx, current.y) {
              neighbors.push(GridPos { x: current.x + dx, y: current.y });
@@ -204,54 +233,58 @@ fn prune_neighbors(grid: &Grid2D, current: GridPos, parent: Option<GridPos>) ->
              neighbors.push(GridPos { x: current.x, y: current.y + dy });
         }
         
-        if !is_walkable(grid, current.x - dx, current.y) && is_walkable(grid, current.x - dx, current.y + dy) {
-            if grid.diagonal_movement_allowed(GridPos { x: current.x - dx, y: current.y }, 0, dy) { // Loose check? No, move is diagonal from current?
-               // The move is from current to (current.x - dx, current.y + dy).
-               // Direction is (-dx, dy).
-               if grid.diagonal_movement_allowed(current, -dx, dy) {
-                   neighbors.push(GridPos { x: current.x - dx, y: current.y + dy });
-               }
-            }
+        // The move is from current to (current.x - dx, current.y + dy), direction (-dx, dy).
+        if !is_walkable(grid, current.x - dx, current.y)
+            && is_walkable(grid, current.x - dx, current.y + dy)
+            && grid.diagonal_movement_allowed(GridPos { x: current.x - dx, y: current.y }, 0, dy)
+            && grid.diagonal_movement_allowed(current, -dx, dy)
+        {
+            neighbors.push(GridPos { x: current.x - dx, y: current.y + dy });
         }
-        if !is_walkable(grid, current.x, current.y - dy) && is_walkable(grid, current.x + dx, current.y - dy) {
-             if grid.diagonal_movement_allowed(current, dx, -dy) {
-                neighbors.push(GridPos { x: current.x + dx, y: current.y - dy });
-             }
+        if !is_walkable(grid, current.x, current.y - dy)
+            && is_walkable(grid, current.x + dx, current.y - dy)
+            && grid.diagonal_movement_allowed(current, dx, -dy)
+        {
+            neighbors.push(GridPos { x: current.x + dx, y: current.y - dy });
         }
     } else if dx != 0 {
         if is_walkable(grid, current.x + dx, current.y) {
              neighbors.push(GridPos { x: current.x + dx, y: current.y });
         }
-        if !is_walkable(grid, current.x, current.y + 1) && is_walkable(grid, current.x + dx, current.y + 1) {
-             if grid.diagonal_movement_allowed(current, dx, 1) {
-                neighbors.push(GridPos { x: current.x + dx, y: current.y + 1 });
-             }
+        if !is_walkable(grid, current.x, current.y + 1)
+            && is_walkable(grid, current.x + dx, current.y + 1)
+            && grid.diagonal_movement_allowed(current, dx, 1)
+        {
+            neighbors.push(GridPos { x: current.x + dx, y: current.y + 1 });
         }
-        if !is_walkable(grid, current.x, current.y - 1) && is_walkable(grid, current.x + dx, current.y - 1) {
-             if grid.diagonal_movement_allowed(current, dx, -1) {
-                neighbors.push(GridPos { x: current.x + dx, y: current.y - 1 });
-             }
+        if !is_walkable(grid, current.x, current.y - 1)
+            && is_walkable(grid, current.x + dx, current.y - 1)
+            && grid.diagonal_movement_allowed(current, dx, -1)
+        {
+            neighbors.push(GridPos { x: current.x + dx, y: current.y - 1 });
         }
     } else {
          if is_walkable(grid, current.x, current.y + dy) {
              neighbors.push(GridPos { x: current.x, y: current.y + dy });
         }
-        if !is_walkable(grid, current.x + 1, current.y) && is_walkable(grid, current.x + 1, current.y + dy) {
-             if grid.diagonal_movement_allowed(current, 1, dy) {
-                neighbors.push(GridPos { x: current.x + 1, y: current.y + dy });
-             }
+        if !is_walkable(grid, current.x + 1, current.y)
+            && is_walkable(grid, current.x + 1, current.y + dy)
+            && grid.diagonal_movement_allowed(current, 1, dy)
+        {
+            neighbors.push(GridPos { x: current.x + 1, y: current.y + dy });
         }
-        if !is_walkable(grid, current.x - 1, current.y) && is_walkable(grid, current.x - 1, current.y + dy) {
-             if grid.diagonal_movement_allowed(current, -1, dy) {
-                neighbors.push(GridPos { x: current.x - 1, y: current.y + dy });
-             }
+        if !is_walkable(grid, current.x - 1, current.y)
+            && is_walkable(grid, current.x - 1, current.y + dy)
+            && grid.diagonal_movement_allowed(current, -1, dy)
+        {
+            neighbors.push(GridPos { x: current.x - 1, y: current.y + dy });
         }
     }
     
     neighbors
 }
 
-fn jump(grid: &Grid2D, current: GridPos, dx: i32, dy: i32, goal: GridPos) -> Option<GridPos> {
+fn jump<G: JpsGrid>(grid: &G, current: GridPos, dx: i32, dy: i32, goal: GridPos) -> Option<GridPos> {
     let next_x = current.x + dx;
     let next_y = current.y + dy;
     
@@ -259,8 +292,8 @@ fn jump(grid: &Grid2D, current: GridPos, dx: i32, dy: i32, goal: GridPos) -> Opt
         return None;
     }
     
-    if dx != 0 && dy != 0 {
-         if !grid.diagonal_movement_allowed(current, dx, dy) { return None; }
+    if dx != 0 && dy != 0 && !grid.diagonal_movement_allowed(current, dx, dy) {
+        return None;
     }
     
     let next_node = GridPos { x: next_x, y: next_y };
@@ -292,47 +325,104 @@ fn jump(grid: &Grid2D, current: GridPos, dx: i32, dy: i32, goal: GridPos) -> Opt
     jump(grid, next_node, dx, dy, goal)
 }
 
-fn is_walkable(grid: &Grid2D, x: i32, y: i32) -> bool {
+fn is_walkable<G: JpsGrid>(grid: &G, x: i32, y: i32) -> bool {
     !grid.is_blocked(x, y)
 }
 
-trait GridExt {
-    fn diagonal_movement_allowed(&self, from: GridPos, dx: i32, dy: i32) -> bool;
-}
+/// Grids `jps` can search: uniform step costs, blocked cells and diagonal rules.
+pub trait JpsGrid {
+    fn is_blocked(&self, x: i32, y: i32) -> bool;
+    fn diagonal_movement(&self) -> DiagonalMode;
+    fn diagonal_cost(&self) -> f32;
+    /// `jps` refuses grids whose step costs depend on direction, and
+    /// `DiagonalMode::Never`/`OnlyIfBothOpen`.
+    fn has_directional_costs(&self) -> bool;
+    fn is_uniform(&self) -> bool;
 
-impl GridExt for Grid2D {
     fn diagonal_movement_allowed(&self, from: GridPos, dx: i32, dy: i32) -> bool {
-        if self.diagonal_movement == DiagonalMode::Never { return false; }
+        if self.diagonal_movement() == DiagonalMode::Never { return false; }
         let c1_blocked = self.is_blocked(from.x + dx, from.y);
         let c2_blocked = self.is_blocked(from.x, from.y + dy);
-        
-         match self.diagonal_movement {
-            DiagonalMode::Never => false,
-            DiagonalMode::Always => true,
-            DiagonalMode::IfNoObstacle => !c1_blocked || !c2_blocked,
-            DiagonalMode::OnlyIfBothOpen => !c1_blocked && !c2_blocked,
-        }
+        self.diagonal_movement().allows_diagonal(c1_blocked, c2_blocked)
     }
 }
 
-fn reconstruct_path<N: Clone + Eq + std::hash::Hash>(
-    current: N,
-    came_from: &HashMap<N, N>,
+impl JpsGrid for Grid2D {
+    fn is_blocked(&self, x: i32, y: i32) -> bool {
+        Grid2D::is_blocked(self, x, y)
+    }
+
+    fn diagonal_movement(&self) -> DiagonalMode {
+        self.diagonal_movement
+    }
+
+    fn diagonal_cost(&self) -> f32 {
+        self.diagonal_cost
+    }
+
+    fn has_directional_costs(&self) -> bool {
+        Grid2D::has_directional_costs(self)
+    }
+
+    fn is_uniform(&self) -> bool {
+        Grid2D::is_uniform(self)
+    }
+}
+
+impl JpsGrid for MaskedGrid2D<'_> {
+    fn is_blocked(&self, x: i32, y: i32) -> bool {
+        MaskedGrid2D::is_blocked(self, x, y)
+    }
+
+    fn diagonal_movement(&self) -> DiagonalMode {
+        self.base.diagonal_movement
+    }
+
+    fn diagonal_cost(&self) -> f32 {
+        self.base.diagonal_cost
+    }
+
+    fn has_directional_costs(&self) -> bool {
+        self.base.has_directional_costs()
+    }
+
+    fn is_uniform(&self) -> bool {
+        self.base.is_uniform()
+    }
+}
+
+fn reconstruct_path(
+    current: GridPos,
+    came_from: &HashMap<GridPos, GridPos>,
+    g_scores: &HashMap<GridPos, f32>,
     cost: f32,
-    nodes_expanded: usize,
+    stats: SearchStats,
     status: PathStatus
-) -> PathResult<N> {
-    let mut path = vec![current.clone()];
+) -> PathResult<GridPos> {
+    let mut path = vec![current];
     let mut cur = current;
     while let Some(parent) = came_from.get(&cur) {
-        path.push(parent.clone());
-        cur = parent.clone();
+        path.push(*parent);
+        cur = *parent;
     }
     path.reverse();
+    let mut cumulative_costs: Vec<f32> = path.iter()
+        .map(|n| g_scores.get(n).copied().unwrap_or(0.0))
+        .collect();
+    if let Some(last) = cumulative_costs.last_mut() {
+        *last = cost;
+    }
+    // Jumps are straight cardinal or diagonal runs, so this is also the expanded path's length
+    let length = path.windows(2).map(|w| Euclidean.estimate(&w[0], &w[1])).sum();
     PathResult {
         path,
         cost,
-        nodes_expanded,
+        length,
+        cumulative_costs,
+        nodes_expanded: stats.nodes_expanded,
+        best_g_score: stats.best_g_score,
+        nodes_in_open_set: stats.nodes_in_open_set,
+        elapsed: None,
         status,
     }
 }
@@ -5,3 +5,8 @@ pub mod flowfield;
 pub mod theta;
 pub mod replanning;
 pub mod funnel;
+pub mod tour;
+pub mod cbs;
+pub mod nearest;
+pub mod pareto;
+pub mod dispatch;
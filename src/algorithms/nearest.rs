@@ -0,0 +1,154 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::Instant;
+
+use crate::algorithms::astar::{reconstruct_path, AStarConfig};
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::traits::{Graph, PathResult, PathStatus, SearchStats};
+
+// Open-set entry, ordered so the max-heap pops the cheapest first
+struct State<N> {
+    node: N,
+    g_score: f32,
+}
+
+impl<N> PartialEq for State<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g_score == other.g_score
+    }
+}
+
+impl<N> Eq for State<N> {}
+
+impl<N> Ord for State<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.g_score.total_cmp(&self.g_score)
+    }
+}
+
+impl<N> PartialOrd for State<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The `k` members of `targets` cheapest to reach from `start`, nearest first, each with
+/// its path. One Dijkstra search serves them all, stopping once the `k`-th target is
+/// settled; fewer than `k` come back if the rest are unreachable or the config's
+/// `max_iterations`, `timeout` or `deadline` runs out first. `heuristic_weight` and
+/// `tie_breaking` don't apply. Each result's stats are the search's when its target was
+/// settled.
+pub fn nearest_targets<G: Graph>(
+    graph: &G,
+    start: G::Node,
+    targets: &HashSet<G::Node>,
+    k: usize,
+    config: AStarConfig,
+) -> Vec<(G::Node, PathResult<G::Node>)> {
+    let mut found = Vec::with_capacity(k.min(targets.len()));
+    if k == 0 || targets.is_empty() {
+        return found;
+    }
+    let deadline = config.deadline_from(None);
+    let mut open_set = BinaryHeap::new();
+    let mut g_scores = HashMap::new();
+    let mut came_from = HashMap::new();
+    g_scores.insert(start.clone(), 0.0);
+    open_set.push(State { node: start, g_score: 0.0 });
+
+    let mut nodes_expanded = 0;
+    let mut iterations = 0;
+    while let Some(State { node: current, g_score: current_g }) = open_set.pop() {
+        iterations += 1;
+        if config.max_iterations.is_some_and(|max| iterations > max) || deadline.is_some_and(|d| Instant::now() > d) {
+            break;
+        }
+        if g_scores.get(&current).is_some_and(|&best| current_g > best) {
+            continue; // Stale entry
+        }
+        if targets.contains(&current) {
+            let stats = SearchStats::new(nodes_expanded, current_g, open_set.len());
+            let result = reconstruct_path(graph, current.clone(), &came_from, &g_scores, current_g, stats, PathStatus::Found);
+            found.push((current.clone(), result));
+            if found.len() == k {
+                break;
+            }
+        }
+
+        nodes_expanded += 1;
+        graph.neighbors(&current, |neighbor, edge_cost| {
+            let tentative_g = current_g + edge_cost;
+            if g_scores.get(&neighbor).is_some_and(|&existing| tentative_g >= existing) {
+                return;
+            }
+            came_from.insert(neighbor.clone(), current.clone());
+            g_scores.insert(neighbor.clone(), tentative_g);
+            open_set.push(State { node: neighbor, g_score: tentative_g });
+        });
+    }
+    found
+}
+
+impl Grid2D {
+    /// `nearest_targets` over this grid, for targets in a slice (duplicates count once).
+    pub fn nearest_targets(
+        &self,
+        start: GridPos,
+        targets: &[GridPos],
+        k: usize,
+        config: AStarConfig,
+    ) -> Vec<(GridPos, PathResult<GridPos>)> {
+        nearest_targets(self, start, &targets.iter().copied().collect(), k, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::astar;
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::Octile;
+
+    #[test]
+    fn nearest_targets_come_in_true_path_cost_order() {
+        // A swamp band across the middle and a wall with one gap: straight-line distance
+        // and walking cost disagree
+        let mut grid = Grid2D::new(24, 24, DiagonalMode::IfNoObstacle);
+        for y in 8..14 {
+            for x in 0..24 {
+                grid.set_cost(x, y, 4.0);
+            }
+        }
+        grid.set_region_blocked((12, 0, 1, 20), true);
+        let start = GridPos { x: 10, y: 3 };
+        let targets = [
+            GridPos { x: 14, y: 3 },
+            GridPos { x: 2, y: 20 },
+            GridPos { x: 20, y: 22 },
+            GridPos { x: 5, y: 5 },
+            GridPos { x: 11, y: 15 },
+        ];
+
+        let mut expected: Vec<(GridPos, f32)> = targets
+            .iter()
+            .map(|&t| (t, astar(&grid, &Octile, start, t, AStarConfig::default()).cost))
+            .collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+        assert_ne!(expected[0].0, targets[0], "the target over the wall is near, but not by path");
+
+        let nearest = grid.nearest_targets(start, &targets, 3, AStarConfig::default());
+        assert_eq!(nearest.len(), 3);
+        for ((target, result), (expected_target, expected_cost)) in nearest.iter().zip(&expected) {
+            assert_eq!(target, expected_target);
+            assert!((result.cost - expected_cost).abs() < 1e-3, "{target:?}: {} vs {expected_cost}", result.cost);
+            assert_eq!(result.status, PathStatus::Found);
+            assert_eq!((result.path[0], result.path.last()), (start, Some(target)));
+        }
+
+        // Asking for more than there are returns them all, in order
+        let all = grid.nearest_targets(start, &targets, 10, AStarConfig::default());
+        assert_eq!(all.iter().map(|(t, _)| *t).collect::<Vec<_>>(), expected.iter().map(|(t, _)| *t).collect::<Vec<_>>());
+        grid.set_blocked(20, 22, true);
+        assert_eq!(grid.nearest_targets(start, &targets, 10, AStarConfig::default()).len(), 4);
+    }
+}
@@ -1,6 +1,11 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use rayon::prelude::*;
-use crate::traits::{Graph, Heuristic, PathResult};
+use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
 use crate::algorithms::astar::{astar, AStarConfig};
+use crate::graphs::grid2d::GridPos;
+use crate::heatmap::PathHeatmap;
 
 pub fn find_paths_parallel<G, H>(
     graph: &G,
@@ -19,3 +24,163 @@ where
         })
         .collect()
 }
+
+/// `find_paths_parallel` that also records every found path into `heatmap`.
+pub fn find_paths_parallel_recorded<G, H>(
+    graph: &G,
+    heuristic: &H,
+    queries: &[(GridPos, GridPos)],
+    config: AStarConfig,
+    heatmap: &PathHeatmap,
+) -> Vec<PathResult<GridPos>>
+where
+    G: Graph<Node = GridPos> + Sync,
+    H: Heuristic<GridPos> + Sync,
+{
+    queries.par_iter()
+        .map(|&(start, goal)| {
+            let result = astar(graph, heuristic, start, goal, config);
+            if result.status == PathStatus::Found {
+                heatmap.record_shared(&result.path);
+            }
+            result
+        })
+        .collect()
+}
+
+/// Like `find_paths_parallel`, but each query carries its own config (e.g. a longer
+/// timeout for important agents, 1ms for background NPCs). Results are in query order.
+pub fn find_paths_parallel_timed<G, H>(
+    graph: &G,
+    heuristic: &H,
+    queries: &[(G::Node, G::Node, AStarConfig)],
+) -> Vec<PathResult<G::Node>>
+where
+    G: Graph + Sync,
+    G::Node: Send + Sync,
+    H: Heuristic<G::Node> + Sync,
+{
+    queries.par_iter()
+        .map(|(start, goal, config)| {
+            astar(graph, heuristic, start.clone(), goal.clone(), *config)
+        })
+        .collect()
+}
+
+/// Runs queries in descending priority order (255 first; ties keep query order), so under
+/// load the important ones start and finish first. Results are in query order.
+pub fn find_paths_parallel_with_priority<G, H>(
+    graph: &G,
+    heuristic: &H,
+    queries: &[(G::Node, G::Node, u8, AStarConfig)],
+) -> Vec<PathResult<G::Node>>
+where
+    G: Graph + Sync,
+    G::Node: Send + Sync,
+    H: Heuristic<G::Node> + Sync,
+{
+    let mut order: Vec<usize> = (0..queries.len()).collect();
+    order.par_sort_unstable_by_key(|&i| (Reverse(queries[i].2), i));
+
+    // Workers pull from a shared cursor; splitting `order` with par_iter would let idle
+    // threads steal from the low-priority tail before the head has started
+    let next = AtomicUsize::new(0);
+    let workers = rayon::current_num_threads().min(queries.len());
+    let mut finished: Vec<(usize, PathResult<G::Node>)> = (0..workers)
+        .into_par_iter()
+        .flat_map_iter(|_| {
+            let mut done = Vec::new();
+            loop {
+                let slot = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&i) = order.get(slot) else { break };
+                let (start, goal, _, config) = &queries[i];
+                done.push((i, astar(graph, heuristic, start.clone(), goal.clone(), *config)));
+            }
+            done
+        })
+        .collect();
+
+    finished.sort_unstable_by_key(|&(i, _)| i);
+    finished.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Manhattan;
+    use crate::traits::PathStatus;
+
+    // Logs each goal the first time it is estimated toward, i.e. the order queries started
+    struct Recording {
+        log: Mutex<Vec<GridPos>>,
+    }
+
+    impl Heuristic<GridPos> for Recording {
+        fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+            let mut log = self.log.lock().unwrap();
+            if !log.contains(to) {
+                log.push(*to);
+            }
+            Manhattan.estimate(from, to)
+        }
+    }
+
+    // Serpentine maze: long paths with many expansions
+    fn slow_maze() -> Grid2D {
+        let mut grid = Grid2D::new(41, 41, DiagonalMode::Never);
+        for x in (1..41).step_by(2) {
+            let gap = if x % 4 == 1 { 40 } else { 0 };
+            grid.set_region_blocked((x, 0, 1, 41), true);
+            grid.set_blocked(x, gap, false);
+        }
+        grid
+    }
+
+    #[test]
+    fn per_query_configs_are_respected() {
+        let grid = slow_maze();
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 40, y: 40 });
+        let tight = AStarConfig { timeout: Some(Duration::ZERO), ..AStarConfig::default() };
+        let capped = AStarConfig { max_iterations: Some(10), ..AStarConfig::default() };
+        let queries = [
+            (start, goal, AStarConfig::default()),
+            (start, goal, capped),
+            (start, goal, tight),
+        ];
+
+        let results = find_paths_parallel_timed(&grid, &Manhattan, &queries);
+        assert_eq!(results[0].status, PathStatus::Found);
+        assert_eq!(results[1].status, PathStatus::PartialMaxIter);
+        assert_eq!(results[2].status, PathStatus::PartialTimeout);
+    }
+
+    #[test]
+    fn higher_priority_queries_run_first() {
+        let grid = slow_maze();
+        let start = GridPos { x: 0, y: 0 };
+        let goals: Vec<GridPos> = (0..6).map(|i| GridPos { x: 40, y: 40 - i * 2 }).collect();
+        let priorities = [3, 200, 0, 255, 200, 17];
+        let queries: Vec<_> = goals
+            .iter()
+            .zip(priorities)
+            .map(|(&g, p)| (start, g, p, AStarConfig::default()))
+            .collect();
+        let heuristic = Recording { log: Mutex::new(Vec::new()) };
+
+        // A single worker makes start order equal completion order
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let results = pool.install(|| find_paths_parallel_with_priority(&grid, &heuristic, &queries));
+
+        let expected: Vec<GridPos> = [3, 1, 4, 5, 0, 2].iter().map(|&i| goals[i]).collect();
+        assert_eq!(*heuristic.log.lock().unwrap(), expected);
+        // Results still line up with the input
+        for (result, goal) in results.iter().zip(&goals) {
+            assert_eq!(result.status, PathStatus::Found);
+            assert_eq!(result.path.last(), Some(goal));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::traits::Graph;
+
+// A partial path: its two costs and the label it extends
+struct Label<N> {
+    node: N,
+    g: (f32, f32),
+    parent: Option<usize>,
+    dominated: bool, // Pruned by a better label at the same node after it was queued
+}
+
+// Open-list entry, popped in lexicographic (cost, criterion) order
+struct Entry {
+    g: (f32, f32),
+    label: usize,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.g.0.total_cmp(&self.g.0).then(other.g.1.total_cmp(&self.g.1))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// `a` is at least as good as `b` on both counts
+fn covers(a: (f32, f32), b: (f32, f32)) -> bool {
+    a.0 <= b.0 && a.1 <= b.1
+}
+
+/// Paths from `start` to `goal` that trade the graph's edge cost against `criterion`
+/// (another per-edge cost, e.g. exposure) with none better on both: the Pareto front, as
+/// (cost, criterion total, path) triples by increasing cost, so the first is the cheapest
+/// path and the last (if `max_solutions` doesn't cut the list short) the one with the least
+/// criterion. Of paths with equal totals only one is kept.
+///
+/// NAMOA*-style label-setting search without a heuristic: each node keeps every
+/// non-dominated partial path to it, and partial paths dominated by one already at their
+/// node, or by a solution, are pruned. The front can grow with the square of the map,
+/// so keep `max_solutions` modest; the search stops once that many are found.
+pub fn pareto_paths<G, F>(
+    graph: &G,
+    criterion: F,
+    start: G::Node,
+    goal: G::Node,
+    max_solutions: usize,
+) -> Vec<(f32, f32, Vec<G::Node>)>
+where
+    G: Graph,
+    F: Fn(&G::Node, &G::Node) -> f32,
+{
+    let mut solutions = Vec::new();
+    if max_solutions == 0 || !graph.is_passable(&start) {
+        return solutions;
+    }
+    let mut labels = vec![Label { node: start.clone(), g: (0.0, 0.0), parent: None, dominated: false }];
+    let mut at_node: HashMap<G::Node, Vec<usize>> = HashMap::from([(start, vec![0])]);
+    let mut open = BinaryHeap::from([Entry { g: (0.0, 0.0), label: 0 }]);
+
+    while let Some(Entry { g, label }) = open.pop() {
+        if labels[label].dominated {
+            continue;
+        }
+        // Lexicographic order: an earlier solution covers this one iff its criterion is no worse
+        if solutions.last().is_some_and(|&(_, best, _): &(f32, f32, _)| best <= g.1) {
+            continue;
+        }
+        let node = labels[label].node.clone();
+        if node == goal {
+            let mut path = vec![node];
+            let mut cur = labels[label].parent;
+            while let Some(i) = cur {
+                path.push(labels[i].node.clone());
+                cur = labels[i].parent;
+            }
+            path.reverse();
+            solutions.push((g.0, g.1, path));
+            if solutions.len() == max_solutions {
+                break;
+            }
+            continue;
+        }
+
+        graph.neighbors(&node, |next, cost| {
+            let next_g = (g.0 + cost, g.1 + criterion(&node, &next));
+            if solutions.last().is_some_and(|&(_, best, _)| best <= next_g.1) {
+                return;
+            }
+            let existing = at_node.entry(next.clone()).or_default();
+            if existing.iter().any(|&i| covers(labels[i].g, next_g)) {
+                return;
+            }
+            existing.retain(|&i| {
+                let keep = !covers(next_g, labels[i].g);
+                labels[i].dominated |= !keep;
+                keep
+            });
+            existing.push(labels.len());
+            open.push(Entry { g: next_g, label: labels.len() });
+            labels.push(Label { node: next, g: next_g, parent: Some(label), dominated: false });
+        });
+    }
+    solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::graphs::adapters::MultiObjectiveGraph;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Manhattan;
+    use crate::traits::PathStatus;
+
+    // Open ground lit in rows 2 to 6; rows 0-1 and 7-8 are in cover, and so is a short
+    // stretch of row 3 next to the straight route
+    fn exposure(_: &GridPos, to: &GridPos) -> f32 {
+        let covered = !(2..=6).contains(&to.y) || (to.y == 3 && (6..=13).contains(&to.x));
+        if covered { 0.0 } else { 1.0 }
+    }
+
+    #[test]
+    fn weighted_exposure_and_the_pareto_front() {
+        let grid = Grid2D::new(20, 9, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 0, y: 4 }, GridPos { x: 19, y: 4 });
+        let plain = astar(&grid, &Manhattan, start, goal, AStarConfig::default());
+
+        // lambda = 0 is plain A*
+        let blind = MultiObjectiveGraph::new(&grid, vec![Box::new(exposure)], vec![0.0]);
+        let same = astar(&blind, &Manhattan, start, goal, AStarConfig::default());
+        assert_eq!((same.path.clone(), same.cost), (plain.path.clone(), plain.cost));
+        assert_eq!(blind.criteria_totals(&plain.path), vec![19.0]);
+        assert!(plain.path.iter().all(|p| p.y == 4));
+
+        // A large lambda takes cover: out to row 1, along it, and back in
+        let stealthy = MultiObjectiveGraph::new(&grid, vec![Box::new(exposure)], vec![10.0]);
+        let covered = astar(&stealthy, &Manhattan, start, goal, AStarConfig::default());
+        assert_eq!(covered.status, PathStatus::Found);
+        assert_eq!(covered.path.len() - 1, 25);
+        assert_eq!(stealthy.criteria_totals(&covered.path), vec![5.0]);
+
+        let front = pareto_paths(&grid, exposure, start, goal, 32);
+        // Both ends of the trade-off
+        let (first, last) = (&front[0], &front[front.len() - 1]);
+        assert_eq!((first.0, first.1), (19.0, 19.0));
+        assert_eq!((last.0, last.1), (25.0, 5.0));
+        assert!(front.len() > 2, "{:?}", front.iter().map(|s| (s.0, s.1)).collect::<Vec<_>>());
+        for (i, a) in front.iter().enumerate() {
+            assert_eq!((a.2[0], a.2.last()), (start, Some(&goal)));
+            assert_eq!(a.0, grid.path_cost(&a.2));
+            assert_eq!(a.1, a.2.windows(2).map(|w| exposure(&w[0], &w[1])).sum::<f32>());
+            for b in &front[i + 1..] {
+                assert!(!covers((a.0, a.1), (b.0, b.1)) && !covers((b.0, b.1), (a.0, a.1)));
+            }
+        }
+        assert_eq!(pareto_paths(&grid, exposure, start, goal, 1).len(), 1);
+    }
+}
@@ -1,11 +1,50 @@
-use crate::traits::{Graph, PathStatus, Heuristic};
-use crate::algorithms::astar::{astar, AStarConfig};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::time::Instant;
+
+use crate::traits::{path_length, Graph, PathResult, PathStatus, Heuristic, SearchStats};
+use crate::algorithms::astar::{astar, reconstruct_path, AStarConfig};
+use crate::geometry::first_blocked_segment;
+use crate::graphs::grid2d::{Grid2D, GridPos};
+
+/// Upcoming path nodes checked for new obstacles when the replan interval is due.
+const DEFAULT_VALIDITY_HORIZON: usize = 8;
+/// Cost budget of the local search that rejoins a displaced agent to its path.
+const DEFAULT_REANCHOR_RADIUS: f32 = 8.0;
+
+/// How `PathReplanner` computes a new path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplanStrategy {
+    /// A fresh `astar` search every time.
+    #[default]
+    FromScratch,
+    /// One `MovingTargetSearch` kept across updates, for goals that drift a little between
+    /// them (chasing a moving target). Ignores `heuristic_weight` and `tie_breaking`.
+    MovingTarget,
+}
 
 pub struct PathReplanner<N> {
     current_path: Vec<N>,
     current_goal: Option<N>,
     last_replanned_at: Option<std::time::Instant>,
     replan_interval: std::time::Duration,
+    validity_horizon: usize,
+    reanchor_radius: f32,
+    arrival: Option<Arrival<N>>,
+    strategy: ReplanStrategy,
+    moving_target: Option<MovingTargetSearch<N>>,
+}
+
+// Send + Sync so replanners can still move across threads
+type NodePredicate<N> = Box<dyn Fn(&N, &N) -> bool + Send + Sync>;
+
+// When `PathReplanner` considers its agent arrived
+enum Arrival<N> {
+    // Within `hops` steps of the goal along the current path; `eq` matches the agent to a node
+    Hops { hops: f32, eq: NodePredicate<N> },
+    // Close enough by the predicate, path or not
+    Near(NodePredicate<N>),
 }
 
 impl<N: Clone + Eq + PartialEq> PathReplanner<N> {
@@ -15,10 +54,73 @@ impl<N: Clone + Eq + PartialEq> PathReplanner<N> {
             current_goal: None,
             last_replanned_at: None,
             replan_interval,
+            validity_horizon: DEFAULT_VALIDITY_HORIZON,
+            reanchor_radius: DEFAULT_REANCHOR_RADIUS,
+            arrival: None,
+            strategy: ReplanStrategy::FromScratch,
+            moving_target: None,
+        }
+    }
+
+    /// Sets how many upcoming nodes are checked for blockage (minimum 1).
+    pub fn set_validity_horizon(&mut self, horizon: usize) {
+        self.validity_horizon = horizon.max(1);
+    }
+
+    /// Sets how far (in path cost) `update` searches for the path when the agent is found
+    /// off it; 0 disables re-anchoring, so every displacement costs a full replan.
+    pub fn set_reanchor_radius(&mut self, radius: f32) {
+        self.reanchor_radius = radius;
+    }
+
+    pub fn set_strategy(&mut self, strategy: ReplanStrategy) {
+        self.strategy = strategy;
+        self.moving_target = None;
+    }
+
+    /// Reports edited nodes to the `MovingTarget` search (see
+    /// `MovingTargetSearch::notify_changed`); not needed for `FromScratch`.
+    pub fn notify_changed(&mut self, nodes: &[N]) {
+        if let Some(search) = &mut self.moving_target {
+            search.notify_changed(nodes);
+        }
+    }
+
+    /// Counts the agent as arrived within `distance` hops of the goal, measured along the
+    /// current path, from the last node `eq` matches with the agent's position. `update`
+    /// stops replanning for an arrived agent.
+    pub fn set_arrival_tolerance(&mut self, distance: f32, eq: impl Fn(&N, &N) -> bool + Send + Sync + 'static) {
+        self.arrival = Some(Arrival::Hops { hops: distance, eq: Box::new(eq) });
+    }
+
+    /// Whether an agent at `current` has reached `goal`: exactly, unless an arrival
+    /// tolerance is set. Without a current path ending at `goal`, a hop tolerance only
+    /// accepts positions `eq` to the goal.
+    pub fn is_arrived(&self, current: &N, goal: &N) -> bool {
+        match &self.arrival {
+            None => current == goal,
+            Some(Arrival::Near(near)) => near(current, goal),
+            Some(Arrival::Hops { hops, eq }) => {
+                if eq(current, goal) {
+                    return true;
+                }
+                if !self.current_path.last().is_some_and(|last| eq(last, goal)) {
+                    return false;
+                }
+                self.current_path
+                    .iter()
+                    .rposition(|n| eq(n, current))
+                    .is_some_and(|i| (self.current_path.len() - 1 - i) as f32 <= *hops)
+            }
         }
     }
     
-    /// Returns a new path if replanning occurred, or None if the old path is still valid/kept.
+    /// Returns a new path if replanning occurred, or None if the old path is still valid/kept
+    /// or the agent is within its arrival tolerance (see `set_arrival_tolerance`).
+    /// An agent found off its path (knocked back, teleported) is first led back to it with
+    /// `reanchor`; the spliced path counts as a new one. Only if that fails is the whole
+    /// path planned again. A path blocked ahead keeps its walkable stretch (see
+    /// `truncate_at_first_blocked`) and is replanned from the last node before the block.
     pub fn update<G, H>(
         &mut self,
         graph: &G,
@@ -33,12 +135,27 @@ impl<N: Clone + Eq + PartialEq> PathReplanner<N> {
         N: std::hash::Hash + std::fmt::Debug,
     {
         let now = std::time::Instant::now();
+        if self.arrival.is_some() && self.is_arrived(&current_pos, &goal_pos) {
+            return None;
+        }
         
-        let needs_replan = if let Some(ref old_goal) = self.current_goal {
+        let mut needs_replan = if let Some(ref old_goal) = self.current_goal {
              *old_goal != goal_pos || self.current_path.is_empty()
         } else {
             true
         };
+
+        if !needs_replan && !self.current_path.contains(&current_pos) {
+            match reanchor(graph, &self.current_path, current_pos.clone(), self.reanchor_radius) {
+                Some(anchor) => {
+                    let mut path = anchor.stub;
+                    path.extend_from_slice(&self.current_path[anchor.join_index + 1..]);
+                    self.current_path = path.clone();
+                    return Some(path);
+                }
+                None => needs_replan = true,
+            }
+        }
         
         let time_due = if let Some(last) = self.last_replanned_at {
             now.duration_since(last) >= self.replan_interval
@@ -46,19 +163,32 @@ impl<N: Clone + Eq + PartialEq> PathReplanner<N> {
             true
         };
 
-        // Simple policy: Replan if goal moved OR (time is due AND path is invalid/blocked)
-        // Checking path validity is expensive (checking every node).
-        // Instead, we might just check the next few steps.
-        // For now, we replan if goal changed.
+        // Replan if goal moved OR (time is due AND the path ahead is blocked).
+        // Only the next `validity_horizon` nodes are checked to keep this cheap.
         
         if needs_replan || (time_due && self.should_check_validity(graph, &current_pos)) {
-            let result = astar(graph, heuristic, current_pos, goal_pos.clone(), config);
+            // Blocked ahead: keep the stretch still walkable and plan on from its last node,
+            // so an agent already moving toward the next cell carries on undisturbed
+            let mut kept = if needs_replan { Vec::new() } else { self.walkable_prefix(graph, &current_pos) };
+            let start = kept.pop().unwrap_or(current_pos);
+            let result = match self.strategy {
+                ReplanStrategy::FromScratch => astar(graph, heuristic, start, goal_pos.clone(), config),
+                ReplanStrategy::MovingTarget => {
+                    let search = self
+                        .moving_target
+                        .get_or_insert_with(|| MovingTargetSearch::new(start.clone(), goal_pos.clone()));
+                    search.update_start(start);
+                    search.update_target(goal_pos.clone());
+                    search.replan(graph, heuristic, config)
+                }
+            };
             
             if result.status == PathStatus::Found {
-                self.current_path = result.path.clone();
+                kept.extend(result.path);
+                self.current_path = kept.clone();
                 self.current_goal = Some(goal_pos);
                 self.last_replanned_at = Some(now);
-                return Some(result.path);
+                return Some(kept);
             }
         }
         
@@ -66,18 +196,803 @@ impl<N: Clone + Eq + PartialEq> PathReplanner<N> {
     }
     
     fn should_check_validity<G: Graph<Node = N>>(&self, graph: &G, current_pos: &N) -> bool {
-        // Check if the immediate next step in current path is blocked
-        // We need to find where we are in the path. 
-        // This is O(N) search.
+        // Find where we are in the path (O(N) search), then check the nodes ahead
         if let Some(idx) = self.current_path.iter().position(|n| *n == *current_pos) {
-            if idx + 1 < self.current_path.len() {
-                return !graph.is_passable(&self.current_path[idx+1]);
-            }
+            let end = (idx + 1 + self.validity_horizon).min(self.current_path.len());
+            let ahead = &self.current_path[idx..end];
+            return first_blocked_segment(ahead, |n| !graph.is_passable(n)).is_some();
         }
         false
     }
     
+    // From the agent's node up to the last one before the first blocked node; empty if the
+    // agent is off the path, stands on the blocked node, or nothing is blocked
+    fn walkable_prefix<G: Graph<Node = N>>(&self, graph: &G, current_pos: &N) -> Vec<N> {
+        let Some(idx) = self.current_path.iter().position(|n| n == current_pos) else {
+            return Vec::new();
+        };
+        match truncate_at_first_blocked(graph, &self.current_path, idx) {
+            (len, Some(_)) if len > idx => self.current_path[idx..len].to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn get_path(&self) -> &[N] {
         &self.current_path
     }
 }
+
+impl PathReplanner<GridPos> {
+    /// Counts the agent as arrived within Euclidean `distance` (in cells) of the goal,
+    /// whatever its path.
+    pub fn with_euclidean_tolerance(mut self, distance: f32) -> Self {
+        self.arrival = Some(Arrival::Near(Box::new(move |a: &GridPos, b: &GridPos| {
+            let (dx, dy) = ((a.x - b.x) as f32, (a.y - b.y) as f32);
+            (dx * dx + dy * dy).sqrt() <= distance
+        })));
+        self
+    }
+}
+
+/// Where `path` stops being walkable after the graph changed: the length of the prefix
+/// still passable, and the index of the first impassable node at or after `from_index`
+/// (None if the rest is clear, with the whole path as the prefix). Nodes before
+/// `from_index`, already walked, aren't checked. `PathResult::truncate` keeps the prefix;
+/// replan from its last node and `merge` the two.
+pub fn truncate_at_first_blocked<G: Graph>(
+    graph: &G,
+    path: &[G::Node],
+    from_index: usize,
+) -> (usize, Option<usize>) {
+    match path.iter().skip(from_index).position(|n| !graph.is_passable(n)) {
+        Some(offset) => (from_index + offset, Some(from_index + offset)),
+        None => (path.len(), None),
+    }
+}
+
+/// Where `reanchor` leads an agent back onto its path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReanchorResult<N> {
+    pub join_index: usize, // Index of the path node rejoined
+    pub stub: Vec<N>,      // From the agent's position to `path[join_index]`, both included
+    pub cost: f32,         // Cost of `stub`
+}
+
+/// Finds the way back onto `path` for an agent displaced to `current`: a local search out
+/// to `search_radius` (in path cost) from `current`, then the path node reached that makes
+/// the rest of the trip cheapest, i.e. stub cost plus the cost of the path after it. That
+/// favours joining ahead of the agent over walking back to where it was pushed from.
+/// An agent on the path joins where it stands with a one-node stub. None if no path node
+/// lies within the radius.
+///
+/// Steps between path nodes that aren't neighbors (e.g. a smoothed path) count their
+/// `Graph::geometric_distance`.
+pub fn reanchor<G: Graph>(graph: &G, path: &[G::Node], current: G::Node, search_radius: f32) -> Option<ReanchorResult<G::Node>> {
+    if let Some(join_index) = path.iter().position(|n| *n == current) {
+        return Some(ReanchorResult { join_index, stub: vec![current], cost: 0.0 });
+    }
+    let on_path: HashMap<&G::Node, usize> = path.iter().enumerate().map(|(i, n)| (n, i)).collect();
+
+    // Cost of the path from each node to its end
+    let mut remaining = vec![0.0; path.len()];
+    for i in (0..path.len().saturating_sub(1)).rev() {
+        let mut step = None;
+        graph.neighbors(&path[i], |n, cost| {
+            if n == path[i + 1] {
+                step = Some(cost);
+            }
+        });
+        let step = step.or_else(|| graph.geometric_distance(&path[i], &path[i + 1])).unwrap_or(0.0);
+        remaining[i] = remaining[i + 1] + step;
+    }
+
+    // Dijkstra out to the radius
+    let mut g_scores = HashMap::from([(current.clone(), 0.0)]);
+    let mut came_from = HashMap::new();
+    let mut open_set = BinaryHeap::from([ReanchorEntry { g: 0.0, node: current.clone() }]);
+    let mut best: Option<(f32, usize, G::Node)> = None; // (total, join index, node)
+    while let Some(ReanchorEntry { g, node }) = open_set.pop() {
+        if g_scores.get(&node).is_some_and(|&known| g > known) {
+            continue;
+        }
+        if let Some(&i) = on_path.get(&node) {
+            // Later joins win ties
+            let total = g + remaining[i];
+            if best.as_ref().is_none_or(|&(t, j, _)| total < t || (total == t && i > j)) {
+                best = Some((total, i, node.clone()));
+            }
+        }
+        graph.neighbors(&node, |next, cost| {
+            let next_g = g + cost;
+            if next_g > search_radius || g_scores.get(&next).is_some_and(|&known| next_g >= known) {
+                return;
+            }
+            g_scores.insert(next.clone(), next_g);
+            came_from.insert(next.clone(), node.clone());
+            open_set.push(ReanchorEntry { g: next_g, node: next });
+        });
+    }
+
+    let (_, join_index, join) = best?;
+    let cost = g_scores[&join];
+    let mut stub = vec![join];
+    while let Some(prev) = came_from.get(stub.last().unwrap()) {
+        stub.push(prev.clone());
+    }
+    stub.reverse();
+    Some(ReanchorResult { join_index, stub, cost })
+}
+
+// Cheapest first
+struct ReanchorEntry<N> {
+    g: f32,
+    node: N,
+}
+
+impl<N> PartialEq for ReanchorEntry<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.g == other.g
+    }
+}
+
+impl<N> Eq for ReanchorEntry<N> {}
+
+impl<N> Ord for ReanchorEntry<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.g.total_cmp(&self.g)
+    }
+}
+
+impl<N> PartialOrd for ReanchorEntry<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Lifelong Planning A* on a `Grid2D`: repeated searches between the same start and goal
+/// reuse the previous search, so after a few cells change only the affected part of the
+/// search is repaired. Report every edit with `notify_blocked`/`notify_unblocked` (after
+/// applying it to the grid), then call `replan`. Changing the start or goal starts over.
+///
+/// The heuristic must be consistent (e.g. `Diagonal::for_grid`, or `Manhattan` without
+/// diagonal movement).
+pub struct IncrementalGridAstar<H> {
+    width: usize,
+    height: usize,
+    g: Vec<f32>,   // Settled cost from the start
+    rhs: Vec<f32>, // One-step lookahead: min over predecessors of g + step cost
+    came_from: HashMap<usize, usize>, // Predecessor achieving `rhs`
+    queue: BinaryHeap<LpaEntry>,
+    changed: Vec<GridPos>, // Edited cells not yet folded into the search
+    endpoints: Option<(GridPos, GridPos)>,
+    _heuristic: PhantomData<fn(&H)>,
+}
+
+impl<H: Heuristic<GridPos>> IncrementalGridAstar<H> {
+    /// Empty search state sized for `grid`; the first `replan` is a full search.
+    pub fn new(grid: &Grid2D) -> Self {
+        let len = grid.width * grid.height;
+        Self {
+            width: grid.width,
+            height: grid.height,
+            g: vec![f32::INFINITY; len],
+            rhs: vec![f32::INFINITY; len],
+            came_from: HashMap::new(),
+            queue: BinaryHeap::new(),
+            changed: Vec::new(),
+            endpoints: None,
+            _heuristic: PhantomData,
+        }
+    }
+
+    /// Records that `pos` became blocked.
+    pub fn notify_blocked(&mut self, pos: GridPos) {
+        self.changed.push(pos);
+    }
+
+    /// Records that `pos` became passable (or changed cost).
+    pub fn notify_unblocked(&mut self, pos: GridPos) {
+        self.changed.push(pos);
+    }
+
+    /// Shortest path from `start` to `goal` on `grid` as it is now. `nodes_expanded` counts
+    /// only the expansions this call needed.
+    pub fn replan(&mut self, grid: &Grid2D, heuristic: &H, start: GridPos, goal: GridPos) -> PathResult<GridPos> {
+        assert!(
+            grid.width == self.width && grid.height == self.height,
+            "grid is {}x{}, search state is {}x{}",
+            grid.width, grid.height, self.width, self.height
+        );
+        let not_found = |nodes_expanded, nodes_in_open_set| PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded,
+            best_g_score: 0.0,
+            nodes_in_open_set,
+            status: PathStatus::NotFound,
+            elapsed: None,
+        };
+        let (Some(s), Some(goal_idx)) = (self.index(start), self.index(goal)) else {
+            return not_found(0, 0);
+        };
+
+        if self.endpoints != Some((start, goal)) {
+            self.g.fill(f32::INFINITY);
+            self.rhs.fill(f32::INFINITY);
+            self.came_from.clear();
+            self.queue.clear();
+            self.changed.clear();
+            self.endpoints = Some((start, goal));
+            self.rhs[s] = 0.0;
+            self.queue.push(self.entry(heuristic, s, goal));
+        }
+
+        // Edges into, out of and around (for corner cutting) each edited cell changed
+        for pos in std::mem::take(&mut self.changed) {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if let Some(idx) = self.index(GridPos { x: pos.x + dx, y: pos.y + dy }) {
+                        self.update_vertex(grid, heuristic, idx, s, goal);
+                    }
+                }
+            }
+        }
+
+        // Expand until the goal is consistent and nothing queued can improve it
+        let mut nodes_expanded = 0;
+        while let Some(top) = self.queue.peek() {
+            let goal_key = self.key(heuristic, goal_idx, goal);
+            if top.key >= goal_key && self.g[goal_idx] == self.rhs[goal_idx] {
+                break;
+            }
+            let LpaEntry { key, idx } = self.queue.pop().unwrap();
+            if self.g[idx] == self.rhs[idx] || key != self.key(heuristic, idx, goal) {
+                continue; // Stale entry
+            }
+            nodes_expanded += 1;
+            if self.g[idx] > self.rhs[idx] {
+                self.g[idx] = self.rhs[idx];
+            } else {
+                self.g[idx] = f32::INFINITY;
+                self.update_vertex(grid, heuristic, idx, s, goal);
+            }
+            let successors: Vec<usize> =
+                grid.neighbors_iter(self.pos(idx)).filter_map(|(n, _)| self.index(n)).collect();
+            for succ in successors {
+                self.update_vertex(grid, heuristic, succ, s, goal);
+            }
+        }
+
+        if !self.g[goal_idx].is_finite() {
+            return not_found(nodes_expanded, self.queue.len());
+        }
+        let mut indices = vec![goal_idx];
+        while let Some(&prev) = self.came_from.get(indices.last().unwrap()) {
+            indices.push(prev);
+        }
+        indices.reverse();
+        let path: Vec<GridPos> = indices.iter().map(|&i| self.pos(i)).collect();
+        PathResult {
+            length: path_length(grid, &path),
+            path,
+            cost: self.g[goal_idx],
+            cumulative_costs: indices.iter().map(|&i| self.g[i]).collect(),
+            nodes_expanded,
+            best_g_score: self.g[goal_idx],
+            nodes_in_open_set: self.queue.len(),
+            status: PathStatus::Found,
+            elapsed: None,
+        }
+    }
+
+    // Recomputes `rhs` from the predecessors of `idx` and queues it if inconsistent
+    fn update_vertex(&mut self, grid: &Grid2D, heuristic: &H, idx: usize, start: usize, goal: GridPos) {
+        if idx != start {
+            let pos = self.pos(idx);
+            let mut best = (f32::INFINITY, None);
+            for (pred, _) in grid.neighbors_iter(pos) {
+                let Some(p) = self.index(pred) else { continue };
+                // Entry costs can be direction-dependent, so take the step as seen from `pred`
+                if let Some((_, step)) = grid.neighbors_iter(pred).find(|(n, _)| *n == pos) {
+                    if self.g[p] + step < best.0 {
+                        best = (self.g[p] + step, Some(p));
+                    }
+                }
+            }
+            self.rhs[idx] = best.0;
+            match best.1 {
+                Some(p) => self.came_from.insert(idx, p),
+                None => self.came_from.remove(&idx),
+            };
+        }
+        if self.g[idx] != self.rhs[idx] {
+            self.queue.push(self.entry(heuristic, idx, goal));
+        }
+    }
+
+    fn key(&self, heuristic: &H, idx: usize, goal: GridPos) -> (f32, f32) {
+        let best = self.g[idx].min(self.rhs[idx]);
+        (best + heuristic.estimate(&self.pos(idx), &goal), best)
+    }
+
+    fn entry(&self, heuristic: &H, idx: usize, goal: GridPos) -> LpaEntry {
+        LpaEntry { key: self.key(heuristic, idx, goal), idx }
+    }
+
+    fn index(&self, pos: GridPos) -> Option<usize> {
+        let in_bounds = pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height;
+        in_bounds.then(|| pos.y as usize * self.width + pos.x as usize)
+    }
+
+    fn pos(&self, idx: usize) -> GridPos {
+        GridPos { x: (idx % self.width) as i32, y: (idx / self.width) as i32 }
+    }
+}
+
+// Queue entry ordered by smallest key first; entries are never updated in place, so a popped
+// one is stale if its key no longer matches the node's
+#[derive(PartialEq)]
+struct LpaEntry {
+    key: (f32, f32),
+    idx: usize,
+}
+
+impl Eq for LpaEntry {}
+
+impl Ord for LpaEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.0.total_cmp(&self.key.0).then_with(|| other.key.1.total_cmp(&self.key.1))
+    }
+}
+
+impl PartialOrd for LpaEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Incremental search for chasing a moving target on any `Graph`, reusing the previous
+/// search tree (in the style of Fringe-Retrieving A*). Settled nodes keep their exact cost
+/// from the start whatever the goal, so a moved target only re-prioritizes the open list
+/// and the search resumes where it stopped; a target that moved into settled ground costs
+/// no expansions at all. When the agent steps to a node the last search settled, the part
+/// of the tree below it is kept (its costs just shift), and only its border is rebuilt.
+///
+/// Neighbors must be symmetric (if `a` steps to `b`, `b` steps to `a`), as on the grids;
+/// step costs may differ by direction. The heuristic passed to `replan` must be consistent.
+/// An agent off the tree, or an edit reported with `notify_changed` next to explored
+/// nodes, starts the next search over.
+pub struct MovingTargetSearch<N> {
+    start: N,
+    goal: N,
+    root: Option<N>, // Start the tree is rooted at; None before the first search
+    g_scores: HashMap<N, f32>,
+    came_from: HashMap<N, N>,
+    closed: HashSet<N>, // Expanded, with exact costs from `root`
+    changed: Vec<N>,
+}
+
+impl<N: Clone> MovingTargetSearch<N> {
+    pub fn new(start: N, goal: N) -> Self {
+        Self {
+            start,
+            goal,
+            root: None,
+            g_scores: HashMap::new(),
+            came_from: HashMap::new(),
+            closed: HashSet::new(),
+            changed: Vec::new(),
+        }
+    }
+
+    pub fn update_start(&mut self, new_start: N) {
+        self.start = new_start;
+    }
+
+    pub fn update_target(&mut self, new_goal: N) {
+        self.goal = new_goal;
+    }
+
+    /// Records nodes whose cost or passability changed since the last `replan`.
+    pub fn notify_changed(&mut self, nodes: &[N]) {
+        self.changed.extend_from_slice(nodes);
+    }
+}
+
+impl<N: Clone + Eq + std::hash::Hash> MovingTargetSearch<N> {
+    /// Optimal path from the current start to the current target. `nodes_expanded` counts
+    /// only this call's expansions. Honors `max_iterations`, `timeout` and `deadline` like
+    /// `astar`; `heuristic_weight` and `tie_breaking` don't apply.
+    pub fn replan<G, H>(&mut self, graph: &G, heuristic: &H, config: AStarConfig) -> PathResult<N>
+    where
+        G: Graph<Node = N>,
+        H: Heuristic<N>,
+    {
+        let changed = std::mem::take(&mut self.changed);
+        let mut touched = false;
+        for node in &changed {
+            touched |= self.g_scores.contains_key(node);
+            graph.neighbors(node, |n, _| touched |= self.g_scores.contains_key(&n));
+        }
+        if touched || self.root.is_none() {
+            self.restart();
+        } else if self.root.as_ref() != Some(&self.start) {
+            if self.closed.contains(&self.start) {
+                self.reroot(graph);
+            } else {
+                self.restart();
+            }
+        }
+
+        let deadline = config.deadline_from(None);
+        let goal = self.goal.clone();
+        let mut nodes_expanded = 0;
+        if self.closed.contains(&goal) {
+            let stats = SearchStats::new(0, self.g_scores[&goal], 0);
+            return reconstruct_path(graph, goal.clone(), &self.came_from, &self.g_scores, self.g_scores[&goal], stats, PathStatus::Found);
+        }
+        // The open list is everything reached but not expanded, keyed for the current goal
+        let mut open_set: BinaryHeap<MovingTargetEntry<N>> = self
+            .g_scores
+            .iter()
+            .filter(|(n, _)| !self.closed.contains(*n))
+            .map(|(n, &g)| MovingTargetEntry { f: g + heuristic.estimate(n, &goal), g, node: n.clone() })
+            .collect();
+        let mut iterations = 0;
+        while let Some(MovingTargetEntry { g, node, .. }) = open_set.pop() {
+            if self.closed.contains(&node) || self.g_scores.get(&node).is_some_and(|&best| g > best) {
+                continue; // Stale entry
+            }
+            iterations += 1;
+            let limit = if config.max_iterations.is_some_and(|max| iterations > max) {
+                Some(PathStatus::PartialMaxIter)
+            } else if deadline.is_some_and(|d| Instant::now() > d) {
+                Some(PathStatus::PartialTimeout)
+            } else {
+                None
+            };
+            if node == goal || limit.is_some() {
+                let stats = SearchStats::new(nodes_expanded, g, open_set.len());
+                let status = limit.unwrap_or(PathStatus::Found);
+                return reconstruct_path(graph, node, &self.came_from, &self.g_scores, g, stats, status);
+            }
+
+            nodes_expanded += 1;
+            self.closed.insert(node.clone());
+            graph.neighbors(&node, |next, cost| {
+                let next_g = g + cost;
+                if self.closed.contains(&next) || self.g_scores.get(&next).is_some_and(|&known| next_g >= known) {
+                    return;
+                }
+                let f = next_g + heuristic.estimate(&next, &goal);
+                self.g_scores.insert(next.clone(), next_g);
+                self.came_from.insert(next.clone(), node.clone());
+                open_set.push(MovingTargetEntry { f, g: next_g, node: next });
+            });
+        }
+        PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::NotFound,
+            elapsed: None,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.g_scores.clear();
+        self.came_from.clear();
+        self.closed.clear();
+        self.g_scores.insert(self.start.clone(), 0.0);
+        self.root = Some(self.start.clone());
+    }
+
+    // Re-roots the tree at the (settled) start: its subtree keeps exact costs, less the
+    // start's own, and the nodes around the subtree are reached again from it
+    fn reroot<G: Graph<Node = N>>(&mut self, graph: &G) {
+        let mut children: HashMap<&N, Vec<&N>> = HashMap::new();
+        for (child, parent) in &self.came_from {
+            children.entry(parent).or_default().push(child);
+        }
+        let mut subtree = HashSet::from([self.start.clone()]);
+        let mut stack = vec![&self.start];
+        while let Some(node) = stack.pop() {
+            for &child in children.get(node).into_iter().flatten() {
+                subtree.insert(child.clone());
+                stack.push(child);
+            }
+        }
+
+        let offset = self.g_scores[&self.start];
+        let closed: HashSet<N> = self.closed.iter().filter(|n| subtree.contains(*n)).cloned().collect();
+        let mut g_scores: HashMap<N, f32> = subtree.iter().map(|n| (n.clone(), self.g_scores[n] - offset)).collect();
+        let mut came_from: HashMap<N, N> =
+            self.came_from.iter().filter(|(n, _)| subtree.contains(*n) && **n != self.start).map(|(n, p)| (n.clone(), p.clone())).collect();
+        // Dropped nodes next to the kept settled ones are the new border
+        for node in self.g_scores.keys().filter(|n| !subtree.contains(*n)) {
+            let mut best: Option<(f32, N)> = None;
+            graph.neighbors(node, |pred, _| {
+                if !closed.contains(&pred) {
+                    return;
+                }
+                graph.neighbors(&pred, |n, cost| {
+                    let g = g_scores[&pred] + cost;
+                    if n == *node && best.as_ref().is_none_or(|(b, _)| g < *b) {
+                        best = Some((g, pred.clone()));
+                    }
+                });
+            });
+            if let Some((g, pred)) = best {
+                g_scores.insert(node.clone(), g);
+                came_from.insert(node.clone(), pred);
+            }
+        }
+
+        self.g_scores = g_scores;
+        self.came_from = came_from;
+        self.closed = closed;
+        self.root = Some(self.start.clone());
+    }
+}
+
+// Open-set entry: lowest f first, then the deeper node
+struct MovingTargetEntry<N> {
+    f: f32,
+    g: f32,
+    node: N,
+}
+
+impl<N> PartialEq for MovingTargetEntry<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.f == other.f && self.g == other.g
+    }
+}
+
+impl<N> Eq for MovingTargetEntry<N> {}
+
+impl<N> Ord for MovingTargetEntry<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.total_cmp(&self.f).then_with(|| self.g.total_cmp(&other.g))
+    }
+}
+
+impl<N> PartialOrd for MovingTargetEntry<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Manhattan;
+    use std::time::Duration;
+
+    #[test]
+    fn replanner_with_tolerance_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let mut replanner = PathReplanner::<GridPos>::new(Duration::from_millis(100)).with_euclidean_tolerance(2.0);
+        replanner.set_arrival_tolerance(1.0, |a, b| a == b);
+        assert_send_sync(&replanner);
+    }
+
+    #[test]
+    fn replans_when_obstacle_appears_within_horizon() {
+        let mut grid = Grid2D::new(10, 3, DiagonalMode::Never);
+        let start = GridPos { x: 0, y: 1 };
+        let goal = GridPos { x: 9, y: 1 };
+        let mut replanner = PathReplanner::new(Duration::ZERO);
+        replanner.set_validity_horizon(4);
+
+        let config = AStarConfig::default();
+        assert!(replanner.update(&grid, &Manhattan, start, goal, config).is_some());
+        assert!(replanner.update(&grid, &Manhattan, start, goal, config).is_none());
+
+        // Beyond the horizon: not noticed yet
+        let far = replanner.get_path()[6];
+        grid.set_blocked(far.x as usize, far.y as usize, true);
+        assert!(replanner.update(&grid, &Manhattan, start, goal, config).is_none());
+
+        // Three steps ahead: inside the horizon, triggers a replan around it
+        let near = replanner.get_path()[3];
+        grid.set_blocked(near.x as usize, near.y as usize, true);
+        let path = replanner.update(&grid, &Manhattan, start, goal, config).unwrap();
+        assert!(!path.contains(&near) && !path.contains(&far));
+    }
+
+    #[test]
+    fn blocked_paths_keep_their_walkable_prefix() {
+        // A 40-cell run down the middle row of a 3-row hall
+        let mut grid = Grid2D::new(40, 3, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 0, y: 1 }, GridPos { x: 39, y: 1 });
+        let result = astar(&grid, &Manhattan, start, goal, AStarConfig::default());
+        assert_eq!(result.path.len(), 40);
+        assert_eq!(truncate_at_first_blocked(&grid, &result.path, 0), (40, None));
+
+        grid.set_blocked(17, 1, true);
+        assert_eq!(truncate_at_first_blocked(&grid, &result.path, 0), (17, Some(17)));
+        assert_eq!(truncate_at_first_blocked(&grid, &result.path, 5), (17, Some(17)));
+        let prefix = result.truncate(17, &grid);
+        assert_eq!((prefix.path.len(), prefix.cost, prefix.length), (17, 16.0, 16.0));
+        assert_eq!(prefix.path.last(), Some(&GridPos { x: 16, y: 1 }));
+
+        // Replan from node 16 and splice
+        let detour = astar(&grid, &Manhattan, prefix.path[16], goal, AStarConfig::default());
+        let combined = PathResult::merge(prefix, detour).unwrap();
+        assert!(combined.validate());
+        assert_eq!(combined.status, PathStatus::Found);
+        assert_eq!((combined.path[0], combined.path.last()), (start, Some(&goal)));
+        assert_eq!(combined.path[..17], result.path[..17]);
+        assert!(combined.path.iter().all(|p| !grid.is_blocked(p.x, p.y)));
+        assert!(combined.path.windows(2).all(|w| grid.neighbors_iter(w[0]).any(|(n, _)| n == w[1])));
+        assert_eq!(combined.cost, 41.0);
+
+        // The replanner, told of the wall while its agent is at x = 12, walks on to x = 16
+        grid.set_blocked(17, 1, false);
+        let mut replanner = PathReplanner::new(Duration::ZERO);
+        replanner.update(&grid, &Manhattan, start, goal, AStarConfig::default()).unwrap();
+        grid.set_blocked(17, 1, true);
+        let agent = GridPos { x: 12, y: 1 };
+        let spliced = replanner.update(&grid, &Manhattan, agent, goal, AStarConfig::default()).unwrap();
+        assert_eq!(spliced[..5], result.path[12..17]);
+        assert_eq!(spliced[5..], combined.path[17..]);
+    }
+
+    #[test]
+    fn displaced_agent_rejoins_ahead_with_a_valid_stub() {
+        use crate::heuristics::Octile;
+
+        // A 7-wide hall; the path runs down its middle row
+        let mut grid = Grid2D::new(30, 9, DiagonalMode::OnlyIfBothOpen);
+        grid.set_region_blocked((0, 0, 30, 1), true);
+        grid.set_region_blocked((0, 8, 30, 1), true);
+        let (start, goal) = (GridPos { x: 0, y: 4 }, GridPos { x: 29, y: 4 });
+        let path = astar(&grid, &Octile, start, goal, AStarConfig::default()).path;
+        assert!(path.iter().all(|p| p.y == 4));
+
+        // Knocked 3 cells sideways while at x = 10
+        let pushed = GridPos { x: 10, y: 7 };
+        let anchor = reanchor(&grid, &path, pushed, 8.0).unwrap();
+        assert!(path[anchor.join_index].x > 10, "rejoined at {:?}", path[anchor.join_index]);
+        assert_eq!((anchor.stub[0], anchor.stub.last()), (pushed, Some(&path[anchor.join_index])));
+        assert!(anchor.stub.windows(2).all(|w| grid.neighbors_iter(w[0]).any(|(n, _)| n == w[1])));
+        // Rejoining there is as good as planning from scratch
+        let remaining = (path.len() - 1 - anchor.join_index) as f32;
+        let fresh = astar(&grid, &Octile, pushed, goal, AStarConfig::default());
+        assert!((anchor.cost + remaining - fresh.cost).abs() < 1e-4);
+
+        assert_eq!(reanchor(&grid, &path, path[5], 8.0).map(|a| (a.join_index, a.cost)), Some((5, 0.0)));
+        assert!(reanchor(&grid, &path, pushed, 2.0).is_none());
+
+        // The replanner splices the stub onto the rest of its path
+        let mut replanner = PathReplanner::new(Duration::from_secs(60));
+        replanner.update(&grid, &Octile, start, goal, AStarConfig::default()).unwrap();
+        let spliced = replanner.update(&grid, &Octile, pushed, goal, AStarConfig::default()).unwrap();
+        assert_eq!(spliced[..anchor.stub.len()], anchor.stub[..]);
+        assert_eq!(spliced[anchor.stub.len()..], path[anchor.join_index + 1..]);
+        assert_eq!(spliced.last(), Some(&goal));
+        // Too far to find the path again: a full replan
+        replanner.set_reanchor_radius(0.0);
+        let far = GridPos { x: 2, y: 1 };
+        let replanned = replanner.update(&grid, &Octile, far, goal, AStarConfig::default()).unwrap();
+        assert_eq!(replanned[0], far);
+    }
+
+    #[test]
+    fn arrival_tolerance_stops_replanning_near_the_goal() {
+        let grid = Grid2D::new(10, 3, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 0, y: 1 }, GridPos { x: 9, y: 1 });
+        let two_away = GridPos { x: 7, y: 1 };
+
+        let near = PathReplanner::new(Duration::ZERO).with_euclidean_tolerance(3.0);
+        assert!(near.is_arrived(&two_away, &goal));
+        let strict = PathReplanner::new(Duration::ZERO).with_euclidean_tolerance(1.0);
+        assert!(!strict.is_arrived(&two_away, &goal));
+
+        // Hops count along the current path
+        let mut replanner = PathReplanner::new(Duration::ZERO);
+        replanner.set_arrival_tolerance(3.0, |a: &GridPos, b: &GridPos| a == b);
+        assert!(!replanner.is_arrived(&two_away, &goal), "no path yet");
+        assert!(replanner.update(&grid, &Manhattan, start, goal, AStarConfig::default()).is_some());
+        assert!(replanner.is_arrived(&two_away, &goal));
+        assert!(!replanner.is_arrived(&GridPos { x: 5, y: 1 }, &goal));
+        assert!(replanner.update(&grid, &Manhattan, two_away, goal, AStarConfig::default()).is_none());
+        // Even a new goal right there needs no path
+        assert!(replanner.update(&grid, &Manhattan, goal, goal, AStarConfig::default()).is_none());
+
+        replanner.set_arrival_tolerance(1.0, |a: &GridPos, b: &GridPos| a == b);
+        assert!(!replanner.is_arrived(&two_away, &goal));
+    }
+
+    #[test]
+    fn chasing_a_fleeing_target_reuses_earlier_searches() {
+        use crate::graphs::grid2d::GridPattern;
+
+        let mut grid = Grid2D::from_pattern(81, 81, GridPattern::Maze(11), DiagonalMode::Never);
+        let config = AStarConfig::default();
+        // The target flees along a fixed route, one cell per step; the chaser follows its plan
+        let route = astar(&grid, &Manhattan, GridPos { x: 41, y: 41 }, GridPos { x: 79, y: 1 }, config).path;
+        assert!(route.len() > 60, "{}", route.len());
+        let mut chaser = GridPos { x: 1, y: 79 };
+        let mut search = MovingTargetSearch::new(chaser, route[0]);
+        let mut replanner = PathReplanner::new(Duration::ZERO);
+        replanner.set_strategy(ReplanStrategy::MovingTarget);
+
+        let (mut incremental, mut from_scratch) = (0, 0);
+        for (step, &target) in route.iter().take(50).enumerate() {
+            if step == 25 {
+                // A wall opens up next to explored ground; the search starts over
+                let door = (1..80)
+                    .flat_map(|y| (1..80).map(move |x| GridPos { x, y }))
+                    .find(|p| grid.is_blocked(p.x, p.y) && (p.x + p.y) % 2 == 1 && p.x > 10 && p.y > 10)
+                    .unwrap();
+                grid.set_blocked(door.x as usize, door.y as usize, false);
+                search.notify_changed(&[door]);
+                replanner.notify_changed(&[door]);
+            }
+            search.update_start(chaser);
+            search.update_target(target);
+            let result = search.replan(&grid, &Manhattan, config);
+            let fresh = astar(&grid, &Manhattan, chaser, target, config);
+            assert_eq!(result.status, PathStatus::Found);
+            assert_eq!(result.cost, fresh.cost, "step {step}");
+            assert_eq!((result.path[0], result.path.last()), (chaser, Some(&target)));
+            assert!(result.path.windows(2).all(|w| grid.neighbors_iter(w[0]).any(|(n, _)| n == w[1])));
+            assert_eq!(replanner.update(&grid, &Manhattan, chaser, target, config).map(|p| p.len()), Some(result.path.len()));
+            incremental += result.nodes_expanded;
+            from_scratch += fresh.nodes_expanded;
+            chaser = result.path[1];
+        }
+        assert!(incremental * 10 < from_scratch, "{incremental} vs {from_scratch}");
+    }
+
+    #[test]
+    fn incremental_repair_matches_fresh_astar_with_fewer_expansions() {
+        use crate::heuristics::Diagonal;
+
+        let mut grid = Grid2D::new(100, 100, DiagonalMode::Always);
+        grid.set_region_blocked((30, 10, 2, 70), true);
+        grid.set_region_blocked((60, 25, 2, 75), true);
+        let heuristic = Diagonal::for_grid(&grid);
+        let (start, goal) = (GridPos { x: 5, y: 50 }, GridPos { x: 95, y: 60 });
+        let mut planner = IncrementalGridAstar::new(&grid);
+
+        let first = planner.replan(&grid, &heuristic, start, goal);
+        let fresh = astar(&grid, &heuristic, start, goal, AStarConfig::default());
+        assert_eq!(first.status, PathStatus::Found);
+        assert!((first.cost - fresh.cost).abs() < 1e-3);
+
+        // Nothing changed: nothing to expand
+        assert_eq!(planner.replan(&grid, &heuristic, start, goal).nodes_expanded, 0);
+
+        let mid = first.path[first.path.len() / 2];
+        grid.set_blocked(mid.x as usize, mid.y as usize, true);
+        planner.notify_blocked(mid);
+        let repaired = planner.replan(&grid, &heuristic, start, goal);
+        let fresh = astar(&grid, &heuristic, start, goal, AStarConfig::default());
+        assert_eq!(repaired.status, PathStatus::Found);
+        assert!((repaired.cost - fresh.cost).abs() < 1e-3, "{} vs {}", repaired.cost, fresh.cost);
+        assert!(!repaired.path.contains(&mid));
+        assert_eq!((repaired.path[0], *repaired.path.last().unwrap()), (start, goal));
+        assert!(repaired.path.windows(2).all(|w| grid.neighbors_iter(w[0]).any(|(n, _)| n == w[1])));
+        assert!(
+            repaired.nodes_expanded < fresh.nodes_expanded,
+            "repair expanded {}, fresh A* {}",
+            repaired.nodes_expanded,
+            fresh.nodes_expanded
+        );
+
+        // Unblocking restores the original cost
+        grid.set_blocked(mid.x as usize, mid.y as usize, false);
+        planner.notify_unblocked(mid);
+        let restored = planner.replan(&grid, &heuristic, start, goal);
+        assert!((restored.cost - first.cost).abs() < 1e-3);
+    }
+}
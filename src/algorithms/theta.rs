@@ -1,5 +1,5 @@
-use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
-use crate::algorithms::astar::{AStarConfig, TieBreaking};
+use crate::traits::{path_length, search_capacity, Graph, Heuristic, PathResult, PathStatus, SearchStats};
+use crate::algorithms::astar::AStarConfig;
 use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
 use std::hash::Hash;
@@ -55,15 +55,17 @@ where
     H: Heuristic<G::Node>,
     G::Node: Clone + Eq + Hash + std::fmt::Debug,
 {
-    let start_time = Instant::now();
-    let mut open_set = BinaryHeap::new();
-    let mut g_scores = HashMap::new();
-    let mut came_from = HashMap::new();
+    let started = cfg!(feature = "timing").then(Instant::now);
+    let deadline = config.deadline_from(started);
+    let h_start = heuristic.estimate(&start, &goal) * config.heuristic_weight;
+    let capacity = search_capacity(graph.node_count_hint(), h_start);
+    let mut open_set = BinaryHeap::with_capacity(capacity);
+    let mut g_scores = HashMap::with_capacity(capacity);
+    let mut came_from = HashMap::with_capacity(capacity);
     
     g_scores.insert(start.clone(), 0.0);
     came_from.insert(start.clone(), start.clone()); // Parent of start is start
     
-    let h_start = heuristic.estimate(&start, &goal);
     open_set.push(State {
         node: start.clone(),
         cost: h_start,
@@ -72,6 +74,7 @@ where
     });
     
     let mut nodes_expanded = 0;
+    let mut furthest_g: f32 = 0.0;
     let mut iterations = 0;
     
     while let Some(State { node: current, cost: _, g_score: current_g, tie_breaker: _ }) = open_set.pop() {
@@ -80,17 +83,16 @@ where
         // Check limits
         if let Some(max_iter) = config.max_iterations {
             if iterations > max_iter {
-                 return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::PartialMaxIter);
+                 return reconstruct_path(graph, current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::PartialMaxIter).timed(started);
             }
         }
-        if let Some(timeout) = config.timeout {
-            if start_time.elapsed() > timeout {
-                return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::PartialTimeout);
-            }
+        if deadline.is_some_and(|d| Instant::now() > d) {
+            return reconstruct_path(graph, current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::PartialTimeout).timed(started);
         }
         
         if current == goal {
-            return reconstruct_path(current, &came_from, current_g, nodes_expanded, PathStatus::Found);
+            furthest_g = furthest_g.max(current_g);
+            return reconstruct_path(graph, current, &came_from, &g_scores, current_g, SearchStats::new(nodes_expanded, furthest_g, open_set.len()), PathStatus::Found).timed(started);
         }
         
         if let Some(&best_g) = g_scores.get(&current) {
@@ -100,6 +102,7 @@ where
         }
         
         nodes_expanded += 1;
+        furthest_g = furthest_g.max(current_g);
 
         // To access parent(current), we look it up.
         // For start node, parent is start.
@@ -138,15 +141,10 @@ where
             came_from.insert(neighbor.clone(), new_parent);
             g_scores.insert(neighbor.clone(), new_g);
             
-            let h = heuristic.estimate(&neighbor, &goal);
+            let h = heuristic.estimate(&neighbor, &goal) * config.heuristic_weight;
             let f = new_g + h;
             
-            let tb = match config.tie_breaking {
-                TieBreaking::None => 0.0,
-                TieBreaking::PreferHigherG => new_g,
-                TieBreaking::PreferLowerG => -new_g,
-                TieBreaking::CrossProduct => 0.0,
-            };
+            let tb = config.tie_breaking.key(&neighbor, new_g);
             
             open_set.push(State {
                 node: neighbor,
@@ -157,21 +155,29 @@ where
         });
     }
     
-    PathResult {
+    let not_found = PathResult {
         path: vec![],
         cost: 0.0,
+        length: 0.0,
+        cumulative_costs: vec![],
         nodes_expanded,
+        best_g_score: furthest_g,
+        nodes_in_open_set: 0,
         status: PathStatus::NotFound,
-    }
+        elapsed: None,
+    };
+    not_found.timed(started)
 }
 
-fn reconstruct_path<N: Clone + Eq + Hash>(
-    current: N,
-    came_from: &HashMap<N, N>,
+fn reconstruct_path<G: Graph>(
+    graph: &G,
+    current: G::Node,
+    came_from: &HashMap<G::Node, G::Node>,
+    g_scores: &HashMap<G::Node, f32>,
     cost: f32,
-    nodes_expanded: usize,
+    stats: SearchStats,
     status: PathStatus
-) -> PathResult<N> {
+) -> PathResult<G::Node> {
     let mut path = vec![current.clone()];
     let mut cur = current;
     while let Some(parent) = came_from.get(&cur) {
@@ -180,10 +186,21 @@ fn reconstruct_path<N: Clone + Eq + Hash>(
         cur = parent.clone();
     }
     path.reverse();
+    let mut cumulative_costs: Vec<f32> = path.iter()
+        .map(|n| g_scores.get(n).copied().unwrap_or(0.0))
+        .collect();
+    if let Some(last) = cumulative_costs.last_mut() {
+        *last = cost;
+    }
     PathResult {
+        length: path_length(graph, &path),
         path,
         cost,
-        nodes_expanded,
+        cumulative_costs,
+        nodes_expanded: stats.nodes_expanded,
+        best_g_score: stats.best_g_score,
+        nodes_in_open_set: stats.nodes_in_open_set,
+        elapsed: None,
         status,
     }
 }
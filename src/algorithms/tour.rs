@@ -0,0 +1,455 @@
+use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
+use crate::algorithms::astar::{astar, AStarConfig};
+use crate::algorithms::parallel::find_paths_parallel;
+
+/// Above this many points the exact Held-Karp solver gets too expensive
+/// (O(2^n * n^2)) and we fall back to nearest-neighbor + 2-opt.
+const EXACT_TOUR_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourStatus {
+    Found,
+    /// No path exists between `points[from]` and `points[to]`.
+    Unreachable { from: usize, to: usize },
+    /// The search between `points[from]` and `points[to]` hit `config.max_iterations`, so
+    /// the tour can't be planned; a path may still exist.
+    PartialMaxIter { from: usize, to: usize },
+    /// The search between `points[from]` and `points[to]` ran out of time.
+    PartialTimeout { from: usize, to: usize },
+}
+
+/// One leg of a tour, between two consecutive patrol points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TourLeg {
+    pub from: usize,          // Index into the input points
+    pub to: usize,            // Index into the input points
+    pub path_start: usize,    // Index into `TourResult::path` where this leg begins
+    pub cost: f32,
+    pub cumulative_cost: f32, // Tour cost at the end of this leg
+}
+
+#[derive(Debug, Clone)]
+pub struct TourResult<N> {
+    /// Visiting order as indices into the input points. Always starts at 0.
+    pub order: Vec<usize>,
+    /// The stitched path over all legs (junction nodes appear once).
+    pub path: Vec<N>,
+    pub cost: f32,
+    pub legs: Vec<TourLeg>,
+    pub nodes_expanded: usize,
+    pub status: TourStatus,
+}
+
+/// Plans a minimal-cost route visiting every point in `points`, starting at `points[0]`.
+///
+/// When `closed` is true the route returns to `points[0]` (patrol loop).
+/// Pairwise leg costs are computed with A* in parallel; the ordering is solved
+/// exactly for up to 10 points and heuristically (nearest-neighbor + 2-opt) beyond.
+pub fn plan_tour<G, H>(
+    graph: &G,
+    heuristic: &H,
+    points: &[G::Node],
+    closed: bool,
+    config: AStarConfig,
+) -> TourResult<G::Node>
+where
+    G: Graph + Sync,
+    G::Node: Send + Sync,
+    H: Heuristic<G::Node> + Sync,
+{
+    let n = points.len();
+    if n < 2 {
+        return TourResult {
+            order: (0..n).collect(),
+            path: points.to_vec(),
+            cost: 0.0,
+            legs: Vec::new(),
+            nodes_expanded: 0,
+            status: TourStatus::Found,
+        };
+    }
+
+    // 1. Pairwise cost matrix (directed, so weighted graphs with asymmetric costs work too)
+    let pairs: Vec<(usize, usize)> = (0..n)
+        .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+        .collect();
+    let queries: Vec<(G::Node, G::Node)> = pairs
+        .iter()
+        .map(|&(i, j)| (points[i].clone(), points[j].clone()))
+        .collect();
+    let mut results = find_paths_parallel(graph, heuristic, &queries, config);
+
+    let nodes_expanded = results.iter().map(|r| r.nodes_expanded).sum();
+
+    if let Some(k) = results.iter().position(|r| r.status != PathStatus::Found) {
+        let (from, to) = pairs[k];
+        let status = match results[k].status {
+            PathStatus::PartialMaxIter => TourStatus::PartialMaxIter { from, to },
+            PathStatus::PartialTimeout => TourStatus::PartialTimeout { from, to },
+            _ => TourStatus::Unreachable { from, to },
+        };
+        return TourResult {
+            order: Vec::new(),
+            path: Vec::new(),
+            cost: 0.0,
+            legs: Vec::new(),
+            nodes_expanded,
+            status,
+        };
+    }
+
+    let mut costs = vec![vec![0.0f32; n]; n];
+    for (&(i, j), r) in pairs.iter().zip(results.iter()) {
+        costs[i][j] = r.cost;
+    }
+
+    // 2. Ordering
+    let order = if n <= EXACT_TOUR_LIMIT {
+        held_karp(&costs, closed)
+    } else {
+        let mut order = nearest_neighbor(&costs);
+        two_opt(&costs, &mut order, closed);
+        order
+    };
+
+    // 3. Stitch legs
+    let mut stops = order.clone();
+    if closed {
+        stops.push(order[0]);
+    }
+
+    let mut path = Vec::new();
+    let mut legs = Vec::with_capacity(stops.len() - 1);
+    let mut total = 0.0;
+    for w in stops.windows(2) {
+        let (from, to) = (w[0], w[1]);
+        let k = pairs.iter().position(|&p| p == (from, to)).unwrap();
+        let leg_path = std::mem::take(&mut results[k].path);
+        let leg_cost = costs[from][to];
+
+        // Skip the junction node on every leg after the first to avoid duplication
+        let path_start = path.len().saturating_sub(1);
+        if path.is_empty() {
+            path.extend(leg_path);
+        } else {
+            path.extend(leg_path.into_iter().skip(1));
+        }
+
+        total += leg_cost;
+        legs.push(TourLeg {
+            from,
+            to,
+            path_start,
+            cost: leg_cost,
+            cumulative_cost: total,
+        });
+    }
+
+    TourResult {
+        order,
+        path,
+        cost: total,
+        legs,
+        nodes_expanded,
+        status: TourStatus::Found,
+    }
+}
+
+/// Route through `waypoints` in the given order (no reordering, unlike `plan_tour`), one A*
+/// per leg, stitched into a single path. All legs share one time limit: `config.deadline`,
+/// or `timeout` counted from the call.
+///
+/// A leg that runs out of time or iterations ends the route with that leg's status, and
+/// the path is the completed legs plus the partial leg. An unreachable leg gives its status
+/// with an empty path. `nodes_expanded` is summed over the legs that ran.
+pub fn route_via<G, H>(
+    graph: &G,
+    heuristic: &H,
+    waypoints: &[G::Node],
+    config: AStarConfig,
+) -> PathResult<G::Node>
+where
+    G: Graph,
+    H: Heuristic<G::Node>,
+{
+    let config = config.pin_deadline();
+    let mut route = PathResult {
+        path: waypoints.iter().take(1).cloned().collect(),
+        cost: 0.0,
+        length: 0.0,
+        cumulative_costs: waypoints.iter().take(1).map(|_| 0.0).collect(),
+        nodes_expanded: 0,
+        best_g_score: 0.0,
+        nodes_in_open_set: 0,
+        status: PathStatus::Found,
+        elapsed: None,
+    };
+
+    for leg in waypoints.windows(2) {
+        let result = astar(graph, heuristic, leg[0].clone(), leg[1].clone(), config);
+        route.nodes_expanded += result.nodes_expanded;
+        route.nodes_in_open_set = result.nodes_in_open_set;
+        route.best_g_score = route.cost + result.best_g_score;
+        route.status = result.status;
+        if result.path.is_empty() {
+            route.path.clear();
+            route.cumulative_costs.clear();
+            route.cost = 0.0;
+            route.length = 0.0;
+            return route;
+        }
+
+        // The junction node is already the end of the previous leg
+        let offset = route.cost;
+        route.path.extend(result.path.into_iter().skip(1));
+        route.cumulative_costs.extend(result.cumulative_costs.iter().skip(1).map(|c| offset + c));
+        route.cost += result.cost;
+        route.length += result.length;
+        if result.status != PathStatus::Found {
+            return route;
+        }
+    }
+    route
+}
+
+fn tour_cost(costs: &[Vec<f32>], order: &[usize], closed: bool) -> f32 {
+    let mut total: f32 = order.windows(2).map(|w| costs[w[0]][w[1]]).sum();
+    if closed {
+        total += costs[order[order.len() - 1]][order[0]];
+    }
+    total
+}
+
+/// Exact solver over subsets (Held-Karp), with the tour anchored at point 0.
+fn held_karp(costs: &[Vec<f32>], closed: bool) -> Vec<usize> {
+    let n = costs.len();
+    let full = 1usize << n;
+    let mut dp = vec![f32::INFINITY; full * n];
+    let mut parent = vec![usize::MAX; full * n];
+    dp[n] = 0.0; // mask {0} (row 1), ending at 0
+
+    for mask in 1..full {
+        if mask & 1 == 0 {
+            continue;
+        }
+        for last in 0..n {
+            let cur = dp[mask * n + last];
+            if mask & (1 << last) == 0 || cur.is_infinite() {
+                continue;
+            }
+            for next in 0..n {
+                if mask & (1 << next) != 0 {
+                    continue;
+                }
+                let next_mask = mask | (1 << next);
+                let cand = cur + costs[last][next];
+                if cand < dp[next_mask * n + next] {
+                    dp[next_mask * n + next] = cand;
+                    parent[next_mask * n + next] = last;
+                }
+            }
+        }
+    }
+
+    let all = full - 1;
+    let mut best_last = 1;
+    let mut best = f32::INFINITY;
+    for last in 1..n {
+        let mut c = dp[all * n + last];
+        if closed {
+            c += costs[last][0];
+        }
+        if c < best {
+            best = c;
+            best_last = last;
+        }
+    }
+
+    let mut order = Vec::with_capacity(n);
+    let mut mask = all;
+    let mut cur = best_last;
+    while cur != 0 {
+        order.push(cur);
+        let prev = parent[mask * n + cur];
+        mask &= !(1 << cur);
+        cur = prev;
+    }
+    order.push(0);
+    order.reverse();
+    order
+}
+
+fn nearest_neighbor(costs: &[Vec<f32>]) -> Vec<usize> {
+    let n = costs.len();
+    let mut visited = vec![false; n];
+    let mut order = vec![0];
+    visited[0] = true;
+    while order.len() < n {
+        let last = order[order.len() - 1];
+        let next = (0..n)
+            .filter(|&j| !visited[j])
+            .min_by(|&a, &b| costs[last][a].total_cmp(&costs[last][b]))
+            .unwrap();
+        visited[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+/// Segment-reversal improvement. Costs may be asymmetric, so every candidate
+/// is re-evaluated as a whole tour rather than via the usual 4-edge delta.
+fn two_opt(costs: &[Vec<f32>], order: &mut [usize], closed: bool) {
+    let n = order.len();
+    let mut best = tour_cost(costs, order, closed);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        // Index 0 stays fixed as the tour anchor
+        for i in 1..n - 1 {
+            for j in (i + 1)..n {
+                order[i..=j].reverse();
+                let c = tour_cost(costs, order, closed);
+                if c + 1e-4 < best {
+                    best = c;
+                    improved = true;
+                } else {
+                    order[i..=j].reverse();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Diagonal;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn square_tour_follows_perimeter() {
+        let grid = Grid2D::new(12, 12, DiagonalMode::Always);
+        // Given in a crossing order: 0 and 1 are opposite corners, as are 2 and 3
+        let points = [
+            GridPos { x: 0, y: 0 },
+            GridPos { x: 10, y: 10 },
+            GridPos { x: 10, y: 0 },
+            GridPos { x: 0, y: 10 },
+        ];
+
+        let tour = plan_tour(&grid, &Diagonal::default(), &points, true, AStarConfig::default());
+        assert_eq!(tour.status, TourStatus::Found);
+        assert!((tour.cost - 40.0).abs() < 1e-3, "cost {}", tour.cost);
+        assert_eq!(tour.order[0], 0);
+        for leg in &tour.legs {
+            let diagonal_pair = (leg.from.min(leg.to), leg.from.max(leg.to));
+            assert!(diagonal_pair != (0, 1) && diagonal_pair != (2, 3), "crossing leg {:?}", leg);
+        }
+
+        assert_eq!(tour.legs.len(), 4);
+        assert_eq!(tour.path.first(), Some(&points[0]));
+        assert_eq!(tour.path.last(), Some(&points[0]));
+        for leg in &tour.legs {
+            assert_eq!(tour.path[leg.path_start], points[leg.from]);
+        }
+        assert!((tour.legs[3].cumulative_cost - tour.cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn heuristic_ordering_beyond_exact_limit() {
+        let grid = Grid2D::new(20, 20, DiagonalMode::Always);
+        // 12 points along a line, shuffled; the optimal open route walks them in order
+        let xs = [0, 7, 3, 11, 1, 9, 5, 2, 10, 4, 8, 6];
+        let points: Vec<GridPos> = xs.iter().map(|&x| GridPos { x, y: 5 }).collect();
+
+        let tour = plan_tour(&grid, &Diagonal::default(), &points, false, AStarConfig::default());
+        assert_eq!(tour.status, TourStatus::Found);
+        assert_eq!(tour.order.len(), points.len());
+        assert!((tour.cost - 11.0).abs() < 1e-3, "cost {}", tour.cost);
+    }
+
+    #[test]
+    fn unreachable_pair_is_reported() {
+        let mut grid = Grid2D::new(10, 10, DiagonalMode::Always);
+        grid.set_region_blocked((5, 0, 1, 10), true);
+        let points = [
+            GridPos { x: 0, y: 0 },
+            GridPos { x: 2, y: 2 },
+            GridPos { x: 8, y: 8 },
+        ];
+
+        let tour = plan_tour(&grid, &Diagonal::default(), &points, true, AStarConfig::default());
+        assert_eq!(tour.status, TourStatus::Unreachable { from: 0, to: 2 });
+        assert!(tour.path.is_empty());
+
+        // Legs cut short by the limits are reported as such, not as unreachable
+        let open = Grid2D::new(10, 10, DiagonalMode::Always);
+        let capped = AStarConfig { max_iterations: Some(2), ..AStarConfig::default() };
+        let tour = plan_tour(&open, &Diagonal::default(), &points, true, capped);
+        assert_eq!(tour.status, TourStatus::PartialMaxIter { from: 0, to: 1 });
+        let expired = AStarConfig { deadline: Some(Instant::now()), ..AStarConfig::default() };
+        std::thread::sleep(Duration::from_millis(2));
+        let tour = plan_tour(&open, &Diagonal::default(), &points, true, expired);
+        assert_eq!(tour.status, TourStatus::PartialTimeout { from: 0, to: 1 });
+    }
+
+    // A line of integers where expanding anything at or past `slow_from` takes a while
+    struct SlowLine {
+        slow_from: i32,
+        len: i32,
+    }
+
+    impl Graph for SlowLine {
+        type Node = i32;
+
+        fn is_passable(&self, node: &i32) -> bool {
+            (0..self.len).contains(node)
+        }
+
+        fn neighbors<F: FnMut(i32, f32)>(&self, node: &i32, mut visit: F) {
+            if *node >= self.slow_from {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            for n in [node - 1, node + 1] {
+                if self.is_passable(&n) {
+                    visit(n, 1.0);
+                }
+            }
+        }
+    }
+
+    struct LineDistance;
+
+    impl Heuristic<i32> for LineDistance {
+        fn estimate(&self, from: &i32, to: &i32) -> f32 {
+            (to - from).abs() as f32
+        }
+    }
+
+    #[test]
+    fn route_via_stitches_legs_in_order() {
+        let line = SlowLine { slow_from: i32::MAX, len: 20 };
+        let route = route_via(&line, &LineDistance, &[0, 6, 2, 9], AStarConfig::default());
+        assert_eq!(route.status, PathStatus::Found);
+        assert_eq!(route.cost, 17.0);
+        assert_eq!(route.path.len(), 18);
+        assert_eq!(route.cumulative_costs, (0..18).map(|c| c as f32).collect::<Vec<_>>());
+        assert_eq!(&route.path[..8], &[0, 1, 2, 3, 4, 5, 6, 5]);
+    }
+
+    #[test]
+    fn route_deadline_expiring_mid_route_keeps_completed_prefix() {
+        // The first leg is instant; the second needs ~50 slow expansions, well past the deadline
+        let line = SlowLine { slow_from: 10, len: 80 };
+        let config = AStarConfig { deadline: Some(Instant::now() + Duration::from_millis(40)), ..AStarConfig::default() };
+        let route = route_via(&line, &LineDistance, &[0, 10, 60, 70], config);
+
+        assert_eq!(route.status, PathStatus::PartialTimeout);
+        assert_eq!(&route.path[..11], (0..=10).collect::<Vec<_>>().as_slice());
+        let last = *route.path.last().unwrap();
+        assert!(last > 10 && last < 60, "stopped at {last}");
+        assert!(route.path.windows(2).all(|w| w[1] == w[0] + 1));
+        assert_eq!(route.cost, last as f32);
+    }
+}
@@ -2,8 +2,10 @@ use std::collections::{BinaryHeap, HashMap};
 use std::time::{Duration, Instant};
 use std::hash::Hash;
 use std::cmp::Ordering;
-use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
-use crate::algorithms::astar::{TieBreaking, AStarConfig};
+use std::sync::Arc;
+use crate::cache::SharedPathCache;
+use crate::traits::{path_length, search_capacity, Graph, Heuristic, PathResult, PathStatus};
+use crate::algorithms::astar::AStarConfig;
 
 #[derive(Clone, Copy)]
 struct State<N> {
@@ -43,11 +45,15 @@ pub struct BudgetedPathfinder<G: Graph> {
     g_scores: HashMap<G::Node, f32>,
     came_from: HashMap<G::Node, G::Node>,
     goal: Option<G::Node>,
+    start_estimate: f32, // Heuristic from start to goal, for sizing the maps on the first step
     config: AStarConfig,
     nodes_expanded: usize,
+    furthest_g: f32,
     iterations: usize,
     pub status: ComputeStatus<G::Node>,
     pub last_partial: Option<PathResult<G::Node>>,
+    cache: Option<Arc<SharedPathCache<G::Node>>>,        // Found results are stored here
+    cache_lookup: Option<Arc<SharedPathCache<G::Node>>>, // Checked by `start` before searching
 }
 
 impl<G> BudgetedPathfinder<G> 
@@ -61,25 +67,53 @@ where
             g_scores: HashMap::new(),
             came_from: HashMap::new(),
             goal: None,
+            start_estimate: 0.0,
             config,
             nodes_expanded: 0,
+            furthest_g: 0.0,
             iterations: 0,
             status: ComputeStatus::NotStarted,
             last_partial: None,
+            cache: None,
+            cache_lookup: None,
         }
     }
 
+    /// Stores every `Found` result in `cache` when the search completes.
+    pub fn with_cache(mut self, cache: Arc<SharedPathCache<G::Node>>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Makes `start` look the query up in `cache` first; on a hit the pathfinder is
+    /// `Complete` straight away, with no search and zero `nodes_expanded`.
+    pub fn with_cache_lookup(mut self, cache: Arc<SharedPathCache<G::Node>>) -> Self {
+        self.cache_lookup = Some(cache);
+        self
+    }
+
     pub fn start<H>(&mut self, start: G::Node, goal: G::Node, heuristic: &H)
     where H: Heuristic<G::Node> {
         self.open_set.clear();
         self.g_scores.clear();
         self.came_from.clear();
         self.nodes_expanded = 0;
+        self.furthest_g = 0.0;
         self.iterations = 0;
         self.last_partial = None;
+
+        let hit = self.cache_lookup.as_ref().and_then(|cache| cache.lock().unwrap().get(&start, &goal));
+        if let Some(hit) = hit {
+            // The stats describe this query, which searched nothing
+            let result = PathResult { nodes_expanded: 0, nodes_in_open_set: 0, elapsed: None, ..hit };
+            self.goal = Some(goal);
+            self.status = ComputeStatus::Complete(result);
+            return;
+        }
         
         self.g_scores.insert(start.clone(), 0.0);
-        let h = heuristic.estimate(&start, &goal);
+        let h = heuristic.estimate(&start, &goal) * self.config.heuristic_weight;
+        self.start_estimate = h;
         
         self.open_set.push(State {
             node: start.clone(),
@@ -100,20 +134,31 @@ where
         if self.goal.is_none() { return true; }
         
         let start_time = Instant::now();
+        // `start` has no graph to ask for a size hint, so the maps are sized on the first step
+        if self.iterations == 0 {
+            let capacity = search_capacity(graph.node_count_hint(), self.start_estimate);
+            self.open_set.reserve(capacity);
+            self.g_scores.reserve(capacity);
+            self.came_from.reserve(capacity);
+        }
         let goal = self.goal.as_ref().unwrap();
         
         while let Some(State { node: current, cost: f_score, g_score: current_g, tie_breaker: tb }) = self.open_set.pop() {
             self.iterations += 1;
              
              // Check budget
-            if self.iterations % 10 == 0 && start_time.elapsed() > budget {
-                 self.last_partial = Some(self.reconstruct_path(current.clone(), PathStatus::PartialTimeout));
+            if self.iterations.is_multiple_of(10) && start_time.elapsed() > budget {
+                 self.last_partial = Some(self.reconstruct_path(graph, current.clone(), PathStatus::PartialTimeout));
                  self.open_set.push(State { node: current, cost: f_score, g_score: current_g, tie_breaker: tb }); 
                  return false; 
              }
 
             if &current == goal {
-                let res = self.reconstruct_path(current, PathStatus::Found);
+                self.furthest_g = self.furthest_g.max(current_g);
+                let res = self.reconstruct_path(graph, current, PathStatus::Found);
+                if let Some(cache) = &self.cache {
+                    cache.lock().unwrap().insert(res.path[0].clone(), goal.clone(), res.clone());
+                }
                 self.last_partial = Some(res.clone());
                 self.status = ComputeStatus::Complete(res);
                 return true;
@@ -124,6 +169,7 @@ where
             }
             
             self.nodes_expanded += 1;
+            self.furthest_g = self.furthest_g.max(current_g);
             
              graph.neighbors(&current, |neighbor, edge_cost| {
                 let tentative_g = current_g + edge_cost;
@@ -134,14 +180,9 @@ where
                 self.came_from.insert(neighbor.clone(), current.clone());
                 self.g_scores.insert(neighbor.clone(), tentative_g);
                 
-                let h = heuristic.estimate(&neighbor, goal);
+                let h = heuristic.estimate(&neighbor, goal) * self.config.heuristic_weight;
                 let f = tentative_g + h;
-                let tb = match self.config.tie_breaking {
-                    TieBreaking::None => 0.0,
-                    TieBreaking::PreferHigherG => tentative_g,
-                    TieBreaking::PreferLowerG => -tentative_g,
-                    TieBreaking::CrossProduct => 0.0,
-                };
+                let tb = self.config.tie_breaking.key(&neighbor, tentative_g);
                 
                 self.open_set.push(State { node: neighbor, cost: f, g_score: tentative_g, tie_breaker: tb });
             });
@@ -151,14 +192,19 @@ where
         self.status = ComputeStatus::Complete(PathResult {
             path: vec![],
             cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
             nodes_expanded: self.nodes_expanded,
+            best_g_score: self.furthest_g,
+            nodes_in_open_set: 0,
             status: PathStatus::NotFound,
+            elapsed: None,
         });
         self.last_partial = None;
         true
     }
     
-    fn reconstruct_path(&self, current: G::Node, status: PathStatus) -> PathResult<G::Node> {
+    fn reconstruct_path(&self, graph: &G, current: G::Node, status: PathStatus) -> PathResult<G::Node> {
         let mut path = vec![current.clone()];
         let mut cur = current;
         while let Some(parent) = self.came_from.get(&cur) {
@@ -167,15 +213,20 @@ where
         }
         path.reverse();
         
-        let cost = path.last()
-            .and_then(|node| self.g_scores.get(node))
-            .copied()
-            .unwrap_or(0.0);
+        let cumulative_costs: Vec<f32> = path.iter()
+            .map(|node| self.g_scores.get(node).copied().unwrap_or(0.0))
+            .collect();
+        let cost = cumulative_costs.last().copied().unwrap_or(0.0);
 
         PathResult {
+            length: path_length(graph, &path),
             path,
             cost,
+            cumulative_costs,
             nodes_expanded: self.nodes_expanded,
+            best_g_score: self.furthest_g,
+            nodes_in_open_set: self.open_set.len(),
+            elapsed: None,
             status,
         }
     }
@@ -191,4 +242,45 @@ where
     pub fn partial_result(&self) -> Option<&PathResult<G::Node>> {
         self.last_partial.as_ref()
     }
+
+    /// Number of entries currently waiting in the open set.
+    pub fn open_set_size(&self) -> usize {
+        self.open_set.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PathCache;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Diagonal;
+    use std::sync::Mutex;
+
+    #[test]
+    fn completed_search_serves_the_next_pathfinder_from_the_cache() {
+        let mut grid = Grid2D::new(32, 32, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((16, 0, 1, 28), true);
+        let heuristic = Diagonal::for_grid(&grid);
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 30, y: 3 });
+        let cache = Arc::new(Mutex::new(PathCache::new(8, Duration::from_secs(60))));
+
+        let mut first = BudgetedPathfinder::new(AStarConfig::default()).with_cache(cache.clone());
+        first.start(start, goal, &heuristic);
+        while !first.step(&grid, &heuristic, Duration::from_millis(5)) {}
+        let searched = first.take_result().unwrap();
+        assert!(searched.nodes_expanded > 0);
+        assert_eq!(cache.lock().unwrap().len(), 1);
+
+        let mut second = BudgetedPathfinder::new(AStarConfig::default()).with_cache_lookup(cache.clone());
+        second.start(start, goal, &heuristic);
+        assert!(second.step(&grid, &heuristic, Duration::ZERO));
+        let hit = second.take_result().unwrap();
+        assert_eq!((hit.nodes_expanded, hit.status), (0, PathStatus::Found));
+        assert_eq!((hit.path, hit.cost), (searched.path, searched.cost));
+
+        // Other queries still search
+        second.start(goal, start, &heuristic);
+        assert!(second.take_result().is_none());
+    }
 }
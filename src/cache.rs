@@ -1,21 +1,75 @@
-use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use crate::algorithms::astar::{astar, AStarConfig};
+use crate::algorithms::jps::{jps, JpsGrid};
+use crate::graphs::grid2d::{ChangeListener, Grid2D, GridChange, GridPos};
+use crate::graphs::hierarchical::HierarchicalGrid;
+use crate::heatmap::PathHeatmap;
+use crate::rng::Fnv1a;
 use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
 
+// Reports whether a grid edit touches a node
+type ChangeFilter<N> = fn(&GridChange, &N) -> bool;
+// Maximum age for the path between a start and goal
+type TtlFn<N> = Box<dyn Fn(&N, &N) -> Duration + Send + Sync>;
+// Sees every path `cached_path` hands out
+type PathObserver<N> = Box<dyn Fn(&[N]) + Send + Sync>;
+// (min_x, min_y, max_x, max_y) over a path and its endpoints
+type BoundsFn<N> = fn(&N, &N, &[N]) -> (i32, i32, i32, i32);
+
+/// A `PathCache` shared between pathfinders (or threads), usually as `Arc<SharedPathCache<N>>`.
+pub type SharedPathCache<N> = Mutex<PathCache<N>>;
+
+/// Part of the cache key naming what produced a path, so one cache can hold results from
+/// several algorithms or configurations without serving one in place of another (e.g. a
+/// Theta* any-angle path to a caller expecting cell-adjacent A* output).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct CacheTag(pub u64);
+
+impl CacheTag {
+    /// Used by `get`, `insert` and `cached_path`, and by `astar_with_cache` under a config
+    /// with default search settings (see `with_config`).
+    pub const UNTAGGED: CacheTag = CacheTag(0);
+    pub const JPS: CacheTag = CacheTag(1);
+    pub const THETA: CacheTag = CacheTag(2);
+    pub const HPA: CacheTag = CacheTag(3);
+
+    /// This tag refined by the settings of `config` that change which path is found
+    /// (heuristic weight and tie-breaking). Search limits don't matter: only `Found`
+    /// results are cached. The default settings leave the tag as it is, so `get` still
+    /// finds what `astar_with_cache` stored under a default config.
+    pub fn with_config(self, config: &AStarConfig) -> CacheTag {
+        let default = AStarConfig::default();
+        if config.heuristic_weight == default.heuristic_weight && config.tie_breaking == default.tie_breaking {
+            return self;
+        }
+        let mut hasher = Fnv1a::default();
+        (self.0, config.heuristic_weight.to_bits(), config.tie_breaking).hash(&mut hasher);
+        CacheTag(hasher.finish())
+    }
+}
+
 #[derive(Clone)]
 struct CachedPath<N> {
     result: PathResult<N>,
     created: Instant,
     hits: u32,
+    // (min_x, min_y, max_x, max_y) of a grid path, set at insert once bounds are tracked
+    bounds: Option<(i32, i32, i32, i32)>,
 }
 
 pub struct PathCache<N: Hash + Eq> {
-    cache: HashMap<(N, N), CachedPath<N>>,
+    cache: HashMap<(N, N, CacheTag), CachedPath<N>>,
     max_entries: usize,
     max_age: Duration,
+    ttl_fn: Option<TtlFn<N>>, // Overrides `max_age` per entry when set
+    refresh_on_hit: bool,     // Sliding expiry: hits reset the entry's age
+    attached: Option<(ChangeListener, ChangeFilter<N>)>, // Grid subscription from `attach`
+    observer: Option<PathObserver<N>>,
+    bounds_of: Option<BoundsFn<N>>, // Set by `track_bounds`; boxes each entry at insert
 }
 
 impl<N: Hash + Eq + Clone> PathCache<N> {
@@ -26,35 +80,94 @@ impl<N: Hash + Eq + Clone> PathCache<N> {
             cache: HashMap::new(),
             max_entries,
             max_age,
+            ttl_fn: None,
+            refresh_on_hit: false,
+            attached: None,
+            observer: None,
+            bounds_of: None,
         }
     }
 
     /// Returns a cached PathResult clone if present and fresh.
     pub fn get(&mut self, start: &N, goal: &N) -> Option<PathResult<N>> {
-        let key = (start.clone(), goal.clone());
+        self.get_tagged(start, goal, CacheTag::UNTAGGED)
+    }
+
+    /// `get` for an entry inserted with `tag`.
+    pub fn get_tagged(&mut self, start: &N, goal: &N, tag: CacheTag) -> Option<PathResult<N>> {
+        self.apply_attached_changes();
+        let max_age = self.ttl_fn.as_ref().map_or(self.max_age, |ttl| ttl(start, goal));
+        let key = (start.clone(), goal.clone(), tag);
         if let Some(entry) = self.cache.get_mut(&key) {
-            if entry.created.elapsed() < self.max_age {
+            if entry.created.elapsed() < max_age {
                 entry.hits += 1;
+                if self.refresh_on_hit {
+                    entry.created = Instant::now();
+                }
                 return Some(entry.result.clone());
             }
         }
         None
     }
 
+    /// `get`, cut down to the part of the path reachable within `max_cost`
+    /// (`PathResult::truncate_to_cost`, pricing steps with `graph`). A path within budget
+    /// comes back whole; a longer one as a `PartialCostLimit` prefix from `start`. None on a miss.
+    pub fn try_get_partial<G: Graph<Node = N>>(
+        &mut self,
+        start: &N,
+        goal: &N,
+        max_cost: f32,
+        graph: &G,
+    ) -> Option<PathResult<N>> {
+        self.get(start, goal).map(|result| result.truncate_to_cost(max_cost, graph))
+    }
+
+    /// With `refresh`, every hit restarts the entry's age, so paths in regular use never
+    /// expire (sliding instead of fixed expiry). Off by default.
+    pub fn set_refresh_on_hit(&mut self, refresh: bool) {
+        self.refresh_on_hit = refresh;
+    }
+
+    /// Calls `observer` with the path of every `Found` result returned through `cached_path`
+    /// (and the `*_with_cache` wrappers), whether it was a hit or freshly computed.
+    pub fn set_path_observer<F>(&mut self, observer: F)
+    where
+        F: Fn(&[N]) + Send + Sync + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Per-entry maximum age from the start and goal, replacing the `max_age` given to
+    /// `new` (e.g. shorter for paths through frequently changing areas).
+    pub fn set_max_age_per_entry<F>(&mut self, ttl_fn: F)
+    where
+        F: Fn(&N, &N) -> Duration + Send + Sync + 'static,
+    {
+        self.ttl_fn = Some(Box::new(ttl_fn));
+    }
+
     /// Insert a PathResult (typically only on success) into the cache.
     pub fn insert(&mut self, start: N, goal: N, result: PathResult<N>) {
+        self.insert_tagged(start, goal, CacheTag::UNTAGGED, result);
+    }
+
+    /// `insert` under `tag`; only `get_tagged` with the same tag will return it.
+    pub fn insert_tagged(&mut self, start: N, goal: N, tag: CacheTag, result: PathResult<N>) {
         if result.status != PathStatus::Found {
             return;
         }
         if self.cache.len() >= self.max_entries {
             self.evict_one();
         }
+        let bounds = self.bounds_of.map(|bounds_of| bounds_of(&start, &goal, &result.path));
         self.cache.insert(
-            (start, goal),
+            (start, goal, tag),
             CachedPath {
                 result,
                 created: Instant::now(),
                 hits: 0,
+                bounds,
             },
         );
     }
@@ -78,7 +191,7 @@ impl<N: Hash + Eq + Clone> PathCache<N> {
     where
         F: Fn(&N) -> bool,
     {
-        self.cache.retain(|(start, goal), entry| {
+        self.cache.retain(|(start, goal, _), entry| {
             if predicate(start) || predicate(goal) {
                 return false;
             }
@@ -86,6 +199,27 @@ impl<N: Hash + Eq + Clone> PathCache<N> {
         });
     }
 
+    /// Invalidate cached paths that touch any of `changed`, typically the
+    /// drained change list of a grid (`Grid2D::take_changes`).
+    pub fn invalidate_nodes(&mut self, changed: &[N]) {
+        if changed.is_empty() {
+            return;
+        }
+        let changed: HashSet<&N> = changed.iter().collect();
+        self.invalidate_region(|n| changed.contains(n));
+    }
+
+    // Drops paths touched by edits to the attached grid since the last lookup
+    fn apply_attached_changes(&mut self) {
+        let Some((listener, touches)) = &self.attached else { return };
+        let changes = listener.drain();
+        if changes.is_empty() {
+            return;
+        }
+        let touches = *touches;
+        self.invalidate_region(|n| changes.iter().any(|c| touches(c, n)));
+    }
+
     pub fn clear(&mut self) {
         self.cache.clear();
     }
@@ -93,9 +227,100 @@ impl<N: Hash + Eq + Clone> PathCache<N> {
     pub fn len(&self) -> usize {
         self.cache.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+impl PathCache<GridPos> {
+    /// Subscribes to `grid` so edits invalidate affected paths automatically on the next
+    /// lookup. Replaces any previous attachment.
+    pub fn attach(&mut self, grid: &Grid2D) {
+        self.attached = Some((grid.subscribe(), GridChange::contains));
+    }
+
+    /// Keeps each entry's bounding box for `invalidate_rect`, computed as the path is
+    /// inserted. The first `invalidate_rect` turns this on (boxing the paths already cached);
+    /// call it up front so that first invalidation doesn't walk every cached path.
+    pub fn track_bounds(&mut self) {
+        if self.bounds_of.is_some() {
+            return;
+        }
+        self.bounds_of = Some(grid_path_bounds);
+        for ((start, goal, _), entry) in &mut self.cache {
+            entry.bounds = Some(grid_path_bounds(start, goal, &entry.result.path));
+        }
+    }
+
+    /// `invalidate_region` for the cells inside `rect` (x, y, width, height). Each entry keeps
+    /// its path's bounding box (see `track_bounds`), so paths that stay clear of the rect are
+    /// kept without walking them; only paths whose box overlaps it are checked cell by cell.
+    pub fn invalidate_rect(&mut self, rect: (i32, i32, i32, i32)) {
+        let (rx, ry, rw, rh) = rect;
+        if rw <= 0 || rh <= 0 {
+            return;
+        }
+        self.track_bounds();
+        let (rx1, ry1) = (rx.saturating_add(rw - 1), ry.saturating_add(rh - 1));
+        let inside = |p: &GridPos| p.x >= rx && p.x <= rx1 && p.y >= ry && p.y <= ry1;
+        self.cache.retain(|(start, goal, _), entry| {
+            let path = &entry.result.path;
+            let (min_x, min_y, max_x, max_y) = entry.bounds.unwrap_or_else(|| grid_path_bounds(start, goal, path));
+            if max_x < rx || min_x > rx1 || max_y < ry || min_y > ry1 {
+                return true;
+            }
+            !inside(start) && !inside(goal) && !path.iter().any(inside)
+        });
+    }
+
+    /// Records every path the cache hands out into `heatmap` (see `set_path_observer`).
+    pub fn record_into(&mut self, heatmap: Arc<PathHeatmap>) {
+        self.set_path_observer(move |path| heatmap.record_shared(path));
+    }
+}
+
+fn grid_path_bounds(start: &GridPos, goal: &GridPos, path: &[GridPos]) -> (i32, i32, i32, i32) {
+    path.iter().chain([start, goal]).fold((i32::MAX, i32::MAX, i32::MIN, i32::MIN), |b, p| {
+        (b.0.min(p.x), b.1.min(p.y), b.2.max(p.x), b.3.max(p.y))
+    })
+}
+
+/// Returns the cached path from `start` to `goal`, or runs `compute` and caches its result
+/// if `Found`. Works with any search, e.g. `|| theta_star(&grid, &h, start, goal, config)`;
+/// use `cached_path_tagged` when one cache is shared between algorithms.
+pub fn cached_path<N, F>(cache: &mut PathCache<N>, start: N, goal: N, compute: F) -> PathResult<N>
+where
+    N: Hash + Eq + Clone,
+    F: FnOnce() -> PathResult<N>,
+{
+    cached_path_tagged(cache, CacheTag::UNTAGGED, start, goal, compute)
+}
+
+/// `cached_path` with the entry keyed by `tag` as well.
+pub fn cached_path_tagged<N, F>(cache: &mut PathCache<N>, tag: CacheTag, start: N, goal: N, compute: F) -> PathResult<N>
+where
+    N: Hash + Eq + Clone,
+    F: FnOnce() -> PathResult<N>,
+{
+    let result = match cache.get_tagged(&start, &goal, tag) {
+        Some(hit) => hit,
+        None => {
+            let result = compute();
+            cache.insert_tagged(start, goal, tag, result.clone());
+            result
+        }
+    };
+    if let (Some(observer), PathStatus::Found) = (&cache.observer, result.status) {
+        observer(&result.path);
+    }
+    result
 }
 
-/// Run A* with a simple start/goal cache. Only `Found` results are cached.
+/// Run A* with a simple start/goal cache, tagged `CacheTag::UNTAGGED` refined by `config`,
+/// so a path found under another heuristic weight or tie-breaking is not handed back. The
+/// heuristic is not part of the key: give searches with different (e.g. inadmissible)
+/// heuristics their own tag through `cached_path_tagged`. Only `Found` results are cached.
 pub fn astar_with_cache<G, H>(
     graph: &G,
     heuristic: &H,
@@ -108,15 +333,35 @@ where
     G: Graph,
     H: Heuristic<G::Node>,
 {
-    if let Some(hit) = cache.get(&start, &goal) {
-        return hit;
-    }
+    let tag = CacheTag::UNTAGGED.with_config(&config);
+    cached_path_tagged(cache, tag, start.clone(), goal.clone(), || astar(graph, heuristic, start, goal, config))
+}
 
-    let result = astar(graph, heuristic, start.clone(), goal.clone(), config);
-    if result.status == PathStatus::Found {
-        cache.insert(start, goal, result.clone());
-    }
-    result
+/// Run JPS through `cache`, tagged `CacheTag::JPS` refined by `config`.
+pub fn jps_with_cache<G, H>(
+    grid: &G,
+    heuristic: &H,
+    start: GridPos,
+    goal: GridPos,
+    config: AStarConfig,
+    cache: &mut PathCache<GridPos>,
+) -> PathResult<GridPos>
+where
+    G: JpsGrid,
+    H: Heuristic<GridPos>,
+{
+    let tag = CacheTag::JPS.with_config(&config);
+    cached_path_tagged(cache, tag, start, goal, || jps(grid, heuristic, start, goal, config))
+}
+
+/// Run `HierarchicalGrid::find_path` through `cache`, tagged `CacheTag::HPA`.
+pub fn hpa_with_cache(
+    hpa: &HierarchicalGrid,
+    start: GridPos,
+    goal: GridPos,
+    cache: &mut PathCache<GridPos>,
+) -> PathResult<GridPos> {
+    cached_path_tagged(cache, CacheTag::HPA, start, goal, || hpa.find_path(start, goal))
 }
 
 #[cfg(test)]
@@ -154,4 +399,214 @@ mod tests {
         cache.invalidate_region(|p: &GridPos| p.x == 2);
         assert!(cache.get(&GridPos { x: 0, y: 0 }, &GridPos { x: 4, y: 4 }).is_none());
     }
+
+    #[test]
+    fn partial_lookups_truncate_to_the_cost_budget() {
+        let mut cache = PathCache::new(4, Duration::from_secs(60));
+        let mut grid = Grid2D::new(10, 1, DiagonalMode::Never);
+        grid.set_cost(5, 0, 3.0);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 9, y: 0 });
+        assert!(cache.try_get_partial(&start, &goal, 4.0, &grid).is_none());
+
+        let full = astar_with_cache(&grid, &Diagonal::default(), start, goal, AStarConfig::default(), &mut cache);
+        assert_eq!((full.path.len(), full.cost), (10, 11.0));
+
+        let partial = cache.try_get_partial(&start, &goal, 6.5, &grid).unwrap();
+        assert_eq!(partial.status, PathStatus::PartialCostLimit);
+        assert_eq!(partial.path.first(), Some(&start));
+        assert_eq!(partial.path.last(), Some(&GridPos { x: 4, y: 0 }));
+        assert!(partial.cost <= 6.5 && grid.path_cost(&partial.path) == partial.cost);
+
+        // Within budget: the whole cached path, untouched
+        let whole = cache.try_get_partial(&start, &goal, 11.0, &grid).unwrap();
+        assert_eq!((whole.status, whole.path), (PathStatus::Found, full.path));
+        assert_eq!(cache.try_get_partial(&start, &goal, 0.0, &grid).unwrap().path, vec![start]);
+    }
+
+    #[test]
+    fn invalidates_from_grid_change_list() {
+        let mut cache = PathCache::new(4, Duration::from_secs(60));
+        let mut grid = Grid2D::new(5, 5, DiagonalMode::Never);
+        grid.enable_change_tracking();
+        let config = AStarConfig::default();
+
+        let along_top = (GridPos { x: 0, y: 0 }, GridPos { x: 4, y: 0 });
+        let along_bottom = (GridPos { x: 0, y: 4 }, GridPos { x: 4, y: 4 });
+        for (s, g) in [along_top, along_bottom] {
+            let _ = astar_with_cache(&grid, &Diagonal::default(), s, g, config, &mut cache);
+        }
+        assert_eq!(cache.len(), 2);
+
+        grid.set_blocked(2, 0, true);
+        cache.invalidate_nodes(&grid.take_changes());
+        assert!(cache.get(&along_top.0, &along_top.1).is_none());
+        assert!(cache.get(&along_bottom.0, &along_bottom.1).is_some());
+    }
+
+    #[test]
+    fn attached_cache_recomputes_after_grid_edit() {
+        let mut cache = PathCache::new(4, Duration::from_secs(60));
+        let mut grid = Grid2D::new(5, 3, DiagonalMode::Never);
+        cache.attach(&grid);
+        let (start, goal) = (GridPos { x: 0, y: 1 }, GridPos { x: 4, y: 1 });
+        let config = AStarConfig::default();
+
+        let before = astar_with_cache(&grid, &Diagonal::default(), start, goal, config, &mut cache);
+        assert_eq!(before.cost, 4.0);
+        let on_path = before.path[2];
+
+        // Edit a cell on the cached path; no manual invalidation
+        grid.set_blocked(on_path.x as usize, on_path.y as usize, true);
+        let after = astar_with_cache(&grid, &Diagonal::default(), start, goal, config, &mut cache);
+        assert_eq!(after.status, PathStatus::Found);
+        assert!(!after.path.contains(&on_path));
+        assert_eq!(after.cost, 6.0);
+
+        // Edits away from the path keep the entry
+        grid.set_cost(0, 0, 5.0);
+        assert!(cache.get(&start, &goal).is_some());
+    }
+
+    #[test]
+    fn sliding_and_per_entry_expiry() {
+        let grid = Grid2D::new(5, 5, DiagonalMode::Always);
+        let config = AStarConfig::default();
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 4, y: 4 });
+
+        let mut cache = PathCache::new(4, Duration::from_millis(200));
+        cache.set_refresh_on_hit(true);
+        let _ = astar_with_cache(&grid, &Diagonal::default(), start, goal, config, &mut cache);
+        for _ in 0..10 {
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(cache.get(&start, &goal).is_some());
+        }
+
+        // Zero TTL for paths leaving the origin; the rest keep the default
+        let near = GridPos { x: 1, y: 1 };
+        cache.set_max_age_per_entry(|s: &GridPos, _: &GridPos| {
+            if s.x == 0 { Duration::ZERO } else { Duration::from_secs(60) }
+        });
+        let _ = astar_with_cache(&grid, &Diagonal::default(), near, goal, config, &mut cache);
+        assert!(cache.get(&start, &goal).is_none());
+        assert!(cache.get(&near, &goal).is_some());
+    }
+
+    #[test]
+    fn tags_keep_algorithms_apart() {
+        use crate::algorithms::theta::theta_star;
+        use crate::heuristics::Euclidean;
+
+        let mut cache = PathCache::new(8, Duration::from_secs(60));
+        let mut grid = Grid2D::new(12, 12, DiagonalMode::Always);
+        grid.set_region_blocked((5, 2, 1, 8), true);
+        let (start, goal) = (GridPos { x: 1, y: 6 }, GridPos { x: 10, y: 3 });
+        let config = AStarConfig::default();
+        let heuristic = Diagonal::for_grid(&grid);
+
+        let theta = cached_path_tagged(&mut cache, CacheTag::THETA, start, goal, || {
+            theta_star(&grid, &Euclidean, start, goal, config)
+        });
+        assert_eq!(theta.status, PathStatus::Found);
+        // The any-angle path skips cells, so handing it out as A* output would be wrong
+        assert!(theta.path.windows(2).any(|w| (w[0].x - w[1].x).abs() > 1 || (w[0].y - w[1].y).abs() > 1));
+
+        let astar_result = astar_with_cache(&grid, &heuristic, start, goal, config, &mut cache);
+        assert!(astar_result.path.windows(2).all(|w| (w[0].x - w[1].x).abs() <= 1 && (w[0].y - w[1].y).abs() <= 1));
+        let jps_result = jps_with_cache(&grid, &heuristic, start, goal, config, &mut cache);
+        let hpa = HierarchicalGrid::new(Grid2D::new(12, 12, DiagonalMode::Always), 4);
+        let _ = hpa_with_cache(&hpa, start, goal, &mut cache);
+        assert_eq!(cache.len(), 4);
+        assert!((jps_result.cost - astar_result.cost).abs() < 1e-4);
+
+        // Each lookup returns its own algorithm's entry, without recomputing
+        let cached = cached_path_tagged(&mut cache, CacheTag::THETA, start, goal, || unreachable!());
+        assert_eq!(cached.path, theta.path);
+        assert_eq!(cache.get(&start, &goal).unwrap().path, astar_result.path);
+        assert_eq!(cache.get_tagged(&start, &goal, CacheTag::JPS.with_config(&config)).unwrap().path, jps_result.path);
+
+        // A different heuristic weight is a different entry
+        let weighted = AStarConfig { heuristic_weight: 2.0, ..config };
+        assert_ne!(CacheTag::JPS.with_config(&weighted), CacheTag::JPS.with_config(&config));
+        // Pinned: tags must not depend on the toolchain's DefaultHasher
+        assert_eq!(CacheTag::JPS.with_config(&weighted), CacheTag(425974483807919941));
+        assert!(cache.get_tagged(&start, &goal, CacheTag::JPS.with_config(&weighted)).is_none());
+
+        // Likewise for A*: the weighted search runs, and the default entry stays as it was
+        let weighted_astar = astar_with_cache(&grid, &heuristic, start, goal, weighted, &mut cache);
+        assert_eq!(weighted_astar.status, PathStatus::Found);
+        assert_eq!(cache.len(), 5);
+        assert_eq!(cache.get(&start, &goal).unwrap().path, astar_result.path);
+    }
+
+    #[test]
+    fn closure_runs_only_on_miss_and_failures_are_not_cached() {
+        let mut cache = PathCache::new(4, Duration::from_secs(60));
+        let mut grid = Grid2D::new(6, 6, DiagonalMode::Never);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 5 });
+        let calls = std::cell::Cell::new(0);
+        let search = |grid: &Grid2D, cache: &mut PathCache<GridPos>| {
+            cached_path(cache, start, goal, || {
+                calls.set(calls.get() + 1);
+                astar(grid, &Diagonal::for_grid(grid), start, goal, AStarConfig::default())
+            })
+        };
+
+        assert_eq!(search(&grid, &mut cache).cost, 10.0);
+        assert_eq!(search(&grid, &mut cache).cost, 10.0);
+        cache.clear();
+        grid.set_region_blocked((0, 3, 6, 1), true);
+        assert_eq!(search(&grid, &mut cache).status, PathStatus::NotFound);
+        assert!(cache.is_empty());
+        assert_eq!(search(&grid, &mut cache).status, PathStatus::NotFound);
+        assert_eq!(calls.get(), 3);
+    }
+
+    fn found(cells: &[(i32, i32)]) -> PathResult<GridPos> {
+        PathResult {
+            path: cells.iter().map(|&(x, y)| GridPos { x, y }).collect(),
+            cost: cells.len() as f32 - 1.0,
+            length: cells.len() as f32 - 1.0,
+            cumulative_costs: Vec::new(),
+            nodes_expanded: cells.len(),
+            best_g_score: cells.len() as f32 - 1.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::Found,
+            elapsed: None,
+        }
+    }
+
+    #[test]
+    fn rect_invalidation_checks_paths_whose_box_overlaps() {
+        let mut cache = PathCache::new(8, Duration::from_secs(60));
+        // L-shaped path around the rect: its bounding box covers the rect, its cells don't
+        let around = found(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (4, 1), (4, 2), (4, 3), (4, 4)]);
+        let through = found(&[(0, 2), (1, 2), (2, 2), (3, 2)]);
+        let far = found(&[(10, 10), (11, 10)]);
+        for result in [around, through, far] {
+            let (start, goal) = (result.path[0], *result.path.last().unwrap());
+            cache.insert(start, goal, result);
+        }
+
+        cache.invalidate_rect((1, 1, 2, 3));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&GridPos { x: 0, y: 0 }, &GridPos { x: 4, y: 4 }).is_some());
+        assert!(cache.get(&GridPos { x: 0, y: 2 }, &GridPos { x: 3, y: 2 }).is_none());
+
+        // Same outcome as the predicate form, with the boxes now cached
+        cache.invalidate_rect((4, 4, 0, 5));
+        assert_eq!(cache.len(), 2);
+        cache.invalidate_rect((11, 9, 1, 2));
+        assert_eq!(cache.len(), 1);
+        cache.invalidate_region(|p| p.x == 4 && p.y == 2);
+        assert!(cache.is_empty());
+
+        // Once tracked, paths are boxed as they're inserted
+        let late = found(&[(20, 20), (21, 20)]);
+        cache.insert(GridPos { x: 20, y: 20 }, GridPos { x: 21, y: 20 }, late);
+        let key = (GridPos { x: 20, y: 20 }, GridPos { x: 21, y: 20 }, CacheTag::UNTAGGED);
+        assert_eq!(cache.cache[&key].bounds, Some((20, 20, 21, 20)));
+        // A rect reaching past i32::MAX saturates instead of overflowing
+        cache.invalidate_rect((21, 0, i32::MAX, i32::MAX));
+        assert!(cache.is_empty());
+    }
 }
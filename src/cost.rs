@@ -0,0 +1,153 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::graphs::grid2d::GridPos;
+use crate::traits::{Graph, Heuristic};
+
+/// Path cost arithmetic for `astar_int`. `f32` is the usual float model; `Milli` is
+/// fixed-point, so sums come out bit-identical on every platform and compiler (e.g. for
+/// lockstep checksums). Additions saturate at `MAX` rather than wrapping.
+pub trait Cost: Copy + PartialEq + fmt::Debug {
+    const ZERO: Self;
+    const MAX: Self;
+
+    /// `self + other`, saturating at `MAX`.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Total order; for `f32`, `f32::total_cmp`.
+    fn cmp_cost(&self, other: &Self) -> Ordering;
+
+    /// The cost as a float, for display and for mixing with float-only code.
+    fn to_f32(self) -> f32;
+
+    /// A heuristic estimate scaled by `AStarConfig::heuristic_weight`.
+    fn weighted(self, weight: f32) -> Self;
+}
+
+impl Cost for f32 {
+    const ZERO: Self = 0.0;
+    const MAX: Self = f32::INFINITY;
+
+    // Overflow already saturates to infinity
+    fn saturating_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn cmp_cost(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn weighted(self, weight: f32) -> Self {
+        self * weight
+    }
+}
+
+/// Fixed-point cost in thousandths: `Milli(1500)` is 1.5, and `Milli(10)` is 0.010, not 10.
+/// Only ratios matter to a search, so step costs like `Milli(10)`/`Milli(14)` work as well
+/// as `Milli(1000)`/`Milli(1414)`, but costs read back with `to_f32` are in thousandths.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Milli(pub u32);
+
+impl Milli {
+    /// 1.0
+    pub const ONE: Milli = Milli(1000);
+
+    /// `value` rounded to the nearest thousandth; negatives and NaN give 0, anything too
+    /// large (infinity included) `Cost::MAX`.
+    pub fn from_f32(value: f32) -> Self {
+        Milli((value * 1000.0).round() as u32)
+    }
+
+    /// `self * multiplier`, truncated to a whole thousandth and saturating at `Cost::MAX`.
+    pub fn scale(self, multiplier: Milli) -> Self {
+        let product = u64::from(self.0) * u64::from(multiplier.0) / 1000;
+        Milli(product.min(u64::from(u32::MAX)) as u32)
+    }
+
+    /// `self * count`, saturating at `Cost::MAX`.
+    pub fn times(self, count: u32) -> Self {
+        Milli(self.0.saturating_mul(count))
+    }
+}
+
+impl Cost for Milli {
+    const ZERO: Self = Milli(0);
+    const MAX: Self = Milli(u32::MAX);
+
+    fn saturating_add(self, other: Self) -> Self {
+        Milli(self.0.saturating_add(other.0))
+    }
+
+    fn cmp_cost(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+
+    fn to_f32(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    // Unweighted: scaling by a float would break platform-stable sums
+    fn weighted(self, _weight: f32) -> Self {
+        self
+    }
+}
+
+/// A graph whose edges are priced in `C`. Every `Graph` is one for `f32`; `IntGrid2D`
+/// prices a `Grid2D` in `Milli`.
+pub trait CostGraph<C: Cost>: Graph {
+    fn neighbors_with_cost<F>(&self, node: &Self::Node, visit: F)
+    where
+        F: FnMut(Self::Node, C);
+}
+
+impl<G: Graph> CostGraph<f32> for G {
+    fn neighbors_with_cost<F>(&self, node: &Self::Node, visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.neighbors(node, visit);
+    }
+}
+
+/// A heuristic estimating in `C`. Every `Heuristic` is one for `f32`.
+pub trait CostHeuristic<N, C: Cost> {
+    fn estimate_cost(&self, from: &N, to: &N) -> C;
+}
+
+impl<N, H: Heuristic<N>> CostHeuristic<N, f32> for H {
+    fn estimate_cost(&self, from: &N, to: &N) -> f32 {
+        self.estimate(from, to)
+    }
+}
+
+/// `Manhattan` in fixed point: `cardinal` per step along either axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntManhattan {
+    pub cardinal: Milli,
+}
+
+impl CostHeuristic<GridPos, Milli> for IntManhattan {
+    fn estimate_cost(&self, from: &GridPos, to: &GridPos) -> Milli {
+        self.cardinal.times(from.x.abs_diff(to.x).saturating_add(from.y.abs_diff(to.y)))
+    }
+}
+
+/// `Diagonal` in fixed point, e.g. 10/14 to match `IntGrid2D` steps of the same costs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntDiagonal {
+    pub cardinal: Milli,
+    pub diagonal: Milli,
+}
+
+impl CostHeuristic<GridPos, Milli> for IntDiagonal {
+    fn estimate_cost(&self, from: &GridPos, to: &GridPos) -> Milli {
+        let (dx, dy) = (from.x.abs_diff(to.x), from.y.abs_diff(to.y));
+        let (min_d, max_d) = (dx.min(dy), dx.max(dy));
+        self.cardinal.times(max_d - min_d).saturating_add(self.diagonal.times(min_d))
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use crate::algorithms::flowfield::FlowField;
+use crate::graphs::grid2d::GridPos;
+
+// Separation range as a multiple of the agent's diameter
+const SEPARATION_RANGE: f32 = 1.5;
+// Weight of the separation force against the unit flow direction
+const SEPARATION_WEIGHT: f32 = 2.0;
+
+/// A disc moved by `step_crowd`. Positions are in grid coordinates with cell centers on
+/// integers, as `FlowField::sample_bilinear` uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrowdAgent {
+    pub pos: (f32, f32),
+    pub radius: f32,
+    pub max_speed: f32, // Cells per second
+}
+
+/// Advances every agent by `dt` seconds: along the flow field, pushed apart from agents
+/// within three radii, never faster than `max_speed`. Velocities all come
+/// from the positions at the start of the step, then agents move in index order. A move
+/// that would put an agent's center in a blocked (or unreachable) cell slides along the
+/// free axis, or is dropped.
+pub fn step_crowd(agents: &mut [CrowdAgent], field: &FlowField, dt: f32) {
+    let range = |agent: &CrowdAgent| 2.0 * agent.radius * SEPARATION_RANGE;
+    let bucket_size = agents.iter().map(range).fold(f32::EPSILON, f32::max);
+    let bucket = |(x, y): (f32, f32)| ((x / bucket_size).floor() as i32, (y / bucket_size).floor() as i32);
+
+    // Uniform spatial hash; each bucket lists agents in index order
+    let mut buckets: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, agent) in agents.iter().enumerate() {
+        buckets.entry(bucket(agent.pos)).or_default().push(i);
+    }
+
+    let mut neighbors = Vec::new();
+    let velocities: Vec<(f32, f32)> = agents
+        .iter()
+        .enumerate()
+        .map(|(i, agent)| {
+            let (bx, by) = bucket(agent.pos);
+            neighbors.clear();
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let Some(bucket) = buckets.get(&(bx + dx, by + dy)) else { continue };
+                    neighbors.extend(bucket.iter().filter(|&&j| j != i).map(|&j| agents[j].pos));
+                }
+            }
+            let (fx, fy) = flow_direction(field, agent.pos);
+            let (sx, sy) = field.compute_separation_force(&neighbors, agent.pos, range(agent));
+            let (vx, vy) = (fx + sx * SEPARATION_WEIGHT, fy + sy * SEPARATION_WEIGHT);
+            let scale = agent.max_speed / (vx * vx + vy * vy).sqrt().max(1.0);
+            (vx * scale, vy * scale)
+        })
+        .collect();
+
+    for (agent, (vx, vy)) in agents.iter_mut().zip(velocities) {
+        let (x, y) = agent.pos;
+        let (nx, ny) = (x + vx * dt, y + vy * dt);
+        agent.pos = [(nx, ny), (nx, y), (x, ny)]
+            .into_iter()
+            .find(|&pos| is_walkable(field, pos))
+            .unwrap_or(agent.pos);
+    }
+}
+
+// Unit flow direction at `pos`; the cell's own direction where bilinear sampling has no
+// data (the last row and column) or the samples cancel out
+fn flow_direction(field: &FlowField, pos: (f32, f32)) -> (f32, f32) {
+    let (x, y) = field.sample_bilinear(pos.0, pos.1);
+    let (x, y) = if x * x + y * y > 1e-6 { (x, y) } else { field.get_direction(cell_of(pos)).to_vec2() };
+    let len = (x * x + y * y).sqrt();
+    if len > 0.0 { (x / len, y / len) } else { (0.0, 0.0) }
+}
+
+fn cell_of(pos: (f32, f32)) -> GridPos {
+    GridPos { x: pos.0.round() as i32, y: pos.1.round() as i32 }
+}
+
+fn is_walkable(field: &FlowField, pos: (f32, f32)) -> bool {
+    field.get_cost_to_goal(cell_of(pos)).is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D};
+
+    #[test]
+    fn crowd_funnels_through_a_narrow_gap() {
+        // Wall across the map with a 2-cell gap; the goal is far beyond it
+        let mut grid = Grid2D::new(40, 20, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((20, 0, 1, 20), true);
+        grid.set_region_blocked((20, 9, 1, 2), false);
+        let field = FlowField::compute(&grid, GridPos { x: 36, y: 10 });
+
+        let mut agents: Vec<CrowdAgent> = (0..50)
+            .map(|i| CrowdAgent {
+                pos: (3.0 + (i % 10) as f32 * 0.9, 5.0 + (i / 10) as f32 * 2.0),
+                radius: 0.3,
+                max_speed: 4.0,
+            })
+            .collect();
+
+        let mut worst_overlap = 0.0f32;
+        let mut steps = 0;
+        while agents.iter().any(|a| a.pos.0 < 22.0) {
+            assert!(steps < 600, "{} agents still behind the wall", agents.iter().filter(|a| a.pos.0 < 22.0).count());
+            step_crowd(&mut agents, &field, 0.05);
+            steps += 1;
+            for (i, a) in agents.iter().enumerate() {
+                assert!(!grid.is_blocked(a.pos.0.round() as i32, a.pos.1.round() as i32), "{a:?} in a wall");
+                for b in &agents[i + 1..] {
+                    let d = ((a.pos.0 - b.pos.0).powi(2) + (a.pos.1 - b.pos.1).powi(2)).sqrt();
+                    worst_overlap = worst_overlap.max(a.radius + b.radius - d);
+                }
+            }
+        }
+        // Discs may squeeze together at the gap, but never by more than a third of their width
+        assert!(worst_overlap < 0.2, "overlap {worst_overlap}");
+
+        // Once through, the crowd settles around the goal: 50 discs spaced apart by separation
+        // pack into a few cells' radius
+        const ARRIVAL_RADIUS: f32 = 4.0;
+        let distance_to_goal = |a: &CrowdAgent| ((a.pos.0 - 36.0).powi(2) + (a.pos.1 - 10.0).powi(2)).sqrt();
+        for _ in 0..400 {
+            step_crowd(&mut agents, &field, 0.05);
+        }
+        let farthest = agents.iter().map(distance_to_goal).fold(0.0, f32::max);
+        assert!(farthest <= ARRIVAL_RADIUS, "an agent is still {farthest} cells from the goal");
+    }
+}
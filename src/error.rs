@@ -0,0 +1,202 @@
+use std::fmt;
+
+/// Errors from grid construction and grid-to-grid operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridError {
+    /// The two grids involved in an operation do not have the same dimensions.
+    DimensionMismatch,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::DimensionMismatch => write!(f, "grid dimensions do not match"),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// Errors from joining two path segments with `PathResult::merge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeError {
+    /// The front segment does not end where the back segment starts.
+    JunctionMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::JunctionMismatch => write!(f, "path segments do not share a junction node"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Errors from validating an `AStarConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `max_iterations` of zero would stop before expanding the start node.
+    ZeroMaxIterations,
+    /// A zero timeout always expires immediately.
+    ZeroTimeout,
+    /// `heuristic_weight` must be finite and non-negative.
+    InvalidHeuristicWeight,
+    /// `max_cost` must be non-negative (and not NaN).
+    InvalidMaxCost,
+    /// The builder was given both a `timeout` and a `deadline`; set one or the other.
+    TimeoutAndDeadline,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroMaxIterations => write!(f, "max_iterations must be greater than zero"),
+            ConfigError::ZeroTimeout => write!(f, "timeout must be greater than zero"),
+            ConfigError::InvalidHeuristicWeight => write!(f, "heuristic_weight must be finite and non-negative"),
+            ConfigError::InvalidMaxCost => write!(f, "max_cost must be non-negative"),
+            ConfigError::TimeoutAndDeadline => write!(f, "timeout and deadline are mutually exclusive"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Errors from parsing a search trace file with `trace::parse_traces`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceParseError {
+    /// The line (1-based) is not the field expected at that point.
+    MalformedLine(usize),
+    /// The input ended in the middle of a trace.
+    Truncated,
+}
+
+impl fmt::Display for TraceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceParseError::MalformedLine(line) => write!(f, "malformed trace at line {line}"),
+            TraceParseError::Truncated => write!(f, "trace ended unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for TraceParseError {}
+
+/// Reasons `Grid2D::apply_delta` rejects a delta, as `PathforgeError::Delta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaError {
+    /// The grid is not at the delta's base revision (a delta was skipped or reordered).
+    RevisionMismatch { grid_revision: u64, delta_base_revision: u64 },
+    /// The delta names a cell index past the end of the grid.
+    CellOutOfBounds(u32),
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeltaError::RevisionMismatch { grid_revision, delta_base_revision } => {
+                write!(f, "delta is based on revision {delta_base_revision}, grid is at revision {grid_revision}")
+            }
+            DeltaError::CellOutOfBounds(idx) => write!(f, "delta cell {idx} is outside the grid"),
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
+/// Crate-wide error for misuse: invalid input to a constructor, conversion or utility, or a
+/// graph an algorithm doesn't support. Searches report run-time outcomes (not found,
+/// timeouts, limits) as `PathStatus` instead, see `PathStatus::as_error`. The narrower
+/// errors above convert into it with `?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathforgeError {
+    /// A grid position outside the grid.
+    PositionOutOfBounds { x: i32, y: i32 },
+    /// A polygon index past the end of the mesh.
+    PolygonOutOfRange(u32),
+    /// The operation needs at least one node.
+    EmptyPath,
+    /// Consecutive polygons of a path share neither an edge nor an off-mesh link.
+    NotAdjacent { from: u32, to: u32 },
+    /// Mesh arrays that don't fit together: lengths not multiples of 3, or vertex and
+    /// neighbor indices out of range.
+    MalformedMesh,
+    /// The polygon has zero area.
+    DegenerateMesh(u32),
+    /// The algorithm can't handle this graph's cost model.
+    UnsupportedGraph,
+    /// A path needs `required` waypoints to keep line of sight between them, more than
+    /// the `budget` allows.
+    WaypointBudgetExceeded { required: usize, budget: usize },
+    Grid(GridError),
+    Merge(MergeError),
+    Config(ConfigError),
+    Trace(TraceParseError),
+    Delta(DeltaError),
+}
+
+impl fmt::Display for PathforgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathforgeError::PositionOutOfBounds { x, y } => write!(f, "position ({x}, {y}) is outside the grid"),
+            PathforgeError::PolygonOutOfRange(poly) => write!(f, "polygon {poly} is not in the mesh"),
+            PathforgeError::EmptyPath => write!(f, "path is empty"),
+            PathforgeError::NotAdjacent { from, to } => write!(f, "polygons {from} and {to} are not connected"),
+            PathforgeError::MalformedMesh => write!(f, "mesh arrays are inconsistent"),
+            PathforgeError::DegenerateMesh(poly) => write!(f, "polygon {poly} has zero area"),
+            PathforgeError::UnsupportedGraph => write!(f, "the algorithm does not support this graph"),
+            PathforgeError::WaypointBudgetExceeded { required, budget } => {
+                write!(f, "path needs {required} waypoints, budget is {budget}")
+            }
+            PathforgeError::Grid(e) => e.fmt(f),
+            PathforgeError::Merge(e) => e.fmt(f),
+            PathforgeError::Config(e) => e.fmt(f),
+            PathforgeError::Trace(e) => e.fmt(f),
+            PathforgeError::Delta(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PathforgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PathforgeError::Grid(e) => Some(e),
+            PathforgeError::Merge(e) => Some(e),
+            PathforgeError::Config(e) => Some(e),
+            PathforgeError::Trace(e) => Some(e),
+            PathforgeError::Delta(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<GridError> for PathforgeError {
+    fn from(e: GridError) -> Self {
+        PathforgeError::Grid(e)
+    }
+}
+
+impl From<MergeError> for PathforgeError {
+    fn from(e: MergeError) -> Self {
+        PathforgeError::Merge(e)
+    }
+}
+
+impl From<ConfigError> for PathforgeError {
+    fn from(e: ConfigError) -> Self {
+        PathforgeError::Config(e)
+    }
+}
+
+impl From<TraceParseError> for PathforgeError {
+    fn from(e: TraceParseError) -> Self {
+        PathforgeError::Trace(e)
+    }
+}
+
+impl From<DeltaError> for PathforgeError {
+    fn from(e: DeltaError) -> Self {
+        PathforgeError::Delta(e)
+    }
+}
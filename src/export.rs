@@ -0,0 +1,203 @@
+// SVG rendering of grids, paths and flow fields for eyeballing test failures.
+// Each cell is drawn as a 10x10 square; cell (x, y) spans [10x, 10x + 10) x [10y, 10y + 10).
+
+use std::fmt::Write;
+
+use crate::algorithms::flowfield::{Direction, FlowField};
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::heatmap::PathHeatmap;
+use crate::traits::PathResult;
+
+const CELL: f32 = 10.0;
+
+fn open_svg(out: &mut String, width: usize, height: usize) {
+    let (w, h) = (width as f32 * CELL, height as f32 * CELL);
+    // Writing to a String can't fail
+    let _ = writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#);
+    let _ = writeln!(out, r##"<rect width="{w}" height="{h}" fill="#ffffff"/>"##);
+}
+
+fn center(x: f32, y: f32) -> (f32, f32) {
+    (x * CELL + CELL / 2.0, y * CELL + CELL / 2.0)
+}
+
+// Visited cells as orange squares, opaque for the busiest cell and fading with the count
+fn heat_layer(out: &mut String, heatmap: &PathHeatmap) {
+    for (idx, heat) in heatmap.normalized().into_iter().enumerate() {
+        if heat > 0.0 {
+            let (x, y) = ((idx % heatmap.width) as f32, (idx / heatmap.width) as f32);
+            let _ = writeln!(
+                out,
+                r##"<rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="#fb8500" fill-opacity="{heat:.3}"/>"##,
+                x * CELL,
+                y * CELL
+            );
+        }
+    }
+}
+
+impl PathResult<GridPos> {
+    /// Blocked cells as gray squares, the path as a polyline through one circle per node.
+    /// The start is green, the goal red, and the nodes between blue.
+    pub fn to_svg(&self, grid: &Grid2D) -> String {
+        self.render_svg(grid, None)
+    }
+
+    /// `to_svg` with `heatmap` drawn under the path.
+    pub fn to_svg_with_heatmap(&self, grid: &Grid2D, heatmap: &PathHeatmap) -> String {
+        self.render_svg(grid, Some(heatmap))
+    }
+
+    fn render_svg(&self, grid: &Grid2D, heatmap: Option<&PathHeatmap>) -> String {
+        let mut out = String::new();
+        open_svg(&mut out, grid.width, grid.height);
+        if let Some(heatmap) = heatmap {
+            heat_layer(&mut out, heatmap);
+        }
+
+        for y in 0..grid.height as i32 {
+            for x in 0..grid.width as i32 {
+                if grid.is_blocked(x, y) {
+                    let _ = writeln!(
+                        out,
+                        r##"<rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="#808080"/>"##,
+                        x as f32 * CELL,
+                        y as f32 * CELL
+                    );
+                }
+            }
+        }
+
+        if self.path.len() > 1 {
+            let points: Vec<String> = self
+                .path
+                .iter()
+                .map(|p| {
+                    let (cx, cy) = center(p.x as f32, p.y as f32);
+                    format!("{cx},{cy}")
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                r##"<polyline points="{}" fill="none" stroke="#1f6feb" stroke-width="2"/>"##,
+                points.join(" ")
+            );
+        }
+
+        let last = self.path.len().saturating_sub(1);
+        for (i, p) in self.path.iter().enumerate() {
+            let color = if i == 0 {
+                "#2da44e"
+            } else if i == last {
+                "#cf222e"
+            } else {
+                "#1f6feb"
+            };
+            let (cx, cy) = center(p.x as f32, p.y as f32);
+            let _ = writeln!(out, r#"<circle cx="{cx}" cy="{cy}" r="3" fill="{color}"/>"#);
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+impl PathHeatmap {
+    /// Just the heat layer, one square per visited cell.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        open_svg(&mut out, self.width, self.height);
+        heat_layer(&mut out, self);
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+impl FlowField {
+    /// One arrow per cell with a direction; cells without one (the goal, blocked and
+    /// unreachable cells) are left empty.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        open_svg(&mut out, self.width, self.height);
+        out.push_str(concat!(
+            r#"<defs><marker id="head" viewBox="0 0 6 6" refX="5" refY="3" markerWidth="4" markerHeight="4" orient="auto">"#,
+            r##"<path d="M0,0 L6,3 L0,6 z" fill="#333333"/></marker></defs>"##,
+            "\n"
+        ));
+
+        for (idx, &direction) in self.flow.iter().enumerate() {
+            if direction == Direction::None {
+                continue;
+            }
+            let (dx, dy) = direction.to_vec2();
+            let len = (dx * dx + dy * dy).sqrt();
+            let (cx, cy) = center((idx % self.width) as f32, (idx / self.width) as f32);
+            // Arrow spans most of the cell, centered on it
+            let (hx, hy) = (dx / len * CELL * 0.35, dy / len * CELL * 0.35);
+            let _ = writeln!(
+                out,
+                r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#333333" stroke-width="1" marker-end="url(#head)"/>"##,
+                cx - hx,
+                cy - hy,
+                cx + hx,
+                cy + hy
+            );
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::Manhattan;
+
+    #[test]
+    fn path_svg_has_one_circle_per_node() {
+        let mut grid = Grid2D::new(8, 6, DiagonalMode::Never);
+        grid.set_region_blocked((3, 0, 1, 5), true);
+        let result = astar(&grid, &Manhattan, GridPos { x: 0, y: 0 }, GridPos { x: 7, y: 0 }, AStarConfig::default());
+
+        let svg = result.to_svg(&grid);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), result.path.len());
+        assert_eq!(svg.matches(r##"fill="#808080""##).count(), 5);
+        assert_eq!(svg.matches("#2da44e").count(), 1);
+        assert_eq!(svg.matches("#cf222e").count(), 1);
+    }
+
+    #[test]
+    fn flow_field_svg_has_arrow_per_directed_cell() {
+        let mut grid = Grid2D::new(5, 4, DiagonalMode::Always);
+        grid.set_blocked(2, 1, true);
+        let field = FlowField::compute(&grid, GridPos { x: 4, y: 3 });
+
+        let svg = field.to_svg();
+        assert!(svg.starts_with("<svg"));
+        // Everything but the goal and the blocked cell points somewhere
+        assert_eq!(svg.matches("<line").count(), 5 * 4 - 2);
+    }
+
+    #[test]
+    fn heatmap_layer_has_square_per_visited_cell() {
+        let grid = Grid2D::new(6, 4, DiagonalMode::Never);
+        let result = astar(&grid, &Manhattan, GridPos { x: 0, y: 1 }, GridPos { x: 5, y: 1 }, AStarConfig::default());
+        let mut heatmap = PathHeatmap::for_grid(&grid);
+        heatmap.record(&result.path);
+        heatmap.record(&result.path[..2]);
+
+        let svg = result.to_svg_with_heatmap(&grid, &heatmap);
+        assert_eq!(svg.matches("#fb8500").count(), result.path.len());
+        assert_eq!(svg.matches(r#"fill-opacity="1.000""#).count(), 2);
+        assert_eq!(svg.matches("<circle").count(), result.path.len());
+        // The layer comes before the path so it is drawn underneath
+        assert!(svg.find("#fb8500") < svg.find("<polyline"));
+        assert_eq!(heatmap.to_svg().matches("<rect x=").count(), result.path.len());
+        assert!(!result.to_svg(&grid).contains("#fb8500"));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::graphs::grid2d::GridPos;
+
+/// Shortest distance from point `p` to the segment `a`-`b`.
+pub fn point_segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0 // Degenerate segment
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+/// True if the segment `a`-`b` touches or enters the circle. Tangent counts as a hit.
+pub fn segment_circle_intersect(
+    a: (f32, f32),
+    b: (f32, f32),
+    center: (f32, f32),
+    radius: f32,
+) -> bool {
+    point_segment_distance(center, a, b) <= radius
+}
+
+/// Smallest gap between the polyline `path` and any circular obstacle `(x, y, radius)`.
+/// Negative when the path passes through an obstacle; `f32::INFINITY` with no obstacles
+/// or an empty path.
+pub fn path_min_clearance(path: &[(f32, f32)], obstacles: &[(f32, f32, f32)]) -> f32 {
+    let mut min = f32::INFINITY;
+    for &(ox, oy, r) in obstacles {
+        let dist = match path {
+            [] => continue,
+            [p] => point_segment_distance((ox, oy), *p, *p),
+            _ => path
+                .windows(2)
+                .map(|w| point_segment_distance((ox, oy), w[0], w[1]))
+                .fold(f32::INFINITY, f32::min),
+        };
+        min = min.min(dist - r);
+    }
+    min
+}
+
+/// Index of the first segment (`path[i]` -> `path[i + 1]`) with a blocked endpoint,
+/// for node paths sampled against a predicate. `None` if every node is clear.
+pub fn first_blocked_segment<N, F>(path: &[N], blocked: F) -> Option<usize>
+where
+    F: Fn(&N) -> bool,
+{
+    if path.len() < 2 {
+        return None;
+    }
+    if blocked(&path[0]) {
+        return Some(0);
+    }
+    (1..path.len()).find(|&i| blocked(&path[i])).map(|i| i - 1)
+}
+
+/// Every `(i, j)` with `a[i] == b[j]`, ordered by `i` then `j`: where two planned paths
+/// visit the same node, regardless of when.
+pub fn paths_shared_nodes<N: PartialEq>(a: &[N], b: &[N]) -> Vec<(usize, usize)> {
+    let mut shared = Vec::new();
+    for (i, node) in a.iter().enumerate() {
+        shared.extend(b.iter().enumerate().filter(|(_, other)| *other == node).map(|(j, _)| (i, j)));
+    }
+    shared
+}
+
+/// The first node along `a` that `b` also visits, as `(i, j)` with `j` the earliest visit
+/// in `b`. `None` if the paths share no node.
+pub fn first_intersection<N: PartialEq>(a: &[N], b: &[N]) -> Option<(usize, usize)> {
+    a.iter().enumerate().find_map(|(i, node)| Some((i, b.iter().position(|other| other == node)?)))
+}
+
+/// The first pair of segments (`a[i]` -> `a[i + 1]`, `b[j]` -> `b[j + 1]`, ordered by `i`
+/// then `j`) that cross or overlap anywhere other than at a node both paths share: two
+/// diagonal moves passing through each other, or a head-on swap, which a node comparison
+/// misses. Works for any-angle paths too. Waits (repeated nodes) are skipped.
+pub fn paths_cross(a: &[GridPos], b: &[GridPos]) -> Option<(usize, usize)> {
+    crossing_segments(a, b).next()
+}
+
+fn crossing_segments<'p>(a: &'p [GridPos], b: &'p [GridPos]) -> impl Iterator<Item = (usize, usize)> + 'p {
+    a.windows(2).enumerate().flat_map(move |(i, sa)| {
+        b.windows(2).enumerate().filter(move |(_, sb)| segments_cross(sa, sb)).map(move |(j, _)| (i, j))
+    })
+}
+
+// Sign of the turn o -> p -> q
+fn orientation(o: GridPos, p: GridPos, q: GridPos) -> i64 {
+    let (px, py) = (p.x as i64 - o.x as i64, p.y as i64 - o.y as i64);
+    let (qx, qy) = (q.x as i64 - o.x as i64, q.y as i64 - o.y as i64);
+    (px * qy - py * qx).signum()
+}
+
+fn segments_cross(sa: &[GridPos], sb: &[GridPos]) -> bool {
+    let (p1, p2, q1, q2) = (sa[0], sa[1], sb[0], sb[1]);
+    if p1 == p2 || q1 == q2 {
+        return false;
+    }
+    let (d1, d2) = (orientation(q1, q2, p1), orientation(q1, q2, p2));
+    let (d3, d4) = (orientation(p1, p2, q1), orientation(p1, p2, q2));
+    if d1 == 0 && d2 == 0 {
+        // Collinear: crossing only if they overlap for more than a point
+        let key = |p: GridPos| if p1.x != p2.x { p.x } else { p.y };
+        let (a0, a1) = (key(p1).min(key(p2)), key(p1).max(key(p2)));
+        let (b0, b1) = (key(q1).min(key(q2)), key(q1).max(key(q2)));
+        return a0.max(b0) < a1.min(b1);
+    }
+    if d1 * d2 > 0 || d3 * d4 > 0 {
+        return false;
+    }
+    // They meet at exactly one point; a shared node is node sharing, not a crossing
+    !(p1 == q1 || p1 == q2 || p2 == q1 || p2 == q2)
+}
+
+/// Summary of how two planned paths interfere, for staggering agents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConflictReport {
+    /// Pairs of equal nodes (see `paths_shared_nodes`).
+    pub shared_nodes: usize,
+    /// Pairs of segments crossing away from shared nodes (see `paths_cross`).
+    pub crossings: usize,
+    pub first_shared: Option<(usize, usize)>,
+    pub first_crossing: Option<(usize, usize)>,
+}
+
+impl ConflictReport {
+    pub fn new(a: &[GridPos], b: &[GridPos]) -> Self {
+        let shared = paths_shared_nodes(a, b);
+        let mut crossings = crossing_segments(a, b);
+        let first_crossing = crossings.next();
+        Self {
+            shared_nodes: shared.len(),
+            crossings: first_crossing.map_or(0, |_| 1 + crossings.count()),
+            first_shared: first_intersection(a, b),
+            first_crossing,
+        }
+    }
+
+    /// True if the paths share a node or cross.
+    pub fn has_conflict(&self) -> bool {
+        self.shared_nodes > 0 || self.crossings > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_circle_tangent_overlap_and_clear() {
+        // Horizontal segment along y = 0
+        let (a, b) = ((0.0, 0.0), (10.0, 0.0));
+        assert!(segment_circle_intersect(a, b, (5.0, 1.0), 1.0)); // Tangent
+        assert!(segment_circle_intersect(a, b, (5.0, 0.5), 1.0)); // Overlapping
+        assert!(!segment_circle_intersect(a, b, (5.0, 1.5), 1.0)); // Clear
+        // Beyond the end cap the distance is measured to the endpoint
+        assert!(!segment_circle_intersect(a, b, (11.5, 0.0), 1.0));
+        assert!(segment_circle_intersect(a, b, (11.0, 0.0), 1.0));
+    }
+
+    #[test]
+    fn min_clearance_over_polyline() {
+        let path = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)];
+        assert_eq!(path_min_clearance(&path, &[]), f32::INFINITY);
+
+        let clear = [(5.0, 3.0, 1.0), (13.0, 5.0, 0.5)];
+        assert!((path_min_clearance(&path, &clear) - 2.0).abs() < 1e-5);
+
+        let tangent = [(5.0, -2.0, 2.0)];
+        assert!(path_min_clearance(&path, &tangent).abs() < 1e-5);
+
+        let overlapping = [(9.0, 5.0, 2.0)];
+        assert!((path_min_clearance(&path, &overlapping) + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn first_blocked_segment_indices() {
+        let path = [0, 1, 2, 3, 4];
+        assert_eq!(first_blocked_segment(&path, |_| false), None);
+        assert_eq!(first_blocked_segment(&path, |&n| n == 0), Some(0));
+        assert_eq!(first_blocked_segment(&path, |&n| n == 1), Some(0));
+        assert_eq!(first_blocked_segment(&path, |&n| n == 3), Some(2));
+        assert_eq!(first_blocked_segment(&path, |&n| n >= 4), Some(3));
+        assert_eq!(first_blocked_segment(&[7], |_| true), None);
+    }
+
+    #[test]
+    fn path_conflicts_shared_crossing_and_disjoint() {
+        let p = |x, y| GridPos { x, y };
+        // Shared nodes: b walks up column 2, through a's row
+        let a = [p(0, 1), p(1, 1), p(2, 1), p(3, 1)];
+        let b = [p(2, 0), p(2, 1), p(2, 2), p(2, 1)];
+        assert_eq!(paths_shared_nodes(&a, &b), vec![(2, 1), (2, 3)]);
+        assert_eq!(first_intersection(&a, &b), Some((2, 1)));
+        assert_eq!(first_intersection(&b, &a), Some((1, 2)));
+        // Segments meeting only at the shared node don't cross
+        assert_eq!(paths_cross(&a, &b), None);
+        let report = ConflictReport::new(&a, &b);
+        assert_eq!((report.shared_nodes, report.crossings, report.first_shared), (2, 0, Some((2, 1))));
+        assert!(report.has_conflict());
+
+        // Diagonal moves through each other share no node
+        let a = [p(0, 0), p(1, 1), p(2, 2)];
+        let b = [p(3, 0), p(2, 0), p(1, 0), p(0, 1)];
+        assert!(paths_shared_nodes(&a, &b).is_empty());
+        assert_eq!(first_intersection(&a, &b), None);
+        assert_eq!(paths_cross(&a, &b), Some((0, 2)));
+        assert_eq!(paths_cross(&b, &a), Some((2, 0)));
+        let expected =
+            ConflictReport { shared_nodes: 0, crossings: 1, first_shared: None, first_crossing: Some((0, 2)) };
+        assert_eq!(ConflictReport::new(&a, &b), expected);
+
+        // A head-on swap overlaps along the whole step
+        let (a, b) = ([p(4, 4), p(5, 4)], [p(5, 4), p(4, 4)]);
+        assert_eq!(paths_cross(&a, &b), Some((0, 0)));
+
+        // Disjoint paths, one of them any-angle
+        let a = [p(0, 0), p(9, 3)];
+        let b = [p(0, 5), p(4, 5), p(4, 5), p(9, 6)];
+        assert_eq!(paths_cross(&a, &b), None);
+        assert_eq!(ConflictReport::new(&a, &b), ConflictReport::default());
+        assert!(!ConflictReport::new(&a, &b).has_conflict());
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::traits::Graph;
+
+/// A one-way connection between two layers (stairs, elevators, ladders).
+/// Add a second transition in the opposite direction for two-way links.
+#[derive(Debug, Clone)]
+pub struct Transition<N> {
+    pub from_layer: usize,
+    pub from_node: N,
+    pub to_layer: usize,
+    pub to_node: N,
+    pub cost: f32,
+}
+
+// (layer, node) -> outgoing (to_layer, to_node, cost)
+type TransitionMap<N> = HashMap<(usize, N), Vec<(usize, N, f32)>>;
+
+/// Stacks several graphs of the same type (e.g. building floors) into one searchable graph.
+/// Nodes are `(layer, node)`; within-layer movement delegates to the layer graph and
+/// cross-layer movement only happens through the registered transitions.
+pub struct LayeredGraph<G: Graph> {
+    pub layers: Vec<G>,
+    transitions: TransitionMap<G::Node>,
+}
+
+impl<G: Graph> LayeredGraph<G> {
+    pub fn new(layers: Vec<G>, transitions: Vec<Transition<G::Node>>) -> Self {
+        let mut map: TransitionMap<G::Node> = HashMap::new();
+        for t in transitions {
+            map.entry((t.from_layer, t.from_node))
+                .or_default()
+                .push((t.to_layer, t.to_node, t.cost));
+        }
+        Self {
+            layers,
+            transitions: map,
+        }
+    }
+
+    pub fn layer(&self, index: usize) -> Option<&G> {
+        self.layers.get(index)
+    }
+}
+
+impl<G: Graph> Graph for LayeredGraph<G> {
+    type Node = (usize, G::Node);
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        match self.layers.get(node.0) {
+            Some(layer) => layer.is_passable(&node.1),
+            None => false,
+        }
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        let (layer_idx, ref inner) = *node;
+        let Some(layer) = self.layers.get(layer_idx) else {
+            return;
+        };
+
+        layer.neighbors(inner, |n, cost| visit((layer_idx, n), cost));
+
+        if let Some(links) = self.transitions.get(node) {
+            for (to_layer, to_node, cost) in links {
+                let target = (*to_layer, to_node.clone());
+                if self.is_passable(&target) {
+                    visit(target, *cost);
+                }
+            }
+        }
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        // Line of sight never spans floors; only transitions connect layers
+        if from.0 != to.0 {
+            return false;
+        }
+        match self.layers.get(from.0) {
+            Some(layer) => layer.can_traverse(&from.1, &to.1),
+            None => false,
+        }
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    // Floors share no coordinate frame, so only same-layer pairs have a distance
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        if from.0 != to.0 {
+            return None;
+        }
+        self.layers.get(from.0)?.geometric_distance(&from.1, &to.1)
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.layers.iter().map(|layer| layer.node_count_hint()).sum()
+    }
+}
+
+/// Extra per-edge cost, from the edge's endpoints (e.g. how exposed the cell entered is).
+pub type Criterion<'a, N> = Box<dyn Fn(&N, &N) -> f32 + 'a>;
+
+/// A graph whose edges cost the base cost plus a weighted sum of extra criteria, so plain
+/// `astar` trades them off: `distance + lambda * visibility` for stealth, say. A criterion
+/// without a weight counts with weight 0. A heuristic admissible for the base graph stays
+/// admissible as long as criteria and weights are non-negative. See `pareto_paths` for
+/// the whole trade-off at once.
+pub struct MultiObjectiveGraph<'a, G: Graph> {
+    pub base: &'a G,
+    pub weights: Vec<f32>,
+    criteria: Vec<Criterion<'a, G::Node>>,
+}
+
+impl<'a, G: Graph> MultiObjectiveGraph<'a, G> {
+    pub fn new(base: &'a G, criteria: Vec<Criterion<'a, G::Node>>, weights: Vec<f32>) -> Self {
+        Self { base, weights, criteria }
+    }
+
+    /// Weighted cost of the edge `from` -> `to` with base cost `base_cost`.
+    pub fn edge_cost(&self, from: &G::Node, to: &G::Node, base_cost: f32) -> f32 {
+        let extra: f32 = self.criteria.iter().zip(&self.weights).map(|(criterion, w)| w * criterion(from, to)).sum();
+        base_cost + extra
+    }
+
+    /// Unweighted total of each criterion along `path`, in order.
+    pub fn criteria_totals(&self, path: &[G::Node]) -> Vec<f32> {
+        self.criteria.iter().map(|criterion| path.windows(2).map(|w| criterion(&w[0], &w[1])).sum()).collect()
+    }
+}
+
+impl<G: Graph> Graph for MultiObjectiveGraph<'_, G> {
+    type Node = G::Node;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.base.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.base.neighbors(node, |n, cost| {
+            let cost = self.edge_cost(node, &n, cost);
+            visit(n, cost);
+        });
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        self.base.can_traverse(from, to)
+    }
+
+    fn layer_count(&self) -> usize {
+        self.base.layer_count()
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.base.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.base.geometric_distance(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
+    use crate::heuristics::Zero;
+    use crate::traits::PathStatus;
+
+    #[test]
+    fn astar_crosses_floors_via_staircase() {
+        let ground = Grid2D::new(5, 5, DiagonalMode::Never);
+        let upper = Grid2D::new(5, 5, DiagonalMode::Never);
+        let stairs = GridPos { x: 4, y: 0 };
+        let graph = LayeredGraph::new(
+            vec![ground, upper],
+            vec![Transition {
+                from_layer: 0,
+                from_node: stairs,
+                to_layer: 1,
+                to_node: stairs,
+                cost: 3.0,
+            }],
+        );
+        assert_eq!(graph.layer_count(), 2);
+
+        let start = (0, GridPos { x: 0, y: 0 });
+        let goal = (1, GridPos { x: 4, y: 4 });
+        let result = astar(&graph, &Zero, start, goal, AStarConfig::default());
+
+        assert_eq!(result.status, PathStatus::Found);
+        // 4 steps to the stairs + 3 for the stairs + 4 steps on the upper floor
+        assert!((result.cost - 11.0).abs() < 1e-4, "cost {}", result.cost);
+        assert!(result.path.contains(&(0, stairs)));
+        assert!(result.path.contains(&(1, stairs)));
+
+        // Transitions are one-way
+        let back = astar(&graph, &Zero, goal, start, AStarConfig::default());
+        assert_eq!(back.status, PathStatus::NotFound);
+    }
+}
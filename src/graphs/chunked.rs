@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::graphs::grid2d::{grid_steps, CellType, DiagonalMode, GridPos};
+use crate::heuristics::Euclidean;
+use crate::traits::{Graph, Heuristic};
+
+/// Side length of a `ChunkedGrid2D` chunk, in cells.
+pub const CHUNK_SIZE: i32 = 32;
+
+type Generator = Box<dyn Fn(GridPos) -> CellType + Send + Sync>;
+type Chunk = Box<[CellType]>; // CHUNK_SIZE rows of CHUNK_SIZE cells
+
+/// Unbounded grid over all of i32 x i32 for procedurally generated worlds, stored as
+/// `CHUNK_SIZE` x `CHUNK_SIZE` chunks created on demand. Cells default to passable with
+/// cost 1.0, or to whatever the generator returns; a chunk is generated (and kept) the
+/// first time any of its cells is read. Without a generator, reads of untouched chunks
+/// allocate nothing.
+///
+/// Searches with `astar`, `theta_star` and the other `Graph`-generic algorithms; give them
+/// an `AStarConfig::max_iterations` if the goal may be unreachable, or they will explore
+/// without end. Not a `JpsGrid` (jumps only stop at obstacles, the goal or the map edge, and
+/// this map has no edge), and `FlowField`, `DistanceField` and `HierarchicalGrid` need a
+/// bounded `Grid2D`.
+pub struct ChunkedGrid2D {
+    pub diagonal_movement: DiagonalMode,
+    pub diagonal_cost: f32,
+    chunks: RwLock<HashMap<(i32, i32), Chunk>>,
+    generator: Option<Generator>,
+}
+
+impl ChunkedGrid2D {
+    pub fn new(diagonal_movement: DiagonalMode) -> Self {
+        Self {
+            diagonal_movement,
+            diagonal_cost: std::f32::consts::SQRT_2,
+            chunks: RwLock::new(HashMap::new()),
+            generator: None,
+        }
+    }
+
+    /// Cells of each chunk come from `generator` when the chunk is first touched.
+    pub fn with_generator<F>(diagonal_movement: DiagonalMode, generator: F) -> Self
+    where
+        F: Fn(GridPos) -> CellType + Send + Sync + 'static,
+    {
+        Self { generator: Some(Box::new(generator)), ..Self::new(diagonal_movement) }
+    }
+
+    /// Number of chunks in memory.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.read().unwrap().len()
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> CellType {
+        let (key, offset) = Self::locate(x, y);
+        if let Some(chunk) = self.chunks.read().unwrap().get(&key) {
+            return chunk[offset];
+        }
+        if self.generator.is_none() {
+            return CellType::Passable(1.0);
+        }
+        let mut chunks = self.chunks.write().unwrap();
+        chunks.entry(key).or_insert_with(|| Self::generate(&self.generator, key))[offset]
+    }
+
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.cell(x, y) == CellType::Blocked
+    }
+
+    /// Cost multiplier of (x, y); `f32::INFINITY` if blocked.
+    pub fn get_cost(&self, x: i32, y: i32) -> f32 {
+        match self.cell(x, y) {
+            CellType::Passable(cost) => cost,
+            CellType::Blocked => f32::INFINITY,
+        }
+    }
+
+    pub fn set_blocked(&mut self, x: i32, y: i32, blocked: bool) {
+        self.set_cell(x, y, if blocked { CellType::Blocked } else { CellType::Passable(1.0) });
+    }
+
+    pub fn set_cost(&mut self, x: i32, y: i32, cost: f32) {
+        self.set_cell(x, y, CellType::Passable(cost));
+    }
+
+    pub fn set_cell(&mut self, x: i32, y: i32, cell: CellType) {
+        let (key, offset) = Self::locate(x, y);
+        let generator = &self.generator;
+        let chunks = self.chunks.get_mut().unwrap();
+        chunks.entry(key).or_insert_with(|| Self::generate(generator, key))[offset] = cell;
+    }
+
+    // Chunk key and row-major offset within the chunk
+    fn locate(x: i32, y: i32) -> ((i32, i32), usize) {
+        let key = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let offset = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+        (key, offset as usize)
+    }
+
+    fn generate(generator: &Option<Generator>, (cx, cy): (i32, i32)) -> Chunk {
+        let cells = (0..CHUNK_SIZE * CHUNK_SIZE).map(|offset| match generator {
+            Some(generator) => generator(GridPos {
+                x: cx * CHUNK_SIZE + offset % CHUNK_SIZE,
+                y: cy * CHUNK_SIZE + offset / CHUNK_SIZE,
+            }),
+            None => CellType::Passable(1.0),
+        });
+        cells.collect()
+    }
+}
+
+impl Graph for ChunkedGrid2D {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    // `Grid2D`'s steps and corner rules; costs as `Grid2D` without directional costs
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        let blocked = |x, y| self.is_blocked(x, y);
+        let cost = |next: GridPos, dx: i32, dy: i32| {
+            // `grid_steps` only prices cells `blocked` let through, so this is always Passable
+            let CellType::Passable(cost) = self.cell(next.x, next.y) else { return 0.0 };
+            cost * if dx != 0 && dy != 0 { self.diagonal_cost } else { 1.0 }
+        };
+        for (next, cost) in grid_steps(*node, self.diagonal_movement, blocked, cost) {
+            visit(next, cost);
+        }
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        Some(Euclidean.estimate(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::heuristics::Diagonal;
+    use crate::traits::PathStatus;
+
+    #[test]
+    fn paths_cross_chunk_boundaries_and_negative_coordinates() {
+        let mut grid = ChunkedGrid2D::new(DiagonalMode::IfNoObstacle);
+        // Wall along x = 0 (a chunk edge) from y = -20 to 20
+        for y in -20..=20 {
+            grid.set_blocked(0, y, true);
+        }
+        assert_eq!(grid.chunk_count(), 2);
+        assert!(grid.is_blocked(0, -20) && !grid.is_blocked(-1, -20));
+        assert_eq!(grid.get_cost(1_000_000, -1_000_000), 1.0);
+
+        let (start, goal) = (GridPos { x: -5, y: 0 }, GridPos { x: 5, y: 0 });
+        let result = astar(&grid, &Diagonal::default(), start, goal, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::Found);
+        assert!(result.path.iter().any(|p| p.y.abs() > 20));
+        assert!(result.path.windows(2).all(|w| (w[1].x - w[0].x).abs() <= 1 && (w[1].y - w[0].y).abs() <= 1));
+        // Reads without a generator don't allocate
+        assert_eq!(grid.chunk_count(), 2);
+    }
+
+    #[test]
+    fn generated_chunks_stay_proportional_to_the_explored_area() {
+        // Pillars every 8 cells, both ways
+        let grid = ChunkedGrid2D::with_generator(DiagonalMode::OnlyIfBothOpen, |p| {
+            if p.x.rem_euclid(8) == 4 && p.y.rem_euclid(8) == 4 { CellType::Blocked } else { CellType::Passable(1.0) }
+        });
+        assert!(grid.is_blocked(-4, 12));
+        assert_eq!(grid.chunk_count(), 1);
+
+        let (start, goal) = (GridPos { x: -40, y: 3 }, GridPos { x: 200, y: 3 });
+        let result = astar(&grid, &Diagonal::default(), start, goal, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::Found);
+        assert_eq!(result.cost, 240.0);
+        // A straight run across 8 chunk columns touches little more than its own row of chunks
+        assert!(grid.chunk_count() <= 3 * 9, "{} chunks", grid.chunk_count());
+    }
+}
@@ -1,4 +1,14 @@
-use crate::traits::Graph;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::cost::{Cost, CostGraph, Milli};
+use crate::error::{DeltaError, GridError, PathforgeError};
+use crate::rng::SplitMix64;
+use crate::heuristics::{Diagonal, Euclidean};
+use crate::traits::{Graph, GraphWithHeuristic, Heuristic};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct GridPos {
@@ -6,7 +16,73 @@ pub struct GridPos {
     pub y: i32,
 }
 
+/// Compass direction of a grid step (N is -y); a `FlowField` cell's way toward its goal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    None,
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    pub(crate) fn to_vec2(self) -> (f32, f32) {
+        match self {
+            Direction::None => (0.0, 0.0),
+            Direction::N => (0.0, -1.0),
+            Direction::NE => (1.0, -1.0),
+            Direction::E => (1.0, 0.0),
+            Direction::SE => (1.0, 1.0),
+            Direction::S => (0.0, 1.0),
+            Direction::SW => (-1.0, 1.0),
+            Direction::W => (-1.0, 0.0),
+            Direction::NW => (-1.0, -1.0),
+        }
+    }
+}
+
+/// Row-major index of a cell (`y * width + x`) into a grid's per-cell arrays. Built only
+/// through bounds checks (`Grid2D::index_of`, `CellIndex::of`), so a position off one edge
+/// never wraps around to a cell on the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CellIndex(pub(crate) usize);
+
+impl CellIndex {
+    /// The raw row-major index.
+    #[inline]
+    pub fn get(self) -> usize {
+        self.0
+    }
+
+    /// Index of `pos` in a `width` x `height` row-major array; None outside it.
+    #[inline]
+    pub fn of(pos: GridPos, width: usize, height: usize) -> Option<CellIndex> {
+        let inside = pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < width && (pos.y as usize) < height;
+        inside.then(|| CellIndex(pos.y as usize * width + pos.x as usize))
+    }
+
+    /// (x, y) of this index in a row-major array `width` cells wide.
+    #[inline]
+    pub fn coords(self, width: usize) -> (usize, usize) {
+        (self.0 % width, self.0 / width)
+    }
+
+    /// `coords` as a `GridPos`.
+    #[inline]
+    pub fn to_pos(self, width: usize) -> GridPos {
+        let (x, y) = self.coords(width);
+        GridPos { x: x as i32, y: y as i32 }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CellType {
     Passable(f32),   // with movement cost multiplier
     Blocked,
@@ -20,11 +96,246 @@ pub enum DiagonalMode {
     OnlyIfBothOpen,  // Both adjacent cardinals must be open (strict corner cutting)
 }
 
+impl DiagonalMode {
+    // Whether a diagonal step may pass between its two side cells, given which are blocked
+    pub(crate) fn allows_diagonal(self, side_a_blocked: bool, side_b_blocked: bool) -> bool {
+        match self {
+            DiagonalMode::Never => false,
+            DiagonalMode::Always => true,
+            DiagonalMode::IfNoObstacle => !side_a_blocked || !side_b_blocked,
+            DiagonalMode::OnlyIfBothOpen => !side_a_blocked && !side_b_blocked,
+        }
+    }
+}
+
+/// Cost summary over the passable cells of a region. All zero when `count` is 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub count: usize,
+}
+
+/// Summed-area table of passable cells for O(1) region counts.
+/// Built from a snapshot; rebuild after the grid changes.
+#[derive(Clone, Debug)]
+pub struct GridPrefixSums {
+    width: usize,
+    height: usize,
+    // (width + 1) x (height + 1), row 0 and column 0 are zero
+    sums: Vec<u32>,
+}
+
+impl GridPrefixSums {
+    /// Number of passable cells in `rect` = (x, y, width, height), clipped to the grid.
+    pub fn region_passable_count(&self, rect: (usize, usize, usize, usize)) -> usize {
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        let stride = self.width + 1;
+        let at = |x: usize, y: usize| self.sums[y * stride + x] as i64;
+        (at(x1, y1) - at(x0, y1) - at(x1, y0) + at(x0, y0)) as usize
+    }
+}
+
+// Clips (x, y, w, h) to the grid, returning half-open bounds (x0, y0, x1, y1)
+fn clip_rect(rect: (usize, usize, usize, usize), width: usize, height: usize) -> (usize, usize, usize, usize) {
+    let (rx, ry, rw, rh) = rect;
+    let x0 = rx.min(width);
+    let y0 = ry.min(height);
+    (x0, y0, rx.saturating_add(rw).min(width), ry.saturating_add(rh).min(height))
+}
+
+/// Passable regions of a grid, labelled with the grid's own movement rules.
+#[derive(Clone, Debug)]
+pub struct ConnectedComponents {
+    /// Component id per cell (row-major); None for blocked cells.
+    pub labels: Vec<Option<u32>>,
+    /// Cell count of each component, indexed by id.
+    pub sizes: Vec<usize>,
+    pub count: usize,
+}
+
+/// Distance from each cell center to the nearest obstacle edge, in cells. Cells outside the
+/// grid count as obstacles, so a cell between two walls has 0.5. Blocked cells are 0.
+/// Uses an 8-neighbor chamfer approximation (overestimates true distance by at most ~8%).
+#[derive(Clone, Debug)]
+pub struct ClearanceField {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+}
+
+impl ClearanceField {
+    /// Clearance at (x, y); 0.0 outside the grid.
+    pub fn get(&self, x: i32, y: i32) -> f32 {
+        CellIndex::of(GridPos { x, y }, self.width, self.height).map_or(0.0, |cell| self.values[cell.0])
+    }
+}
+
+/// Obstacle layouts for `Grid2D::from_pattern`. Walls and rings are one cell thick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GridPattern {
+    Empty,
+    /// Perfect maze from the seed: 1-wide corridors on odd rows and columns, exactly one
+    /// route between any two of them, walled around the edge.
+    Maze(u64),
+    /// Columns at every multiple of `spacing` (from `spacing` on), open for the last
+    /// `gap_height` rows.
+    VerticalWalls { spacing: usize, gap_height: usize },
+    /// Rows at every multiple of `spacing` (from `spacing` on), open for the last
+    /// `gap_width` columns.
+    HorizontalWalls { spacing: usize, gap_width: usize },
+    /// `cell_size` squares, blocked where the square's row plus column is odd; (0, 0) is open.
+    Checkerboard { cell_size: usize },
+    /// Each cell blocked with probability `density`.
+    Random { seed: u64, density: f32 },
+    /// Square rings around the center cell at every multiple of `ring_spacing`, each with a
+    /// one-cell door, alternating between the east and west sides.
+    Concentric { ring_spacing: usize },
+}
+
+/// Named terrain zones painted over a grid, each with a cost multiplier.
+/// Apply with `Grid2D::apply_terrain_zones`, which requires the grid's dimensions.
+#[derive(Clone, Debug, Default)]
+pub struct TerrainZoneMap {
+    pub width: usize,
+    pub height: usize,
+    /// Multiplier per zone name; a painted zone missing here counts as 1.0.
+    pub zones: HashMap<String, f32>,
+    /// Zone per cell (row-major); None leaves the cell's cost alone.
+    pub cell_zone: Vec<Option<String>>,
+}
+
+impl TerrainZoneMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, zones: HashMap::new(), cell_zone: vec![None; width * height] }
+    }
+
+    pub fn define_zone(&mut self, zone: &str, multiplier: f32) {
+        self.zones.insert(zone.to_string(), multiplier);
+    }
+
+    /// Paints `rect` = (x, y, width, height), clipped to the map.
+    pub fn paint_rect(&mut self, rect: (usize, usize, usize, usize), zone: &str) {
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.cell_zone[y * self.width + x] = Some(zone.to_string());
+            }
+        }
+    }
+
+    /// Paints every cell whose center lies within `radius` of `center`'s center.
+    pub fn paint_circle(&mut self, center: (usize, usize), radius: usize, zone: &str) {
+        let (cx, cy) = center;
+        let r2 = radius * radius;
+        for y in cy.saturating_sub(radius)..(cy + radius + 1).min(self.height) {
+            for x in cx.saturating_sub(radius)..(cx + radius + 1).min(self.width) {
+                if x.abs_diff(cx).pow(2) + y.abs_diff(cy).pow(2) <= r2 {
+                    self.cell_zone[y * self.width + x] = Some(zone.to_string());
+                }
+            }
+        }
+    }
+
+    /// Multiplier for the zone painted at (x, y), or None if the cell is unpainted or
+    /// outside the map.
+    pub fn multiplier_at(&self, x: usize, y: usize) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let zone = self.cell_zone.get(y * self.width + x)?.as_ref()?;
+        Some(self.zones.get(zone).copied().unwrap_or(1.0))
+    }
+}
+
+/// Kind of edit reported in a `GridChange`, named after the operation that made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridChangeKind {
+    Blocked,
+    Unblocked,
+    CostChanged, // Cell or directional cost
+    Cleared,
+}
+
+/// A grid edit delivered to `ChangeListener`s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridChange {
+    pub kind: GridChangeKind,
+    /// (x, y, width, height) covering every modified cell, clipped to the grid.
+    pub rect: (usize, usize, usize, usize),
+}
+
+impl GridChange {
+    fn cell(kind: GridChangeKind, x: usize, y: usize) -> Self {
+        Self { kind, rect: (x, y, 1, 1) }
+    }
+
+    pub fn contains(&self, pos: &GridPos) -> bool {
+        let (rx, ry, rw, rh) = self.rect;
+        pos.x >= 0
+            && pos.y >= 0
+            && (pos.x as usize) >= rx
+            && (pos.x as usize) < rx + rw
+            && (pos.y as usize) >= ry
+            && (pos.y as usize) < ry + rh
+    }
+
+    /// Every cell position in `rect`.
+    pub fn cells(&self) -> impl Iterator<Item = GridPos> {
+        let (rx, ry, rw, rh) = self.rect;
+        (ry..ry + rh).flat_map(move |y| (rx..rx + rw).map(move |x| GridPos { x: x as i32, y: y as i32 }))
+    }
+}
+
+/// Cell edits between two revisions of a grid, for sending map changes to copies of it
+/// (e.g. clients of a game server). Made by `Grid2D::drain_changes_as_delta`, applied with
+/// `Grid2D::apply_delta`. Each cell appears at most once, with its final state.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridDelta {
+    /// Revision the receiving grid must be at.
+    pub base_revision: u64,
+    /// Revision the receiving grid is at afterwards.
+    pub revision: u64,
+    /// Row-major indices of cells that became blocked, ascending.
+    pub blocked: Vec<u32>,
+    /// Row-major indices and costs of cells that became passable or changed cost, ascending.
+    pub passable: Vec<(u32, f32)>,
+}
+
+impl GridDelta {
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty() && self.passable.is_empty()
+    }
+}
+
+/// Receiving end of `Grid2D::subscribe`. Changes queue up until drained; dropping the
+/// listener unsubscribes it.
+pub struct ChangeListener {
+    rx: Mutex<Receiver<GridChange>>, // Mutex keeps owners like HierarchicalGrid Sync
+}
+
+impl ChangeListener {
+    /// Returns all changes received since the last call, oldest first.
+    pub fn drain(&self) -> Vec<GridChange> {
+        self.rx.lock().unwrap().try_iter().collect()
+    }
+}
+
 pub struct Grid2D {
     pub width: usize,
     pub height: usize,
     pub cells: Vec<CellType>,
     pub diagonal_movement: DiagonalMode,
+    pub diagonal_cost: f32,  // Multiplier for diagonal steps: SQRT_2 (default), 1.0 Chebyshev, 1.5 roguelike
+    // Optional direction-dependent multipliers, indexed N, NE, E, SE, S, SW, W, NW
+    directional_bias: Option<[f32; 8]>,
+    directional_costs: Option<Vec<[f32; 8]>>, // Per cell, applied when entering it
+    // Modified cells since the last `take_changes`; None while tracking is off
+    changes: Option<Vec<GridPos>>,
+    listeners: Mutex<Vec<Sender<GridChange>>>,
+    revision: u64, // Bumped by `drain_changes_as_delta`, set by `apply_delta`
 }
 
 impl Grid2D {
@@ -34,160 +345,1994 @@ impl Grid2D {
             height,
             cells: vec![CellType::Passable(1.0); width * height],
             diagonal_movement,
+            diagonal_cost: std::f32::consts::SQRT_2,
+            directional_bias: None,
+            directional_costs: None,
+            changes: None,
+            listeners: Mutex::new(Vec::new()),
+            revision: 0,
+        }
+    }
+
+    /// Builder-style variant of `set_diagonal_cost`.
+    pub fn with_diagonal_cost(mut self, cost: f32) -> Self {
+        self.diagonal_cost = cost;
+        self
+    }
+
+    /// Sets the base cost of a diagonal step (scaled by the target cell's cost, like cardinals).
+    /// Reported as a change to every cell, see `costs_changed_everywhere`.
+    pub fn set_diagonal_cost(&mut self, cost: f32) {
+        if self.diagonal_cost != cost {
+            self.diagonal_cost = cost;
+            self.costs_changed_everywhere();
         }
     }
 
     pub fn set_blocked(&mut self, x: usize, y: usize, blocked: bool) {
         if x < self.width && y < self.height {
-            self.cells[y * self.width + x] = if blocked {
+            let cell = if blocked {
                 CellType::Blocked
             } else {
                 CellType::Passable(1.0)
             };
+            if self.set_cell_at(y * self.width + x, cell) {
+                let kind = if blocked { GridChangeKind::Blocked } else { GridChangeKind::Unblocked };
+                self.notify(GridChange::cell(kind, x, y));
+            }
         }
     }
 
     pub fn set_cost(&mut self, x: usize, y: usize, cost: f32) {
-        if x < self.width && y < self.height {
-            self.cells[y * self.width + x] = CellType::Passable(cost);
+        if x < self.width && y < self.height && self.set_cell_at(y * self.width + x, CellType::Passable(cost)) {
+            self.notify(GridChange::cell(GridChangeKind::CostChanged, x, y));
         }
     }
 
-    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
-        if x < 0 || y < 0 { return true; }
-        let ux = x as usize;
-        let uy = y as usize;
-        if ux >= self.width || uy >= self.height { return true; }
-        match self.cells[uy * self.width + ux] {
-            CellType::Blocked => true,
-            _ => false,
+    /// Multiplies the cost of entering (x, y) while moving in `direction` (wind, currents, slopes).
+    /// Stacks with the cell cost and any global bias. `Direction::None` is ignored.
+    pub fn set_directional_cost(&mut self, x: usize, y: usize, direction: Direction, multiplier: f32) {
+        let Some(d) = direction_index(direction) else { return };
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let len = self.cells.len();
+        let costs = self.directional_costs.get_or_insert_with(|| vec![[1.0; 8]; len]);
+        let idx = y * self.width + x;
+        if costs[idx][d] != multiplier {
+            costs[idx][d] = multiplier;
+            if let Some(changes) = self.changes.as_mut() {
+                changes.push(GridPos { x: x as i32, y: y as i32 });
+            }
+            self.notify(GridChange::cell(GridChangeKind::CostChanged, x, y));
         }
     }
-    
+
+    /// Grid-wide multiplier per movement direction, indexed N, NE, E, SE, S, SW, W, NW.
+    /// Reported as a change to every cell, like `set_diagonal_cost`.
+    pub fn set_directional_bias(&mut self, bias: [f32; 8]) {
+        if self.directional_bias != Some(bias) {
+            self.directional_bias = Some(bias);
+            self.costs_changed_everywhere();
+        }
+    }
+
+    /// Removes both the global bias and all per-cell directional costs. Reported as a change
+    /// to every cell, like `set_diagonal_cost`.
+    pub fn clear_directional_costs(&mut self) {
+        if self.directional_bias.is_some() || self.directional_costs.is_some() {
+            self.directional_bias = None;
+            self.directional_costs = None;
+            self.costs_changed_everywhere();
+        }
+    }
+
+    /// True if any movement direction is priced differently from the others.
+    pub fn has_directional_costs(&self) -> bool {
+        self.directional_bias.is_some_and(|b| b.iter().any(|&m| m != 1.0))
+            || self.directional_costs.as_ref().is_some_and(|c| c.iter().flatten().any(|&m| m != 1.0))
+    }
+
+    /// Smallest combined directional multiplier on the grid (1.0 without directional costs).
+    /// Scale heuristics by this to keep them admissible, see `heuristics::Scaled`.
+    pub fn min_directional_multiplier(&self) -> f32 {
+        let bias_min = self.directional_bias.map_or(1.0, |b| b.iter().copied().fold(f32::INFINITY, f32::min));
+        let cell_min = self
+            .directional_costs
+            .as_ref()
+            .map_or(1.0, |c| c.iter().flatten().copied().fold(f32::INFINITY, f32::min));
+        bias_min * cell_min
+    }
+
+    // Cost of stepping by (dx, dy) into `to`, which must be in bounds and passable
+    pub(crate) fn entry_cost(&self, to: &GridPos, dx: i32, dy: i32) -> f32 {
+        let base = if dx != 0 && dy != 0 { self.diagonal_cost } else { 1.0 };
+        self.get_cost(to.x, to.y) * base * self.directional_multiplier(to.x, to.y, dx, dy)
+    }
+
+    // Combined multiplier for stepping into (x, y) by (dx, dy); (x, y) must be in bounds
+    fn directional_multiplier(&self, x: i32, y: i32, dx: i32, dy: i32) -> f32 {
+        let d = step_index(dx, dy);
+        let mut m = self.directional_bias.map_or(1.0, |b| b[d]);
+        if let (Some(costs), Some(cell)) = (&self.directional_costs, self.index_of(GridPos { x, y })) {
+            m *= costs[cell.0][d];
+        }
+        m
+    }
+
+    /// Start recording modified cells so caches can invalidate precisely via `take_changes`.
+    pub fn enable_change_tracking(&mut self) {
+        if self.changes.is_none() {
+            self.changes = Some(Vec::new());
+        }
+    }
+
+    /// Drains the cells modified since the last call. Empty if tracking is disabled.
+    pub fn take_changes(&mut self) -> Vec<GridPos> {
+        match self.changes.as_mut() {
+            Some(changes) => std::mem::take(changes),
+            None => Vec::new(),
+        }
+    }
+
+    /// Number of deltas drained from or applied to this grid, plus grid-wide cost edits a
+    /// delta can't carry (`set_diagonal_cost`, `set_directional_bias`, `clear_directional_costs`).
+    /// Starts at 0.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Drains the tracked changes (see `enable_change_tracking`) into a delta from the current
+    /// revision to the next one. Replaces `take_changes` for grids that feed copies.
+    pub fn drain_changes_as_delta(&mut self) -> GridDelta {
+        let changes = self.take_changes();
+        let mut indices: Vec<u32> = changes.into_iter().filter_map(|p| self.index_of(p)).map(|cell| cell.0 as u32).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let mut delta = GridDelta { base_revision: self.revision, revision: self.revision + 1, ..GridDelta::default() };
+        for idx in indices {
+            match self.cells[idx as usize] {
+                CellType::Blocked => delta.blocked.push(idx),
+                CellType::Passable(cost) => delta.passable.push((idx, cost)),
+            }
+        }
+        self.revision = delta.revision;
+        delta
+    }
+
+    /// Applies `delta` if this grid is at its base revision, notifying listeners (and so
+    /// attached caches) of every changed cell. Nothing is applied on error, which is always
+    /// `PathforgeError::Delta`.
+    pub fn apply_delta(&mut self, delta: &GridDelta) -> Result<(), PathforgeError> {
+        if delta.base_revision != self.revision {
+            let mismatch = DeltaError::RevisionMismatch {
+                grid_revision: self.revision,
+                delta_base_revision: delta.base_revision,
+            };
+            return Err(mismatch.into());
+        }
+        let updates = delta
+            .blocked
+            .iter()
+            .map(|&idx| (idx, CellType::Blocked))
+            .chain(delta.passable.iter().map(|&(idx, cost)| (idx, CellType::Passable(cost))));
+        if let Some((idx, _)) = updates.clone().find(|&(idx, _)| idx as usize >= self.cells.len()) {
+            return Err(DeltaError::CellOutOfBounds(idx).into());
+        }
+        for (idx, cell) in updates {
+            let idx = idx as usize;
+            let was_blocked = self.cells[idx] == CellType::Blocked;
+            if self.set_cell_at(idx, cell) {
+                let kind = match (was_blocked, cell) {
+                    (_, CellType::Blocked) => GridChangeKind::Blocked,
+                    (true, _) => GridChangeKind::Unblocked,
+                    (false, _) => GridChangeKind::CostChanged,
+                };
+                let (x, y) = CellIndex(idx).coords(self.width);
+                self.notify(GridChange::cell(kind, x, y));
+            }
+        }
+        self.revision = delta.revision;
+        Ok(())
+    }
+
+    /// Registers a listener that receives a `GridChange` for every subsequent edit made by
+    /// `set_blocked`, `set_cost`, `set_directional_cost`, `set_directional_bias`,
+    /// `clear_directional_costs`, `set_diagonal_cost`, `set_region_blocked`, the cost painting
+    /// methods, `apply_delta` and `clear`.
+    /// Edits that leave the grid unchanged are not reported.
+    pub fn subscribe(&self) -> ChangeListener {
+        let (tx, rx) = mpsc::channel();
+        self.listeners.lock().unwrap().push(tx);
+        ChangeListener { rx: Mutex::new(rx) }
+    }
+
+    // For edits to step costs across the whole grid, which no `GridDelta` can carry: every cell
+    // is recorded as changed, listeners get one grid-wide `CostChanged`, and the revision moves
+    // on so copies fail their next delta (`RevisionMismatch`) and know to resync
+    fn costs_changed_everywhere(&mut self) {
+        let width = self.width;
+        if let Some(changes) = self.changes.as_mut() {
+            changes.extend((0..self.cells.len()).map(|idx| CellIndex(idx).to_pos(width)));
+        }
+        self.revision += 1;
+        self.notify(GridChange { kind: GridChangeKind::CostChanged, rect: (0, 0, self.width, self.height) });
+    }
+
+    fn notify(&self, change: GridChange) {
+        let mut listeners = self.listeners.lock().unwrap();
+        // Sending fails once the listener is dropped
+        listeners.retain(|tx| tx.send(change).is_ok());
+    }
+
+    // Returns true if the cell actually changed
+    fn set_cell_at(&mut self, idx: usize, cell: CellType) -> bool {
+        if self.cells[idx] == cell {
+            return false;
+        }
+        self.cells[idx] = cell;
+        let pos = self.pos_of(CellIndex(idx));
+        if let Some(changes) = self.changes.as_mut() {
+            changes.push(pos);
+        }
+        true
+    }
+
+    /// Whether `pos` lies on the grid.
+    #[inline]
+    pub fn contains(&self, pos: GridPos) -> bool {
+        self.index_of(pos).is_some()
+    }
+
+    /// Index of `pos` into `cells` (and the grid's other per-cell arrays); None off the grid.
+    #[inline]
+    pub fn index_of(&self, pos: GridPos) -> Option<CellIndex> {
+        CellIndex::of(pos, self.width, self.height)
+    }
+
+    /// The cell at `index`, which should come from `index_of` on a grid of this width.
+    #[inline]
+    pub fn pos_of(&self, index: CellIndex) -> GridPos {
+        index.to_pos(self.width)
+    }
+
+    /// True for blocked cells and anywhere off the grid.
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.index_of(GridPos { x, y }).is_none_or(|cell| self.cells[cell.0] == CellType::Blocked)
+    }
+
+    /// Cost multiplier of (x, y); `f32::INFINITY` if blocked or off the grid.
     pub fn get_cost(&self, x: i32, y: i32) -> f32 {
-        if x < 0 || y < 0 { return f32::INFINITY; }
-        let ux = x as usize;
-        let uy = y as usize;
-        if ux >= self.width || uy >= self.height { return f32::INFINITY; }
-        match self.cells[uy * self.width + ux] {
-            CellType::Passable(c) => c,
-            CellType::Blocked => f32::INFINITY,
+        match self.index_of(GridPos { x, y }).map(|cell| self.cells[cell.0]) {
+            Some(CellType::Passable(c)) => c,
+            Some(CellType::Blocked) | None => f32::INFINITY,
         }
     }
     
+    /// Sets every cell in `rect` = (x, y, width, height), reported to listeners as one change.
     pub fn set_region_blocked(&mut self, rect: (usize, usize, usize, usize), blocked: bool) {
-        let (rx, ry, rw, rh) = rect;
-        for y in ry..(ry + rh) {
-            for x in rx..(rx + rw) {
-                self.set_blocked(x, y, blocked);
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        let cell = if blocked { CellType::Blocked } else { CellType::Passable(1.0) };
+        let mut changed = false;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                changed |= self.set_cell_at(y * self.width + x, cell);
             }
         }
+        if changed {
+            let kind = if blocked { GridChangeKind::Blocked } else { GridChangeKind::Unblocked };
+            self.notify(GridChange { kind, rect: (x0, y0, x1 - x0, y1 - y0) });
+        }
     }
-    
-    pub fn clear(&mut self) {
-        for c in self.cells.iter_mut() {
-            *c = CellType::Passable(1.0);
+
+    /// Sets every painted cell's cost to its zone's multiplier. Blocked and unpainted cells
+    /// are left as they are; listeners get one change covering the affected cells. Fails
+    /// with `DimensionMismatch`, changing nothing, unless the map is the grid's size.
+    pub fn apply_terrain_zones(&mut self, zone_map: &TerrainZoneMap) -> Result<(), GridError> {
+        if zone_map.width != self.width || zone_map.height != self.height {
+            return Err(GridError::DimensionMismatch);
         }
+        self.paint_costs((0, 0, self.width, self.height), |x, y| zone_map.multiplier_at(x, y));
+        Ok(())
     }
 
-    /// Returns true if every passable cell uses the default uniform cost of 1.0.
-    /// JPS and other uniform-cost optimizations rely on this invariant.
-    pub fn is_uniform(&self) -> bool {
-        self.cells.iter().all(|c| match c {
-            CellType::Passable(cost) => (*cost - 1.0).abs() < f32::EPSILON,
-            CellType::Blocked => true,
-        })
+    /// Sets the cost of every passable cell whose center lies within `radius` of `center`
+    /// (slow zones such as mud or hazards). Blocked cells are left alone; listeners get one
+    /// change covering the affected cells.
+    pub fn paint_circle_cost(&mut self, center: GridPos, radius: f32, cost: f32) {
+        self.paint_gradient_circle(center, radius, radius, cost, cost);
     }
-}
 
-use crate::heuristics::Position;
-impl Position for GridPos {
-    fn x(&self) -> f32 { self.x as f32 }
-    fn y(&self) -> f32 { self.y as f32 }
-}
+    /// `paint_circle_cost` with `inner_cost` up to `inner_radius`, then a linear blend to
+    /// `outer_cost` at `outer_radius`.
+    pub fn paint_gradient_circle(
+        &mut self,
+        center: GridPos,
+        inner_radius: f32,
+        outer_radius: f32,
+        inner_cost: f32,
+        outer_cost: f32,
+    ) {
+        let (cx, cy) = (center.x as f32, center.y as f32);
+        let bounds = self.float_bounds((cx - outer_radius, cy - outer_radius), (cx + outer_radius, cy + outer_radius));
+        self.paint_costs(bounds, |x, y| {
+            let d = ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt();
+            if d > outer_radius {
+                None
+            } else if d <= inner_radius {
+                Some(inner_cost)
+            } else {
+                let t = (d - inner_radius) / (outer_radius - inner_radius);
+                Some(inner_cost + (outer_cost - inner_cost) * t)
+            }
+        });
+    }
 
-impl Graph for Grid2D {
-    type Node = GridPos;
+    /// Sets the cost of every passable cell whose center lies within `thickness / 2` of the
+    /// segment from `from` to `to` (roads, rivers). Blocked cells are left alone.
+    pub fn paint_line_cost(&mut self, from: GridPos, to: GridPos, thickness: f32, cost: f32) {
+        let half = thickness / 2.0;
+        let (ax, ay) = (from.x as f32, from.y as f32);
+        let (dx, dy) = (to.x as f32 - ax, to.y as f32 - ay);
+        let len2 = dx * dx + dy * dy;
+        let bounds = self.float_bounds((ax.min(ax + dx) - half, ay.min(ay + dy) - half), (ax.max(ax + dx) + half, ay.max(ay + dy) + half));
+        self.paint_costs(bounds, |x, y| {
+            let (px, py) = (x as f32 - ax, y as f32 - ay);
+            let t = if len2 > 0.0 { ((px * dx + py * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+            let d2 = (px - t * dx).powi(2) + (py - t * dy).powi(2);
+            (d2 <= half * half).then_some(cost)
+        });
+    }
 
-    fn is_passable(&self, node: &Self::Node) -> bool {
-        !self.is_blocked(node.x, node.y)
+    /// Value noise terrain: every passable cell gets a cost in `[min_cost, max_cost]`,
+    /// blended bilinearly between random values on a lattice `scale` cells apart. The same
+    /// `seed` always gives the same costs. Blocked cells are left alone. A `scale` that
+    /// isn't positive (including NaN) has no lattice to sample, and leaves the grid unchanged.
+    pub fn apply_noise_cost(&mut self, seed: u64, scale: f32, min_cost: f32, max_cost: f32) {
+        if scale.is_nan() || scale <= 0.0 {
+            return;
+        }
+        let value = |i: i64, j: i64| SplitMix64(lattice_seed(seed, i, j)).next_f32();
+        self.paint_costs((0, 0, self.width, self.height), |x, y| {
+            let (fx, fy) = (x as f32 / scale, y as f32 / scale);
+            let (i, j) = (fx.floor() as i64, fy.floor() as i64);
+            let (tx, ty) = (fx - fx.floor(), fy - fy.floor());
+            let top = value(i, j) + (value(i + 1, j) - value(i, j)) * tx;
+            let bottom = value(i, j + 1) + (value(i + 1, j + 1) - value(i, j + 1)) * tx;
+            Some(min_cost + (max_cost - min_cost) * (top + (bottom - top) * ty))
+        });
     }
 
-    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    /// Gradient (Perlin-style) noise terrain: smoother and less blocky than
+    /// `apply_noise_cost`. `frequency` is lattice cells per grid cell, so 0.1 gives features
+    /// about 10 cells across. Costs span `[min_cost, max_cost]`; blocked cells are left alone.
+    /// A non-finite `frequency` leaves the grid unchanged, as for `apply_noise_cost`.
+    pub fn apply_perlin_cost_approx(&mut self, seed: u64, frequency: f32, min_cost: f32, max_cost: f32) {
+        if !frequency.is_finite() {
+            return;
+        }
+        // Random unit gradient at a lattice point, dotted with the offset from it
+        let ramp = |i: i64, j: i64, dx: f32, dy: f32| {
+            let angle = SplitMix64(lattice_seed(seed, i, j)).next_f32() * std::f32::consts::TAU;
+            angle.cos() * dx + angle.sin() * dy
+        };
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        self.paint_costs((0, 0, self.width, self.height), |x, y| {
+            // Sample cell centers half a cell in, so low frequencies don't sit on the lattice
+            let (fx, fy) = ((x as f32 + 0.5) * frequency, (y as f32 + 0.5) * frequency);
+            let (i, j) = (fx.floor() as i64, fy.floor() as i64);
+            let (dx, dy) = (fx - fx.floor(), fy - fy.floor());
+            let (u, v) = (fade(dx), fade(dy));
+            let top = ramp(i, j, dx, dy) + (ramp(i + 1, j, dx - 1.0, dy) - ramp(i, j, dx, dy)) * u;
+            let bottom = ramp(i, j + 1, dx, dy - 1.0) + (ramp(i + 1, j + 1, dx - 1.0, dy - 1.0) - ramp(i, j + 1, dx, dy - 1.0)) * u;
+            // 2D gradient noise stays within +-sqrt(1/2)
+            let noise = ((top + (bottom - top) * v) * std::f32::consts::SQRT_2).clamp(-1.0, 1.0);
+            Some(min_cost + (max_cost - min_cost) * (noise + 1.0) / 2.0)
+        });
+    }
+
+    // Sets passable cells in `bounds` = (x0, y0, x1, y1), exclusive end, to `cost_at`'s
+    // value; None leaves a cell alone. One listener change covers the cells that changed.
+    fn paint_costs<F>(&mut self, bounds: (usize, usize, usize, usize), cost_at: F)
     where
-        F: FnMut(Self::Node, f32),
+        F: Fn(usize, usize) -> Option<f32>,
     {
-        // Cardinals
-        let dirs = [(0, 1), (1, 0), (0, -1), (-1, 0)];
-        for (dx, dy) in dirs.iter() {
-            let nx = node.x + dx;
-            let ny = node.y + dy;
-            if !self.is_blocked(nx, ny) {
-                visit(GridPos { x: nx, y: ny }, self.get_cost(nx, ny));
+        let (bx0, by0, bx1, by1) = bounds;
+        // Bounding box of the changed cells
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for y in by0..by1 {
+            for x in bx0..bx1 {
+                let idx = y * self.width + x;
+                let Some(cost) = cost_at(x, y) else { continue };
+                if self.cells[idx] != CellType::Blocked && self.set_cell_at(idx, CellType::Passable(cost)) {
+                    (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1));
+                }
             }
         }
-        
-        // Diagonals
-        if self.diagonal_movement != DiagonalMode::Never {
-            let diag_dirs = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
-            let diag_cost_mult = std::f32::consts::SQRT_2; // Standard 1.414
-            
-            for (dx, dy) in diag_dirs.iter() {
-                let nx = node.x + dx;
-                let ny = node.y + dy;
-                
-                if !self.is_blocked(nx, ny) {
-                    let cost = self.get_cost(nx, ny) * diag_cost_mult;
-                    
-                    // Check diagonal rules
-                    let c1_blocked = self.is_blocked(node.x + dx, node.y);
-                    let c2_blocked = self.is_blocked(node.x, node.y + dy);
-                    
-                    let allowed = match self.diagonal_movement {
-                        DiagonalMode::Never => false,
-                        DiagonalMode::Always => true,
-                        DiagonalMode::IfNoObstacle => !c1_blocked || !c2_blocked,
-                        DiagonalMode::OnlyIfBothOpen => !c1_blocked && !c2_blocked,
-                    };
-                    
-                    if allowed {
-                        visit(GridPos { x: nx, y: ny }, cost);
-                    }
+        if x1 > 0 {
+            self.notify(GridChange { kind: GridChangeKind::CostChanged, rect: (x0, y0, x1 - x0, y1 - y0) });
+        }
+    }
+
+    // Cells whose centers may lie in the box from `min` to `max`, clipped to the grid
+    fn float_bounds(&self, min: (f32, f32), max: (f32, f32)) -> (usize, usize, usize, usize) {
+        let clip = |v: f32, limit: usize| (v.max(0.0) as usize).min(limit);
+        (
+            clip(min.0.ceil(), self.width),
+            clip(min.1.ceil(), self.height),
+            clip(max.0.floor() + 1.0, self.width),
+            clip(max.1.floor() + 1.0, self.height),
+        )
+    }
+
+    pub fn clear(&mut self) {
+        let mut changed = false;
+        for idx in 0..self.cells.len() {
+            changed |= self.set_cell_at(idx, CellType::Passable(1.0));
+        }
+        if changed {
+            self.notify(GridChange {
+                kind: GridChangeKind::Cleared,
+                rect: (0, 0, self.width, self.height),
+            });
+        }
+    }
+
+    /// Number of passable cells in `rect` = (x, y, width, height), clipped to the grid.
+    pub fn region_passable_count(&self, rect: (usize, usize, usize, usize)) -> usize {
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        (y0..y1)
+            .map(|y| {
+                self.cells[y * self.width + x0..y * self.width + x1]
+                    .iter()
+                    .filter(|c| matches!(c, CellType::Passable(_)))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Passable share of the (clipped) region in [0, 1]. 0.0 for an empty region.
+    pub fn region_passable_fraction(&self, rect: (usize, usize, usize, usize)) -> f32 {
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        let area = (x1 - x0) * (y1 - y0);
+        if area == 0 {
+            return 0.0;
+        }
+        self.region_passable_count(rect) as f32 / area as f32
+    }
+
+    /// Min/max/mean movement cost over the passable cells in the region.
+    pub fn region_cost_stats(&self, rect: (usize, usize, usize, usize)) -> CostStats {
+        let (x0, y0, x1, y1) = clip_rect(rect, self.width, self.height);
+        let mut stats = CostStats { min: f32::INFINITY, max: f32::NEG_INFINITY, mean: 0.0, count: 0 };
+        let mut sum = 0.0;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if let CellType::Passable(c) = self.cells[y * self.width + x] {
+                    stats.min = stats.min.min(c);
+                    stats.max = stats.max.max(c);
+                    sum += c;
+                    stats.count += 1;
                 }
             }
         }
+        if stats.count == 0 {
+            return CostStats { min: 0.0, max: 0.0, mean: 0.0, count: 0 };
+        }
+        stats.mean = sum / stats.count as f32;
+        stats
     }
 
-    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
-        let x0 = from.x;
-        let y0 = from.y;
-        let x1 = to.x;
-        let y1 = to.y;
-        
-        let dx = (x1 - x0).abs();
-        let dy = (y1 - y0).abs();
-        let sx = if x0 < x1 { 1 } else { -1 };
-        let sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx - dy;
-        
-        let mut x = x0;
-        let mut y = y0;
-        
-        while x != x1 || y != y1 {
-            if self.is_blocked(x, y) { return false; }
-            let e2 = 2 * err;
-            if e2 > -dy {
-                err -= dy;
-                x += sx;
+    /// Precomputes passable counts in O(width * height) for O(1) region queries.
+    pub fn build_prefix_sums(&self) -> GridPrefixSums {
+        let stride = self.width + 1;
+        let mut sums = vec![0u32; stride * (self.height + 1)];
+        for y in 0..self.height {
+            let mut row = 0;
+            for x in 0..self.width {
+                if let CellType::Passable(_) = self.cells[y * self.width + x] {
+                    row += 1;
+                }
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
             }
-            if e2 < dx {
-                err += dx;
-                y += sy;
+        }
+        GridPrefixSums { width: self.width, height: self.height, sums }
+    }
+
+    /// Builds the obstacle distance field; rebuild after the grid changes.
+    pub fn clearance_field(&self) -> ClearanceField {
+        use std::f32::consts::SQRT_2;
+        let (w, h) = (self.width, self.height);
+        // Center-to-center distance to the nearest blocked cell, seeded with the border
+        let mut dist: Vec<f32> = (0..w * h)
+            .map(|idx| match self.cells[idx] {
+                CellType::Blocked => 0.0,
+                CellType::Passable(_) => {
+                    let (x, y) = (idx % w, idx / w);
+                    (x + 1).min(y + 1).min(w - x).min(h - y) as f32
+                }
+            })
+            .collect();
+
+        // Two raster passes propagate distances from the already-visited half of the neighbors
+        let relax = |dist: &mut Vec<f32>, x: usize, y: usize, nx: i32, ny: i32, step: f32| {
+            if nx >= 0 && ny >= 0 && (nx as usize) < w && (ny as usize) < h {
+                let candidate = dist[ny as usize * w + nx as usize] + step;
+                let d = &mut dist[y * w + x];
+                *d = d.min(candidate);
+            }
+        };
+        for y in 0..h {
+            for x in 0..w {
+                let (xi, yi) = (x as i32, y as i32);
+                relax(&mut dist, x, y, xi - 1, yi, 1.0);
+                relax(&mut dist, x, y, xi, yi - 1, 1.0);
+                relax(&mut dist, x, y, xi - 1, yi - 1, SQRT_2);
+                relax(&mut dist, x, y, xi + 1, yi - 1, SQRT_2);
             }
         }
-        // Check destination
-        if self.is_blocked(x, y) { return false; }
-        
-        true
+        for y in (0..h).rev() {
+            for x in (0..w).rev() {
+                let (xi, yi) = (x as i32, y as i32);
+                relax(&mut dist, x, y, xi + 1, yi, 1.0);
+                relax(&mut dist, x, y, xi, yi + 1, 1.0);
+                relax(&mut dist, x, y, xi + 1, yi + 1, SQRT_2);
+                relax(&mut dist, x, y, xi - 1, yi + 1, SQRT_2);
+            }
+        }
+
+        let values = dist.into_iter().map(|d| (d - 0.5).max(0.0)).collect();
+        ClearanceField { width: w, height: h, values }
+    }
+
+    /// Cells visible from `source` within `max_range` (Euclidean, center to center), as a
+    /// row-major mask; read it with `sample_visibility`. Walls bounding the view are visible.
+    ///
+    /// Uses symmetric shadowcasting over four quadrants, O(r^2): `a` sees `b` exactly when
+    /// `b` sees `a`, and light doesn't leak through diagonal gaps between walls. It can
+    /// disagree with Bresenham `can_traverse` on a few cells at grazing angles.
+    pub fn compute_visibility_map(&self, source: GridPos, max_range: f32) -> Vec<bool> {
+        let mut visible = vec![false; self.cells.len()];
+        let Some(source_cell) = self.index_of(source) else {
+            return visible;
+        };
+        visible[source_cell.0] = true;
+        let max_depth = max_range.max(0.0).floor() as i64;
+        let range_sq = max_range * max_range;
+
+        // Row `depth` of a quadrant spans columns between two slopes, kept as exact fractions
+        // (numerator, denominator) so edge cases don't depend on float rounding
+        struct Row {
+            depth: i64,
+            start: (i64, i64),
+            end: (i64, i64),
+        }
+        // Quadrant-local (depth, col) to grid position: north, east, south, west
+        let to_grid = |quadrant: usize, depth: i64, col: i64| -> (i64, i64) {
+            let (x, y) = (source.x as i64, source.y as i64);
+            match quadrant {
+                0 => (x + col, y - depth),
+                1 => (x + depth, y + col),
+                2 => (x + col, y + depth),
+                _ => (x - depth, y + col),
+            }
+        };
+        let wall = |(x, y): (i64, i64)| self.is_blocked(x as i32, y as i32); // Outside counts as wall
+
+        for quadrant in 0..4 {
+            let mut rows = vec![Row { depth: 1, start: (-1, 1), end: (1, 1) }];
+            while let Some(mut row) = rows.pop() {
+                if row.depth > max_depth {
+                    continue;
+                }
+                // Columns whose centers fall within [start, end], rounding ties outward
+                let d = row.depth;
+                let min_col = (2 * d * row.start.0 + row.start.1).div_euclid(2 * row.start.1);
+                let max_col = -(row.end.1 - 2 * d * row.end.0).div_euclid(2 * row.end.1);
+                let mut prev_wall = None;
+                for col in min_col..=max_col {
+                    let (x, y) = to_grid(quadrant, d, col);
+                    let is_wall = wall((x, y));
+                    let symmetric = col * row.start.1 >= d * row.start.0 && col * row.end.1 <= d * row.end.0;
+                    let (dx, dy) = ((x - source.x as i64) as f32, (y - source.y as i64) as f32);
+                    if (is_wall || symmetric) && !self.is_outside(x, y) && dx * dx + dy * dy <= range_sq {
+                        visible[y as usize * self.width + x as usize] = true;
+                    }
+                    // Left edge of a wall: the view continues past it from this column's slope
+                    let slope = (2 * col - 1, 2 * d);
+                    if prev_wall == Some(true) && !is_wall {
+                        row.start = slope;
+                    }
+                    if prev_wall == Some(false) && is_wall {
+                        rows.push(Row { depth: d + 1, start: row.start, end: slope });
+                    }
+                    prev_wall = Some(is_wall);
+                }
+                if prev_wall == Some(false) {
+                    rows.push(Row { depth: d + 1, start: row.start, end: row.end });
+                }
+            }
+        }
+        visible
+    }
+
+    /// Looks up `pos` in a mask from `compute_visibility_map`; false outside the grid.
+    pub fn sample_visibility(&self, vis: &[bool], pos: GridPos) -> bool {
+        self.index_of(pos).and_then(|cell| vis.get(cell.0)).copied().unwrap_or(false)
+    }
+
+    fn is_outside(&self, x: i64, y: i64) -> bool {
+        x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64
+    }
+
+    /// Copy of the grid with every passable cell within `radius` of a blocked cell or the grid
+    /// border also blocked, i.e. the cells an agent of that radius can stand on. Distance is
+    /// Chebyshev (a square of side `2 * radius + 1`); surviving cells keep their costs.
+    ///
+    /// Separable erosion: a horizontal pass over rows, then a vertical pass, each parallel by row.
+    pub fn erode_passable(&self, radius: usize) -> Grid2D {
+        let (w, h) = (self.width, self.height);
+        let r = radius as isize;
+
+        // Horizontal: near a blocked cell within the row, counting both ends of the row
+        let mut near_h = vec![false; w * h];
+        near_h.par_chunks_mut(w.max(1)).enumerate().for_each(|(y, row)| {
+            let cells = &self.cells[y * w..(y + 1) * w];
+            let mut last: isize = -1;
+            for (x, near) in row.iter_mut().enumerate() {
+                if matches!(cells[x], CellType::Blocked) {
+                    last = x as isize;
+                }
+                *near = x as isize - last <= r;
+            }
+            let mut next = w as isize;
+            for x in (0..w).rev() {
+                if matches!(cells[x], CellType::Blocked) {
+                    next = x as isize;
+                }
+                row[x] |= next - x as isize <= r;
+            }
+        });
+
+        // Vertical: OR of the horizontal result over rows y - r..=y + r; rows past the top or
+        // bottom edge count as blocked
+        let mut eroded = vec![false; w * h];
+        eroded.par_chunks_mut(w.max(1)).enumerate().for_each(|(y, row)| {
+            if y < radius || y + radius >= h {
+                row.fill(true);
+                return;
+            }
+            for src in y - radius..=y + radius {
+                for (out, near) in row.iter_mut().zip(&near_h[src * w..(src + 1) * w]) {
+                    *out |= *near;
+                }
+            }
+        });
+
+        let mut grid = Grid2D::new(w, h, self.diagonal_movement);
+        grid.diagonal_cost = self.diagonal_cost;
+        grid.directional_bias = self.directional_bias;
+        grid.directional_costs = self.directional_costs.clone();
+        grid.cells = self
+            .cells
+            .iter()
+            .zip(&eroded)
+            .map(|(cell, &blocked)| if blocked { CellType::Blocked } else { *cell })
+            .collect();
+        grid
+    }
+
+    /// Labels every passable region reachable through `neighbors` (respects the diagonal mode).
+    pub fn connected_components(&self) -> ConnectedComponents {
+        let mut labels = vec![None; self.cells.len()];
+        let mut sizes = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for start in 0..self.cells.len() {
+            if labels[start].is_some() || matches!(self.cells[start], CellType::Blocked) {
+                continue;
+            }
+            let id = sizes.len() as u32;
+            let mut size = 0;
+            labels[start] = Some(id);
+            queue.push_back(self.pos_of(CellIndex(start)));
+            while let Some(pos) = queue.pop_front() {
+                size += 1;
+                self.neighbors(&pos, |n, _| {
+                    let Some(CellIndex(idx)) = self.index_of(n) else { return };
+                    if labels[idx].is_none() {
+                        labels[idx] = Some(id);
+                        queue.push_back(n);
+                    }
+                });
+            }
+            sizes.push(size);
+        }
+
+        ConnectedComponents { count: sizes.len(), labels, sizes }
+    }
+
+    /// Generates a room-and-corridor map with a single connected passable region.
+    /// See `generate_room_layout`.
+    pub fn generate_rooms(
+        width: usize,
+        height: usize,
+        min_room_size: usize,
+        max_room_size: usize,
+        seed: u64,
+        diagonal: DiagonalMode,
+    ) -> Grid2D {
+        Self::generate_room_layout(width, height, min_room_size, max_room_size, seed, diagonal).0
+    }
+
+    /// BSP room generator. The map is split recursively until every partition is at most
+    /// `max_room_size` on a side, then each partition gets one room inset by a 1-cell wall.
+    /// Sibling partitions are joined by 1-wide L-shaped corridors. Returns the grid and the
+    /// room rectangles as (x, y, width, height). Deterministic for a given `seed`.
+    ///
+    /// `max_room_size` is raised to `2 * (min_room_size + 2)` if needed so partitions can split.
+    pub fn generate_room_layout(
+        width: usize,
+        height: usize,
+        min_room_size: usize,
+        max_room_size: usize,
+        seed: u64,
+        diagonal: DiagonalMode,
+    ) -> (Grid2D, Vec<(usize, usize, usize, usize)>) {
+        let mut grid = Grid2D::new(width, height, diagonal);
+        grid.cells.fill(CellType::Blocked);
+
+        let min_room = min_room_size.max(1);
+        let min_leaf = min_room + 2;
+        let bsp = RoomBsp {
+            min_room,
+            min_leaf,
+            max_leaf: max_room_size.max(2 * min_leaf),
+        };
+        let mut rng = SplitMix64(seed);
+        let mut rooms = Vec::new();
+        let mut links = Vec::new();
+        bsp.split((0, 0, width, height), &mut rng, &mut rooms, &mut links);
+
+        for &(rx, ry, rw, rh) in &rooms {
+            for y in ry..ry + rh {
+                for x in rx..rx + rw {
+                    grid.cells[y * width + x] = CellType::Passable(1.0);
+                }
+            }
+        }
+        for &(a, b) in &links {
+            let horizontal_first = rng.next_u64() & 1 == 0;
+            grid.carve_corridor(room_center(rooms[a]), room_center(rooms[b]), horizontal_first);
+        }
+
+        // The BSP links form a tree, but re-check and join any room left isolated
+        loop {
+            let components = grid.connected_components();
+            if components.count <= 1 {
+                break;
+            }
+            let label = |r: (usize, usize, usize, usize)| {
+                let (cx, cy) = room_center(r);
+                components.labels[cy * width + cx]
+            };
+            let main = label(rooms[0]);
+            match rooms.iter().position(|&r| label(r) != main) {
+                Some(i) => grid.carve_corridor(room_center(rooms[i]), room_center(rooms[0]), true),
+                None => break,
+            }
+        }
+
+        // Open up lone wall cells so every wall touches another wall or the border
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let (xi, yi) = (x as i32, y as i32);
+                if grid.is_blocked(xi, yi)
+                    && !grid.is_blocked(xi - 1, yi)
+                    && !grid.is_blocked(xi + 1, yi)
+                    && !grid.is_blocked(xi, yi - 1)
+                    && !grid.is_blocked(xi, yi + 1)
+                {
+                    grid.cells[y * width + x] = CellType::Passable(1.0);
+                }
+            }
+        }
+
+        (grid, rooms)
+    }
+
+    /// Grid with a generated obstacle layout, for systematic benchmarks and tests.
+    /// Deterministic for a given pattern (including its seed). Panics if a spacing or cell
+    /// size is zero.
+    pub fn from_pattern(width: usize, height: usize, pattern: GridPattern, diagonal: DiagonalMode) -> Grid2D {
+        let mut grid = Grid2D::new(width, height, diagonal);
+        let blocked: Box<dyn Fn(usize, usize) -> bool> = match pattern {
+            GridPattern::Empty => return grid,
+            GridPattern::Maze(seed) => {
+                grid.carve_maze(seed);
+                return grid;
+            }
+            GridPattern::VerticalWalls { spacing, gap_height } => {
+                assert!(spacing > 0, "wall spacing must be positive");
+                let wall_height = height.saturating_sub(gap_height);
+                Box::new(move |x, y| x > 0 && x % spacing == 0 && y < wall_height)
+            }
+            GridPattern::HorizontalWalls { spacing, gap_width } => {
+                assert!(spacing > 0, "wall spacing must be positive");
+                let wall_width = width.saturating_sub(gap_width);
+                Box::new(move |x, y| y > 0 && y % spacing == 0 && x < wall_width)
+            }
+            GridPattern::Checkerboard { cell_size } => {
+                assert!(cell_size > 0, "checkerboard cell size must be positive");
+                Box::new(move |x, y| (x / cell_size + y / cell_size) % 2 == 1)
+            }
+            GridPattern::Random { seed, density } => {
+                let mut rng = SplitMix64(seed);
+                let cells: Vec<bool> = (0..width * height).map(|_| rng.next_f32() < density).collect();
+                Box::new(move |x, y| cells[y * width + x])
+            }
+            GridPattern::Concentric { ring_spacing } => {
+                assert!(ring_spacing > 0, "ring spacing must be positive");
+                let (cx, cy) = ((width / 2) as i64, (height / 2) as i64);
+                Box::new(move |x, y| {
+                    let (dx, dy) = (x as i64 - cx, y as i64 - cy);
+                    let r = dx.abs().max(dy.abs());
+                    if r == 0 || r % ring_spacing as i64 != 0 {
+                        return false;
+                    }
+                    // Doors alternate between the east and west sides
+                    let door = if (r / ring_spacing as i64) % 2 == 0 { r } else { -r };
+                    (dx, dy) != (door, 0)
+                })
+            }
+        };
+        for y in 0..height {
+            for x in 0..width {
+                if blocked(x, y) {
+                    grid.cells[y * width + x] = CellType::Blocked;
+                }
+            }
+        }
+        grid
+    }
+
+    // Perfect maze by randomized depth-first search: rooms on odd coordinates, walls between
+    // them and around the edge (no change tracking: used during generation)
+    fn carve_maze(&mut self, seed: u64) {
+        self.cells.fill(CellType::Blocked);
+        let (cols, rows) = (self.width.saturating_sub(1) / 2, self.height.saturating_sub(1) / 2);
+        if cols == 0 || rows == 0 {
+            return;
+        }
+        let mut rng = SplitMix64(seed);
+        let mut visited = vec![false; cols * rows];
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        self.cells[self.width + 1] = CellType::Passable(1.0);
+        while let Some(&(c, r)) = stack.last() {
+            let mut options = [(0, 0); 4];
+            let mut count = 0;
+            for (dc, dr) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (nc, nr) = (c as i64 + dc, r as i64 + dr);
+                if nc < 0 || nr < 0 || nc as usize >= cols || nr as usize >= rows {
+                    continue;
+                }
+                let (nc, nr) = (nc as usize, nr as usize);
+                if !visited[nr * cols + nc] {
+                    options[count] = (nc, nr);
+                    count += 1;
+                }
+            }
+            if count == 0 {
+                stack.pop();
+                continue;
+            }
+            let (nc, nr) = options[rng.range(0, count - 1)];
+            visited[nr * cols + nc] = true;
+            // The room and the wall between it and the current one
+            self.cells[(2 * nr + 1) * self.width + 2 * nc + 1] = CellType::Passable(1.0);
+            self.cells[(r + nr + 1) * self.width + c + nc + 1] = CellType::Passable(1.0);
+            stack.push((nc, nr));
+        }
+    }
+
+    // Carves a 1-wide L between two cells (no change tracking: used during generation)
+    fn carve_corridor(&mut self, from: (usize, usize), to: (usize, usize), horizontal_first: bool) {
+        let corner = if horizontal_first { (to.0, from.1) } else { (from.0, to.1) };
+        for (a, b) in [(from, corner), (corner, to)] {
+            for y in a.1.min(b.1)..=a.1.max(b.1) {
+                for x in a.0.min(b.0)..=a.0.max(b.0) {
+                    self.cells[y * self.width + x] = CellType::Passable(1.0);
+                }
+            }
+        }
+    }
+
+    /// The neighbors `Graph::neighbors` visits, with the same costs and order, as an iterator.
+    pub fn neighbors_iter(&self, pos: GridPos) -> impl Iterator<Item = (GridPos, f32)> + '_ {
+        self.neighbors_where(pos, |x, y| self.is_blocked(x, y))
+    }
+
+    /// Number of cells reachable in one step from `pos` (respects the diagonal mode).
+    pub fn passable_neighbors_count(&self, pos: GridPos) -> usize {
+        self.neighbors_iter(pos).count()
+    }
+
+    /// Every passable cell (row-major) with its `neighbors_iter` edges, for exporting the
+    /// graph to other libraries.
+    pub fn to_adjacency_list(&self) -> Vec<(GridPos, Vec<(GridPos, f32)>)> {
+        self.passable_cells().map(|pos| (pos, self.neighbors_iter(pos).collect())).collect()
+    }
+
+    /// Every directed edge as (from, to, cost); see `edges` to avoid collecting.
+    pub fn to_edge_list(&self) -> Vec<(GridPos, GridPos, f32)> {
+        self.edges().collect()
+    }
+
+    /// Every directed edge as (from, to, cost), in `to_adjacency_list` order.
+    pub fn edges(&self) -> impl Iterator<Item = (GridPos, GridPos, f32)> + '_ {
+        self.passable_cells().flat_map(move |pos| self.neighbors_iter(pos).map(move |(to, cost)| (pos, to, cost)))
+    }
+
+    pub(crate) fn passable_cells(&self) -> impl Iterator<Item = GridPos> + '_ {
+        (0..self.cells.len())
+            .filter(|&idx| self.cells[idx] != CellType::Blocked)
+            .map(|idx| self.pos_of(CellIndex(idx)))
+    }
+
+    /// View of this grid with `blocked_extra` also blocked; see `MaskedGrid2D`.
+    pub fn masked<'a>(&'a self, blocked_extra: &'a HashSet<GridPos>) -> MaskedGrid2D<'a> {
+        MaskedGrid2D {
+            base: self,
+            blocked_extra,
+        }
+    }
+
+    /// Returns true if every passable cell uses the default uniform cost of 1.0.
+    /// JPS and other uniform-cost optimizations rely on this invariant.
+    pub fn is_uniform(&self) -> bool {
+        self.cells.iter().all(|c| match c {
+            CellType::Passable(cost) => (*cost - 1.0).abs() < f32::EPSILON,
+            CellType::Blocked => true,
+        })
+    }
+}
+
+struct RoomBsp {
+    min_room: usize,
+    min_leaf: usize, // Smallest partition that still fits a room plus its walls
+    max_leaf: usize,
+}
+
+impl RoomBsp {
+    // Returns the indices of the rooms placed inside `rect`
+    fn split(
+        &self,
+        rect: (usize, usize, usize, usize),
+        rng: &mut SplitMix64,
+        rooms: &mut Vec<(usize, usize, usize, usize)>,
+        links: &mut Vec<(usize, usize)>,
+    ) -> Vec<usize> {
+        let (x, y, w, h) = rect;
+        if w > self.max_leaf || h > self.max_leaf {
+            let (first, second) = if w >= h {
+                let cut = rng.range(self.min_leaf, w - self.min_leaf);
+                ((x, y, cut, h), (x + cut, y, w - cut, h))
+            } else {
+                let cut = rng.range(self.min_leaf, h - self.min_leaf);
+                ((x, y, w, cut), (x, y + cut, w, h - cut))
+            };
+            let mut left = self.split(first, rng, rooms, links);
+            let right = self.split(second, rng, rooms, links);
+
+            // Join the closest pair of rooms across the cut
+            let dist = |&(a, b): &(usize, usize)| {
+                let (ca, cb) = (room_center(rooms[a]), room_center(rooms[b]));
+                ca.0.abs_diff(cb.0) + ca.1.abs_diff(cb.1)
+            };
+            if let Some(link) = left
+                .iter()
+                .flat_map(|&a| right.iter().map(move |&b| (a, b)))
+                .min_by_key(dist)
+            {
+                links.push(link);
+            }
+            left.extend(right);
+            return left;
+        }
+
+        if w < self.min_leaf || h < self.min_leaf {
+            return Vec::new();
+        }
+        let rw = rng.range(self.min_room, w - 2);
+        let rh = rng.range(self.min_room, h - 2);
+        let rx = x + 1 + rng.range(0, w - 2 - rw);
+        let ry = y + 1 + rng.range(0, h - 2 - rh);
+        rooms.push((rx, ry, rw, rh));
+        vec![rooms.len() - 1]
+    }
+}
+
+// Seed for the noise lattice point (i, j), mixed so neighbouring points are unrelated
+fn lattice_seed(seed: u64, i: i64, j: i64) -> u64 {
+    seed ^ (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (j as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+}
+
+// Index into directional tables: N, NE, E, SE, S, SW, W, NW (N is -y, matching `Direction`)
+fn direction_index(direction: Direction) -> Option<usize> {
+    match direction {
+        Direction::None => None,
+        Direction::N => Some(0),
+        Direction::NE => Some(1),
+        Direction::E => Some(2),
+        Direction::SE => Some(3),
+        Direction::S => Some(4),
+        Direction::SW => Some(5),
+        Direction::W => Some(6),
+        Direction::NW => Some(7),
+    }
+}
+
+fn step_index(dx: i32, dy: i32) -> usize {
+    match (dx.signum(), dy.signum()) {
+        (0, -1) => 0,
+        (1, -1) => 1,
+        (1, 0) => 2,
+        (1, 1) => 3,
+        (0, 1) => 4,
+        (-1, 1) => 5,
+        (-1, 0) => 6,
+        _ => 7,
+    }
+}
+
+fn room_center(room: (usize, usize, usize, usize)) -> (usize, usize) {
+    (room.0 + room.2 / 2, room.1 + room.3 / 2)
+}
+
+use crate::heuristics::Position;
+impl Position for GridPos {
+    fn x(&self) -> f32 { self.x as f32 }
+    fn y(&self) -> f32 { self.y as f32 }
+}
+
+// Shared by `Grid2D` and `MaskedGrid2D`, which differ only in which cells are blocked
+// Whether `mode` allows the step from `node` by (dx, dy), each -1, 0 or 1 and not both 0:
+// the target isn't `blocked`, and a diagonal also passes the corner rule for its side cells
+fn step_allowed<B>(node: GridPos, dx: i32, dy: i32, mode: DiagonalMode, blocked: &B) -> bool
+where
+    B: Fn(i32, i32) -> bool,
+{
+    if blocked(node.x + dx, node.y + dy) {
+        return false;
+    }
+    dx == 0 || dy == 0 || mode.allows_diagonal(blocked(node.x + dx, node.y), blocked(node.x, node.y + dy))
+}
+
+// Steps out of `node` that `mode` allows, cardinals first, then diagonals, each priced by
+// `cost(next, dx, dy)`. Cells for which `blocked` is true are never entered. The `neighbors`
+// of `Grid2D`, `RleGrid` and `ChunkedGrid2D` all come from here, so they can't drift apart.
+pub(crate) fn grid_steps<B, C>(
+    node: GridPos,
+    mode: DiagonalMode,
+    blocked: B,
+    cost: C,
+) -> impl Iterator<Item = (GridPos, f32)>
+where
+    B: Fn(i32, i32) -> bool,
+    C: Fn(GridPos, i32, i32) -> f32,
+{
+    const STEPS: [(i32, i32); 8] = [(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+    let count = if mode == DiagonalMode::Never { 4 } else { 8 };
+    STEPS[..count].iter().filter_map(move |&(dx, dy)| {
+        let next = GridPos { x: node.x + dx, y: node.y + dy };
+        step_allowed(node, dx, dy, mode, &blocked).then(|| (next, cost(next, dx, dy)))
+    })
+}
+
+impl Grid2D {
+    // Steps out of `node` that `diagonal_movement` allows, with entry costs; cells for which
+    // `blocked` is true are never entered. Cardinals come first, then diagonals.
+    pub(crate) fn neighbors_where<'a, B>(
+        &'a self,
+        node: GridPos,
+        blocked: B,
+    ) -> impl Iterator<Item = (GridPos, f32)> + 'a
+    where
+        B: Fn(i32, i32) -> bool + 'a,
+    {
+        grid_steps(node, self.diagonal_movement, blocked, move |next, dx, dy| self.entry_cost(&next, dx, dy))
+    }
+
+    // Cost of the single step `from` -> `to` under the same rules as `neighbors_where`;
+    // None if the cells aren't neighbors or the move isn't allowed
+    fn step_cost(&self, from: GridPos, to: GridPos) -> Option<f32> {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let blocked = |x, y| self.is_blocked(x, y);
+        if dx.abs() > 1 || dy.abs() > 1 || (dx == 0 && dy == 0) {
+            return None;
+        }
+        if !step_allowed(from, dx, dy, self.diagonal_movement, &blocked) {
+            return None;
+        }
+        Some(self.entry_cost(&to, dx, dy))
+    }
+
+    // Bresenham line of sight
+    pub(crate) fn line_clear<B>(from: &GridPos, to: &GridPos, blocked: B) -> bool
+    where
+        B: Fn(i32, i32) -> bool,
+    {
+        let x0 = from.x;
+        let y0 = from.y;
+        let x1 = to.x;
+        let y1 = to.y;
+        
+        let dx = (x1 - x0).abs();
+        let dy = (y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx - dy;
+        
+        let mut x = x0;
+        let mut y = y0;
+        
+        while x != x1 || y != y1 {
+            if blocked(x, y) { return false; }
+            let e2 = 2 * err;
+            if e2 > -dy {
+                err -= dy;
+                x += sx;
+            }
+            if e2 < dx {
+                err += dx;
+                y += sy;
+            }
+        }
+        // Check destination
+        if blocked(x, y) { return false; }
+        
+        true
+    }
+}
+
+impl Graph for Grid2D {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.neighbors_iter(*node).for_each(|(n, cost)| visit(n, cost));
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        Self::line_clear(from, to, |x, y| self.is_blocked(x, y))
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        Some(self.width * self.height)
+    }
+
+    // Center to center, in cells; terrain costs don't stretch distance
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        Some(Euclidean.estimate(from, to))
+    }
+
+    // O(1) per step: prices the move directly instead of scanning the neighbors
+    fn path_cost(&self, path: &[Self::Node]) -> f32 {
+        path.windows(2).map(|w| self.step_cost(w[0], w[1]).unwrap_or(f32::INFINITY)).sum()
+    }
+}
+
+impl GraphWithHeuristic for Grid2D {
+    type DefaultHeuristic = Diagonal;
+
+    // Octile distance matched to the grid's diagonal cost, so it stays admissible
+    fn default_heuristic(&self) -> Diagonal {
+        Diagonal::for_grid(self)
+    }
+}
+
+/// Read-only view of a `Grid2D` with extra cells blocked, for per-query constraints
+/// (e.g. cells reserved by other agents). Borrows both the grid and the set, so building
+/// one is O(1); lookups pay one hash probe per cell check.
+pub struct MaskedGrid2D<'a> {
+    pub base: &'a Grid2D,
+    pub blocked_extra: &'a HashSet<GridPos>,
+}
+
+impl MaskedGrid2D<'_> {
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.base.is_blocked(x, y) || self.blocked_extra.contains(&GridPos { x, y })
+    }
+}
+
+impl Graph for MaskedGrid2D<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.base.neighbors_where(*node, |x, y| self.is_blocked(x, y)).for_each(|(n, cost)| visit(n, cost));
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        Grid2D::line_clear(from, to, |x, y| self.is_blocked(x, y))
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.base.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.base.geometric_distance(from, to)
+    }
+}
+
+/// View of a `Grid2D` priced in fixed point for `astar_int`: each step costs `cardinal` or
+/// `diagonal` (e.g. 10/14), scaled by the entered cell's cost and any directional costs, each
+/// rounded to a thousandth first. Integer sums don't drift across platforms the way float
+/// ones can. Same neighbors and corner rules as the grid; its `diagonal_cost` is unused.
+/// As a plain `Graph` the steps come out as `Milli::to_f32`, so float algorithms search it
+/// with the same prices.
+pub struct IntGrid2D<'a> {
+    pub base: &'a Grid2D,
+    pub cardinal: Milli,
+    pub diagonal: Milli,
+}
+
+impl<'a> IntGrid2D<'a> {
+    pub fn new(base: &'a Grid2D, cardinal: Milli, diagonal: Milli) -> Self {
+        Self { base, cardinal, diagonal }
+    }
+
+    /// Fixed-point cost of stepping from `from` into its neighbor `to`.
+    pub fn step_cost(&self, from: GridPos, to: GridPos) -> Milli {
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let step = if dx != 0 && dy != 0 { self.diagonal } else { self.cardinal };
+        let direction = Milli::from_f32(self.base.directional_multiplier(to.x, to.y, dx, dy));
+        step.scale(Milli::from_f32(self.base.get_cost(to.x, to.y))).scale(direction)
+    }
+}
+
+impl Graph for IntGrid2D<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.base.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.neighbors_with_cost(node, |n, cost: Milli| visit(n, cost.to_f32()));
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        self.base.can_traverse(from, to)
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.base.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.base.geometric_distance(from, to)
+    }
+}
+
+impl CostGraph<Milli> for IntGrid2D<'_> {
+    fn neighbors_with_cost<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, Milli),
+    {
+        self.base.neighbors_iter(*node).for_each(|(n, _)| visit(n, self.step_cost(*node, n)));
+    }
+}
+
+/// View of a `Grid2D` for agents that keep their distance from obstacles: cells whose
+/// `ClearanceField` value is below `min_clearance` count as blocked, for searches, corner
+/// cutting and `can_traverse` alike, so smoothing keeps the buffer too. A nonzero `penalty`
+/// also steers paths away from walls softly, scaling each step's cost by
+/// `1 + penalty / clearance` of the cell entered.
+///
+/// The clearance field is built once in `new`; build a new view after editing the grid.
+pub struct ClearanceConstrainedGrid<'a> {
+    pub base: &'a Grid2D,
+    pub min_clearance: f32,
+    pub penalty: f32,
+    clearance: ClearanceField,
+}
+
+impl<'a> ClearanceConstrainedGrid<'a> {
+    /// Hard constraint only; set `penalty` for soft avoidance.
+    pub fn new(base: &'a Grid2D, min_clearance: f32) -> Self {
+        Self { base, min_clearance, penalty: 0.0, clearance: base.clearance_field() }
+    }
+
+    pub fn clearance(&self) -> &ClearanceField {
+        &self.clearance
+    }
+
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.base.is_blocked(x, y) || self.clearance.get(x, y) < self.min_clearance
+    }
+}
+
+impl Graph for ClearanceConstrainedGrid<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        for (n, cost) in self.base.neighbors_where(*node, |x, y| self.is_blocked(x, y)) {
+            // Passable cells have clearance of at least 0.5
+            visit(n, cost * (1.0 + self.penalty / self.clearance.get(n.x, n.y)));
+        }
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        Grid2D::line_clear(from, to, |x, y| self.is_blocked(x, y))
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.base.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.base.geometric_distance(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_list_reports_modified_cells_only() {
+        let mut grid = Grid2D::new(6, 6, DiagonalMode::Always);
+        grid.set_blocked(2, 2, true);
+        assert!(grid.take_changes().is_empty(), "tracking is opt-in");
+
+        grid.enable_change_tracking();
+        grid.set_region_blocked((1, 1, 2, 2), true);
+        let mut changes = grid.take_changes();
+        changes.sort_by_key(|p| (p.y, p.x));
+        assert_eq!(
+            changes,
+            vec![GridPos { x: 1, y: 1 }, GridPos { x: 2, y: 1 }, GridPos { x: 1, y: 2 }]
+        );
+
+        grid.set_cost(4, 4, 3.0);
+        grid.set_cost(4, 4, 3.0);
+        assert_eq!(grid.take_changes(), vec![GridPos { x: 4, y: 4 }]);
+
+        grid.clear();
+        assert_eq!(grid.take_changes().len(), 5);
+    }
+
+    #[test]
+    fn listeners_receive_one_event_per_edit() {
+        let mut grid = Grid2D::new(6, 6, DiagonalMode::Always);
+        let listener = grid.subscribe();
+
+        grid.set_blocked(2, 2, true);
+        grid.set_blocked(2, 2, true); // No-op, not reported
+        grid.set_region_blocked((4, 4, 5, 5), true);
+        grid.set_cost(0, 0, 2.0);
+        grid.clear();
+
+        let events = listener.drain();
+        assert_eq!(
+            events,
+            vec![
+                GridChange { kind: GridChangeKind::Blocked, rect: (2, 2, 1, 1) },
+                GridChange { kind: GridChangeKind::Blocked, rect: (4, 4, 2, 2) }, // Clipped
+                GridChange { kind: GridChangeKind::CostChanged, rect: (0, 0, 1, 1) },
+                GridChange { kind: GridChangeKind::Cleared, rect: (0, 0, 6, 6) },
+            ]
+        );
+        assert!(events[1].contains(&GridPos { x: 5, y: 5 }));
+        assert_eq!(events[1].cells().count(), 4);
+        assert!(listener.drain().is_empty());
+
+        // Grid-wide step cost edits reach listeners, tracked changes and the revision
+        grid.enable_change_tracking();
+        grid.set_diagonal_cost(1.5);
+        grid.set_diagonal_cost(1.5);
+        assert_eq!(listener.drain(), vec![GridChange { kind: GridChangeKind::CostChanged, rect: (0, 0, 6, 6) }]);
+        assert_eq!((grid.take_changes().len(), grid.revision()), (36, 1));
+
+        drop(listener);
+        grid.set_blocked(1, 1, true);
+        assert!(grid.listeners.lock().unwrap().is_empty(), "dropped listeners are pruned");
+    }
+
+    #[test]
+    fn region_queries_and_cost_stats() {
+        let mut grid = Grid2D::new(5, 4, DiagonalMode::Never);
+        grid.set_region_blocked((0, 0, 2, 2), true);
+        grid.set_cost(3, 1, 4.0);
+        grid.set_cost(4, 1, 0.5);
+
+        assert_eq!(grid.region_passable_count((0, 0, 5, 4)), 16);
+        assert_eq!(grid.region_passable_count((1, 1, 3, 1)), 2);
+        assert_eq!(grid.region_passable_count((3, 3, 10, 10)), 2); // Clipped
+        assert!((grid.region_passable_fraction((0, 0, 4, 2)) - 0.5).abs() < 1e-6);
+        assert_eq!(grid.region_passable_fraction((9, 9, 2, 2)), 0.0);
+
+        let stats = grid.region_cost_stats((2, 1, 3, 1));
+        assert_eq!(stats.count, 3);
+        assert_eq!((stats.min, stats.max), (0.5, 4.0));
+        assert!((stats.mean - 5.5 / 3.0).abs() < 1e-6);
+        assert_eq!(grid.region_cost_stats((0, 0, 2, 2)).count, 0);
+    }
+
+    #[test]
+    fn prefix_sums_match_brute_force_on_random_grids() {
+        let mut seed: u32 = 0x2545_f491;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+
+        for _ in 0..8 {
+            let (w, h) = (1 + next() % 20, 1 + next() % 20);
+            let mut grid = Grid2D::new(w, h, DiagonalMode::Never);
+            for _ in 0..(w * h / 3) {
+                grid.set_blocked(next() % w, next() % h, true);
+            }
+            let sums = grid.build_prefix_sums();
+            for _ in 0..50 {
+                let rect = (next() % (w + 2), next() % (h + 2), next() % (w + 2), next() % (h + 2));
+                assert_eq!(sums.region_passable_count(rect), grid.region_passable_count(rect), "{:?}", rect);
+            }
+            assert_eq!(sums.region_passable_count((0, 0, w, h)), grid.region_passable_count((0, 0, w, h)));
+        }
+    }
+
+    #[test]
+    fn generated_rooms_are_connected() {
+        for seed in [1, 7, 42, 1234] {
+            let (width, height, max_room) = (64, 48, 12);
+            let (grid, rooms) = Grid2D::generate_room_layout(width, height, 3, max_room, seed, DiagonalMode::Never);
+
+            assert_eq!(grid.connected_components().count, 1, "seed {}", seed);
+            assert!(rooms.len() >= width * height / (max_room * max_room), "seed {}: {} rooms", seed, rooms.len());
+            for &(rx, ry, rw, rh) in &rooms {
+                assert!(rw >= 3 && rh >= 3 && rw <= max_room && rh <= max_room);
+                assert_eq!(grid.region_passable_count((rx, ry, rw, rh)), rw * rh);
+            }
+
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    if grid.is_blocked(x, y) {
+                        let open = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                            .iter()
+                            .filter(|(dx, dy)| !grid.is_blocked(x + dx, y + dy))
+                            .count();
+                        assert!(open < 4, "lone wall at ({}, {})", x, y);
+                    }
+                }
+            }
+        }
+
+        let a = Grid2D::generate_rooms(40, 40, 3, 10, 9, DiagonalMode::Always);
+        let b = Grid2D::generate_rooms(40, 40, 3, 10, 9, DiagonalMode::Always);
+        assert_eq!(a.cells, b.cells);
+    }
+
+    #[test]
+    fn connected_components_counts_regions() {
+        let mut grid = Grid2D::new(7, 3, DiagonalMode::Never);
+        grid.set_region_blocked((3, 0, 1, 3), true);
+        let components = grid.connected_components();
+        assert_eq!(components.count, 2);
+        assert_eq!(components.sizes, vec![9, 9]);
+        assert_eq!(components.labels[3], None);
+    }
+
+    #[test]
+    fn directional_bias_scales_step_costs() {
+        let mut grid = Grid2D::new(3, 3, DiagonalMode::Always).with_diagonal_cost(1.5);
+        let listener = grid.subscribe();
+        let everywhere = GridChange { kind: GridChangeKind::CostChanged, rect: (0, 0, 3, 3) };
+        assert!(!grid.has_directional_costs());
+        grid.set_directional_bias([1.0, 1.0, 3.0, 1.0, 1.0, 1.0, 0.5, 1.0]);
+        assert_eq!(listener.drain(), vec![everywhere]);
+        grid.set_cost(2, 1, 2.0);
+        assert!(grid.has_directional_costs());
+        assert_eq!(grid.min_directional_multiplier(), 0.5);
+
+        let mut costs = std::collections::HashMap::new();
+        grid.neighbors(&GridPos { x: 1, y: 1 }, |n, c| {
+            costs.insert((n.x, n.y), c);
+        });
+        assert_eq!(costs[&(2, 1)], 6.0); // East: cell cost 2 * bias 3
+        assert_eq!(costs[&(0, 1)], 0.5); // West
+        assert_eq!(costs[&(1, 0)], 1.0); // North
+        assert_eq!(costs[&(2, 2)], 1.5); // South-east diagonal, unbiased
+
+        grid.set_directional_cost(1, 0, Direction::N, 4.0);
+        grid.set_directional_cost(1, 0, Direction::None, 9.0);
+        grid.neighbors(&GridPos { x: 1, y: 1 }, |n, c| {
+            costs.insert((n.x, n.y), c);
+        });
+        assert_eq!(costs[&(1, 0)], 4.0);
+
+        listener.drain();
+        grid.clear_directional_costs();
+        grid.clear_directional_costs(); // No-op, not reported
+        assert!(!grid.has_directional_costs());
+        assert_eq!(listener.drain(), vec![everywhere]);
+        assert_eq!(grid.revision(), 2);
+    }
+
+    #[test]
+    fn visibility_through_single_opening() {
+        // 9x9 room (walls at 5 and 15) with one gap in its east wall at (15, 10)
+        let mut grid = Grid2D::new(30, 21, DiagonalMode::Always);
+        grid.set_region_blocked((5, 5, 11, 11), true);
+        grid.set_region_blocked((6, 6, 9, 9), false);
+        grid.set_blocked(15, 10, false);
+        let source = GridPos { x: 10, y: 10 };
+        let vis = grid.compute_visibility_map(source, 20.0);
+        let seen = |x, y| grid.sample_visibility(&vis, GridPos { x, y });
+
+        assert!(seen(10, 10) && seen(6, 6) && seen(14, 14));
+        assert!(seen(5, 5) && seen(15, 9)); // The walls themselves
+        // Through the opening, along the line of the gap
+        assert!(seen(15, 10) && seen(20, 10) && seen(25, 10) && seen(29, 10));
+        // Behind the walls
+        assert!(!seen(2, 10) && !seen(10, 2) && !seen(10, 18) && !seen(20, 4) && !seen(20, 16));
+        // The cone widens but stays narrow
+        assert!(!seen(28, 14) && !seen(28, 6));
+
+        // Range limit and out-of-grid lookups
+        let short = grid.compute_visibility_map(source, 3.0);
+        assert!(grid.sample_visibility(&short, GridPos { x: 13, y: 10 }));
+        assert!(!grid.sample_visibility(&short, GridPos { x: 14, y: 10 }));
+        assert!(!grid.sample_visibility(&short, GridPos { x: 12, y: 13 })); // ~3.6 away
+        assert!(!grid.sample_visibility(&vis, GridPos { x: -1, y: 10 }));
+
+        // Visibility is symmetric between open cells
+        for y in 0..21 {
+            for x in 0..30 {
+                let other = GridPos { x, y };
+                if grid.is_blocked(x, y) || (x - 10) * (x - 10) + (y - 10) * (y - 10) > 400 {
+                    continue;
+                }
+                let back = grid.compute_visibility_map(other, 20.0);
+                assert_eq!(seen(x, y), grid.sample_visibility(&back, source), "{other:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn cell_indices_stop_at_every_edge() {
+        use crate::algorithms::flowfield::FlowField;
+
+        let mut grid = Grid2D::new(5, 3, DiagonalMode::Always);
+        grid.set_cost(4, 2, 2.0);
+        let corner = GridPos { x: 4, y: 2 };
+        assert_eq!(grid.index_of(corner), Some(CellIndex(14)));
+        assert_eq!(grid.pos_of(CellIndex(14)), corner);
+        assert_eq!(grid.index_of(GridPos { x: 4, y: 0 }), Some(CellIndex(4)));
+        assert_eq!(grid.index_of(GridPos { x: 0, y: 2 }).map(CellIndex::get), Some(10));
+        for pos in grid.passable_cells() {
+            assert_eq!(grid.pos_of(grid.index_of(pos).unwrap()), pos);
+        }
+
+        // One past the last column would be the next row's first cell if it wrapped
+        for (x, y) in [(5, 0), (5, 2), (0, 3), (4, 3), (-1, 0), (0, -1), (-1, -1), (i32::MIN, 0), (0, i32::MAX)] {
+            let pos = GridPos { x, y };
+            assert!(!grid.contains(pos) && grid.index_of(pos).is_none(), "{pos:?}");
+            assert!(grid.is_blocked(x, y));
+            assert_eq!(grid.get_cost(x, y), f32::INFINITY);
+        }
+        assert_eq!(grid.get_cost(4, 2), 2.0);
+        assert!(grid.contains(corner) && !grid.is_blocked(4, 2));
+
+        // The far corner steps only inward, the next row's start isn't a neighbor of the row end
+        let inward: Vec<GridPos> = grid.neighbors_iter(corner).map(|(n, _)| n).collect();
+        assert_eq!(inward, vec![GridPos { x: 4, y: 1 }, GridPos { x: 3, y: 2 }, GridPos { x: 3, y: 1 }]);
+        assert!(grid.neighbors_iter(GridPos { x: 4, y: 0 }).all(|(n, _)| n.x >= 3));
+
+        let field = FlowField::compute(&grid, GridPos { x: 0, y: 0 });
+        assert!(field.get_cost_to_goal(corner).is_finite());
+        assert_eq!(field.get_cost_to_goal(GridPos { x: 5, y: 0 }), f32::INFINITY);
+        assert_eq!(field.get_direction(GridPos { x: -1, y: 2 }), Direction::None);
+        assert_eq!(field.get_direction(corner), Direction::NW);
+        assert_eq!(CellIndex::of(GridPos { x: 2, y: 1 }, 5, 3).map(|c| c.coords(5)), Some((2, 1)));
+    }
+
+    #[test]
+    fn neighbors_iter_matches_callback() {
+        let modes = [DiagonalMode::Never, DiagonalMode::Always, DiagonalMode::IfNoObstacle, DiagonalMode::OnlyIfBothOpen];
+        for mode in modes {
+            let mut grid = Grid2D::new(6, 5, mode);
+            let walls = [(1, 0), (0, 1), (3, 2), (4, 3), (5, 4)];
+            for (x, y) in walls {
+                grid.set_blocked(x, y, true);
+            }
+            grid.set_cost(2, 2, 3.0);
+            let open = |x: i32, y: i32| {
+                (0..6).contains(&x) && (0..5).contains(&y) && !walls.contains(&(x as usize, y as usize))
+            };
+            for y in -1..6 {
+                for x in -1..7 {
+                    let pos = GridPos { x, y };
+                    // Written out from the corner rules, sorted since only the set is compared
+                    let mut expected = Vec::new();
+                    for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                        if (dx, dy) == (0, 0) || !open(x + dx, y + dy) {
+                            continue;
+                        }
+                        let open_sides = [open(x + dx, y), open(x, y + dy)].iter().filter(|&&o| o).count();
+                        let diagonal_ok = match mode {
+                            DiagonalMode::Never => false,
+                            DiagonalMode::Always => true,
+                            DiagonalMode::IfNoObstacle => open_sides >= 1,
+                            DiagonalMode::OnlyIfBothOpen => open_sides == 2,
+                        };
+                        if dx != 0 && dy != 0 && !diagonal_ok {
+                            continue;
+                        }
+                        let terrain = if (x + dx, y + dy) == (2, 2) { 3.0 } else { 1.0 };
+                        let step = if dx != 0 && dy != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+                        expected.push((GridPos { x: x + dx, y: y + dy }, terrain * step));
+                    }
+                    expected.sort_by_key(|(n, _)| (n.x, n.y));
+
+                    let mut from_iter: Vec<_> = grid.neighbors_iter(pos).collect();
+                    let mut from_callback = Vec::new();
+                    grid.neighbors(&pos, |n, cost| from_callback.push((n, cost)));
+                    from_iter.sort_by_key(|(n, _)| (n.x, n.y));
+                    from_callback.sort_by_key(|(n, _)| (n.x, n.y));
+                    assert_eq!(from_iter, expected, "{mode:?} at {pos:?}");
+                    assert_eq!(from_callback, expected, "{mode:?} at {pos:?}");
+                    assert_eq!(grid.passable_neighbors_count(pos), expected.len());
+                }
+            }
+
+            // Corner (0, 0) has both cardinals blocked; (5, 0) sits against two map edges
+            let corner = grid.passable_neighbors_count(GridPos { x: 0, y: 0 });
+            let edge = grid.passable_neighbors_count(GridPos { x: 5, y: 0 });
+            let (want_corner, want_edge) = match mode {
+                DiagonalMode::Never => (0, 2),
+                DiagonalMode::Always => (1, 3),
+                DiagonalMode::IfNoObstacle | DiagonalMode::OnlyIfBothOpen => (0, 3),
+            };
+            assert_eq!((corner, edge), (want_corner, want_edge), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn erode_passable_matches_brute_force() {
+        let mut grid = Grid2D::new(37, 23, DiagonalMode::Always);
+        let mut rng = SplitMix64(5);
+        for _ in 0..25 {
+            grid.set_blocked(rng.range(0, 36), rng.range(0, 22), true);
+        }
+        grid.set_cost(18, 11, 2.5);
+
+        for radius in [0, 1, 2, 5] {
+            let eroded = grid.erode_passable(radius);
+            let r = radius as i32;
+            for y in 0..23 {
+                for x in 0..37 {
+                    // Blocked if any cell of the square around (x, y) is blocked or off the grid
+                    let expected = (-r..=r).any(|dy| (-r..=r).any(|dx| grid.is_blocked(x + dx, y + dy)));
+                    assert_eq!(eroded.is_blocked(x, y), expected, "radius {radius} at ({x}, {y})");
+                    if !expected {
+                        assert_eq!(eroded.get_cost(x, y), grid.get_cost(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn terrain_zones_steer_astar_around_swamp() {
+        use crate::algorithms::astar::{astar, AStarConfig};
+        use crate::heuristics::Manhattan;
+        use crate::traits::PathStatus;
+
+        // A swamp across rows 1-3 with a clear corridor along row 0
+        let mut grid = Grid2D::new(12, 5, DiagonalMode::Never);
+        grid.set_blocked(6, 4, true);
+        let mut zones = TerrainZoneMap::new(12, 5);
+        zones.define_zone("swamp", 3.0);
+        zones.paint_rect((2, 1, 8, 4), "swamp");
+        zones.paint_circle((0, 4), 1, "road"); // Undefined zone: multiplier 1.0
+        assert_eq!(grid.apply_terrain_zones(&TerrainZoneMap::new(12, 4)), Err(GridError::DimensionMismatch));
+        assert_eq!(grid.get_cost(4, 2), 1.0);
+        assert_eq!(grid.apply_terrain_zones(&zones), Ok(()));
+        assert_eq!((zones.multiplier_at(12, 0), zones.multiplier_at(0, 5)), (None, None));
+
+        assert_eq!(grid.get_cost(4, 2), 3.0);
+        assert_eq!(grid.get_cost(0, 3), 1.0);
+        assert!(grid.is_blocked(6, 4), "blocked cells stay blocked");
+
+        let (start, goal) = (GridPos { x: 1, y: 2 }, GridPos { x: 10, y: 2 });
+        let result = astar(&grid, &Manhattan, start, goal, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::Found);
+        assert!(result.path.iter().all(|p| grid.get_cost(p.x, p.y) == 1.0));
+        // Up to the corridor, across, and back down: 2 + 9 + 2 steps
+        assert_eq!(result.cost, 13.0);
+    }
+
+    #[test]
+    fn painted_circles_and_lines_set_costs_around_blocked_cells() {
+        let mut grid = Grid2D::new(5, 5, DiagonalMode::Always);
+        let listener = grid.subscribe();
+        grid.paint_circle_cost(GridPos { x: 2, y: 2 }, 1.0, 4.0);
+        let painted: Vec<(i32, i32)> =
+            (0..25).map(|i| (i % 5, i / 5)).filter(|&(x, y)| grid.get_cost(x, y) == 4.0).collect();
+        assert_eq!(painted, vec![(2, 1), (1, 2), (2, 2), (3, 2), (2, 3)]);
+        assert_eq!(listener.drain(), vec![GridChange { kind: GridChangeKind::CostChanged, rect: (1, 1, 3, 3) }]);
+
+        // Gradient: inner cost within 1, fading to the outer cost at 3; corners are too far
+        let mut grid = Grid2D::new(7, 7, DiagonalMode::Always);
+        grid.set_blocked(3, 4, true);
+        grid.paint_gradient_circle(GridPos { x: 3, y: 3 }, 1.0, 3.0, 5.0, 1.0);
+        assert!(grid.is_blocked(3, 4));
+        assert_eq!(grid.get_cost(3, 2), 5.0);
+        assert_eq!(grid.get_cost(3, 1), 3.0);
+        assert_eq!(grid.get_cost(3, 0), 1.0);
+        assert_eq!(grid.get_cost(1, 1), 5.0 - 4.0 * (8f32.sqrt() - 1.0) / 2.0);
+        assert_eq!(grid.get_cost(0, 0), 1.0);
+
+        // A road two cells thick along the top edge, running off both sides, then a diagonal
+        let mut grid = Grid2D::new(6, 6, DiagonalMode::Always);
+        grid.paint_line_cost(GridPos { x: -2, y: 0 }, GridPos { x: 8, y: 0 }, 2.0, 0.5);
+        assert!((0..6).all(|x| grid.get_cost(x, 0) == 0.5 && grid.get_cost(x, 1) == 0.5));
+        assert_eq!(grid.get_cost(0, 2), 1.0);
+        grid.paint_line_cost(GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 5 }, 1.0, 2.0);
+        assert!((0..6).all(|i| grid.get_cost(i, i) == 2.0));
+        assert_eq!(grid.get_cost(4, 5), 1.0);
+    }
+
+    #[test]
+    fn noise_costs_stay_in_range_vary_and_skip_blocked_cells() {
+        let costs = |grid: &Grid2D| -> Vec<f32> {
+            (0..40 * 30).map(|i| grid.get_cost(i % 40, i / 40)).filter(|c| c.is_finite()).collect()
+        };
+        for perlin in [false, true] {
+            let noisy = |seed: u64, blocked: (usize, usize, usize, usize)| {
+                let mut grid = Grid2D::new(40, 30, DiagonalMode::Always);
+                grid.set_region_blocked(blocked, true);
+                if perlin {
+                    grid.apply_perlin_cost_approx(seed, 0.15, 1.0, 5.0);
+                } else {
+                    grid.apply_noise_cost(seed, 8.0, 1.0, 5.0);
+                }
+                grid
+            };
+            let grid = noisy(7, (10, 10, 5, 5));
+            assert!(grid.is_blocked(12, 12));
+            let values = costs(&grid);
+            assert_eq!(values.len(), 40 * 30 - 25);
+            assert!(values.iter().all(|c| (1.0..=5.0).contains(c)));
+            let (lo, hi) = values.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &c| (lo.min(c), hi.max(c)));
+            assert!(hi - lo > 1.0, "perlin {perlin}: costs only span {lo}..{hi}");
+            // Smooth: neighbours differ far less than the full range
+            assert!((0..39).all(|x| (grid.get_cost(x, 0) - grid.get_cost(x + 1, 0)).abs() < 1.5));
+
+            // Same seed, same terrain; another seed, another
+            assert_eq!(noisy(7, (0, 0, 0, 0)).get_cost(0, 0), grid.get_cost(0, 0));
+            assert_ne!(costs(&noisy(7, (0, 0, 0, 0))), costs(&noisy(8, (0, 0, 0, 0))));
+        }
+
+        // Scales with no lattice to sample leave the costs as they were
+        let mut grid = Grid2D::new(8, 8, DiagonalMode::Always);
+        grid.set_cost(3, 3, 2.0);
+        let before = grid.cells.clone();
+        for scale in [0.0, -4.0, f32::NAN] {
+            grid.apply_noise_cost(1, scale, 1.0, 5.0);
+        }
+        grid.apply_perlin_cost_approx(1, f32::NAN, 1.0, 5.0);
+        grid.apply_perlin_cost_approx(1, f32::INFINITY, 1.0, 5.0);
+        assert_eq!(grid.cells, before);
+    }
+
+    #[test]
+    fn exported_edges_match_the_diagonal_mode() {
+        let grid = Grid2D::new(3, 3, DiagonalMode::Never);
+        // 12 undirected edges, each in both directions
+        assert_eq!(grid.edges().count(), 24);
+        let edges = grid.to_edge_list();
+        assert!(edges.iter().all(|&(a, b, cost)| cost == 1.0 && edges.contains(&(b, a, cost))));
+
+        let mut grid = Grid2D::new(3, 3, DiagonalMode::Always);
+        assert_eq!(grid.edges().count(), 24 + 2 * 8);
+        grid.set_blocked(1, 1, true);
+        let adjacency = grid.to_adjacency_list();
+        assert_eq!(adjacency.len(), 8);
+        assert_eq!(adjacency[0], (GridPos { x: 0, y: 0 }, vec![(GridPos { x: 0, y: 1 }, 1.0), (GridPos { x: 1, y: 0 }, 1.0)]));
+        assert_eq!(adjacency.iter().map(|(_, n)| n.len()).sum::<usize>(), grid.to_edge_list().len());
+    }
+
+    // Three rounds of server edits, drained as deltas
+    fn recorded_deltas(server: &mut Grid2D) -> Vec<GridDelta> {
+        server.enable_change_tracking();
+        let mut deltas = Vec::new();
+        server.set_region_blocked((8, 0, 1, 16), true);
+        deltas.push(server.drain_changes_as_delta());
+        server.set_blocked(8, 7, false); // Destroyed wall
+        server.set_cost(3, 3, 2.5);
+        server.set_cost(3, 3, 4.0); // Only the final cost is sent
+        deltas.push(server.drain_changes_as_delta());
+        server.paint_circle_cost(GridPos { x: 12, y: 12 }, 2.0, 3.0);
+        server.set_blocked(8, 8, false);
+        deltas.push(server.drain_changes_as_delta());
+        deltas
+    }
+
+    #[test]
+    fn delta_stream_brings_stale_copy_up_to_date() {
+        let mut server = Grid2D::new(16, 16, DiagonalMode::Always);
+        let deltas = recorded_deltas(&mut server);
+        assert_eq!(server.revision(), 3);
+        assert_eq!(deltas[1].passable, vec![(3 * 16 + 3, 4.0), (7 * 16 + 8, 1.0)]);
+
+        let mut client = Grid2D::new(16, 16, DiagonalMode::Always);
+        for delta in &deltas {
+            client.apply_delta(delta).unwrap();
+        }
+        let bits = |grid: &Grid2D| -> Vec<Option<u32>> {
+            let bits = |cell: &CellType| match cell {
+                CellType::Passable(cost) => Some(cost.to_bits()),
+                CellType::Blocked => None,
+            };
+            grid.cells.iter().map(bits).collect()
+        };
+        assert_eq!(bits(&client), bits(&server));
+        assert_eq!(client.revision(), server.revision());
+
+        // Skipped, reordered and repeated deltas are rejected without touching the grid
+        let mut stale = Grid2D::new(16, 16, DiagonalMode::Always);
+        let mismatch = |grid_revision, delta_base_revision| {
+            Err(PathforgeError::Delta(DeltaError::RevisionMismatch { grid_revision, delta_base_revision }))
+        };
+        assert_eq!(stale.apply_delta(&deltas[1]), mismatch(0, 1));
+        assert!(stale.cells.iter().all(|&c| c == CellType::Passable(1.0)));
+        stale.apply_delta(&deltas[0]).unwrap();
+        assert_eq!(stale.apply_delta(&deltas[0]), mismatch(1, 0));
+        let outside = GridDelta { base_revision: 1, revision: 2, blocked: vec![256], passable: Vec::new() };
+        assert_eq!(stale.apply_delta(&outside), Err(PathforgeError::Delta(DeltaError::CellOutOfBounds(256))));
+        assert_eq!(stale.revision(), 1);
+    }
+
+    #[test]
+    fn applied_delta_invalidates_attached_cache() {
+        use crate::algorithms::astar::AStarConfig;
+        use crate::cache::{astar_with_cache, PathCache};
+        use crate::heuristics::Manhattan;
+        use std::time::Duration;
+
+        let mut server = Grid2D::new(16, 16, DiagonalMode::Never);
+        let deltas = recorded_deltas(&mut server);
+        let mut client = Grid2D::new(16, 16, DiagonalMode::Never);
+        let mut cache = PathCache::new(4, Duration::from_secs(60));
+        cache.attach(&client);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 15, y: 0 });
+        let open = astar_with_cache(&client, &Manhattan, start, goal, AStarConfig::default(), &mut cache);
+        assert_eq!(open.cost, 15.0);
+
+        client.apply_delta(&deltas[0]).unwrap();
+        assert!(cache.get(&start, &goal).is_none(), "wall from the delta dropped the cached path");
+        client.apply_delta(&deltas[1]).unwrap();
+        let around = astar_with_cache(&client, &Manhattan, start, goal, AStarConfig::default(), &mut cache);
+        assert_eq!(around.cost, 15.0 + 14.0); // Down to the gap at y = 7 and back up
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn delta_serializes_compactly() {
+        let mut server = Grid2D::new(16, 16, DiagonalMode::Always);
+        let deltas = recorded_deltas(&mut server);
+        let json = serde_json::to_string(&deltas[0]).unwrap();
+        assert_eq!(serde_json::from_str::<GridDelta>(&json).unwrap(), deltas[0]);
+        // 16 blocked cells: a few bytes each on top of the fixed header
+        assert!(json.len() < 60 + 16 * 4, "{json}");
+    }
+
+    #[test]
+    fn clearance_constraint_takes_the_wide_gap_and_penalty_keeps_to_the_middle() {
+        use crate::algorithms::astar::{astar, AStarConfig};
+        use crate::heuristics::Octile;
+        use crate::smoothing::{smooth_path, SmoothingMethod};
+        use crate::traits::PathStatus;
+
+        // A thick wall with a 3-wide gap on the straight line and a 5-wide one further down
+        let mut grid = Grid2D::new(31, 21, DiagonalMode::OnlyIfBothOpen);
+        grid.set_region_blocked((14, 0, 3, 21), true);
+        grid.set_region_blocked((14, 9, 3, 3), false);
+        grid.set_region_blocked((14, 14, 3, 5), false);
+        let (start, goal) = (GridPos { x: 5, y: 10 }, GridPos { x: 25, y: 10 });
+        let through_gap = |path: &[GridPos]| path.iter().find(|p| p.x == 15).map(|p| p.y);
+
+        let direct = astar(&grid, &Octile, start, goal, AStarConfig::default());
+        assert_eq!(through_gap(&direct.path), Some(10));
+
+        let wide = ClearanceConstrainedGrid::new(&grid, 2.0);
+        assert!(!wide.is_passable(&GridPos { x: 15, y: 10 }) && wide.is_passable(&GridPos { x: 15, y: 16 }));
+        let result = astar(&wide, &Octile, start, goal, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::Found);
+        assert!(result.path.iter().all(|p| wide.clearance().get(p.x, p.y) >= 2.0));
+        assert!((14..19).contains(&through_gap(&result.path).unwrap()));
+        assert!(result.cost > direct.cost);
+        // Smoothing can't shortcut back past the walls
+        let smoothed = smooth_path(&wide, &result.path, SmoothingMethod::RemoveRedundant);
+        assert!(smoothed.path.len() < result.path.len());
+        assert!(smoothed.path.windows(2).all(|w| wide.can_traverse(&w[0], &w[1])));
+        assert!(!wide.can_traverse(&start, &goal));
+
+        // Soft avoidance only: a 7-wide hall walked from corner to corner bows out to its middle row
+        let hall = Grid2D::new(40, 7, DiagonalMode::Always);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 39, y: 0 });
+        assert!(astar(&hall, &Octile, start, goal, AStarConfig::default()).path.iter().all(|p| p.y == 0));
+        let mut soft = ClearanceConstrainedGrid::new(&hall, 0.0);
+        soft.penalty = 4.0;
+        let hugging = astar(&soft, &Octile, start, goal, AStarConfig::default()).path;
+        assert_eq!(hugging.len(), 40);
+        assert!(hugging[10..30].iter().all(|p| p.y == 3), "{hugging:?}");
+    }
+
+    #[test]
+    fn patterns_block_the_expected_cells() {
+        let blocked = |grid: &Grid2D| grid.cells.iter().filter(|c| **c == CellType::Blocked).count();
+        let from = |w, h, pattern| Grid2D::from_pattern(w, h, pattern, DiagonalMode::Always);
+
+        assert_eq!(blocked(&from(16, 9, GridPattern::Empty)), 0);
+        // The old benchmark maze: 15 walls, each open for the last 5 rows
+        let walls = from(64, 64, GridPattern::VerticalWalls { spacing: 4, gap_height: 5 });
+        assert_eq!(blocked(&walls), 15 * 59);
+        assert!(walls.is_blocked(4, 58) && !walls.is_blocked(4, 59) && !walls.is_blocked(5, 0));
+        let rows = from(30, 20, GridPattern::HorizontalWalls { spacing: 5, gap_width: 2 });
+        assert_eq!(blocked(&rows), 3 * 28);
+        assert!(rows.is_blocked(0, 15) && !rows.is_blocked(28, 15));
+        assert_eq!(blocked(&from(8, 8, GridPattern::Checkerboard { cell_size: 1 })), 32);
+        // 3x3 squares over 10x7: open squares are 3x3 and 1-wide columns/rows at the edges
+        let board = from(10, 7, GridPattern::Checkerboard { cell_size: 3 });
+        assert_eq!(blocked(&board), 27 + 3 + 3 + 1);
+        assert!(!board.is_blocked(0, 0) && board.is_blocked(3, 0) && board.is_blocked(0, 3));
+
+        let random = from(100, 100, GridPattern::Random { seed: 9, density: 0.3 });
+        assert!((2700..3300).contains(&blocked(&random)), "{}", blocked(&random));
+        assert_eq!(random.cells, from(100, 100, GridPattern::Random { seed: 9, density: 0.3 }).cells);
+        assert_ne!(random.cells, from(100, 100, GridPattern::Random { seed: 10, density: 0.3 }).cells);
+
+        // Rings at 3, 6 and 9 around (10, 10), less a door each
+        let rings = from(21, 21, GridPattern::Concentric { ring_spacing: 3 });
+        assert_eq!(blocked(&rings), 8 * (3 + 6 + 9) - 3);
+        assert!(!rings.is_blocked(16, 10) && !rings.is_blocked(7, 10) && rings.is_blocked(13, 10));
+        assert_eq!(rings.connected_components().count, 1);
+
+        // 15x10 rooms, all joined by 149 openings, and every open cell reachable
+        let maze = from(31, 21, GridPattern::Maze(4));
+        assert_eq!(31 * 21 - blocked(&maze), 2 * 15 * 10 - 1);
+        assert_eq!(maze.connected_components().count, 1);
+        assert_ne!(maze.cells, from(31, 21, GridPattern::Maze(5)).cells);
+        assert!((0..31).all(|x| maze.is_blocked(x, 0) && maze.is_blocked(x, 20)));
     }
 }
@@ -1,4 +1,6 @@
-use crate::traits::Graph;
+use crate::error::GridError;
+use crate::heuristics::{Euclidean, Position};
+use crate::traits::{Graph, GraphWithHeuristic, Heuristic};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct GridPos3D {
@@ -18,6 +20,8 @@ pub struct Grid3D {
     pub height: usize,
     pub depth: usize,
     pub voxels: Vec<VoxelType>,
+    // Modified voxels since the last `take_changes`; None while tracking is off
+    changes: Option<Vec<GridPos3D>>,
 }
 
 impl Grid3D {
@@ -27,29 +31,126 @@ impl Grid3D {
             height,
             depth,
             voxels: vec![VoxelType::Passable(1.0); width * height * depth],
+            changes: None,
         }
     }
 
     pub fn set_blocked(&mut self, x: usize, y: usize, z: usize, blocked: bool) {
         if x < self.width && y < self.height && z < self.depth {
-            self.voxels[z * self.width * self.height + y * self.width + x] = if blocked {
+            let voxel = if blocked {
                 VoxelType::Blocked
             } else {
                 VoxelType::Passable(1.0)
             };
+            self.set_voxel_at(z * self.width * self.height + y * self.width + x, voxel);
         }
     }
 
+    /// Sets every voxel in the inclusive box `min..=max` (clamped to the grid).
+    pub fn fill_box(&mut self, min: GridPos3D, max: GridPos3D, voxel: VoxelType) {
+        let lo = |v: i32| v.max(0) as usize;
+        let (x1, y1, z1) = (max.x.min(self.width as i32 - 1), max.y.min(self.height as i32 - 1), max.z.min(self.depth as i32 - 1));
+        if x1 < 0 || y1 < 0 || z1 < 0 {
+            return;
+        }
+        for z in lo(min.z)..=z1 as usize {
+            for y in lo(min.y)..=y1 as usize {
+                for x in lo(min.x)..=x1 as usize {
+                    self.set_voxel_at(z * self.width * self.height + y * self.width + x, voxel);
+                }
+            }
+        }
+    }
+
+    /// Replaces the 6-connected region of voxels matching `predicate` that contains `pos`
+    /// (e.g. seal an enclosed cave). Returns the number of voxels changed.
+    pub fn flood_fill_from<P>(&mut self, pos: GridPos3D, predicate: P, new_type: VoxelType) -> usize
+    where
+        P: Fn(VoxelType) -> bool,
+    {
+        let Some(start) = self.index_of(pos) else { return 0; };
+        if !predicate(self.voxels[start]) {
+            return 0;
+        }
+
+        let mut visited = vec![false; self.voxels.len()];
+        let mut stack = vec![pos];
+        visited[start] = true;
+        let mut region = Vec::new();
+
+        while let Some(p) = stack.pop() {
+            region.push(self.index_of(p).unwrap());
+            let dirs = [(0, 0, 1), (0, 0, -1), (0, 1, 0), (0, -1, 0), (1, 0, 0), (-1, 0, 0)];
+            for (dx, dy, dz) in dirs {
+                let n = GridPos3D { x: p.x + dx, y: p.y + dy, z: p.z + dz };
+                if let Some(idx) = self.index_of(n) {
+                    if !visited[idx] && predicate(self.voxels[idx]) {
+                        visited[idx] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        let mut changed = 0;
+        for idx in region {
+            if self.set_voxel_at(idx, new_type) {
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Start recording modified voxels so caches can invalidate precisely via `take_changes`.
+    pub fn enable_change_tracking(&mut self) {
+        if self.changes.is_none() {
+            self.changes = Some(Vec::new());
+        }
+    }
+
+    /// Drains the voxels modified since the last call. Empty if tracking is disabled.
+    pub fn take_changes(&mut self) -> Vec<GridPos3D> {
+        match self.changes.as_mut() {
+            Some(changes) => std::mem::take(changes),
+            None => Vec::new(),
+        }
+    }
+
+    fn index_of(&self, pos: GridPos3D) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.z < 0 {
+            return None;
+        }
+        let (x, y, z) = (pos.x as usize, pos.y as usize, pos.z as usize);
+        if x >= self.width || y >= self.height || z >= self.depth {
+            return None;
+        }
+        Some(z * self.width * self.height + y * self.width + x)
+    }
+
+    // Returns true if the voxel actually changed
+    fn set_voxel_at(&mut self, idx: usize, voxel: VoxelType) -> bool {
+        if self.voxels[idx] == voxel {
+            return false;
+        }
+        self.voxels[idx] = voxel;
+        if let Some(changes) = self.changes.as_mut() {
+            let layer = self.width * self.height;
+            changes.push(GridPos3D {
+                x: (idx % self.width) as i32,
+                y: ((idx % layer) / self.width) as i32,
+                z: (idx / layer) as i32,
+            });
+        }
+        true
+    }
+
     pub fn is_blocked(&self, x: i32, y: i32, z: i32) -> bool {
         if x < 0 || y < 0 || z < 0 { return true; }
         let ux = x as usize;
         let uy = y as usize;
         let uz = z as usize;
         if ux >= self.width || uy >= self.height || uz >= self.depth { return true; }
-        match self.voxels[uz * self.width * self.height + uy * self.width + ux] {
-            VoxelType::Blocked => true,
-            _ => false,
-        }
+        matches!(self.voxels[uz * self.width * self.height + uy * self.width + ux], VoxelType::Blocked)
     }
 
     pub fn get_cost(&self, x: i32, y: i32, z: i32) -> f32 {
@@ -60,6 +161,96 @@ impl Grid3D {
             VoxelType::Blocked => f32::INFINITY,
         }
     }
+
+    /// Passable where either grid is passable. Overlapping cells keep the cheaper cost.
+    pub fn union(&self, other: &Grid3D) -> Result<Grid3D, GridError> {
+        self.combined(other, union_voxel)
+    }
+
+    /// Passable only where both grids are passable. Overlapping cells keep the higher cost.
+    pub fn intersection(&self, other: &Grid3D) -> Result<Grid3D, GridError> {
+        self.combined(other, intersect_voxel)
+    }
+
+    /// Passable where `self` is passable and `other` is blocked.
+    pub fn subtract(&self, other: &Grid3D) -> Result<Grid3D, GridError> {
+        self.combined(other, subtract_voxel)
+    }
+
+    pub fn union_assign(&mut self, other: &Grid3D) -> Result<(), GridError> {
+        self.combine_assign(other, union_voxel)
+    }
+
+    pub fn intersection_assign(&mut self, other: &Grid3D) -> Result<(), GridError> {
+        self.combine_assign(other, intersect_voxel)
+    }
+
+    pub fn subtract_assign(&mut self, other: &Grid3D) -> Result<(), GridError> {
+        self.combine_assign(other, subtract_voxel)
+    }
+
+    /// The neighbors `Graph::neighbors` visits, with the same costs and order, as an iterator.
+    pub fn neighbors_iter(&self, pos: GridPos3D) -> impl Iterator<Item = (GridPos3D, f32)> + '_ {
+        // 6-connectivity (face neighbors only for simplicity)
+        const DIRS: [(i32, i32, i32); 6] = [
+            (0, 0, 1), (0, 0, -1),
+            (0, 1, 0), (0, -1, 0),
+            (1, 0, 0), (-1, 0, 0)
+        ];
+        DIRS.iter().filter_map(move |&(dx, dy, dz)| {
+            let (nx, ny, nz) = (pos.x + dx, pos.y + dy, pos.z + dz);
+            (!self.is_blocked(nx, ny, nz)).then(|| (GridPos3D { x: nx, y: ny, z: nz }, self.get_cost(nx, ny, nz)))
+        })
+    }
+
+    fn same_dimensions(&self, other: &Grid3D) -> bool {
+        self.width == other.width && self.height == other.height && self.depth == other.depth
+    }
+
+    fn combined(&self, other: &Grid3D, op: fn(VoxelType, VoxelType) -> VoxelType) -> Result<Grid3D, GridError> {
+        if !self.same_dimensions(other) {
+            return Err(GridError::DimensionMismatch);
+        }
+        Ok(Grid3D {
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            voxels: self.voxels.iter().zip(&other.voxels).map(|(&a, &b)| op(a, b)).collect(),
+            changes: None,
+        })
+    }
+
+    fn combine_assign(&mut self, other: &Grid3D, op: fn(VoxelType, VoxelType) -> VoxelType) -> Result<(), GridError> {
+        if !self.same_dimensions(other) {
+            return Err(GridError::DimensionMismatch);
+        }
+        for idx in 0..self.voxels.len() {
+            self.set_voxel_at(idx, op(self.voxels[idx], other.voxels[idx]));
+        }
+        Ok(())
+    }
+}
+
+fn union_voxel(a: VoxelType, b: VoxelType) -> VoxelType {
+    match (a, b) {
+        (VoxelType::Passable(ca), VoxelType::Passable(cb)) => VoxelType::Passable(ca.min(cb)),
+        (VoxelType::Passable(c), VoxelType::Blocked) | (VoxelType::Blocked, VoxelType::Passable(c)) => VoxelType::Passable(c),
+        (VoxelType::Blocked, VoxelType::Blocked) => VoxelType::Blocked,
+    }
+}
+
+fn intersect_voxel(a: VoxelType, b: VoxelType) -> VoxelType {
+    match (a, b) {
+        (VoxelType::Passable(ca), VoxelType::Passable(cb)) => VoxelType::Passable(ca.max(cb)),
+        _ => VoxelType::Blocked,
+    }
+}
+
+fn subtract_voxel(a: VoxelType, b: VoxelType) -> VoxelType {
+    match (a, b) {
+        (VoxelType::Passable(c), VoxelType::Blocked) => VoxelType::Passable(c),
+        _ => VoxelType::Blocked,
+    }
 }
 
 impl Graph for Grid3D {
@@ -73,21 +264,154 @@ impl Graph for Grid3D {
     where
         F: FnMut(Self::Node, f32),
     {
-        // 6-connectivity (face neighbors only for simplicity)
-        let dirs = [
-            (0, 0, 1), (0, 0, -1),
-            (0, 1, 0), (0, -1, 0),
-            (1, 0, 0), (-1, 0, 0)
-        ];
+        self.neighbors_iter(*node).for_each(|(n, cost)| visit(n, cost));
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        Some(self.width * self.height * self.depth)
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        Some(Euclidean.estimate(from, to))
+    }
+}
+
+impl GraphWithHeuristic for Grid3D {
+    type DefaultHeuristic = Euclidean;
+
+    fn default_heuristic(&self) -> Euclidean {
+        Euclidean
+    }
+}
+
+impl Position for GridPos3D {
+    fn x(&self) -> f32 { self.x as f32 }
+    fn y(&self) -> f32 { self.y as f32 }
+    fn z(&self) -> f32 { self.z as f32 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Blocks every voxel with x >= split (keep_low) or x < split (!keep_low)
+    fn half_grid(split: usize, keep_low: bool) -> Grid3D {
+        let mut grid = Grid3D::new(4, 4, 4);
+        for z in 0..4 {
+            for y in 0..4 {
+                for x in 0..4 {
+                    grid.set_blocked(x, y, z, (x < split) != keep_low);
+                }
+            }
+        }
+        grid
+    }
 
-        for (dx, dy, dz) in dirs.iter() {
-            let nx = node.x + dx;
-            let ny = node.y + dy;
-            let nz = node.z + dz;
-            
-            if !self.is_blocked(nx, ny, nz) {
-                visit(GridPos3D { x: nx, y: ny, z: nz }, self.get_cost(nx, ny, nz));
+    #[test]
+    fn union_of_halves_is_fully_passable() {
+        let low = half_grid(2, true);
+        let high = half_grid(2, false);
+        let merged = low.union(&high).unwrap();
+        assert!(merged.voxels.iter().all(|v| matches!(v, VoxelType::Passable(_))));
+
+        let mut assigned = half_grid(2, true);
+        assigned.union_assign(&high).unwrap();
+        assert_eq!(assigned.voxels, merged.voxels);
+    }
+
+    #[test]
+    fn subtract_keeps_non_overlapping_part() {
+        let low = half_grid(3, true); // x in 0..3 passable
+        let overlap = half_grid(1, false); // x in 1..4 passable
+        let diff = low.subtract(&overlap).unwrap();
+        for z in 0..4 {
+            for y in 0..4 {
+                for x in 0..4 {
+                    assert_eq!(diff.is_blocked(x, y, z), x != 0, "voxel ({}, {}, {})", x, y, z);
+                }
             }
         }
     }
+
+    #[test]
+    fn cost_conflicts_and_dimension_checks() {
+        let mut a = Grid3D::new(2, 2, 2);
+        let mut b = Grid3D::new(2, 2, 2);
+        a.voxels[0] = VoxelType::Passable(2.0);
+        b.voxels[0] = VoxelType::Passable(5.0);
+        assert_eq!(a.union(&b).unwrap().voxels[0], VoxelType::Passable(2.0));
+        assert_eq!(a.intersection(&b).unwrap().voxels[0], VoxelType::Passable(5.0));
+
+        let other = Grid3D::new(3, 2, 2);
+        assert_eq!(a.union(&other).err(), Some(GridError::DimensionMismatch));
+        assert_eq!(a.intersection_assign(&other), Err(GridError::DimensionMismatch));
+    }
+
+    #[test]
+    fn change_list_tracks_bulk_operations_exactly() {
+        let mut grid = Grid3D::new(4, 4, 4);
+        grid.set_blocked(1, 1, 1, true);
+        grid.enable_change_tracking();
+
+        // (1,1,1) is already blocked, so only the other 7 voxels of the box change
+        grid.fill_box(GridPos3D { x: 0, y: 0, z: 0 }, GridPos3D { x: 1, y: 1, z: 1 }, VoxelType::Blocked);
+        let mut changes = grid.take_changes();
+        changes.sort_by_key(|p| (p.z, p.y, p.x));
+        let mut expected = Vec::new();
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    if (x, y, z) != (1, 1, 1) {
+                        expected.push(GridPos3D { x, y, z });
+                    }
+                }
+            }
+        }
+        assert_eq!(changes, expected);
+        assert!(grid.take_changes().is_empty());
+    }
+
+    #[test]
+    fn flood_fill_seals_enclosed_cave() {
+        let mut grid = Grid3D::new(5, 5, 5);
+        // Hollow 3x3x3 shell with a single-voxel cave in the middle
+        grid.fill_box(GridPos3D { x: 1, y: 1, z: 1 }, GridPos3D { x: 3, y: 3, z: 3 }, VoxelType::Blocked);
+        grid.set_blocked(2, 2, 2, false);
+        grid.enable_change_tracking();
+
+        let changed = grid.flood_fill_from(
+            GridPos3D { x: 2, y: 2, z: 2 },
+            |v| matches!(v, VoxelType::Passable(_)),
+            VoxelType::Blocked,
+        );
+        assert_eq!(changed, 1);
+        assert_eq!(grid.take_changes(), vec![GridPos3D { x: 2, y: 2, z: 2 }]);
+        // The outside region is untouched
+        assert!(!grid.is_blocked(0, 0, 0));
+    }
+
+    #[test]
+    fn neighbors_iter_matches_callback() {
+        let mut grid = Grid3D::new(3, 3, 3);
+        grid.set_blocked(1, 1, 2, true);
+        let at = |x, y, z| GridPos3D { x, y, z };
+        // Face neighbors inside the grid, minus the blocked voxel above the center, at cost 1
+        let cases = [
+            (at(1, 1, 1), vec![at(0, 1, 1), at(1, 0, 1), at(1, 1, 0), at(1, 2, 1), at(2, 1, 1)]),
+            (at(0, 0, 0), vec![at(0, 0, 1), at(0, 1, 0), at(1, 0, 0)]),
+            (at(2, 1, 2), vec![at(2, 0, 2), at(2, 1, 1), at(2, 2, 2)]),
+        ];
+        let key = |(n, _): &(GridPos3D, f32)| (n.x, n.y, n.z);
+        for (pos, cells) in cases {
+            let mut expected: Vec<_> = cells.into_iter().map(|n| (n, 1.0)).collect();
+            expected.sort_by_key(key);
+            let mut from_iter: Vec<_> = grid.neighbors_iter(pos).collect();
+            let mut from_callback = Vec::new();
+            grid.neighbors(&pos, |n, cost| from_callback.push((n, cost)));
+            from_iter.sort_by_key(key);
+            from_callback.sort_by_key(key);
+            assert_eq!(from_iter, expected, "{pos:?}");
+            assert_eq!(from_callback, expected, "{pos:?}");
+        }
+    }
 }
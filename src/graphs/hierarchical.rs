@@ -1,19 +1,120 @@
-use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::graphs::grid2d::{ChangeListener, Grid2D, GridPos};
+use crate::graphs::grid3d::GridPos3D;
 use crate::algorithms::astar::{astar, AStarConfig};
-use crate::heuristics::{Euclidean, Manhattan};
-use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
-use std::collections::HashMap;
+use crate::heuristics::{Diagonal, Euclidean, Manhattan};
+use crate::traits::{path_length, Graph, Heuristic, PathResult, PathStatus};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::{Duration, Instant};
 use rayon::prelude::*;
 
+/// Identifies an abstract node by content: a hash of its position and cluster, so rebuilding
+/// the same grid (or a grid edited elsewhere) yields the same IDs for the same entrances.
+/// In the rare case two entrances hash alike, the one added later takes the next free value.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct AbstractNodeId(usize);
 
+impl AbstractNodeId {
+    fn for_node(pos: GridPos, cluster: (usize, usize)) -> Self {
+        Self::from_words(&[pos.x as u64, pos.y as u64, cluster.0 as u64, cluster.1 as u64])
+    }
+
+    // The same for a voxel of a `HierarchicalGrid3D` and its cluster
+    pub(crate) fn for_voxel(pos: GridPos3D, cluster: (usize, usize, usize)) -> Self {
+        let (x, y, z) = (pos.x as u64, pos.y as u64, pos.z as u64);
+        Self::from_words(&[x, y, z, cluster.0 as u64, cluster.1 as u64, cluster.2 as u64])
+    }
+
+    // FNV-1a over the position and cluster coordinates
+    fn from_words(words: &[u64]) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for word in words {
+            for byte in word.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        // Keep clear of the virtual start and goal
+        AbstractNodeId((hash as usize).min(usize::MAX - 2))
+    }
+
+    // This id, or the first value after it (wrapping, clear of the virtual nodes) not `taken`
+    pub(crate) fn first_free(self, taken: impl Fn(&AbstractNodeId) -> bool) -> Self {
+        let mut id = self;
+        while taken(&id) {
+            id = AbstractNodeId(if id.0 >= usize::MAX - 2 { 0 } else { id.0 + 1 });
+        }
+        id
+    }
+
+    /// The raw hash value, stable across builds for the same position.
+    pub fn value(&self) -> usize {
+        self.0
+    }
+
+    /// Formerly the index into a `Vec` of nodes; now the same as `value`.
+    #[deprecated(note = "ids are hashes, not indices: use `value`, or look nodes up with `pos_for_abstract_id`")]
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+// Per-query stand-ins for the start and goal positions in the abstract search
+pub(crate) const VIRTUAL_START: AbstractNodeId = AbstractNodeId(usize::MAX - 1);
+pub(crate) const VIRTUAL_GOAL: AbstractNodeId = AbstractNodeId(usize::MAX);
+
+/// High-level route through the abstract graph, without per-cluster refinement.
 #[derive(Debug, Clone)]
+pub struct AbstractPathResult {
+    pub path: Vec<AbstractNodeId>,
+    pub positions: Vec<GridPos>, // Grid position of each node in `path`
+    pub estimated_cost: f32,     // Sum of abstract edge costs; approximates the refined cost
+    pub status: PathStatus,
+}
+
+/// Separate search settings for the two levels of `HierarchicalGrid::find_path_with_hpa_config`.
+/// Each config's `timeout` counts from the call; `deadline`s apply as given.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HpaConfig {
+    /// The search over the abstract graph of entrances.
+    pub abstract_config: AStarConfig,
+    /// Every grid-level search: connecting the start and goal to their clusters' entrances,
+    /// the same-cluster search and the sealed-cluster fallback.
+    pub local_config: AStarConfig,
+}
+
+impl HpaConfig {
+    /// Default settings, with each local search capped at `cluster_size^2` expansions
+    /// (one cluster's worth of cells): a safe bound that keeps a query from flooding the
+    /// grid when its start or goal is walled in.
+    pub fn for_cluster_size(cluster_size: usize) -> Self {
+        let local_config = AStarConfig { max_iterations: Some(cluster_size * cluster_size), ..AStarConfig::default() };
+        Self { local_config, ..Self::default() }
+    }
+}
+
+// (x, y, width, height) of a cluster in cells
+type ClusterRect = (usize, usize, usize, usize);
+
+// A grid-level connection from a query position to an abstract node, or back
+type Connection = (AbstractNodeId, f32, Vec<GridPos>);
+
+// Next hop toward the goal in `find_paths_grouped`'s shortest-path tree
+#[derive(Clone, Copy)]
+enum Hop {
+    Goal(usize), // Index into the goal connections
+    Edge(usize), // Index into the node's `edges` entry
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct AbstractEdge {
     pub target: AbstractNodeId,
     pub cost: f32,
     // We cache the actual path steps to avoid recomputing them during refinement
     pub path: Vec<GridPos>, 
+    // Cells in the border opening this edge crosses; usize::MAX for intra-cluster edges.
+    // Read through `HierarchicalGrid::entrance_width_between`
+    entrance_width: usize,
 }
 
 pub struct HierarchicalGrid {
@@ -21,12 +122,18 @@ pub struct HierarchicalGrid {
     pub cluster_size: usize,
     
     // The Abstract Graph
-    pub nodes: Vec<GridPos>, // ID -> Real Position
+    pub nodes: HashMap<AbstractNodeId, GridPos>, // ID -> Real Position
     pub edges: HashMap<AbstractNodeId, Vec<AbstractEdge>>,
     
     // Lookups
-    // (cluster_x, cluster_y) -> List of Abstract Nodes belonging to this cluster
+    position_to_id: HashMap<GridPos, AbstractNodeId>,
+    // Top-left cell of a cluster -> List of Abstract Nodes belonging to this cluster
     cluster_nodes: HashMap<(usize, usize), Vec<AbstractNodeId>>,
+    // Top-left cell of a split cluster -> The sub-clusters replacing it
+    split_clusters: HashMap<(usize, usize), Vec<ClusterRect>>,
+
+    // Subscription to `base_grid` edits, see `attach`
+    listener: Option<ChangeListener>,
 }
 
 impl HierarchicalGrid {
@@ -34,14 +141,42 @@ impl HierarchicalGrid {
         let mut hp = Self {
             base_grid,
             cluster_size,
-            nodes: Vec::new(),
+            nodes: HashMap::new(),
             edges: HashMap::new(),
+            position_to_id: HashMap::new(),
             cluster_nodes: HashMap::new(),
+            split_clusters: HashMap::new(),
+            listener: None,
         };
         hp.preprocess();
         hp
     }
 
+    /// Starts queueing edits made to `base_grid`; `apply_pending_changes` folds them into
+    /// the abstract graph.
+    pub fn attach(&mut self) {
+        if self.listener.is_none() {
+            self.listener = Some(self.base_grid.subscribe());
+        }
+    }
+
+    /// Rebuilds the abstract graph if `base_grid` changed since the last call.
+    /// Returns true if it did. Always false unless `attach` was called.
+    /// The rebuild starts from full-size clusters, undoing `split_large_clusters`.
+    pub fn apply_pending_changes(&mut self) -> bool {
+        let Some(listener) = &self.listener else { return false };
+        if listener.drain().is_empty() {
+            return false;
+        }
+        self.nodes.clear();
+        self.edges.clear();
+        self.position_to_id.clear();
+        self.cluster_nodes.clear();
+        self.split_clusters.clear();
+        self.preprocess();
+        true
+    }
+
     fn preprocess(&mut self) {
         self.build_abstract_nodes();
         self.build_intra_cluster_edges();
@@ -52,8 +187,8 @@ impl HierarchicalGrid {
         let h = self.base_grid.height;
         let cs = self.cluster_size;
         
-        let cluster_cols = (w + cs - 1) / cs;
-        let cluster_rows = (h + cs - 1) / cs;
+        let cluster_cols = w.div_ceil(cs);
+        let cluster_rows = h.div_ceil(cs);
 
         // Vertical borders (between cluster (cx, cy) and (cx+1, cy))
         for cy in 0..cluster_rows {
@@ -139,28 +274,154 @@ impl HierarchicalGrid {
             (GridPos { x: mid as i32, y: fixed as i32 }, GridPos { x: mid as i32, y: neighbor_fixed as i32 })
         };
 
-        let id1 = self.add_node(pos1);
-        let id2 = self.add_node(pos2);
+        let id1 = self.find_or_add_node(pos1);
+        let id2 = self.find_or_add_node(pos2);
 
         // Add "Inter-edge" (cost 1.0, immediate neighbor)
-        self.add_edge(id1, id2, 1.0, vec![pos1, pos2]);
-        self.add_edge(id2, id1, 1.0, vec![pos2, pos1]);
+        let width = end - start + 1;
+        self.add_edge(id1, id2, 1.0, vec![pos1, pos2], width);
+        self.add_edge(id2, id1, 1.0, vec![pos2, pos1], width);
     }
 
-    fn add_node(&mut self, pos: GridPos) -> AbstractNodeId {
-        let id = AbstractNodeId(self.nodes.len());
-        self.nodes.push(pos);
+    // A cell on two borders of its cluster (a corner) can be an entrance twice; it gets one node
+    fn find_or_add_node(&mut self, pos: GridPos) -> AbstractNodeId {
+        if let Some(&id) = self.position_to_id.get(&pos) {
+            return id;
+        }
+        let (x, y) = self.base_grid.index_of(pos).expect("entrances lie on the grid").coords(self.base_grid.width);
+        let hashed = AbstractNodeId::for_node(pos, (x / self.cluster_size, y / self.cluster_size));
+        // `position_to_id` decides which node a position is; a colliding hash just moves on
+        let id = hashed.first_free(|id| self.nodes.contains_key(id));
+        self.nodes.insert(id, pos);
+        self.position_to_id.insert(pos, id);
         self.edges.insert(id, Vec::new());
-        
-        let cx = pos.x as usize / self.cluster_size;
-        let cy = pos.y as usize / self.cluster_size;
-        self.cluster_nodes.entry((cx, cy)).or_default().push(id);
+        self.cluster_nodes.entry(self.cluster_of(pos)).or_default().push(id);
         
         id
     }
 
-    fn add_edge(&mut self, from: AbstractNodeId, to: AbstractNodeId, cost: f32, path: Vec<GridPos>) {
-        self.edges.get_mut(&from).unwrap().push(AbstractEdge { target: to, cost, path });
+    // Key (top-left cell) of the cluster or sub-cluster containing `pos`. Positions off the
+    // grid (whose searches fail anyway) get a key from their coordinates clamped at 0.
+    fn cluster_of(&self, pos: GridPos) -> (usize, usize) {
+        let cs = self.cluster_size;
+        let (x, y) = match self.base_grid.index_of(pos) {
+            Some(cell) => cell.coords(self.base_grid.width),
+            None => (pos.x.max(0) as usize, pos.y.max(0) as usize),
+        };
+        let top = (x / cs * cs, y / cs * cs);
+        self.split_clusters
+            .get(&top)
+            .and_then(|rects| rects.iter().find(|r| (r.0..r.0 + r.2).contains(&x) && (r.1..r.1 + r.3).contains(&y)))
+            .map_or(top, |r| (r.0, r.1))
+    }
+
+    // Bounds of the cluster or sub-cluster with key `key`, clipped to the grid
+    fn cluster_rect(&self, key: (usize, usize)) -> ClusterRect {
+        let cs = self.cluster_size;
+        let top = (key.0 / cs * cs, key.1 / cs * cs);
+        if let Some(rect) = self.split_clusters.get(&top).and_then(|rects| rects.iter().find(|r| (r.0, r.1) == key)) {
+            return *rect;
+        }
+        (key.0, key.1, cs.min(self.base_grid.width - key.0), cs.min(self.base_grid.height - key.1))
+    }
+
+    /// Number of abstract nodes in each cluster that has any, keyed by the cluster's top-left
+    /// cell and sorted by it. Sub-clusters from `split_large_clusters` are listed separately.
+    pub fn cluster_node_counts(&self) -> Vec<((usize, usize), usize)> {
+        let mut counts: Vec<((usize, usize), usize)> =
+            self.cluster_nodes.iter().map(|(&key, nodes)| (key, nodes.len())).collect();
+        counts.sort_unstable();
+        counts
+    }
+
+    /// Halves every cluster with more than `max_nodes_per_cluster` abstract nodes across its
+    /// longer side, repeating until each part is small enough or a single cell. Entrances are
+    /// added along the new internal borders, and intra-cluster edges are rebuilt for the split
+    /// clusters only. Keeps the O(N²) edge computation in check for open areas with many
+    /// entrances.
+    pub fn split_large_clusters(&mut self, max_nodes_per_cluster: usize) {
+        let mut pending: Vec<(usize, usize)> = self
+            .cluster_nodes
+            .iter()
+            .filter(|(_, nodes)| nodes.len() > max_nodes_per_cluster)
+            .map(|(&key, _)| key)
+            .collect();
+        pending.sort_unstable();
+        let mut rebuilt = Vec::new();
+
+        while let Some(key) = pending.pop() {
+            let (x, y, w, h) = self.cluster_rect(key);
+            if w < 2 && h < 2 {
+                rebuilt.push(key);
+                continue;
+            }
+            let (first, second) = if w >= h {
+                ((x, y, w / 2, h), (x + w / 2, y, w - w / 2, h))
+            } else {
+                ((x, y, w, h / 2), (x, y + h / 2, w, h - h / 2))
+            };
+
+            // Old intra-cluster edges, between two of this cluster's nodes
+            let members = self.cluster_nodes.remove(&key).unwrap_or_default();
+            let member_set: HashSet<AbstractNodeId> = members.iter().copied().collect();
+            for id in &members {
+                if let Some(edges) = self.edges.get_mut(id) {
+                    edges.retain(|e| !member_set.contains(&e.target));
+                }
+            }
+            rebuilt.retain(|k| *k != key);
+
+            let top = (x / self.cluster_size * self.cluster_size, y / self.cluster_size * self.cluster_size);
+            let rects = self.split_clusters.entry(top).or_default();
+            rects.retain(|r| (r.0, r.1) != key);
+            rects.extend([first, second]);
+            for id in members {
+                let pos = self.nodes[&id];
+                self.cluster_nodes.entry(self.cluster_of(pos)).or_default().push(id);
+            }
+            if w >= h {
+                self.detect_entrances(second.0 - 1, y, y + h, true, second.0);
+            } else {
+                self.detect_entrances(second.1 - 1, x, x + w, false, second.1);
+            }
+
+            for part in [(first.0, first.1), (second.0, second.1)] {
+                let count = self.cluster_nodes.get(&part).map_or(0, Vec::len);
+                if count > max_nodes_per_cluster {
+                    pending.push(part);
+                } else {
+                    rebuilt.push(part);
+                }
+            }
+        }
+
+        rebuilt.sort_unstable();
+        self.add_cluster_edges(&rebuilt, rebuilt.len() > 50);
+    }
+
+    /// The abstract node at `pos`, if `pos` is an entrance in the current build.
+    pub fn abstract_id_for_pos(&self, pos: GridPos) -> Option<AbstractNodeId> {
+        self.position_to_id.get(&pos).copied()
+    }
+
+    /// Position of abstract node `id`, if it exists in the current build.
+    pub fn pos_for_abstract_id(&self, id: AbstractNodeId) -> Option<GridPos> {
+        self.nodes.get(&id).copied()
+    }
+
+    /// Width in cells of the border opening between `from` and `to`, if they are the two
+    /// sides of an entrance. Corridors inside a cluster are not measured.
+    pub fn entrance_width_between(&self, from: AbstractNodeId, to: AbstractNodeId) -> Option<usize> {
+        self.edges
+            .get(&from)?
+            .iter()
+            .filter(|e| e.target == to && e.entrance_width != usize::MAX)
+            .map(|e| e.entrance_width)
+            .max()
+    }
+
+    fn add_edge(&mut self, from: AbstractNodeId, to: AbstractNodeId, cost: f32, path: Vec<GridPos>, entrance_width: usize) {
+        self.edges.get_mut(&from).unwrap().push(AbstractEdge { target: to, cost, path, entrance_width });
     }
 
     fn process_cluster(&self, cluster_coords: &(usize, usize)) -> Vec<(AbstractNodeId, AbstractNodeId, f32, Vec<GridPos>)> {
@@ -173,8 +434,8 @@ impl HierarchicalGrid {
                     for j in (i + 1)..nodes.len() {
                         let id_a = nodes[i];
                         let id_b = nodes[j];
-                        let pos_a = self.nodes[id_a.0];
-                        let pos_b = self.nodes[id_b.0];
+                        let pos_a = self.nodes[&id_a];
+                        let pos_b = self.nodes[&id_b];
 
                         let result = astar(
                             &self.base_grid, 
@@ -201,7 +462,13 @@ impl HierarchicalGrid {
         let clusters: Vec<(usize, usize)> = self.cluster_nodes.keys().cloned().collect();
         
         // Use parallel execution only if we have enough work (threshold > 50 clusters)
-        let new_edges: Vec<(AbstractNodeId, AbstractNodeId, f32, Vec<GridPos>)> = if clusters.len() > 50 {
+        self.add_cluster_edges(&clusters, clusters.len() > 50);
+    }
+
+    // Each node's intra-cluster edges come from its own cluster only, so the order clusters
+    // are processed in doesn't change the result
+    fn add_cluster_edges(&mut self, clusters: &[(usize, usize)], parallel: bool) {
+        let new_edges: Vec<(AbstractNodeId, AbstractNodeId, f32, Vec<GridPos>)> = if parallel {
             clusters.par_iter()
                 .flat_map(|c| self.process_cluster(c))
                 .collect()
@@ -212,36 +479,187 @@ impl HierarchicalGrid {
         };
 
         for (from, to, cost, path) in new_edges {
-            self.add_edge(from, to, cost, path);
+            self.add_edge(from, to, cost, path, usize::MAX);
         }
     }
     
+    /// Runs only the abstract search: start and goal are linked to the abstract nodes of
+    /// their clusters by straight-line estimates instead of local A*, and edges are not
+    /// refined. Start and goal in the same cluster give an empty `path`.
+    pub fn find_abstract_path(&self, start: GridPos, goal: GridPos) -> AbstractPathResult {
+        let estimate = Diagonal::for_grid(&self.base_grid);
+        let cluster_of = |p: GridPos| self.cluster_of(p);
+
+        if self.base_grid.is_blocked(start.x, start.y) || self.base_grid.is_blocked(goal.x, goal.y) {
+            return AbstractPathResult { path: vec![], positions: vec![], estimated_cost: 0.0, status: PathStatus::NotFound };
+        }
+        if cluster_of(start) == cluster_of(goal) {
+            return AbstractPathResult {
+                path: vec![],
+                positions: vec![],
+                estimated_cost: estimate.estimate(&start, &goal),
+                status: PathStatus::Found,
+            };
+        }
+
+        let link = |pos: GridPos| -> Vec<(AbstractNodeId, f32)> {
+            self.cluster_nodes
+                .get(&cluster_of(pos))
+                .map(|nodes| nodes.iter().map(|&id| (id, estimate.estimate(&pos, &self.nodes[&id]))).collect())
+                .unwrap_or_default()
+        };
+        let (start_links, goal_links) = (link(start), link(goal));
+        if start_links.is_empty() || goal_links.is_empty() {
+            return AbstractPathResult {
+                path: vec![],
+                positions: vec![],
+                estimated_cost: 0.0,
+                status: PathStatus::NoAbstractConnection,
+            };
+        }
+        let result = self.search_abstract(goal, &Euclidean, &start_links, &goal_links, AStarConfig::default(), 1);
+
+        // Drop the virtual start and goal
+        let path: Vec<AbstractNodeId> = result
+            .path
+            .into_iter()
+            .filter(|id| *id != VIRTUAL_START && *id != VIRTUAL_GOAL)
+            .collect();
+        AbstractPathResult {
+            positions: path.iter().map(|id| self.nodes[id]).collect(),
+            path,
+            estimated_cost: result.cost,
+            status: result.status,
+        }
+    }
+
+    // A* over the abstract graph from VIRTUAL_START to VIRTUAL_GOAL, through entrances at
+    // least `min_width` cells wide
+    fn search_abstract<H: Heuristic<GridPos>>(
+        &self,
+        goal: GridPos,
+        heuristic: &H,
+        start_edges: &[(AbstractNodeId, f32)],
+        goal_edges: &[(AbstractNodeId, f32)],
+        config: AStarConfig,
+        min_width: usize,
+    ) -> PathResult<AbstractNodeId> {
+        let search_graph = AbstractSearchGraph {
+            hp: self,
+            start_edges,
+            goal_edges,
+            min_width,
+        };
+        let search_heuristic = AbstractHeuristic {
+            hp: self,
+            goal_pos: goal,
+            inner: heuristic,
+        };
+        astar(
+            &search_graph,
+            &search_heuristic,
+            VIRTUAL_START,
+            VIRTUAL_GOAL,
+            config
+        )
+    }
+    
     pub fn find_path(&self, start: GridPos, goal: GridPos) -> PathResult<GridPos> {
+        self.find_path_with_config(start, goal, AStarConfig::default())
+    }
+
+    /// `find_path` with one time limit for the whole query: `config.deadline`, or `timeout`
+    /// counted from the call, bounds every internal search (connections, abstract search and
+    /// fallback) together. Other `config` fields are not used. Running out of time gives
+    /// `PartialTimeout`, with the partial start connection as the path if that is where it
+    /// happened and an empty path otherwise.
+    pub fn find_path_with_config(&self, start: GridPos, goal: GridPos, config: AStarConfig) -> PathResult<GridPos> {
+        self.find_path_through(start, goal, &Euclidean, &Self::shared_limit(config), 1)
+    }
+
+    /// `find_path` with `heuristic` for every search and separate settings for the abstract
+    /// and the grid-level searches, e.g. a generous timeout for the former and a tight
+    /// iteration cap (see `HpaConfig::for_cluster_size`) for the latter. When local searches
+    /// hit their `max_iterations` and leave the start or goal unconnected, the result is
+    /// `PartialMaxIter` with an empty path.
+    pub fn find_path_with_hpa_config<H: Heuristic<GridPos>>(
+        &self,
+        start: GridPos,
+        goal: GridPos,
+        heuristic: &H,
+        hpa_config: &HpaConfig,
+    ) -> PathResult<GridPos> {
+        let pinned = HpaConfig {
+            abstract_config: hpa_config.abstract_config.pin_deadline(),
+            local_config: hpa_config.local_config.pin_deadline(),
+        };
+        self.find_path_through(start, goal, heuristic, &pinned, 1)
+    }
+
+    // Both levels limited by `config`'s time budget only, as `find_path_with_config` documents
+    fn shared_limit(config: AStarConfig) -> HpaConfig {
+        let limit = AStarConfig { deadline: config.pin_deadline().deadline, ..AStarConfig::default() };
+        HpaConfig { abstract_config: limit, local_config: limit }
+    }
+
+    /// `find_path` for groups that can't squeeze through narrow gaps: the route only crosses
+    /// cluster borders through openings at least `min_width` cells wide. Narrow corridors
+    /// inside a cluster are not detected. When no route has wide enough openings (including a
+    /// start or goal with no wide enough way out of its cluster) the status is
+    /// `NoAbstractConnection`.
+    pub fn find_path_min_width(&self, start: GridPos, goal: GridPos, min_width: usize) -> PathResult<GridPos> {
+        self.find_path_through(start, goal, &Euclidean, &HpaConfig::default(), min_width)
+    }
+
+    // `configs` have their deadlines pinned already. The whole query is timed, whichever
+    // internal searches it runs
+    fn find_path_through<H: Heuristic<GridPos>>(
+        &self,
+        start: GridPos,
+        goal: GridPos,
+        heuristic: &H,
+        configs: &HpaConfig,
+        min_width: usize,
+    ) -> PathResult<GridPos> {
+        let started = cfg!(feature = "timing").then(Instant::now);
+        self.search_through(start, goal, heuristic, configs, min_width).timed(started)
+    }
+
+    fn search_through<H: Heuristic<GridPos>>(
+        &self,
+        start: GridPos,
+        goal: GridPos,
+        heuristic: &H,
+        configs: &HpaConfig,
+        min_width: usize,
+    ) -> PathResult<GridPos> {
+        let local = configs.local_config;
+        let expired = || local.deadline.is_some_and(|d| Instant::now() > d);
+
         // 1. Insert Start and Goal as temporary nodes
         // But we can't modify self. So we build a temporary graph wrapper or
         // just do the logic ad-hoc. Ad-hoc is easier for this snippet.
         
-        // Start cluster
-        let s_cx = start.x as usize / self.cluster_size;
-        let s_cy = start.y as usize / self.cluster_size;
-        
-        // Goal cluster
-        let g_cx = goal.x as usize / self.cluster_size;
-        let g_cy = goal.y as usize / self.cluster_size;
+        let start_cluster = self.cluster_of(start);
+        let goal_cluster = self.cluster_of(goal);
         
         // If same cluster, just run normal A*
-        if s_cx == g_cx && s_cy == g_cy {
-             return astar(&self.base_grid, &Euclidean, start, goal, AStarConfig::default());
+        if start_cluster == goal_cluster {
+             return astar(&self.base_grid, heuristic, start, goal, local);
         }
 
         // 2. Connect Start to its cluster's abstract nodes
-        let mut start_edges: Vec<(AbstractNodeId, f32, Vec<GridPos>)> = Vec::new();
-        if let Some(nodes) = self.cluster_nodes.get(&(s_cx, s_cy)) {
+        let mut start_edges: Vec<Connection> = Vec::new();
+        let mut start_capped = false;
+        if let Some(nodes) = self.cluster_nodes.get(&start_cluster) {
             for &target_id in nodes {
-                let target_pos = self.nodes[target_id.0];
-                let res = astar(&self.base_grid, &Euclidean, start, target_pos, AStarConfig::default());
-                if res.status == PathStatus::Found {
-                    start_edges.push((target_id, res.cost, res.path));
+                let target_pos = self.nodes[&target_id];
+                let res = astar(&self.base_grid, heuristic, start, target_pos, local);
+                match res.status {
+                    PathStatus::PartialTimeout => return res,
+                    PathStatus::PartialMaxIter => start_capped = true,
+                    PathStatus::Found => start_edges.push((target_id, res.cost, res.path)),
+                    _ => {}
                 }
             }
         }
@@ -249,130 +667,63 @@ impl HierarchicalGrid {
         // 3. Connect Goal to its cluster's abstract nodes (incoming)
         // Effectively we want edges FROM abstract nodes TO goal.
         // Since graph is undirected (mostly), we calculate From Goal To Abstract and reverse.
-        let mut goal_edges: Vec<(AbstractNodeId, f32, Vec<GridPos>)> = Vec::new();
-        if let Some(nodes) = self.cluster_nodes.get(&(g_cx, g_cy)) {
-            for &src_id in nodes {
-                let src_pos = self.nodes[src_id.0];
-                let res = astar(&self.base_grid, &Euclidean, src_pos, goal, AStarConfig::default());
-                if res.status == PathStatus::Found {
-                    goal_edges.push((src_id, res.cost, res.path));
-                }
-            }
+        let (goal_edges, goal_capped) = self.connect_goal(goal, heuristic, local);
+        if expired() {
+            return PathResult {
+                path: vec![],
+                cost: 0.0,
+                length: 0.0,
+                cumulative_costs: vec![],
+                nodes_expanded: 0,
+                best_g_score: 0.0,
+                nodes_in_open_set: 0,
+                status: PathStatus::PartialTimeout,
+                elapsed: None,
+            };
         }
 
-        // 4. Run A* on Abstract Graph
-        // Nodes: 0..N (abstract), N+1 (Start), N+2 (Goal)
-        // We use a custom Graph struct for the search
-        
-        let start_id_virtual = AbstractNodeId(usize::MAX - 1);
-        let goal_id_virtual = AbstractNodeId(usize::MAX);
-        
-        struct AbstractSearchGraph<'a> {
-            hp: &'a HierarchicalGrid,
-            start_edges: &'a [(AbstractNodeId, f32, Vec<GridPos>)],
-            goal_edges: &'a [(AbstractNodeId, f32, Vec<GridPos>)],
-            #[allow(dead_code)]
-            start_pos: GridPos,
-            #[allow(dead_code)]
-            goal_pos: GridPos,
-            start_id: AbstractNodeId,
-            goal_id: AbstractNodeId,
-        }
-        
-        impl<'a> Graph for AbstractSearchGraph<'a> {
-            type Node = AbstractNodeId;
-            
-            fn is_passable(&self, _node: &Self::Node) -> bool { true }
-            
-            fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
-            where F: FnMut(Self::Node, f32) {
-                if *node == self.start_id {
-                    for (target, cost, _) in self.start_edges {
-                        visit(*target, *cost);
-                    }
-                } else if *node == self.goal_id {
-                    // Goal has no outgoing neighbors
-                } else {
-                    // Real abstract node
-                    if let Some(edges) = self.hp.edges.get(node) {
-                        for edge in edges {
-                            visit(edge.target, edge.cost);
-                        }
-                    }
-                    // Also check if we can reach goal directly
-                    // (This is the reverse of goal_edges)
-                    for (src, cost, _) in self.goal_edges {
-                        if src == node {
-                            visit(self.goal_id, *cost);
-                        }
-                    }
-                }
-            }
-        }
-        
-        struct AbstractHeuristic<'a> {
-            hp: &'a HierarchicalGrid,
-            goal_pos: GridPos,
-            goal_id: AbstractNodeId,
-        }
-        
-        impl<'a> Heuristic<AbstractNodeId> for AbstractHeuristic<'a> {
-            fn estimate(&self, from: &AbstractNodeId, _to: &AbstractNodeId) -> f32 {
-                // We always estimate to the real goal position
-                let from_pos = if *from == AbstractNodeId(usize::MAX - 1) {
-                    // Start virtual node - strictly speaking heuristic not needed for start,
-                    // but let's say dist is large. 
-                    // Actually A* calls h(start, goal).
-                    // We don't store start pos in Heuristic? We can.
-                    // But we only need from_pos.
-                    return 0.0; // Hacky?
-                } else if *from == self.goal_id {
-                    return 0.0;
-                } else {
-                    self.hp.nodes[from.0]
-                };
-                
-                // Euclidean
-                let dx = (from_pos.x - self.goal_pos.x) as f32;
-                let dy = (from_pos.y - self.goal_pos.y) as f32;
-                (dx*dx + dy*dy).sqrt()
-            }
+        // Out of local iterations before reaching any entrance: not known to be sealed
+        if (start_edges.is_empty() && start_capped) || (goal_edges.is_empty() && goal_capped) {
+            return PathResult {
+                path: vec![],
+                cost: 0.0,
+                length: 0.0,
+                cumulative_costs: vec![],
+                nodes_expanded: 0,
+                best_g_score: 0.0,
+                nodes_in_open_set: 0,
+                status: PathStatus::PartialMaxIter,
+                elapsed: None,
+            };
         }
 
-        let search_graph = AbstractSearchGraph {
-            hp: self,
-            start_edges: &start_edges,
-            goal_edges: &goal_edges,
-            start_pos: start,
-            goal_pos: goal,
-            start_id: start_id_virtual,
-            goal_id: goal_id_virtual,
-        };
+        // Sealed start or goal: only a direct search between neighboring clusters can help
+        if start_edges.is_empty() || goal_edges.is_empty() {
+            return self.sealed_fallback(start, goal, heuristic, local, min_width);
+        }
 
-        // Heuristic needs to handle the virtual start node correctly if called
-        // But usually we map IDs.
-        // Let's just use 0.0 for virtual nodes in a wrapper.
-        // Actually, we can just implement Heuristic for AbstractSearchGraph
-        let search_heuristic = AbstractHeuristic {
-            hp: self,
-            goal_pos: goal,
-            goal_id: goal_id_virtual,
-        };
-        
-        let abstract_result = astar(
-            &search_graph,
-            &search_heuristic,
-            start_id_virtual,
-            goal_id_virtual,
-            AStarConfig::default()
-        );
+        // 4. Run A* on Abstract Graph
+        let start_costs: Vec<(AbstractNodeId, f32)> = start_edges.iter().map(|(id, c, _)| (*id, *c)).collect();
+        let goal_costs: Vec<(AbstractNodeId, f32)> = goal_edges.iter().map(|(id, c, _)| (*id, *c)).collect();
+        let abstract_result =
+            self.search_abstract(goal, heuristic, &start_costs, &goal_costs, configs.abstract_config, min_width);
         
         if abstract_result.status != PathStatus::Found {
+             // Only narrow openings lead on: the grid itself may still connect
+             let status = match abstract_result.status {
+                 PathStatus::NotFound if min_width > 1 => PathStatus::NoAbstractConnection,
+                 status => status,
+             };
              return PathResult {
                  path: vec![],
                  cost: 0.0,
+                 length: 0.0,
+                 cumulative_costs: vec![],
                  nodes_expanded: abstract_result.nodes_expanded,
-                 status: abstract_result.status,
+                 best_g_score: abstract_result.best_g_score,
+                 nodes_in_open_set: abstract_result.nodes_in_open_set,
+                 status,
+                 elapsed: None,
              };
         }
         
@@ -386,11 +737,11 @@ impl HierarchicalGrid {
             let current = ap[i];
             let next = ap[i+1];
             
-            let segment_path: &[GridPos] = if current == start_id_virtual {
+            let segment_path: &[GridPos] = if current == VIRTUAL_START {
                 // Start -> Next
                 let (_, _, ref p) = start_edges.iter().find(|(id, _, _)| *id == next).unwrap();
                 p
-            } else if next == goal_id_virtual {
+            } else if next == VIRTUAL_GOAL {
                 // Current -> Goal
                 let (_, _, ref p) = goal_edges.iter().find(|(id, _, _)| *id == current).unwrap();
                 p
@@ -410,10 +761,782 @@ impl HierarchicalGrid {
         }
         
         PathResult {
+            length: path_length(&self.base_grid, &full_path),
             path: full_path,
             cost: abstract_result.cost,
+            cumulative_costs: vec![], // Refined segments don't carry per-node costs
             nodes_expanded: abstract_result.nodes_expanded, // Note: this doesn't count low-level expansions
+            best_g_score: abstract_result.best_g_score,
+            nodes_in_open_set: abstract_result.nodes_in_open_set,
             status: PathStatus::Found,
+            elapsed: None,
+        }
+    }
+
+    // Paths from each abstract node of the goal's cluster to the goal, and whether any
+    // search stopped at `config.max_iterations`
+    fn connect_goal<H: Heuristic<GridPos>>(&self, goal: GridPos, heuristic: &H, config: AStarConfig) -> (Vec<Connection>, bool) {
+        let mut goal_edges = Vec::new();
+        let mut capped = false;
+        if let Some(nodes) = self.cluster_nodes.get(&self.cluster_of(goal)) {
+            for &src_id in nodes {
+                let src_pos = self.nodes[&src_id];
+                let res = astar(&self.base_grid, heuristic, src_pos, goal, config);
+                capped |= res.status == PathStatus::PartialMaxIter;
+                if res.status == PathStatus::Found {
+                    goal_edges.push((src_id, res.cost, res.path));
+                }
+            }
+        }
+        (goal_edges, capped)
+    }
+
+    // Paths from `start` to each reachable target, from one Dijkstra over the base grid that
+    // stops once every target is settled. Same costs as one A* per target.
+    fn connect_start(&self, start: GridPos, targets: &[(AbstractNodeId, GridPos)], config: AStarConfig) -> Vec<Connection> {
+        let deadline = config.deadline_from(None);
+        let mut best: HashMap<GridPos, (f32, Option<GridPos>)> = HashMap::new();
+        let mut settled: HashSet<GridPos> = HashSet::new();
+        let mut frontier = BinaryHeap::new();
+        best.insert(start, (0.0, None));
+        frontier.push(Frontier { cost: 0.0, pos: start });
+
+        while let Some(Frontier { cost, pos }) = frontier.pop() {
+            if !settled.insert(pos) {
+                continue; // Stale entry
+            }
+            if targets.iter().all(|(_, t)| settled.contains(t))
+                || config.max_iterations.is_some_and(|max| settled.len() > max)
+                || deadline.is_some_and(|d| Instant::now() > d)
+            {
+                break;
+            }
+            self.base_grid.neighbors(&pos, |n, step| {
+                let next = cost + step;
+                if best.get(&n).is_none_or(|&(c, _)| next < c) {
+                    best.insert(n, (next, Some(pos)));
+                    frontier.push(Frontier { cost: next, pos: n });
+                }
+            });
         }
+
+        targets
+            .iter()
+            .filter(|(_, pos)| settled.contains(pos))
+            .map(|&(id, pos)| {
+                let mut path = vec![pos];
+                while let Some(prev) = best[path.last().unwrap()].1 {
+                    path.push(prev);
+                }
+                path.reverse();
+                (id, best[&pos].0, path)
+            })
+            .collect()
+    }
+
+    // Sealed start or goal: the abstract graph can't help. Neighboring clusters may still
+    // connect directly (e.g. diagonally across a shared corner), so search just those two.
+    // That search doesn't know how wide the gap it finds is, so it is skipped for `min_width > 1`.
+    fn sealed_fallback<H: Heuristic<GridPos>>(
+        &self,
+        start: GridPos,
+        goal: GridPos,
+        heuristic: &H,
+        config: AStarConfig,
+        min_width: usize,
+    ) -> PathResult<GridPos> {
+        let (s, g) = (self.cluster_rect(self.cluster_of(start)), self.cluster_rect(self.cluster_of(goal)));
+        // Touching along a side or at a corner
+        let adjacent = s.0 <= g.0 + g.2 && g.0 <= s.0 + s.2 && s.1 <= g.1 + g.3 && g.1 <= s.1 + s.3;
+        if adjacent && min_width <= 1 {
+            let bounded = ClusterBounded {
+                base_grid: &self.base_grid,
+                clusters: [s, g],
+            };
+            let local = astar(&bounded, heuristic, start, goal, config);
+            if matches!(local.status, PathStatus::Found | PathStatus::PartialTimeout) {
+                return local;
+            }
+        }
+        PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: 0,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::NoAbstractConnection,
+            elapsed: None,
+        }
+    }
+
+    /// `find_path` from many starts to one goal, e.g. a squad ordered to the same spot.
+    /// The goal connections and a shortest-path tree over the abstract graph are built once;
+    /// each start then only connects to its cluster's abstract nodes (one Dijkstra for all of
+    /// them) and follows the tree. Results are in `starts` order, with the same status and
+    /// cost as `find_path`; among equal-cost routes a different one may be returned.
+    ///
+    /// `config` bounds each start's grid-level search; `AStarConfig::default()` matches `find_path`.
+    pub fn find_paths_grouped(&self, starts: &[GridPos], goal: GridPos, config: AStarConfig) -> Vec<PathResult<GridPos>> {
+        let cluster_of = |p: GridPos| self.cluster_of(p);
+        let (goal_edges, _) = self.connect_goal(goal, &Euclidean, AStarConfig::default());
+
+        // Backward Dijkstra from the goal over the abstract graph
+        let mut incoming: HashMap<AbstractNodeId, Vec<(AbstractNodeId, usize, f32)>> = HashMap::new();
+        for (&from, edges) in &self.edges {
+            for (k, edge) in edges.iter().enumerate() {
+                incoming.entry(edge.target).or_default().push((from, k, edge.cost));
+            }
+        }
+        let mut to_goal: HashMap<AbstractNodeId, (f32, Hop)> = HashMap::new();
+        let dist = |to_goal: &HashMap<AbstractNodeId, (f32, Hop)>, id: &AbstractNodeId| {
+            to_goal.get(id).map_or(f32::INFINITY, |&(d, _)| d)
+        };
+        let mut frontier = BinaryHeap::new();
+        for (i, &(id, cost, _)) in goal_edges.iter().enumerate() {
+            if cost < dist(&to_goal, &id) {
+                to_goal.insert(id, (cost, Hop::Goal(i)));
+                frontier.push(Frontier { cost, pos: id });
+            }
+        }
+        while let Some(Frontier { cost, pos: node }) = frontier.pop() {
+            if cost > dist(&to_goal, &node) {
+                continue;
+            }
+            for &(from, k, edge_cost) in incoming.get(&node).into_iter().flatten() {
+                let next = cost + edge_cost;
+                if next < dist(&to_goal, &from) {
+                    to_goal.insert(from, (next, Hop::Edge(k)));
+                    frontier.push(Frontier { cost: next, pos: from });
+                }
+            }
+        }
+
+        // Abstract nodes (with positions) of each start cluster, looked up once per cluster
+        let mut cluster_targets: HashMap<(usize, usize), Vec<(AbstractNodeId, GridPos)>> = HashMap::new();
+
+        starts
+            .iter()
+            .map(|&start| {
+                if cluster_of(start) == cluster_of(goal) {
+                    return astar(&self.base_grid, &Euclidean, start, goal, config);
+                }
+                let targets = cluster_targets.entry(cluster_of(start)).or_insert_with_key(|cluster| {
+                    self.cluster_nodes
+                        .get(cluster)
+                        .map(|ids| ids.iter().map(|&id| (id, self.nodes[&id])).collect())
+                        .unwrap_or_default()
+                });
+                let start_edges = self.connect_start(start, targets, config);
+                if start_edges.is_empty() || goal_edges.is_empty() {
+                    return self.sealed_fallback(start, goal, &Euclidean, config, 1);
+                }
+
+                let best = start_edges
+                    .iter()
+                    .map(|(id, cost, path)| (*id, cost + dist(&to_goal, id), path))
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                let Some((mut node, cost, start_path)) = best.filter(|b| b.1.is_finite()) else {
+                    return PathResult {
+                        path: vec![],
+                        cost: 0.0,
+                        length: 0.0,
+                        cumulative_costs: vec![],
+                        nodes_expanded: 0,
+                        best_g_score: 0.0,
+                        nodes_in_open_set: 0,
+                        status: PathStatus::NotFound,
+                        elapsed: None,
+                    };
+                };
+
+                // Follow the tree, appending each refined segment without its first cell
+                let mut path = start_path.clone();
+                loop {
+                    match to_goal[&node].1 {
+                        Hop::Goal(i) => {
+                            path.extend_from_slice(&goal_edges[i].2[1..]);
+                            break;
+                        }
+                        Hop::Edge(k) => {
+                            let edge = &self.edges[&node][k];
+                            path.extend_from_slice(&edge.path[1..]);
+                            node = edge.target;
+                        }
+                    }
+                }
+                PathResult {
+                    length: path_length(&self.base_grid, &path),
+                    path,
+                    cost,
+                    cumulative_costs: vec![],
+                    nodes_expanded: 0,
+                    best_g_score: cost,
+                    nodes_in_open_set: 0,
+                    status: PathStatus::Found,
+                    elapsed: None,
+                }
+            })
+            .collect()
+    }
+}
+
+// Min-heap entry for the Dijkstra searches in `find_paths_grouped`
+struct Frontier<N> {
+    cost: f32,
+    pos: N,
+}
+
+impl<N> PartialEq for Frontier<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl<N> Eq for Frontier<N> {}
+
+impl<N> Ord for Frontier<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl<N> PartialOrd for Frontier<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Progress of a `HierarchicalGridBuilder`, e.g. for a loading bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildProgress {
+    pub clusters_done: usize,
+    pub clusters_total: usize,
+    pub finished: bool,
+}
+
+/// Builds a `HierarchicalGrid` a slice at a time, so preprocessing a large map can be
+/// spread over frames. The result is identical to `HierarchicalGrid::new`.
+///
+/// The first `step` detects all entrances (one linear pass over the cluster borders); later
+/// steps connect entrances within clusters, in parallel batches of one cluster per thread,
+/// until the budget runs out. Every step makes progress even with a zero budget.
+pub struct HierarchicalGridBuilder {
+    grid: HierarchicalGrid,
+    clusters: Vec<(usize, usize)>, // Top-left cell of every cluster, row-major
+    clusters_done: usize,
+    entrances_done: bool,
+}
+
+impl HierarchicalGridBuilder {
+    pub fn new(base_grid: Grid2D, cluster_size: usize) -> Self {
+        let cols = base_grid.width.div_ceil(cluster_size);
+        let rows = base_grid.height.div_ceil(cluster_size);
+        let clusters = (0..rows)
+            .flat_map(|cy| (0..cols).map(move |cx| (cx * cluster_size, cy * cluster_size)))
+            .collect();
+        Self {
+            grid: HierarchicalGrid {
+                base_grid,
+                cluster_size,
+                nodes: HashMap::new(),
+                edges: HashMap::new(),
+                position_to_id: HashMap::new(),
+                cluster_nodes: HashMap::new(),
+                split_clusters: HashMap::new(),
+                listener: None,
+            },
+            clusters,
+            clusters_done: 0,
+            entrances_done: false,
+        }
+    }
+
+    /// Works until `budget` is spent (checked between batches) or the build is complete.
+    pub fn step(&mut self, budget: Duration) -> BuildProgress {
+        let started = Instant::now();
+        if !self.entrances_done {
+            self.grid.build_abstract_nodes();
+            self.entrances_done = true;
+            if started.elapsed() >= budget {
+                return self.progress();
+            }
+        }
+
+        let batch = rayon::current_num_threads().max(1);
+        while self.clusters_done < self.clusters.len() {
+            let end = (self.clusters_done + batch).min(self.clusters.len());
+            let chunk = &self.clusters[self.clusters_done..end];
+            self.grid.add_cluster_edges(chunk, batch > 1);
+            self.clusters_done = end;
+            if started.elapsed() >= budget {
+                break;
+            }
+        }
+        self.progress()
+    }
+
+    pub fn progress(&self) -> BuildProgress {
+        BuildProgress {
+            clusters_done: self.clusters_done,
+            clusters_total: self.clusters.len(),
+            finished: self.entrances_done && self.clusters_done == self.clusters.len(),
+        }
+    }
+
+    /// Completes any remaining work and returns the grid.
+    pub fn finish(mut self) -> HierarchicalGrid {
+        while !self.progress().finished {
+            self.step(Duration::MAX);
+        }
+        self.grid
+    }
+}
+
+// The abstract graph plus one query's virtual start and goal nodes
+struct AbstractSearchGraph<'a> {
+    hp: &'a HierarchicalGrid,
+    start_edges: &'a [(AbstractNodeId, f32)],
+    goal_edges: &'a [(AbstractNodeId, f32)], // Abstract node -> goal
+    min_width: usize,                        // Narrower entrances are skipped
+}
+
+impl<'a> Graph for AbstractSearchGraph<'a> {
+    type Node = AbstractNodeId;
+
+    fn is_passable(&self, _node: &Self::Node) -> bool { true }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where F: FnMut(Self::Node, f32) {
+        if *node == VIRTUAL_START {
+            for (target, cost) in self.start_edges {
+                visit(*target, *cost);
+            }
+        } else if *node == VIRTUAL_GOAL {
+            // Goal has no outgoing neighbors
+        } else {
+            // Real abstract node
+            if let Some(edges) = self.hp.edges.get(node) {
+                for edge in edges.iter().filter(|e| e.entrance_width >= self.min_width) {
+                    visit(edge.target, edge.cost);
+                }
+            }
+            // Also check if we can reach goal directly
+            for (src, cost) in self.goal_edges {
+                if src == node {
+                    visit(VIRTUAL_GOAL, *cost);
+                }
+            }
+        }
+    }
+}
+
+// The base grid restricted to a pair of clusters, for the sealed-cluster fallback
+struct ClusterBounded<'a> {
+    base_grid: &'a Grid2D,
+    clusters: [ClusterRect; 2],
+}
+
+impl ClusterBounded<'_> {
+    fn contains(&self, pos: &GridPos) -> bool {
+        let Some(cell) = self.base_grid.index_of(*pos) else {
+            return false;
+        };
+        let (x, y) = cell.coords(self.base_grid.width);
+        self.clusters.iter().any(|r| (r.0..r.0 + r.2).contains(&x) && (r.1..r.1 + r.3).contains(&y))
+    }
+}
+
+impl Graph for ClusterBounded<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.contains(node) && self.base_grid.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where F: FnMut(Self::Node, f32) {
+        self.base_grid.neighbors(node, |n, cost| {
+            if self.contains(&n) {
+                visit(n, cost);
+            }
+        });
+    }
+}
+
+struct AbstractHeuristic<'a, H> {
+    hp: &'a HierarchicalGrid,
+    goal_pos: GridPos,
+    inner: &'a H,
+}
+
+impl<H: Heuristic<GridPos>> Heuristic<AbstractNodeId> for AbstractHeuristic<'_, H> {
+    fn estimate(&self, from: &AbstractNodeId, _to: &AbstractNodeId) -> f32 {
+        // Virtual nodes: the start is only estimated once, the goal is the target
+        if *from == VIRTUAL_START || *from == VIRTUAL_GOAL {
+            return 0.0;
+        }
+        // From the node's grid position to the real goal position
+        self.inner.estimate(&self.hp.nodes[from], &self.goal_pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::rng::SplitMix64;
+
+    // 3x3 clusters of 10 cells, walled so the only route snakes through all nine
+    fn serpentine() -> HierarchicalGrid {
+        let mut grid = Grid2D::new(30, 30, DiagonalMode::Never);
+        grid.set_region_blocked((10, 0, 1, 30), true);
+        grid.set_region_blocked((20, 0, 1, 30), true);
+        grid.set_region_blocked((0, 10, 30, 1), true);
+        grid.set_region_blocked((0, 20, 30, 1), true);
+        for (x, y) in [(10, 5), (20, 5), (25, 10), (20, 15), (10, 15), (5, 20), (10, 25), (20, 25)] {
+            grid.set_blocked(x, y, false);
+        }
+        HierarchicalGrid::new(grid, 10)
+    }
+
+    fn cluster_sequence(hp: &HierarchicalGrid, positions: &[GridPos]) -> Vec<(i32, i32)> {
+        let cs = hp.cluster_size as i32;
+        let mut seq: Vec<(i32, i32)> = positions.iter().map(|p| (p.x / cs, p.y / cs)).collect();
+        seq.dedup();
+        seq
+    }
+
+    #[test]
+    fn abstract_and_local_searches_take_separate_configs() {
+        let hp = serpentine();
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 27, y: 27 });
+        let plain = hp.find_path(start, goal);
+
+        let manhattan = hp.find_path_with_hpa_config(start, goal, &Manhattan, &HpaConfig::default());
+        assert_eq!(manhattan.status, PathStatus::Found);
+        assert!((manhattan.cost - plain.cost).abs() < 1e-4);
+        let capped = hp.find_path_with_hpa_config(start, goal, &Manhattan, &HpaConfig::for_cluster_size(10));
+        assert_eq!((capped.status, capped.path.len()), (PathStatus::Found, manhattan.path.len()));
+
+        // Local searches too short to reach an entrance leave the start unconnected
+        let starved = HpaConfig {
+            local_config: AStarConfig { max_iterations: Some(4), ..AStarConfig::default() },
+            ..HpaConfig::default()
+        };
+        let res = hp.find_path_with_hpa_config(start, goal, &Manhattan, &starved);
+        assert_eq!(res.status, PathStatus::PartialMaxIter);
+        assert!(res.path.is_empty());
+        // Near enough to the entrance, the same cap is fine; the abstract search isn't capped
+        let near = hp.find_path_with_hpa_config(GridPos { x: 9, y: 5 }, GridPos { x: 11, y: 5 }, &Manhattan, &starved);
+        assert_eq!(near.status, PathStatus::Found);
+
+        // A local cost cap below every start-to-entrance path leaves the start unconnected, so
+        // the query fails however much room the abstract search gets
+        let short_reach = HpaConfig {
+            local_config: AStarConfig { max_cost: Some(1.5), ..AStarConfig::default() },
+            abstract_config: AStarConfig { max_iterations: Some(100_000), ..AStarConfig::default() },
+        };
+        let res = hp.find_path_with_hpa_config(start, goal, &Manhattan, &short_reach);
+        assert_eq!((res.status, res.path.is_empty()), (PathStatus::NoAbstractConnection, true));
+        // The cap is on local searches only: one covering the connections still finds the path
+        let local_config = AStarConfig { max_cost: Some(40.0), ..AStarConfig::default() };
+        let reach = HpaConfig { local_config, ..short_reach };
+        assert_eq!(hp.find_path_with_hpa_config(start, goal, &Manhattan, &reach).status, PathStatus::Found);
+
+        // The abstract search has its own limit
+        let rushed = HpaConfig {
+            abstract_config: AStarConfig { max_iterations: Some(2), ..AStarConfig::default() },
+            ..HpaConfig::default()
+        };
+        assert_eq!(hp.find_path_with_hpa_config(start, goal, &Manhattan, &rushed).status, PathStatus::PartialMaxIter);
+    }
+
+    #[test]
+    fn passed_deadline_gives_partial_timeout() {
+        let hp = serpentine();
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 27, y: 27 });
+        let passed = Instant::now();
+        std::thread::sleep(Duration::from_millis(2));
+
+        let config = AStarConfig { deadline: Some(passed), ..AStarConfig::default() };
+        assert_eq!(hp.find_path_with_config(start, goal, config).status, PathStatus::PartialTimeout);
+        // Either level running out of time ends the query
+        let local = HpaConfig { local_config: config, ..HpaConfig::default() };
+        assert_eq!(hp.find_path_with_hpa_config(start, goal, &Manhattan, &local).status, PathStatus::PartialTimeout);
+        let abstract_only = HpaConfig { abstract_config: config, ..HpaConfig::default() };
+        let res = hp.find_path_with_hpa_config(start, goal, &Manhattan, &abstract_only);
+        assert_eq!((res.status, res.path.is_empty()), (PathStatus::PartialTimeout, true));
+        // Time left: found as usual
+        let later = AStarConfig { deadline: Some(Instant::now() + Duration::from_secs(60)), ..AStarConfig::default() };
+        assert_eq!(hp.find_path_with_config(start, goal, later).status, PathStatus::Found);
+    }
+
+    #[test]
+    fn abstract_path_crosses_same_clusters_as_refined_path() {
+        let hp = serpentine();
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 27, y: 27 });
+
+        let refined = hp.find_path(start, goal);
+        let abstract_path = hp.find_abstract_path(start, goal);
+        assert_eq!(refined.status, PathStatus::Found);
+        assert_eq!(abstract_path.status, PathStatus::Found);
+        assert_eq!(abstract_path.positions.len(), abstract_path.path.len());
+        for (id, pos) in abstract_path.path.iter().zip(&abstract_path.positions) {
+            assert_eq!(hp.pos_for_abstract_id(*id), Some(*pos));
+        }
+
+        let mut waypoints = vec![start];
+        waypoints.extend_from_slice(&abstract_path.positions);
+        waypoints.push(goal);
+        let expected = vec![(0, 0), (1, 0), (2, 0), (2, 1), (1, 1), (0, 1), (0, 2), (1, 2), (2, 2)];
+        assert_eq!(cluster_sequence(&hp, &refined.path), expected);
+        assert_eq!(cluster_sequence(&hp, &waypoints), expected);
+        assert!(abstract_path.estimated_cost > 0.0);
+    }
+
+    #[test]
+    fn abstract_path_same_cluster_and_blocked_goal() {
+        let hp = serpentine();
+        let same = hp.find_abstract_path(GridPos { x: 1, y: 1 }, GridPos { x: 4, y: 5 });
+        assert_eq!(same.status, PathStatus::Found);
+        assert!(same.path.is_empty());
+        assert!((same.estimated_cost - 7.0).abs() < 1e-4);
+
+        let blocked = hp.find_abstract_path(GridPos { x: 1, y: 1 }, GridPos { x: 10, y: 0 });
+        assert_eq!(blocked.status, PathStatus::NotFound);
+    }
+
+    #[test]
+    fn attached_grid_edits_rebuild_abstract_graph() {
+        let mut hp = serpentine();
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 27, y: 27 });
+        assert!(!hp.apply_pending_changes(), "not attached");
+
+        hp.attach();
+        assert!(!hp.apply_pending_changes());
+        // Close the first gap in the serpentine, sealing the start cluster
+        hp.base_grid.set_blocked(10, 5, true);
+        assert!(hp.apply_pending_changes());
+        assert_eq!(hp.find_path(start, goal).status, PathStatus::NoAbstractConnection);
+
+        hp.base_grid.set_blocked(10, 5, false);
+        assert!(hp.apply_pending_changes());
+        assert_eq!(hp.find_path(start, goal).status, PathStatus::Found);
+    }
+
+    // Two 10x10 clusters side by side on a 20x20 map, with cluster (0, 0) walled off on its
+    // straight borders. `corner_open` leaves the diagonal step (9, 9) -> (10, 10) available.
+    fn sealed_corner(corner_open: bool) -> HierarchicalGrid {
+        let mut grid = Grid2D::new(20, 20, DiagonalMode::Always);
+        grid.set_region_blocked((10, 0, 1, 11), true);
+        grid.set_region_blocked((0, 10, 11, 1), true);
+        grid.set_blocked(10, 10, !corner_open);
+        HierarchicalGrid::new(grid, 10)
+    }
+
+    #[test]
+    fn sealed_start_or_goal_cluster_reports_no_abstract_connection() {
+        let hp = sealed_corner(false);
+        let (inside, outside) = (GridPos { x: 2, y: 2 }, GridPos { x: 15, y: 15 });
+        assert!(!hp.cluster_nodes.contains_key(&(0, 0)), "no entrances into the sealed cluster");
+
+        let from_sealed = hp.find_path(inside, outside);
+        assert_eq!(from_sealed.status, PathStatus::NoAbstractConnection);
+        assert!(from_sealed.path.is_empty());
+
+        let into_sealed = hp.find_path(outside, inside);
+        assert_eq!(into_sealed.status, PathStatus::NoAbstractConnection);
+        assert_eq!(hp.find_abstract_path(outside, inside).status, PathStatus::NoAbstractConnection);
+    }
+
+    #[test]
+    fn adjacent_clusters_fall_back_to_bounded_search() {
+        let hp = sealed_corner(true);
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 12, y: 12 });
+
+        let res = hp.find_path(start, goal);
+        assert_eq!(res.status, PathStatus::Found);
+        assert!(res.path.contains(&GridPos { x: 9, y: 9 }) && res.path.contains(&GridPos { x: 10, y: 10 }));
+        assert!(res.path.windows(2).all(|w| (w[0].x - w[1].x).abs() <= 1 && (w[0].y - w[1].y).abs() <= 1));
+
+        // Also adjacent, but the only way out through the corner runs via cluster (1, 1), which
+        // a search bounded to the start and goal clusters doesn't enter
+        assert_eq!(hp.find_path(start, GridPos { x: 15, y: 5 }).status, PathStatus::NoAbstractConnection);
+    }
+
+    #[test]
+    fn grouped_queries_match_individual_find_path() {
+        let mut grid = Grid2D::new(40, 40, DiagonalMode::Always);
+        let mut rng = SplitMix64(7);
+        for _ in 0..320 {
+            grid.set_blocked(rng.range(0, 39), rng.range(0, 39), true);
+        }
+        let hp = HierarchicalGrid::new(grid, 8);
+        let goal = GridPos { x: 36, y: 35 };
+        let mut starts: Vec<GridPos> = (0..40)
+            .map(|_| GridPos { x: rng.range(0, 39) as i32, y: rng.range(0, 39) as i32 })
+            .collect();
+        starts.push(GridPos { x: 34, y: 33 }); // Same cluster as the goal
+
+        let grouped = hp.find_paths_grouped(&starts, goal, AStarConfig::default());
+        assert_eq!(grouped.len(), starts.len());
+        for (start, res) in starts.iter().zip(&grouped) {
+            let single = hp.find_path(*start, goal);
+            assert_eq!(res.status, single.status, "from {start:?}");
+            if res.status != PathStatus::Found {
+                continue;
+            }
+            assert!((res.cost - single.cost).abs() < 1e-3, "from {start:?}: {} vs {}", res.cost, single.cost);
+            assert_eq!(res.path.first(), Some(start));
+            assert_eq!(res.path.last(), Some(&goal));
+            assert!(res.path.windows(2).all(|w| (w[0].x - w[1].x).abs() <= 1 && (w[0].y - w[1].y).abs() <= 1));
+        }
+    }
+
+    #[test]
+    fn abstract_ids_are_stable_across_builds() {
+        let a = serpentine();
+        let b = serpentine();
+        assert!(!a.nodes.is_empty());
+        for (&id, &pos) in &a.nodes {
+            assert_eq!(b.abstract_id_for_pos(pos), Some(id));
+            assert_eq!(b.pos_for_abstract_id(id), Some(pos));
+            assert_eq!(a.abstract_id_for_pos(pos), Some(id));
+        }
+        assert_eq!(a.abstract_id_for_pos(GridPos { x: 2, y: 2 }), None);
+
+        // Opening a new gap adds entrances; the existing ones keep their IDs even though
+        // they are now inserted in a different order
+        let mut c = serpentine();
+        c.attach();
+        c.base_grid.set_blocked(10, 1, false);
+        assert!(c.apply_pending_changes());
+        assert!(c.nodes.len() > a.nodes.len());
+        for (&id, &pos) in &a.nodes {
+            assert_eq!(c.abstract_id_for_pos(pos), Some(id), "{pos:?}");
+        }
+
+        // A hash collision moves the newcomer to the next free value; both nodes survive
+        let mut d = serpentine();
+        let (fresh, squatter) = (GridPos { x: 2, y: 2 }, GridPos { x: 3, y: 3 });
+        let hashed = AbstractNodeId::for_node(fresh, (0, 0));
+        d.nodes.insert(hashed, squatter);
+        let id = d.find_or_add_node(fresh);
+        assert_ne!(id, hashed);
+        assert_eq!((d.pos_for_abstract_id(id), d.nodes[&hashed]), (Some(fresh), squatter));
+        assert_eq!(d.abstract_id_for_pos(fresh), Some(id));
+        let last = AbstractNodeId(usize::MAX - 2);
+        assert_eq!(last.first_free(|id| *id == last), AbstractNodeId(0));
+    }
+
+    #[test]
+    fn budgeted_builder_matches_blocking_constructor() {
+        let make_grid = || {
+            let mut grid = Grid2D::new(61, 45, DiagonalMode::Always);
+            let mut rng = SplitMix64(21);
+            for _ in 0..500 {
+                grid.set_blocked(rng.range(0, 60), rng.range(0, 44), true);
+            }
+            grid
+        };
+        let blocking = HierarchicalGrid::new(make_grid(), 8);
+
+        let mut builder = HierarchicalGridBuilder::new(make_grid(), 8);
+        let mut steps = 0;
+        let mut last_done = 0;
+        loop {
+            let progress = builder.step(Duration::ZERO);
+            steps += 1;
+            assert_eq!(progress.clusters_total, 8 * 6);
+            assert!(progress.clusters_done >= last_done);
+            last_done = progress.clusters_done;
+            if progress.finished {
+                break;
+            }
+        }
+        assert!(steps > 2);
+        let built = builder.finish();
+
+        assert_eq!(built.nodes, blocking.nodes);
+        assert_eq!(built.edges, blocking.edges);
+        let (start, goal) = (GridPos { x: 1, y: 1 }, GridPos { x: 58, y: 42 });
+        let (a, b) = (built.find_path(start, goal), blocking.find_path(start, goal));
+        assert_eq!((a.status, a.path, a.cost), (b.status, b.path, b.cost));
+    }
+
+    #[test]
+    fn oversized_cluster_splits_into_two_halves() {
+        // 3x3 clusters of 16. The middle one gets 8 entrances on each side (every other cell
+        // of the neighboring columns is blocked) and 2 on the top and bottom, where a wall
+        // down its middle column also splits the border: 20 in total.
+        let mut grid = Grid2D::new(48, 48, DiagonalMode::Never);
+        for y in (17..32).step_by(2) {
+            grid.set_blocked(15, y, true);
+            grid.set_blocked(32, y, true);
+        }
+        grid.set_region_blocked((24, 16, 1, 16), true);
+        let mut hp = HierarchicalGrid::new(grid, 16);
+        let middle = (16, 16);
+        let counts = hp.cluster_node_counts();
+        assert_eq!(counts.iter().find(|(key, _)| *key == middle), Some(&(middle, 20)));
+
+        let (start, goal) = (GridPos { x: 2, y: 20 }, GridPos { x: 45, y: 26 });
+        let before = hp.find_path(start, goal);
+        let untouched: Vec<_> = counts.iter().filter(|(key, _)| *key != middle).cloned().collect();
+        let edges_elsewhere = hp.edges[&hp.abstract_id_for_pos(GridPos { x: 39, y: 15 }).unwrap()].clone();
+
+        hp.split_large_clusters(10);
+        let counts = hp.cluster_node_counts();
+        // The wall leaves nothing to connect across the new border, so no entrances are added
+        assert!(counts.contains(&((16, 16), 10)), "{counts:?}");
+        assert!(counts.contains(&((24, 16), 10)), "{counts:?}");
+        assert_eq!(counts.len(), untouched.len() + 2);
+        assert!(untouched.iter().all(|c| counts.contains(c)));
+        assert_eq!(hp.edges[&hp.abstract_id_for_pos(GridPos { x: 39, y: 15 }).unwrap()], edges_elsewhere);
+
+        // Still routes through the split halves, via the border rows above and below
+        let after = hp.find_path(start, goal);
+        assert_eq!(before.status, PathStatus::Found);
+        assert_eq!(after.status, PathStatus::Found);
+        assert!(after.path.windows(2).all(|w| (w[0].x - w[1].x).abs() + (w[0].y - w[1].y).abs() == 1));
+        assert!(after.path.iter().all(|p| !hp.base_grid.is_blocked(p.x, p.y)));
+
+        // Queries inside a half stay local
+        let local = hp.find_path(GridPos { x: 17, y: 20 }, GridPos { x: 22, y: 28 });
+        assert_eq!(local.status, PathStatus::Found);
+
+        // On an open map the halves connect through entrances on the new borders
+        let mut open = HierarchicalGrid::new(Grid2D::new(32, 32, DiagonalMode::Never), 16);
+        open.split_large_clusters(3);
+        assert!(open.cluster_node_counts().iter().all(|&(_, n)| n <= 3));
+        let across = open.find_path(GridPos { x: 1, y: 1 }, GridPos { x: 30, y: 30 });
+        assert_eq!(across.status, PathStatus::Found);
+        assert_eq!(across.cost, 58.0);
+    }
+
+    #[test]
+    fn min_width_routes_around_narrow_entrances() {
+        // Wall down x = 10 with a 1-cell gap at the top and a 3-cell gap lower down
+        let mut grid = Grid2D::new(20, 20, DiagonalMode::Never);
+        grid.set_region_blocked((10, 0, 1, 20), true);
+        grid.set_blocked(10, 1, false);
+        grid.set_region_blocked((10, 15, 1, 3), false);
+        let hp = HierarchicalGrid::new(grid, 10);
+        let id = |x, y| hp.abstract_id_for_pos(GridPos { x, y }).unwrap();
+        assert_eq!(hp.entrance_width_between(id(9, 1), id(10, 1)), Some(1));
+        assert_eq!(hp.entrance_width_between(id(10, 16), id(9, 16)), Some(3));
+        assert_eq!(hp.entrance_width_between(id(9, 1), id(9, 16)), None);
+
+        let (start, goal) = (GridPos { x: 2, y: 1 }, GridPos { x: 17, y: 1 });
+        let squeezed = hp.find_path(start, goal);
+        assert_eq!(squeezed.cost, 15.0);
+        let wide = hp.find_path_min_width(start, goal, 2);
+        assert_eq!(wide.status, PathStatus::Found);
+        assert!(wide.path.contains(&GridPos { x: 10, y: 16 }) && !wide.path.contains(&GridPos { x: 10, y: 1 }));
+        assert!(wide.cost > squeezed.cost + 20.0);
+        assert_eq!(hp.find_path_min_width(start, goal, 1).cost, squeezed.cost);
+        assert_eq!(hp.find_path_min_width(start, goal, 4).status, PathStatus::NoAbstractConnection);
     }
 }
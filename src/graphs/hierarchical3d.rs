@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rayon::prelude::*;
+
+use crate::algorithms::astar::{astar, AStarConfig};
+use crate::graphs::grid3d::{Grid3D, GridPos3D};
+use crate::graphs::hierarchical::{AbstractNodeId, VIRTUAL_GOAL, VIRTUAL_START};
+use crate::heuristics::{Euclidean, Manhattan};
+use crate::traits::{path_length, Graph, Heuristic, PathResult, PathStatus};
+
+/// `AbstractEdge` for voxel maps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbstractEdge3D {
+    pub target: AbstractNodeId,
+    pub cost: f32,
+    pub path: Vec<GridPos3D>, // Cached for refinement
+}
+
+// Cluster coordinates: a voxel's position divided by the cluster size
+type ClusterKey = (usize, usize, usize);
+
+// A voxel-level connection from a query position to an abstract node, or back
+type Connection = (AbstractNodeId, f32, Vec<GridPos3D>);
+
+/// `HierarchicalGrid` for `Grid3D`: the map is cut into cubic clusters, each contiguous
+/// passable patch of a face shared by two clusters becomes an entrance (one abstract node on
+/// either side, at the patch voxel nearest its centroid), and the entrances of a cluster are
+/// linked by A* inside it. `find_path` connects the start and goal to their clusters'
+/// entrances, searches the abstract graph and stitches the cached paths together: close to
+/// optimal, with far fewer expansions than a flat search of a large voxel map.
+///
+/// Searches stay inside their cluster, so a start or goal that only reaches its cluster's
+/// entrances by leaving the cluster gives `NoAbstractConnection`. Rebuild after editing the
+/// grid.
+pub struct HierarchicalGrid3D {
+    pub base_grid: Grid3D,
+    pub cluster_size: usize,
+    pub nodes: HashMap<AbstractNodeId, GridPos3D>,
+    pub edges: HashMap<AbstractNodeId, Vec<AbstractEdge3D>>,
+    position_to_id: HashMap<GridPos3D, AbstractNodeId>,
+    cluster_nodes: HashMap<ClusterKey, Vec<AbstractNodeId>>,
+}
+
+impl HierarchicalGrid3D {
+    /// Builds the abstract graph, linking each cluster's entrances in parallel.
+    pub fn new(base_grid: Grid3D, cluster_size: usize) -> Self {
+        Self::build(base_grid, cluster_size, true)
+    }
+
+    fn build(base_grid: Grid3D, cluster_size: usize, parallel: bool) -> Self {
+        let mut hp = Self {
+            base_grid,
+            cluster_size: cluster_size.max(1),
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            position_to_id: HashMap::new(),
+            cluster_nodes: HashMap::new(),
+        };
+        hp.build_abstract_nodes();
+        hp.build_intra_cluster_edges(parallel);
+        hp
+    }
+
+    pub fn cluster_of(&self, pos: GridPos3D) -> ClusterKey {
+        let cs = self.cluster_size;
+        (pos.x.max(0) as usize / cs, pos.y.max(0) as usize / cs, pos.z.max(0) as usize / cs)
+    }
+
+    pub fn abstract_id_for_pos(&self, pos: GridPos3D) -> Option<AbstractNodeId> {
+        self.position_to_id.get(&pos).copied()
+    }
+
+    fn build_abstract_nodes(&mut self) {
+        let cs = self.cluster_size;
+        let dims = [self.base_grid.width, self.base_grid.height, self.base_grid.depth];
+        let counts = dims.map(|n| n.div_ceil(cs));
+        // The face between clusters k and k + 1 along `axis`, for every cluster in the other two
+        for axis in 0..3 {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            for k in 0..counts[axis].saturating_sub(1) {
+                for cu in 0..counts[u] {
+                    for cv in 0..counts[v] {
+                        let u_range = (cu * cs, ((cu + 1) * cs).min(dims[u]));
+                        let v_range = (cv * cs, ((cv + 1) * cs).min(dims[v]));
+                        self.detect_face_entrances(axis, (k + 1) * cs - 1, u_range, v_range);
+                    }
+                }
+            }
+        }
+    }
+
+    // Splits one cluster face into 4-connected patches where both sides are passable
+    fn detect_face_entrances(&mut self, axis: usize, low: usize, u_range: (usize, usize), v_range: (usize, usize)) {
+        let (nu, nv) = (u_range.1 - u_range.0, v_range.1 - v_range.0);
+        let cell = |i: usize| (u_range.0 + i % nu, v_range.0 + i / nu);
+        let open: Vec<bool> = (0..nu * nv)
+            .map(|i| {
+                let (u, v) = cell(i);
+                let passable = |a| self.base_grid.is_passable(&voxel(axis, a, u, v));
+                passable(low) && passable(low + 1)
+            })
+            .collect();
+
+        let mut seen = vec![false; open.len()];
+        for first in 0..open.len() {
+            if !open[first] || seen[first] {
+                continue;
+            }
+            let mut patch = vec![first];
+            seen[first] = true;
+            let mut next = 0;
+            while next < patch.len() {
+                let i = patch[next];
+                next += 1;
+                let (iu, iv) = (i % nu, i / nu);
+                let adjacent = [
+                    (iu > 0).then(|| i - 1),
+                    (iu + 1 < nu).then(|| i + 1),
+                    (iv > 0).then(|| i - nu),
+                    (iv + 1 < nv).then(|| i + nu),
+                ];
+                for j in adjacent.into_iter().flatten() {
+                    if open[j] && !seen[j] {
+                        seen[j] = true;
+                        patch.push(j);
+                    }
+                }
+            }
+
+            // The patch voxel nearest the centroid (which may lie outside a concave patch)
+            let n = patch.len() as f32;
+            let cu = patch.iter().map(|&i| (i % nu) as f32).sum::<f32>() / n;
+            let cv = patch.iter().map(|&i| (i / nu) as f32).sum::<f32>() / n;
+            let distance = |i: usize| ((i % nu) as f32 - cu).powi(2) + ((i / nu) as f32 - cv).powi(2);
+            let center = *patch.iter().min_by(|&&a, &&b| distance(a).total_cmp(&distance(b))).unwrap();
+            let (u, v) = cell(center);
+            self.create_entrance(voxel(axis, low, u, v), voxel(axis, low + 1, u, v));
+        }
+    }
+
+    fn create_entrance(&mut self, pos1: GridPos3D, pos2: GridPos3D) {
+        let id1 = self.find_or_add_node(pos1);
+        let id2 = self.find_or_add_node(pos2);
+        let cost = |p: GridPos3D| self.base_grid.get_cost(p.x, p.y, p.z);
+        let (to2, to1) = (cost(pos2), cost(pos1));
+        self.add_edge(id1, AbstractEdge3D { target: id2, cost: to2, path: vec![pos1, pos2] });
+        self.add_edge(id2, AbstractEdge3D { target: id1, cost: to1, path: vec![pos2, pos1] });
+    }
+
+    // A voxel on several faces of its cluster (an edge or corner) gets one node
+    fn find_or_add_node(&mut self, pos: GridPos3D) -> AbstractNodeId {
+        if let Some(&id) = self.position_to_id.get(&pos) {
+            return id;
+        }
+        let cluster = self.cluster_of(pos);
+        let id = AbstractNodeId::for_voxel(pos, cluster).first_free(|id| self.nodes.contains_key(id));
+        self.nodes.insert(id, pos);
+        self.position_to_id.insert(pos, id);
+        self.edges.insert(id, Vec::new());
+        self.cluster_nodes.entry(cluster).or_default().push(id);
+        id
+    }
+
+    fn add_edge(&mut self, from: AbstractNodeId, edge: AbstractEdge3D) {
+        self.edges.get_mut(&from).unwrap().push(edge);
+    }
+
+    // Bounds of cluster `key`, clipped to the grid
+    fn cluster_box(&self, key: ClusterKey) -> ClusterBox<'_> {
+        let cs = self.cluster_size as i32;
+        let min = GridPos3D { x: key.0 as i32 * cs, y: key.1 as i32 * cs, z: key.2 as i32 * cs };
+        let max = GridPos3D { x: min.x + cs - 1, y: min.y + cs - 1, z: min.z + cs - 1 };
+        ClusterBox { grid: &self.base_grid, min, max }
+    }
+
+    // Paths between every pair of the cluster's entrances, both ways
+    fn process_cluster(&self, key: &ClusterKey) -> Vec<(AbstractNodeId, AbstractEdge3D)> {
+        let mut local_edges = Vec::new();
+        let Some(nodes) = self.cluster_nodes.get(key) else {
+            return local_edges;
+        };
+        let bounded = self.cluster_box(*key);
+        for (i, &id_a) in nodes.iter().enumerate() {
+            for &id_b in &nodes[i + 1..] {
+                let (pos_a, pos_b) = (self.nodes[&id_a], self.nodes[&id_b]);
+                let result = astar(&bounded, &Manhattan, pos_a, pos_b, AStarConfig::default());
+                if result.status == PathStatus::Found {
+                    let mut reversed = result.path.clone();
+                    reversed.reverse();
+                    // Costs are paid on entering a voxel, so the way back differs by the endpoints
+                    let cost = |p: GridPos3D| self.base_grid.get_cost(p.x, p.y, p.z);
+                    let back = result.cost - cost(pos_b) + cost(pos_a);
+                    local_edges.push((id_a, AbstractEdge3D { target: id_b, cost: result.cost, path: result.path }));
+                    local_edges.push((id_b, AbstractEdge3D { target: id_a, cost: back, path: reversed }));
+                }
+            }
+        }
+        local_edges
+    }
+
+    // Each node's intra-cluster edges come from its own cluster only; clusters are taken in
+    // sorted order so the parallel and sequential builds produce identical edge lists
+    fn build_intra_cluster_edges(&mut self, parallel: bool) {
+        let mut clusters: Vec<ClusterKey> = self.cluster_nodes.keys().copied().collect();
+        clusters.sort_unstable();
+        let new_edges: Vec<(AbstractNodeId, AbstractEdge3D)> = if parallel {
+            clusters.par_iter().flat_map(|c| self.process_cluster(c)).collect()
+        } else {
+            clusters.iter().flat_map(|c| self.process_cluster(c)).collect()
+        };
+        for (from, edge) in new_edges {
+            self.add_edge(from, edge);
+        }
+    }
+
+    /// Connects `start` and `goal` to the entrances of their clusters, searches the abstract
+    /// graph and refines the route from the cached entrance-to-entrance paths. Start and goal
+    /// in the same cluster get a plain A* over the whole grid. `cumulative_costs` is empty.
+    pub fn find_path(&self, start: GridPos3D, goal: GridPos3D) -> PathResult<GridPos3D> {
+        let started = cfg!(feature = "timing").then(Instant::now);
+        self.search(start, goal).timed(started)
+    }
+
+    fn search(&self, start: GridPos3D, goal: GridPos3D) -> PathResult<GridPos3D> {
+        let failed = |status| PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: 0,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status,
+            elapsed: None,
+        };
+        if !self.base_grid.is_passable(&start) || !self.base_grid.is_passable(&goal) {
+            return failed(PathStatus::NotFound);
+        }
+        let (start_cluster, goal_cluster) = (self.cluster_of(start), self.cluster_of(goal));
+        if start_cluster == goal_cluster {
+            return astar(&self.base_grid, &Euclidean, start, goal, AStarConfig::default());
+        }
+
+        let connect = |cluster: ClusterKey, to_goal: bool| -> Vec<Connection> {
+            let bounded = self.cluster_box(cluster);
+            let nodes = self.cluster_nodes.get(&cluster).map_or(&[][..], Vec::as_slice);
+            nodes
+                .iter()
+                .filter_map(|&id| {
+                    let (from, to) = if to_goal { (self.nodes[&id], goal) } else { (start, self.nodes[&id]) };
+                    let result = astar(&bounded, &Euclidean, from, to, AStarConfig::default());
+                    (result.status == PathStatus::Found).then_some((id, result.cost, result.path))
+                })
+                .collect()
+        };
+        let (start_edges, goal_edges) = (connect(start_cluster, false), connect(goal_cluster, true));
+        if start_edges.is_empty() || goal_edges.is_empty() {
+            return failed(PathStatus::NoAbstractConnection);
+        }
+
+        let search_graph = AbstractSearchGraph3D { hp: self, start_edges: &start_edges, goal_edges: &goal_edges };
+        let heuristic = AbstractHeuristic3D { hp: self, goal };
+        let abstract_result = astar(&search_graph, &heuristic, VIRTUAL_START, VIRTUAL_GOAL, AStarConfig::default());
+        if abstract_result.status != PathStatus::Found {
+            return PathResult {
+                nodes_expanded: abstract_result.nodes_expanded,
+                best_g_score: abstract_result.best_g_score,
+                nodes_in_open_set: abstract_result.nodes_in_open_set,
+                ..failed(abstract_result.status)
+            };
+        }
+
+        // Stitch the segments, dropping each one's first voxel after the first segment
+        let mut full_path: Vec<GridPos3D> = Vec::new();
+        for pair in abstract_result.path.windows(2) {
+            let (current, next) = (pair[0], pair[1]);
+            let segment: &[GridPos3D] = if current == VIRTUAL_START {
+                &start_edges.iter().find(|(id, _, _)| *id == next).unwrap().2
+            } else if next == VIRTUAL_GOAL {
+                &goal_edges.iter().find(|(id, _, _)| *id == current).unwrap().2
+            } else {
+                &self.edges[&current].iter().find(|e| e.target == next).unwrap().path
+            };
+            let skip = usize::from(!full_path.is_empty());
+            full_path.extend_from_slice(&segment[skip..]);
+        }
+
+        PathResult {
+            length: path_length(&self.base_grid, &full_path),
+            path: full_path,
+            cost: abstract_result.cost,
+            cumulative_costs: vec![],
+            nodes_expanded: abstract_result.nodes_expanded, // Abstract expansions only
+            best_g_score: abstract_result.best_g_score,
+            nodes_in_open_set: abstract_result.nodes_in_open_set,
+            status: PathStatus::Found,
+            elapsed: None,
+        }
+    }
+}
+
+// The voxel at `a` along `axis` and (u, v) along the two axes after it
+fn voxel(axis: usize, a: usize, u: usize, v: usize) -> GridPos3D {
+    let mut coords = [0; 3];
+    coords[axis] = a as i32;
+    coords[(axis + 1) % 3] = u as i32;
+    coords[(axis + 2) % 3] = v as i32;
+    GridPos3D { x: coords[0], y: coords[1], z: coords[2] }
+}
+
+// The base grid restricted to one cluster (inclusive bounds)
+struct ClusterBox<'a> {
+    grid: &'a Grid3D,
+    min: GridPos3D,
+    max: GridPos3D,
+}
+
+impl ClusterBox<'_> {
+    fn contains(&self, pos: &GridPos3D) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x)
+            && (self.min.y..=self.max.y).contains(&pos.y)
+            && (self.min.z..=self.max.z).contains(&pos.z)
+    }
+}
+
+impl Graph for ClusterBox<'_> {
+    type Node = GridPos3D;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.contains(node) && self.grid.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.grid.neighbors_iter(*node).filter(|(n, _)| self.contains(n)).for_each(|(n, cost)| visit(n, cost));
+    }
+}
+
+// The abstract graph plus one query's virtual start and goal nodes
+struct AbstractSearchGraph3D<'a> {
+    hp: &'a HierarchicalGrid3D,
+    start_edges: &'a [Connection],
+    goal_edges: &'a [Connection], // Abstract node -> goal
+}
+
+impl Graph for AbstractSearchGraph3D<'_> {
+    type Node = AbstractNodeId;
+
+    fn is_passable(&self, _node: &Self::Node) -> bool {
+        true
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        if *node == VIRTUAL_START {
+            self.start_edges.iter().for_each(|(target, cost, _)| visit(*target, *cost));
+        } else if *node != VIRTUAL_GOAL {
+            if let Some(edges) = self.hp.edges.get(node) {
+                edges.iter().for_each(|e| visit(e.target, e.cost));
+            }
+            if let Some((_, cost, _)) = self.goal_edges.iter().find(|(src, _, _)| src == node) {
+                visit(VIRTUAL_GOAL, *cost);
+            }
+        }
+    }
+}
+
+// Euclidean from an abstract node's voxel to the real goal
+struct AbstractHeuristic3D<'a> {
+    hp: &'a HierarchicalGrid3D,
+    goal: GridPos3D,
+}
+
+impl Heuristic<AbstractNodeId> for AbstractHeuristic3D<'_> {
+    fn estimate(&self, from: &AbstractNodeId, _to: &AbstractNodeId) -> f32 {
+        if *from == VIRTUAL_START || *from == VIRTUAL_GOAL {
+            return 0.0;
+        }
+        Euclidean.estimate(&self.hp.nodes[from], &self.goal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 24^3 split into eight rooms by full walls at x, y and z = 12, with a one-voxel door
+    // between some rooms; clusters of 8 don't line up with the walls
+    fn rooms() -> Grid3D {
+        let mut grid = Grid3D::new(24, 24, 24);
+        for a in 0..24 {
+            for b in 0..24 {
+                grid.set_blocked(12, a, b, true);
+                grid.set_blocked(a, 12, b, true);
+                grid.set_blocked(a, b, 12, true);
+            }
+        }
+        let doors = [(12, 3, 3), (12, 18, 20), (5, 12, 18), (20, 12, 4), (4, 20, 12), (19, 6, 12), (3, 3, 12)];
+        for (x, y, z) in doors {
+            grid.set_blocked(x, y, z, false);
+        }
+        grid
+    }
+
+    #[test]
+    fn refined_paths_stay_close_to_flat_astar() {
+        let hp = HierarchicalGrid3D::new(rooms(), 8);
+        let p = |x, y, z| GridPos3D { x, y, z };
+        for (start, goal) in [(p(1, 1, 1), p(22, 22, 22)), (p(22, 1, 1), p(1, 22, 22)), (p(2, 20, 3), p(21, 4, 19))] {
+            let flat = astar(&hp.base_grid, &Manhattan, start, goal, AStarConfig::default());
+            let found = hp.find_path(start, goal);
+            assert_eq!((flat.status, found.status), (PathStatus::Found, PathStatus::Found));
+            let bounded = found.cost >= flat.cost - 1e-3 && found.cost <= flat.cost * 1.25;
+            assert!(bounded, "{} vs {}", found.cost, flat.cost);
+            assert_eq!((found.path[0], *found.path.last().unwrap()), (start, goal));
+            for w in found.path.windows(2) {
+                let step = (w[0].x - w[1].x).abs() + (w[0].y - w[1].y).abs() + (w[0].z - w[1].z).abs();
+                assert!(step == 1 && hp.base_grid.is_passable(&w[1]), "{:?} -> {:?}", w[0], w[1]);
+            }
+            assert_eq!(found.cost, (found.path.len() - 1) as f32);
+        }
+
+        // Same cluster, walled-in goal, and a blocked start
+        let near = hp.find_path(p(1, 1, 1), p(5, 6, 7));
+        assert_eq!(near.cost, 15.0);
+        let mut sealed = rooms();
+        sealed.fill_box(p(17, 17, 17), p(19, 19, 19), crate::graphs::grid3d::VoxelType::Blocked);
+        sealed.set_blocked(18, 18, 18, false);
+        let sealed = HierarchicalGrid3D::new(sealed, 8);
+        assert_eq!(sealed.find_path(p(1, 1, 1), p(18, 18, 18)).status, PathStatus::NoAbstractConnection);
+        assert_eq!(hp.find_path(p(12, 0, 0), p(1, 1, 1)).status, PathStatus::NotFound);
+    }
+
+    #[test]
+    fn parallel_build_matches_sequential() {
+        let parallel = HierarchicalGrid3D::build(rooms(), 8, true);
+        let sequential = HierarchicalGrid3D::build(rooms(), 8, false);
+        assert!(!parallel.nodes.is_empty());
+        assert_eq!(parallel.nodes, sequential.nodes);
+        assert_eq!(parallel.edges, sequential.edges);
+        // Every face patch gives two nodes joined both ways
+        let id = parallel.abstract_id_for_pos(GridPos3D { x: 7, y: 3, z: 3 }).unwrap();
+        let across = parallel.abstract_id_for_pos(GridPos3D { x: 8, y: 3, z: 3 }).unwrap();
+        assert!(parallel.edges[&id].iter().any(|e| e.target == across && e.cost == 1.0));
+    }
+}
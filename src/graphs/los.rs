@@ -0,0 +1,173 @@
+use rayon::prelude::*;
+
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::traits::Graph;
+
+/// Precomputed line-of-sight table for a `Grid2D`, for smoothers and visibility checks that
+/// call `can_traverse` far more often than the grid changes.
+///
+/// Every cell stores one bit per cell within Euclidean distance `max_los_distance`, so memory
+/// grows with `width * height * radius^2`: radius 10 is ~317 bits (40 bytes) per cell, about
+/// 640 KiB for a 128x128 grid, and radius 32 is ~400 bytes per cell. Use
+/// `estimated_memory_bytes` before building. Farther pairs fall back to Bresenham on the grid.
+///
+/// The cache borrows the grid, so it cannot go stale; rebuild it after editing the grid.
+/// It implements `Graph` by delegating to the grid, with `can_traverse` served from the table.
+pub struct LineOfSightCache<'a> {
+    grid: &'a Grid2D,
+    radius: i32,
+    // (2r + 1)^2 square of offsets -> bit index within a cell's disk, None outside the disk
+    offset_bits: Vec<Option<u32>>,
+    words_per_cell: usize,
+    bits: Vec<u64>,
+}
+
+impl<'a> LineOfSightCache<'a> {
+    /// Precomputes visibility between every cell and all cells within `max_los_distance`.
+    pub fn build(grid: &'a Grid2D, max_los_distance: usize) -> Self {
+        let radius = max_los_distance as i32;
+        let (offset_bits, offsets) = disk_offsets(radius);
+        let words_per_cell = offsets.len().div_ceil(64);
+        let mut bits = vec![0u64; grid.width * grid.height * words_per_cell];
+
+        // The disk always holds at least the (0, 0) offset, so chunks are never empty
+        bits.par_chunks_mut(words_per_cell).enumerate().for_each(|(idx, words)| {
+            let from = GridPos {
+                x: (idx % grid.width) as i32,
+                y: (idx / grid.width) as i32,
+            };
+            for (bit, &(dx, dy)) in offsets.iter().enumerate() {
+                let to = GridPos { x: from.x + dx, y: from.y + dy };
+                if grid.can_traverse(&from, &to) {
+                    words[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        });
+
+        Self {
+            grid,
+            radius,
+            offset_bits,
+            words_per_cell,
+            bits,
+        }
+    }
+
+    /// Same answer as `Grid2D::can_traverse(a, b)`: O(1) within `max_los_distance`,
+    /// Bresenham beyond it.
+    pub fn can_see(&self, a: GridPos, b: GridPos) -> bool {
+        match self.bit_index(&a, &b) {
+            Some((word, mask)) => self.bits[word] & mask != 0,
+            None => self.grid.can_traverse(&a, &b),
+        }
+    }
+
+    /// Bytes used by the table for a `(width, height)` grid and LOS radius.
+    pub fn estimated_memory_bytes(grid_size: (usize, usize), radius: usize) -> usize {
+        let (offset_bits, disk) = disk_offsets(radius as i32);
+        let table = grid_size.0 * grid_size.1 * disk.len().div_ceil(64) * std::mem::size_of::<u64>();
+        table + offset_bits.len() * std::mem::size_of::<Option<u32>>()
+    }
+
+    pub fn grid(&self) -> &'a Grid2D {
+        self.grid
+    }
+
+    // (word, mask) of the bit for a -> b, or None if the pair isn't in the table
+    fn bit_index(&self, a: &GridPos, b: &GridPos) -> Option<(usize, u64)> {
+        let (w, h) = (self.grid.width as i32, self.grid.height as i32);
+        if a.x < 0 || a.y < 0 || a.x >= w || a.y >= h {
+            return None;
+        }
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        if dx.abs() > self.radius || dy.abs() > self.radius {
+            return None;
+        }
+        let side = 2 * self.radius + 1;
+        let bit = self.offset_bits[((dy + self.radius) * side + dx + self.radius) as usize]? as usize;
+        let cell = (a.y * w + a.x) as usize;
+        Some((cell * self.words_per_cell + bit / 64, 1 << (bit % 64)))
+    }
+}
+
+// Lookup square for offsets within `radius`, and the disk offsets in bit order
+fn disk_offsets(radius: i32) -> (Vec<Option<u32>>, Vec<(i32, i32)>) {
+    let side = (2 * radius + 1) as usize;
+    let mut lookup = vec![None; side * side];
+    let mut disk = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius {
+                lookup[(dy + radius) as usize * side + (dx + radius) as usize] = Some(disk.len() as u32);
+                disk.push((dx, dy));
+            }
+        }
+    }
+    (lookup, disk)
+}
+
+impl Graph for LineOfSightCache<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.grid.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.grid.neighbors(node, visit);
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        self.can_see(*from, *to)
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.grid.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.grid.geometric_distance(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::rng::SplitMix64;
+
+    #[test]
+    fn matches_bresenham_inside_and_beyond_radius() {
+        let mut grid = Grid2D::new(24, 18, DiagonalMode::Always);
+        let mut rng = SplitMix64(11);
+        for _ in 0..90 {
+            grid.set_blocked(rng.range(0, 23), rng.range(0, 17), true);
+        }
+        let los = LineOfSightCache::build(&grid, 6);
+
+        for ay in 0..18 {
+            for ax in 0..24 {
+                for by in -1..19 {
+                    for bx in -1..25 {
+                        let (a, b) = (GridPos { x: ax, y: ay }, GridPos { x: bx, y: by });
+                        assert_eq!(los.can_see(a, b), grid.can_traverse(&a, &b), "{a:?} -> {b:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn memory_estimate_matches_table() {
+        let grid = Grid2D::new(128, 128, DiagonalMode::Always);
+        let los = LineOfSightCache::build(&grid, 10);
+        let estimate = LineOfSightCache::estimated_memory_bytes((128, 128), 10);
+        assert_eq!(estimate, los.bits.len() * 8 + los.offset_bits.len() * std::mem::size_of::<Option<u32>>());
+        // 317 bits round up to five words per cell
+        assert_eq!(los.words_per_cell, 5);
+        assert!(estimate < 1 << 20);
+    }
+}
@@ -2,3 +2,10 @@ pub mod grid2d;
 pub mod hierarchical;
 pub mod navmesh;
 pub mod grid3d;
+pub mod adapters;
+pub mod los;
+pub mod rle;
+pub mod chunked;
+pub mod zones;
+pub mod visibility;
+pub mod hierarchical3d;
@@ -1,5 +1,44 @@
-use crate::traits::Graph;
-use crate::algorithms::funnel::Portal;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::traits::{Graph, GraphWithHeuristic, Heuristic, PathResult, PathStatus};
+use crate::algorithms::astar::{astar, AStarConfig};
+use crate::algorithms::funnel::{string_pull, Portal};
+use crate::error::PathforgeError;
+
+// Floor for polygon cost multipliers: zero or negative edge costs break A*
+const MIN_COST_MULTIPLIER: f32 = 0.01;
+
+// (from_poly, to_poly) -> (left, right)
+type PortalCache = HashMap<(u32, u32), ([f32; 3], [f32; 3]), BuildHasherDefault<PolyPairHasher>>;
+
+// Multiplicative hash for the small integer keys of the portal cache. The default
+// SipHash costs more than the 3-slot adjacency scan the cache is meant to replace.
+#[derive(Default)]
+struct PolyPairHasher(u64);
+
+impl Hasher for PolyPairHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
 
 /// A navigation mesh based on a "Struct of Arrays" layout for cache locality.
 /// Currently assumes all polygons are triangles.
@@ -17,17 +56,272 @@ pub struct NavMesh {
     /// Stride 3 for triangles: [t0_n1, t0_n2, t0_n3, ...]
     /// -1 indicates a boundary edge (no neighbor).
     pub neighbors: Vec<i32>,
+
+    /// Area type per polygon (e.g. 0 ground, 1 water), 0 by default.
+    /// Filters address areas 0..32 as bits of a `u32` mask.
+    pub areas: Vec<u8>,
+
+    /// User-defined flag bits per polygon, 0 by default. See `NavMeshQueryFilter::include_flags`.
+    pub flags: Vec<u32>,
+
+    /// Polygons too steep to walk (see `build_from_heightmap`), false by default. Blocked
+    /// polygons keep their adjacency but are never passable or visited as neighbors.
+    pub blocked_polys: Vec<bool>,
+
+    // Multiplies the cost of entering each polygon; see `set_poly_cost_multiplier`
+    cost_multipliers: Vec<f32>,
+
+    // Bumped by every cost edit
+    revision: u64,
+
+    // Sorted by `start_poly` so `off_mesh_links_from` can slice
+    off_mesh_links: Vec<OffMeshLink>,
+
+    /// Precomputed (left, right) portal for every adjacent (from, to) polygon pair.
+    /// None until `compute_portal_cache` is called.
+    portal_cache: Option<PortalCache>,
+
+    // Polygon centroids at construction, shared with every `CentroidHeuristic`
+    centroids: Arc<[(f32, f32, f32)]>,
 }
 
 impl NavMesh {
     pub fn new(vertices: Vec<f32>, polygons: Vec<u32>, neighbors: Vec<i32>) -> Self {
-        Self {
+        let poly_count = polygons.len() / 3;
+        let mut mesh = Self {
             vertices,
             polygons,
             neighbors,
+            areas: vec![0; poly_count],
+            flags: vec![0; poly_count],
+            blocked_polys: vec![false; poly_count],
+            cost_multipliers: vec![1.0; poly_count],
+            revision: 0,
+            off_mesh_links: Vec::new(),
+            portal_cache: None,
+            centroids: Arc::from([]),
+        };
+        mesh.centroids = (0..poly_count as u32).map(|p| mesh.centroid(p)).collect();
+        mesh
+    }
+
+    /// `new` for untrusted input (e.g. loaded from disk), which `new` would accept and later
+    /// panic on: checks that the arrays fit together and that no triangle has zero area.
+    pub fn try_new(vertices: Vec<f32>, polygons: Vec<u32>, neighbors: Vec<i32>) -> Result<Self, PathforgeError> {
+        let (vertex_count, poly_count) = (vertices.len() / 3, polygons.len() / 3);
+        let fits = vertices.len().is_multiple_of(3)
+            && polygons.len().is_multiple_of(3)
+            && neighbors.len() == polygons.len()
+            && polygons.iter().all(|&v| (v as usize) < vertex_count)
+            && neighbors.iter().all(|&n| n == -1 || (0..poly_count as i32).contains(&n));
+        if !fits {
+            return Err(PathforgeError::MalformedMesh);
+        }
+        let mesh = Self::new(vertices, polygons, neighbors);
+        for poly in 0..poly_count as u32 {
+            let i = poly as usize * 3;
+            let [a, b, c] = [0, 1, 2].map(|k| mesh.get_vertex(mesh.polygons[i + k]));
+            let (u, v) = ((b.0 - a.0, b.1 - a.1, b.2 - a.2), (c.0 - a.0, c.1 - a.1, c.2 - a.2));
+            let cross = (u.1 * v.2 - u.2 * v.1, u.2 * v.0 - u.0 * v.2, u.0 * v.1 - u.1 * v.0);
+            if Self::dist_sq(cross, (0.0, 0.0, 0.0)) == 0.0 {
+                return Err(PathforgeError::DegenerateMesh(poly));
+            }
+        }
+        Ok(mesh)
+    }
+
+    /// Triangle mesh over a heightmap: sample (x, z) becomes vertex (x, elevation, z), and
+    /// each square of four neighboring samples is split into two triangles, wound
+    /// counterclockwise in the XZ plane. Triangles tilted more than `walkable_angle_deg`
+    /// from the horizontal are marked in `blocked_polys`. Square (i, j) owns polygons
+    /// 2 * (j * (width - 1) + i) and the one after it.
+    ///
+    /// Panics if `elevations` doesn't hold `width * height` samples.
+    pub fn build_from_heightmap(heightmap: &HeightMap, walkable_angle_deg: f32) -> NavMesh {
+        let (width, height) = (heightmap.width, heightmap.height);
+        assert_eq!(heightmap.elevations.len(), width * height, "heightmap needs width * height elevations");
+        let (cols, rows) = (width.saturating_sub(1), height.saturating_sub(1));
+
+        let mut vertices = Vec::with_capacity(width * height * 3);
+        for z in 0..height {
+            for x in 0..width {
+                vertices.extend_from_slice(&[x as f32, heightmap.elevation(x, z), z as f32]);
+            }
+        }
+        let v = |x: usize, z: usize| (z * width + x) as u32;
+        let poly = |i: usize, j: usize| 2 * (j * cols + i) as i32;
+        let mut polygons = Vec::with_capacity(cols * rows * 6);
+        let mut neighbors = Vec::with_capacity(cols * rows * 6);
+        for j in 0..rows {
+            for i in 0..cols {
+                // Lower-left half: bottom edge, diagonal, left edge
+                polygons.extend_from_slice(&[v(i, j), v(i + 1, j), v(i, j + 1)]);
+                neighbors.extend_from_slice(&[
+                    if j > 0 { poly(i, j - 1) + 1 } else { -1 },
+                    poly(i, j) + 1,
+                    if i > 0 { poly(i - 1, j) + 1 } else { -1 },
+                ]);
+                // Upper-right half: right edge, top edge, diagonal
+                polygons.extend_from_slice(&[v(i + 1, j), v(i + 1, j + 1), v(i, j + 1)]);
+                neighbors.extend_from_slice(&[
+                    if i + 1 < cols { poly(i + 1, j) } else { -1 },
+                    if j + 1 < rows { poly(i, j + 1) } else { -1 },
+                    poly(i, j),
+                ]);
+            }
+        }
+
+        let mut mesh = NavMesh::new(vertices, polygons, neighbors);
+        let max_tilt = walkable_angle_deg.to_radians();
+        mesh.blocked_polys = mesh
+            .polygons
+            .chunks(3)
+            .map(|tri| {
+                let [a, b, c] = [0, 1, 2].map(|k| mesh.get_vertex(tri[k]));
+                let (u, w) = ((b.0 - a.0, b.1 - a.1, b.2 - a.2), (c.0 - a.0, c.1 - a.1, c.2 - a.2));
+                let normal = (u.1 * w.2 - u.2 * w.1, u.2 * w.0 - u.0 * w.2, u.0 * w.1 - u.1 * w.0);
+                let tilt = (normal.1.abs() / Self::dist_sq(normal, (0.0, 0.0, 0.0)).sqrt()).acos();
+                tilt > max_tilt
+            })
+            .collect();
+        mesh
+    }
+
+    /// Whether `poly` is marked in `blocked_polys`.
+    pub fn is_poly_blocked(&self, poly: u32) -> bool {
+        self.blocked_polys.get(poly as usize).copied().unwrap_or(false)
+    }
+
+    /// The neighbors `Graph::neighbors` visits, with the same costs and order, as an iterator.
+    /// Off-mesh links leaving `poly` come after the adjacent polygons. Blocked polygons are
+    /// skipped.
+    pub fn neighbors_iter(&self, poly: u32) -> impl Iterator<Item = (u32, f32)> + '_ {
+        let start_index = poly as usize * 3;
+        // Safety check
+        let edges = self.neighbors.get(start_index..start_index + 3).unwrap_or(&[]);
+        let center_current = if edges.is_empty() { (0.0, 0.0, 0.0) } else { self.centroid(poly) };
+
+        let adjacent = edges.iter().filter(|&&n| n != -1 && !self.is_poly_blocked(n as u32)).map(move |&n| {
+            // Cost: Distance between centroids (Approximation for A*)
+            // TODO: Use edge midpoints for more accurate traversal cost
+            let cost = Self::dist_sq(center_current, self.centroid(n as u32)).sqrt();
+            (n as u32, cost * self.poly_cost_multiplier(n as u32))
+        });
+        let links = self.off_mesh_links_from(poly).iter().filter(|link| !self.is_poly_blocked(link.end_poly));
+        adjacent.chain(links.map(|link| (link.end_poly, link.cost * self.poly_cost_multiplier(link.end_poly))))
+    }
+
+    /// Scales the cost of entering `poly` (by edge or off-mesh link), e.g. to keep units
+    /// away from a battle. 1.0 is normal; below 1.0 the default `CentroidHeuristic` may
+    /// overestimate, so paths are no longer guaranteed shortest. Clamped to at least 0.01.
+    /// Bumps `revision`; polygons not in the mesh are ignored.
+    pub fn set_poly_cost_multiplier(&mut self, poly: u32, multiplier: f32) {
+        if let Some(current) = self.cost_multipliers.get_mut(poly as usize) {
+            *current = multiplier.max(MIN_COST_MULTIPLIER);
+            self.revision += 1;
+        }
+    }
+
+    /// Cost multiplier of `poly`; 1.0 unless set (or for polygons not in the mesh).
+    pub fn poly_cost_multiplier(&self, poly: u32) -> f32 {
+        self.cost_multipliers.get(poly as usize).copied().unwrap_or(1.0)
+    }
+
+    /// Adds `amount` to the multiplier of every polygon whose centroid lies within `radius`
+    /// of `center` in the XZ plane, keeping it at least 0.01 (a negative `amount` lowers costs).
+    /// O(N) over the polygons, like `get_poly_at_pos`.
+    pub fn add_cost_in_circle(&mut self, center: [f32; 3], radius: f32, amount: f32) {
+        let mut changed = false;
+        for (multiplier, &(x, _, z)) in self.cost_multipliers.iter_mut().zip(self.centroids.iter()) {
+            if (x - center[0]).powi(2) + (z - center[2]).powi(2) <= radius * radius {
+                *multiplier = (*multiplier + amount).max(MIN_COST_MULTIPLIER);
+                changed = true;
+            }
+        }
+        if changed {
+            self.revision += 1;
+        }
+    }
+
+    /// Relaxes every multiplier toward 1.0 over `dt` seconds, exponentially at `rate` per
+    /// second; ones within 0.001 of 1.0 snap back to it. Bumps `revision` if any moved.
+    pub fn decay_costs(&mut self, dt: f32, rate: f32) {
+        let keep = (-rate * dt).exp();
+        let mut changed = false;
+        for multiplier in self.cost_multipliers.iter_mut().filter(|m| **m != 1.0) {
+            let relaxed = 1.0 + (*multiplier - 1.0) * keep;
+            *multiplier = if (relaxed - 1.0).abs() < 1e-3 { 1.0 } else { relaxed };
+            changed = true;
+        }
+        if changed {
+            self.revision += 1;
         }
     }
 
+    /// Counts cost edits (`set_poly_cost_multiplier`, `add_cost_in_circle`, `decay_costs`);
+    /// paths cached at an older revision may no longer be the cheapest. Starts at 0.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Adds a one-way link; add a second one for the way back (usually with its own animation).
+    pub fn add_off_mesh_link(&mut self, link: OffMeshLink) {
+        let at = self.off_mesh_links.partition_point(|l| l.start_poly <= link.start_poly);
+        self.off_mesh_links.insert(at, link);
+    }
+
+    /// Off-mesh links that can be taken from polygon `poly`, in the order they were added.
+    pub fn off_mesh_links_from(&self, poly: u32) -> &[OffMeshLink] {
+        let first = self.off_mesh_links.partition_point(|l| l.start_poly < poly);
+        let end = self.off_mesh_links.partition_point(|l| l.start_poly <= poly);
+        &self.off_mesh_links[first..end]
+    }
+
+    // The link a path stepping from `from` to `to` takes, unless the two share an edge
+    fn link_between(&self, from: u32, to: u32) -> Option<&OffMeshLink> {
+        if self.find_shared_edge(from, to).is_some() {
+            return None;
+        }
+        self.off_mesh_links_from(from).iter().find(|link| link.end_poly == to)
+    }
+
+    /// Whether polygon `poly` may be entered under `filter`.
+    pub fn passes_filter(&self, poly: u32, filter: &NavMeshQueryFilter) -> bool {
+        let idx = poly as usize;
+        let area_bit = self.areas.get(idx).and_then(|&a| 1u32.checked_shl(a as u32)).unwrap_or(0);
+        let flags = self.flags.get(idx).copied().unwrap_or(0);
+        filter.include_areas.is_none_or(|mask| mask & area_bit != 0)
+            && filter.exclude_areas.is_none_or(|mask| mask & area_bit == 0)
+            && filter.include_flags.is_none_or(|mask| mask & flags != 0)
+    }
+
+    /// `Graph::neighbors`, skipping polygons rejected by `filter`.
+    pub fn neighbors_filtered(&self, node: &u32, filter: &NavMeshQueryFilter, mut visit: impl FnMut(u32, f32)) {
+        self.neighbors(node, |n, cost| {
+            if self.passes_filter(n, filter) {
+                visit(n, cost);
+            }
+        });
+    }
+
+    /// Precomputes the shared edge of every adjacent polygon pair so `get_portals`
+    /// skips the per-edge search. Call `invalidate_portal_cache` when adjacency changes.
+    pub fn compute_portal_cache(&mut self) {
+        let mut cache = PortalCache::with_capacity_and_hasher(self.neighbors.len(), Default::default());
+        for (slot, &neighbor) in self.neighbors.iter().enumerate() {
+            if neighbor >= 0 {
+                let poly = (slot / 3) as u32;
+                cache.insert((poly, neighbor as u32), self.edge_portal(slot - slot % 3, slot % 3));
+            }
+        }
+        self.portal_cache = Some(cache);
+    }
+
+    /// Drops the portal cache; lookups fall back to scanning the adjacency arrays.
+    pub fn invalidate_portal_cache(&mut self) {
+        self.portal_cache = None;
+    }
+
     /// Returns the (x, y, z) of a vertex by its index.
     #[inline]
     pub fn get_vertex(&self, index: u32) -> (f32, f32, f32) {
@@ -62,7 +356,16 @@ impl NavMesh {
     }
 
     /// Converts a path of polygon indices into a list of portals for the funnel algorithm.
-    pub fn get_portals(&self, path: &[u32], start_pos: [f32; 3], end_pos: [f32; 3]) -> Vec<Portal> {
+    /// Steps along off-mesh links get no portal; `find_path` pulls the string separately on
+    /// each side of a link. Fails on an empty path, a polygon not in the mesh, or a step
+    /// between polygons joined by neither an edge nor a link.
+    pub fn get_portals(&self, path: &[u32], start_pos: [f32; 3], end_pos: [f32; 3]) -> Result<Vec<Portal>, PathforgeError> {
+        if path.is_empty() {
+            return Err(PathforgeError::EmptyPath);
+        }
+        if let Some(&poly) = path.iter().find(|&&p| p as usize >= self.polygons.len() / 3) {
+            return Err(PathforgeError::PolygonOutOfRange(poly));
+        }
         let mut portals = Vec::with_capacity(path.len() + 1);
 
         // Start Portal (degenerate)
@@ -74,40 +377,49 @@ impl NavMesh {
 
             if let Some((left, right)) = self.find_shared_edge(curr, next) {
                 portals.push(Portal { left, right });
+            } else if self.link_between(curr, next).is_none() {
+                return Err(PathforgeError::NotAdjacent { from: curr, to: next });
             }
         }
 
         // End Portal (degenerate)
         portals.push(Portal { left: end_pos, right: end_pos });
 
-        portals
+        Ok(portals)
     }
 
     // Helper to find shared edge between two polygons
     // Returns (left_vertex, right_vertex)
     fn find_shared_edge(&self, p1: u32, p2: u32) -> Option<([f32; 3], [f32; 3])> {
+         if let Some(edge) = self.portal_cache.as_ref().and_then(|c| c.get(&(p1, p2))) {
+             return Some(*edge);
+         }
+
          let start_idx = (p1 as usize) * 3;
          
          // Check all 3 neighbors of p1 to find p2
          for i in 0..3 {
              if self.neighbors[start_idx + i] == p2 as i32 {
-                 // Found it! The edge is between vertex i and (i+1)%3
-                 let v1_idx = self.polygons[start_idx + i];
-                 let v2_idx = self.polygons[start_idx + (i + 1) % 3];
-                 
-                 let v1 = self.get_vertex_arr(v1_idx);
-                 let v2 = self.get_vertex_arr(v2_idx);
-
-                 // Winding: v1 -> v2 is CCW edge of p1.
-                 // When crossing v1->v2 to leave p1:
-                 // v1 is Right, v2 is Left.
-                 return Some((v2, v1));
+                 return Some(self.edge_portal(start_idx, i));
              }
          }
          None
     }
 
-    /// Finds the polygon ID that contains the given position (XZ plane).
+    // Portal for leaving the polygon at `start_idx` through its edge `i`
+    fn edge_portal(&self, start_idx: usize, i: usize) -> ([f32; 3], [f32; 3]) {
+         // The edge is between vertex i and (i+1)%3
+         let v1 = self.get_vertex_arr(self.polygons[start_idx + i]);
+         let v2 = self.get_vertex_arr(self.polygons[start_idx + (i + 1) % 3]);
+
+         // Winding: v1 -> v2 is CCW edge of p1.
+         // When crossing v1->v2 to leave p1:
+         // v1 is Right, v2 is Left.
+         (v2, v1)
+    }
+
+    /// Finds the polygon ID that contains the given position (XZ plane). Blocked polygons
+    /// don't count, so a position over one gives None.
     /// Currently uses an O(N) brute-force search. 
     /// TODO: Optimize with a spatial partition (BVH or Grid) for large meshes.
     pub fn get_poly_at_pos(&self, pos: [f32; 3]) -> Option<u32> {
@@ -118,13 +430,191 @@ impl NavMesh {
             let v2 = self.get_vertex_arr(self.polygons[idx as usize + 1]);
             let v3 = self.get_vertex_arr(self.polygons[idx as usize + 2]);
 
-            if Self::is_point_in_triangle(pos, v1, v2, v3) {
+            if Self::is_point_in_triangle(pos, v1, v2, v3) && !self.is_poly_blocked(i as u32) {
                 return Some(i as u32);
             }
         }
         None
     }
 
+    /// Unblocked polygon closest to `pos` in the XZ plane and the closest point on it (`pos`
+    /// itself if it lies inside). None for a mesh without unblocked polygons. O(N) like
+    /// `get_poly_at_pos`.
+    pub fn find_nearest_poly(&self, pos: [f32; 3]) -> Option<(u32, [f32; 3])> {
+        let mut best: Option<(u32, [f32; 3], f32)> = None;
+        for poly in (0..(self.polygons.len() / 3) as u32).filter(|&p| !self.is_poly_blocked(p)) {
+            let idx = poly as usize * 3;
+            let tri = [0, 1, 2].map(|k| self.get_vertex_arr(self.polygons[idx + k]));
+            if Self::is_point_in_triangle(pos, tri[0], tri[1], tri[2]) {
+                return Some((poly, pos));
+            }
+            for k in 0..3 {
+                let point = Self::closest_on_segment_xz(pos, tri[k], tri[(k + 1) % 3]);
+                let (dx, dz) = (point[0] - pos[0], point[2] - pos[2]);
+                let d = dx * dx + dz * dz;
+                if best.is_none_or(|(_, _, best_d)| d < best_d) {
+                    best = Some((poly, point, d));
+                }
+            }
+        }
+        best.map(|(poly, point, _)| (poly, point))
+    }
+
+    /// Walks a ray over the mesh in the XZ plane, from the polygon under `origin` along the
+    /// horizontal part of `dir`, for `max_dist` (measured in XZ). Stops at the first boundary
+    /// edge or edge into a blocked polygon it crosses; off-mesh links don't carry rays. An
+    /// origin off the mesh (or over a blocked polygon) is a hit at distance 0 with no polygon.
+    /// The stopping point takes its height from the mesh.
+    pub fn raycast(&self, origin: [f32; 3], dir: [f32; 3], max_dist: f32) -> RaycastHit {
+        let Some(mut poly) = self.get_poly_at_pos(origin) else {
+            return RaycastHit { hit: true, distance: 0.0, position: origin, poly: None };
+        };
+        let len = (dir[0] * dir[0] + dir[2] * dir[2]).sqrt();
+        if len == 0.0 || max_dist <= 0.0 {
+            return RaycastHit { hit: false, distance: 0.0, position: origin, poly: Some(poly) };
+        }
+        // The ray is origin + t * (dx, dz) for t in 0..=1
+        let (dx, dz) = (dir[0] / len * max_dist, dir[2] / len * max_dist);
+        let cross = |ax: f32, az: f32, bx: f32, bz: f32| ax * bz - az * bx;
+
+        let mut t = 0.0;
+        // A straight ray enters each polygon once, unless it only grazes a vertex
+        for _ in 0..self.polygons.len() / 3 {
+            let idx = poly as usize * 3;
+            let tri = [0, 1, 2].map(|k| self.get_vertex_arr(self.polygons[idx + k]));
+            // Leave through the edge the ray crosses last (the triangle is convex)
+            let mut exit: Option<(usize, f32, f32)> = None;
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let (ex, ez) = (b[0] - a[0], b[2] - a[2]);
+                let denom = cross(dx, dz, ex, ez);
+                if denom == 0.0 {
+                    continue;
+                }
+                let (ox, oz) = (a[0] - origin[0], a[2] - origin[2]);
+                let (edge_t, s) = (cross(ox, oz, ex, ez) / denom, cross(ox, oz, dx, dz) / denom);
+                if (0.0..=1.0).contains(&s) && exit.is_none_or(|(_, best, _)| edge_t > best) {
+                    exit = Some((k, edge_t, s));
+                }
+            }
+            let Some((k, edge_t, s)) = exit.filter(|&(_, edge_t, _)| edge_t < 1.0) else {
+                let end = [origin[0] + dx, 0.0, origin[2] + dz];
+                let position = [end[0], Self::height_in_triangle(end, tri), end[2]];
+                return RaycastHit { hit: false, distance: max_dist, position, poly: Some(poly) };
+            };
+            let next = self.neighbors[idx + k];
+            if next == -1 || self.is_poly_blocked(next as u32) {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                let position = [origin[0] + dx * edge_t, a[1] + (b[1] - a[1]) * s, origin[2] + dz * edge_t];
+                return RaycastHit { hit: true, distance: edge_t.max(0.0) * max_dist, position, poly: Some(poly) };
+            }
+            (poly, t) = (next as u32, edge_t.max(t));
+        }
+        // Went round in circles at a vertex; stop there
+        let idx = poly as usize * 3;
+        let tri = [0, 1, 2].map(|k| self.get_vertex_arr(self.polygons[idx + k]));
+        let at = [origin[0] + dx * t, 0.0, origin[2] + dz * t];
+        let position = [at[0], Self::height_in_triangle(at, tri), at[2]];
+        RaycastHit { hit: true, distance: t * max_dist, position, poly: Some(poly) }
+    }
+
+    /// `raycast` for many rays, `(origin, dir, max_dist)` each, cast in parallel. Results
+    /// are in ray order.
+    pub fn raycast_batch(&self, rays: &[([f32; 3], [f32; 3], f32)]) -> Vec<RaycastHit> {
+        rays.par_iter().map(|&(origin, dir, max_dist)| self.raycast(origin, dir, max_dist)).collect()
+    }
+
+    /// Whether a straight walk from `a` to `b` (in XZ) stays on the mesh.
+    pub fn is_visible(&self, a: [f32; 3], b: [f32; 3]) -> bool {
+        let dir = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        !self.raycast(a, dir, (dir[0] * dir[0] + dir[2] * dir[2]).sqrt()).hit
+    }
+
+    // Height of the triangle's plane at p's XZ position
+    fn height_in_triangle(p: [f32; 3], [a, b, c]: [[f32; 3]; 3]) -> f32 {
+        let det = (b[2] - c[2]) * (a[0] - c[0]) + (c[0] - b[0]) * (a[2] - c[2]);
+        if det == 0.0 {
+            return a[1];
+        }
+        let wa = ((b[2] - c[2]) * (p[0] - c[0]) + (c[0] - b[0]) * (p[2] - c[2])) / det;
+        let wb = ((c[2] - a[2]) * (p[0] - c[0]) + (a[0] - c[0]) * (p[2] - c[2])) / det;
+        wa * a[1] + wb * b[1] + (1.0 - wa - wb) * c[1]
+    }
+
+    // Closest point to `p` on segment a-b, measured in XZ; y is interpolated along the segment
+    fn closest_on_segment_xz(p: [f32; 3], a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        let (ex, ez) = (b[0] - a[0], b[2] - a[2]);
+        let len_sq = ex * ex + ez * ez;
+        let t = if len_sq > 0.0 {
+            (((p[0] - a[0]) * ex + (p[2] - a[2]) * ez) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        [a[0] + ex * t, a[1] + (b[1] - a[1]) * t, a[2] + ez * t]
+    }
+
+    /// The full query: locate both points, A* over polygons, portals, then string pulling.
+    /// Points off the mesh or over blocked polygons are snapped to the nearest unblocked
+    /// polygon (see `find_nearest_poly`) and flagged in the result. Points in the same polygon
+    /// skip A* and get a straight segment.
+    pub fn find_path(&self, start: [f32; 3], end: [f32; 3], config: AStarConfig) -> NavMeshPathResult {
+        let locate = |pos: [f32; 3]| match self.get_poly_at_pos(pos) {
+            Some(poly) => Some((poly, pos, false)),
+            None => self.find_nearest_poly(pos).map(|(poly, point)| (poly, point, true)),
+        };
+        let (Some((start_poly, start, start_snapped)), Some((end_poly, end, end_snapped))) = (locate(start), locate(end)) else {
+            return NavMeshPathResult::not_found(0, false, false);
+        };
+
+        let (corridor, nodes_expanded) = if start_poly == end_poly {
+            (vec![start_poly], 0)
+        } else {
+            let result = astar(self, &self.default_heuristic(), start_poly, end_poly, config);
+            if result.status != PathStatus::Found {
+                let mut failed = NavMeshPathResult::not_found(result.nodes_expanded, start_snapped, end_snapped);
+                failed.status = result.status;
+                return failed;
+            }
+            (result.path, result.nodes_expanded)
+        };
+
+        // One funnel per stretch between off-mesh links, joined by the link endpoints
+        let mut portals = Vec::new();
+        let mut points = Vec::new();
+        let (mut from, mut from_pos) = (0, start);
+        for i in 0..corridor.len() {
+            let link = corridor.get(i + 1).and_then(|&next| self.link_between(corridor[i], next));
+            if link.is_none() && i + 1 < corridor.len() {
+                continue;
+            }
+            let to_pos = link.map_or(end, |l| l.start_pos);
+            let stretch = self.get_portals(&corridor[from..=i], from_pos, to_pos).expect("A* corridors are connected");
+            let mut pulled = string_pull(&stretch);
+            if pulled.len() == 1 {
+                pulled.push(to_pos); // Endpoints coincide; keep both
+            }
+            portals.extend(stretch);
+            points.extend(pulled);
+            if let Some(l) = link {
+                (from, from_pos) = (i + 1, l.end_pos);
+            }
+        }
+        let length = points
+            .windows(2)
+            .map(|w| Self::dist_sq((w[0][0], w[0][1], w[0][2]), (w[1][0], w[1][1], w[1][2])).sqrt())
+            .sum();
+        NavMeshPathResult {
+            status: PathStatus::Found,
+            corridor,
+            portals,
+            points,
+            length,
+            nodes_expanded,
+            start_snapped,
+            end_snapped,
+        }
+    }
+
     fn is_point_in_triangle(p: [f32; 3], a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> bool {
         fn sign(p1: [f32; 3], p2: [f32; 3], p3: [f32; 3]) -> f32 {
             (p1[0] - p3[0]) * (p2[2] - p3[2]) - (p2[0] - p3[0]) * (p1[2] - p3[2])
@@ -145,37 +635,643 @@ impl Graph for NavMesh {
     type Node = u32; // Polygon Index
 
     fn is_passable(&self, node: &Self::Node) -> bool {
-        (*node as usize) * 3 < self.polygons.len()
+        (*node as usize) * 3 < self.polygons.len() && !self.is_poly_blocked(*node)
     }
 
     fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
     where
         F: FnMut(Self::Node, f32),
     {
-        let poly_index = *node as usize;
-        let start_index = poly_index * 3;
-        
-        // Safety check
-        if start_index >= self.neighbors.len() {
-            return;
+        self.neighbors_iter(*node).for_each(|(n, cost)| visit(n, cost));
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        Some(self.polygons.len() / 3)
+    }
+
+    // Centroid to centroid, like the corridor search's edge costs
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        Some(Self::dist_sq(self.centroid(*from), self.centroid(*to)).sqrt())
+    }
+}
+
+/// Elevation samples on a unit-spaced grid, row-major (`elevations[z * width + x]`), for
+/// `NavMesh::build_from_heightmap`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightMap {
+    pub width: usize,
+    pub height: usize,
+    pub elevations: Vec<f32>,
+}
+
+impl HeightMap {
+    /// Elevation of sample (x, z).
+    pub fn elevation(&self, x: usize, z: usize) -> f32 {
+        self.elevations[z * self.width + x]
+    }
+}
+
+/// Result of `NavMesh::find_path`. Everything but `status`, `nodes_expanded` and the snap
+/// flags is empty unless `status` is `Found`.
+#[derive(Debug, Clone)]
+pub struct NavMeshPathResult {
+    pub status: PathStatus,
+    pub corridor: Vec<u32>,       // Polygons from start to end
+    pub portals: Vec<Portal>,     // Including the degenerate start and end portals (of each stretch between off-mesh links)
+    pub points: Vec<[f32; 3]>,    // String-pulled path, start and end included
+    pub length: f32,              // 3D length of `points`
+    pub nodes_expanded: usize,    // 0 when start and end share a polygon
+    pub start_snapped: bool,      // Start was off the mesh and moved onto it
+    pub end_snapped: bool,
+}
+
+impl NavMeshPathResult {
+    fn not_found(nodes_expanded: usize, start_snapped: bool, end_snapped: bool) -> Self {
+        Self {
+            status: PathStatus::NotFound,
+            corridor: vec![],
+            portals: vec![],
+            points: vec![],
+            length: 0.0,
+            nodes_expanded,
+            start_snapped,
+            end_snapped,
         }
+    }
+}
 
-        let center_current = self.centroid(*node);
+/// Where a `NavMesh::raycast` stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub hit: bool,             // Stopped at a boundary edge (or started off the mesh)
+    pub distance: f32,         // XZ distance travelled; `max_dist` if nothing was hit
+    pub position: [f32; 3],    // Stopping point, on the mesh surface
+    pub poly: Option<u32>,     // Polygon the ray stopped in; None if it started off the mesh
+}
 
-        // Check all 3 edges
-        for i in 0..3 {
-            let neighbor_idx = self.neighbors[start_index + i];
-            if neighbor_idx != -1 {
-                let neighbor_u32 = neighbor_idx as u32;
-                let center_next = self.centroid(neighbor_u32);
-                
-                // Cost: Distance between centroids (Approximation for A*)
-                // TODO: Use edge midpoints for more accurate traversal cost
-                let cost = Self::dist_sq(center_current, center_next).sqrt();
-                
-                visit(neighbor_u32, cost);
-            }
+/// A one-way connection between two polygons that don't share an edge (ladder, jump, swim),
+/// searched like any other neighbor. `animation_id` and `metadata` are for the game layer;
+/// see `PathResult::off_mesh_links_used`.
+///
+/// `CentroidHeuristic` stays admissible as long as `cost` is at least the distance between
+/// the two polygons' centroids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffMeshLink {
+    pub start_poly: u32,
+    pub end_poly: u32,
+    pub start_pos: [f32; 3],
+    pub end_pos: [f32; 3],
+    pub cost: f32,
+    pub animation_id: u32,
+    pub metadata: HashMap<String, String>,
+}
+
+impl OffMeshLink {
+    /// A link costing the straight-line distance between its endpoints, animation 0, no metadata.
+    pub fn new(start_poly: u32, end_poly: u32, start_pos: [f32; 3], end_pos: [f32; 3]) -> Self {
+        let cost = NavMesh::dist_sq(start_pos.into(), end_pos.into()).sqrt();
+        Self { start_poly, end_poly, start_pos, end_pos, cost, animation_id: 0, metadata: HashMap::new() }
+    }
+}
+
+impl PathResult<u32> {
+    /// The off-mesh links taken by this polygon path (e.g. from A* over a `NavMesh`), in
+    /// path order: one per consecutive pair that is joined by a link rather than an edge.
+    pub fn off_mesh_links_used<'m>(&self, mesh: &'m NavMesh) -> Vec<&'m OffMeshLink> {
+        self.path.windows(2).filter_map(|w| mesh.link_between(w[0], w[1])).collect()
+    }
+}
+
+impl GraphWithHeuristic for NavMesh {
+    type DefaultHeuristic = CentroidHeuristic;
+
+    fn default_heuristic(&self) -> CentroidHeuristic {
+        CentroidHeuristic { centroids: Arc::clone(&self.centroids) }
+    }
+}
+
+/// Straight-line distance between polygon centroids; admissible for `NavMesh`'s edge costs.
+/// Cheap to clone: it shares the centroids `NavMesh::new` computed. Those don't follow later
+/// edits to `vertices` or `polygons` (`revision` only tracks cost changes, which don't move
+/// centroids), so rebuild the mesh with `new` after changing its geometry. Polygons not in
+/// the mesh estimate 0.0.
+#[derive(Clone, Debug)]
+pub struct CentroidHeuristic {
+    centroids: Arc<[(f32, f32, f32)]>,
+}
+
+impl Heuristic<u32> for CentroidHeuristic {
+    fn estimate(&self, from: &u32, to: &u32) -> f32 {
+        match (self.centroids.get(*from as usize), self.centroids.get(*to as usize)) {
+            (Some(&from), Some(&to)) => NavMesh::dist_sq(from, to).sqrt(),
+            _ => 0.0,
         }
     }
 }
 
+/// Per-query polygon restrictions, e.g. ground agents excluding water. `None` fields
+/// don't restrict; the default filter accepts every polygon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NavMeshQueryFilter {
+    pub include_areas: Option<u32>, // Polygon's area bit must be set
+    pub exclude_areas: Option<u32>, // Polygon's area bit must be clear
+    pub include_flags: Option<u32>, // Polygon must share at least one flag bit
+}
+
+/// A `NavMesh` seen through a query filter, searchable with any `Graph` algorithm.
+pub struct FilteredNavMesh<'a> {
+    pub mesh: &'a NavMesh,
+    pub filter: &'a NavMeshQueryFilter,
+}
+
+impl Graph for FilteredNavMesh<'_> {
+    type Node = u32;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.mesh.is_passable(node) && self.mesh.passes_filter(*node, self.filter)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.mesh.neighbors_filtered(node, self.filter, visit);
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.mesh.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.mesh.geometric_distance(from, to)
+    }
+}
+
+/// A* over the polygons accepted by `filter`. `NotFound` if the start or goal polygon is rejected.
+pub fn navmesh_astar_filtered<H>(
+    mesh: &NavMesh,
+    heuristic: &H,
+    start: u32,
+    goal: u32,
+    filter: &NavMeshQueryFilter,
+    config: AStarConfig,
+) -> PathResult<u32>
+where
+    H: Heuristic<u32>,
+{
+    let graph = FilteredNavMesh { mesh, filter };
+    if !graph.is_passable(&start) || !graph.is_passable(&goal) {
+        return PathResult {
+            path: vec![],
+            cost: 0.0,
+            length: 0.0,
+            cumulative_costs: vec![],
+            nodes_expanded: 0,
+            best_g_score: 0.0,
+            nodes_in_open_set: 0,
+            status: PathStatus::NotFound,
+            elapsed: None,
+        };
+    }
+    astar(&graph, heuristic, start, goal, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::Zero;
+
+    // Strip of 2 * quads triangles along +X in the XZ plane
+    fn strip(quads: u32) -> NavMesh {
+        let mut vertices = Vec::new();
+        for i in 0..=quads {
+            vertices.extend_from_slice(&[i as f32, 0.0, 0.0]); // bottom: 2i
+            vertices.extend_from_slice(&[i as f32, 0.0, 1.0]); // top: 2i + 1
+        }
+        let mut polygons = Vec::new();
+        let mut neighbors = Vec::new();
+        for k in 0..quads {
+            let (b0, t0, b1, t1) = (2 * k, 2 * k + 1, 2 * k + 2, 2 * k + 3);
+            let (lower, upper) = (2 * k as i32, 2 * k as i32 + 1);
+            polygons.extend_from_slice(&[b0, b1, t0]);
+            neighbors.extend_from_slice(&[-1, upper, if k == 0 { -1 } else { lower - 1 }]);
+            polygons.extend_from_slice(&[b1, t1, t0]);
+            neighbors.extend_from_slice(&[if k + 1 == quads { -1 } else { upper + 1 }, -1, lower]);
+        }
+        NavMesh::new(vertices, polygons, neighbors)
+    }
+
+    // cols x rows unit quads in the XZ plane, two triangles each; quad (i, j) owns
+    // polygons 2 * (j * cols + i) and the one after it
+    fn quad_grid(cols: u32, rows: u32) -> NavMesh {
+        let mut vertices = Vec::new();
+        for z in 0..=rows {
+            for x in 0..=cols {
+                vertices.extend_from_slice(&[x as f32, 0.0, z as f32]);
+            }
+        }
+        let v = |x: u32, z: u32| z * (cols + 1) + x;
+        let mut polygons = Vec::new();
+        for j in 0..rows {
+            for i in 0..cols {
+                polygons.extend_from_slice(&[v(i, j), v(i + 1, j), v(i, j + 1)]);
+                polygons.extend_from_slice(&[v(i + 1, j), v(i + 1, j + 1), v(i, j + 1)]);
+            }
+        }
+        // Polygons sharing an (unordered) edge are neighbors
+        let mut edges: HashMap<(u32, u32), Vec<(usize, usize)>> = HashMap::new();
+        for (p, tri) in polygons.chunks(3).enumerate() {
+            for e in 0..3 {
+                let (a, b) = (tri[e], tri[(e + 1) % 3]);
+                edges.entry((a.min(b), a.max(b))).or_default().push((p, e));
+            }
+        }
+        let mut neighbors = vec![-1; polygons.len()];
+        for sides in edges.values() {
+            if let [(p, e), (q, f)] = sides[..] {
+                neighbors[p * 3 + e] = q as i32;
+                neighbors[q * 3 + f] = p as i32;
+            }
+        }
+        NavMesh::new(vertices, polygons, neighbors)
+    }
+
+    #[test]
+    fn heightmap_mesh_blocks_steep_triangles() {
+        // Flat: every triangle walkable, CCW in XZ, with symmetric adjacency
+        let flat = HeightMap { width: 6, height: 5, elevations: vec![2.0; 30] };
+        let mesh = NavMesh::build_from_heightmap(&flat, 30.0);
+        assert_eq!(mesh.polygons.len() / 3, 2 * 5 * 4);
+        assert!(mesh.blocked_polys.iter().all(|&b| !b));
+        for (p, tri) in mesh.polygons.chunks(3).enumerate() {
+            let [a, b, c] = [0, 1, 2].map(|k| mesh.get_vertex(tri[k]));
+            assert!((b.0 - a.0) * (c.2 - a.2) - (b.2 - a.2) * (c.0 - a.0) > 0.0, "polygon {p} winds clockwise");
+            for &n in mesh.neighbors[p * 3..p * 3 + 3].iter().filter(|&&n| n != -1) {
+                assert!(mesh.neighbors[n as usize * 3..n as usize * 3 + 3].contains(&(p as i32)));
+                assert!(mesh.find_shared_edge(p as u32, n as u32).is_some());
+            }
+        }
+        assert!(NavMesh::try_new(mesh.vertices.clone(), mesh.polygons.clone(), mesh.neighbors.clone()).is_ok());
+
+        // A ridge along x = 6, ten units high, except in the first two rows of samples
+        let (width, height) = (12, 8);
+        let elevations = (0..width * height).map(|k| if k % width == 6 && k / width >= 2 { 10.0 } else { 0.0 }).collect();
+        let ridge = NavMesh::build_from_heightmap(&HeightMap { width, height, elevations }, 45.0);
+        let cols = width - 1;
+        let poly = |i: usize, j: usize| 2 * (j * cols + i) as u32;
+        assert!(ridge.is_poly_blocked(poly(5, 4)) && ridge.is_poly_blocked(poly(6, 4) + 1));
+        assert!(!ridge.is_passable(&poly(6, 4)));
+
+        let (start, goal) = (poly(1, 6), poly(10, 6));
+        let result = astar(&ridge, &ridge.default_heuristic(), start, goal, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::Found);
+        assert!(result.path.iter().all(|&p| !ridge.is_poly_blocked(p)));
+        // Around the ridge through the bottom row of squares
+        assert!(result.path.iter().any(|&p| p < 2 * cols as u32));
+
+        // Points on the slope snap off it, and the path avoids it
+        let on_slope = [5.2, 0.0, 4.2];
+        assert_eq!(ridge.get_poly_at_pos(on_slope), None);
+        let (snapped, _) = ridge.find_nearest_poly(on_slope).unwrap();
+        assert!(!ridge.is_poly_blocked(snapped));
+        let walked = ridge.find_path(on_slope, [1.5, 0.0, 6.5], AStarConfig::default());
+        assert_eq!(walked.status, PathStatus::Found);
+        assert!(walked.start_snapped && walked.corridor.iter().all(|&p| !ridge.is_poly_blocked(p)));
+
+        // Rays stop where the slope starts, at x = 5
+        let ray = ridge.raycast([2.5, 0.0, 4.5], [1.0, 0.0, 0.0], 8.0);
+        assert!(ray.hit && (ray.distance - 2.5).abs() < 1e-3, "{ray:?}");
+        assert!(!ridge.is_poly_blocked(ray.poly.unwrap()));
+        assert!(!ridge.is_visible([2.5, 0.0, 4.5], [9.5, 0.0, 4.5]));
+        assert!(ridge.is_visible([2.5, 0.0, 0.5], [9.5, 0.0, 0.5]));
+
+        // Blocked polygons are still in range for portals
+        assert!(ridge.get_portals(&[poly(5, 4)], [5.2, 0.0, 4.2], [5.2, 0.0, 4.2]).is_ok());
+    }
+
+    #[test]
+    fn area_filter_forces_detour_around_water() {
+        // 5x3 quads; the middle of row 1 is water
+        let mut mesh = quad_grid(5, 3);
+        let quad = |i: u32, j: u32| 2 * (j * 5 + i);
+        for i in 1..=3 {
+            mesh.areas[quad(i, 1) as usize] = 1;
+            mesh.areas[quad(i, 1) as usize + 1] = 1;
+        }
+        let (start, goal) = (quad(0, 1), quad(4, 1) + 1);
+        let config = AStarConfig::default();
+
+        let ground = NavMeshQueryFilter { exclude_areas: Some(1 << 1), ..Default::default() };
+        let walk = navmesh_astar_filtered(&mesh, &Zero, start, goal, &ground, config);
+        assert_eq!(walk.status, PathStatus::Found);
+        assert!(walk.path.iter().all(|&p| mesh.areas[p as usize] == 0));
+
+        let amphibious = NavMeshQueryFilter { include_areas: Some(0b11), ..Default::default() };
+        let swim = navmesh_astar_filtered(&mesh, &Zero, start, goal, &amphibious, config);
+        assert_eq!(swim.status, PathStatus::Found);
+        assert!(swim.path.iter().any(|&p| mesh.areas[p as usize] == 1));
+        assert!(swim.cost < walk.cost, "{} vs {}", swim.cost, walk.cost);
+
+        // Flags: nothing is flagged, so requiring a flag rejects the start
+        let flagged = NavMeshQueryFilter { include_flags: Some(1), ..Default::default() };
+        let none = navmesh_astar_filtered(&mesh, &Zero, start, goal, &flagged, config);
+        assert_eq!(none.status, PathStatus::NotFound);
+    }
+
+    #[test]
+    fn neighbors_iter_matches_callback() {
+        let mut mesh = quad_grid(3, 2);
+        mesh.set_poly_cost_multiplier(4, 2.5);
+        mesh.blocked_polys[8] = true;
+        // Triangles sharing two vertices are neighbors, at the distance between their vertex
+        // means, scaled by the neighbor's multiplier; the blocked one is left out
+        let tri = |p: u32| &mesh.polygons[p as usize * 3..p as usize * 3 + 3];
+        let mean = |p: u32| {
+            let v = |i: u32, axis: usize| mesh.vertices[i as usize * 3 + axis];
+            [0, 1, 2].map(|axis| tri(p).iter().map(|&i| v(i, axis)).sum::<f32>() / 3.0)
+        };
+        for poly in 0..12u32 {
+            let mut expected: Vec<(u32, f32)> = (0..12u32)
+                .filter(|&q| q != poly && q != 8 && tri(q).iter().filter(|i| tri(poly).contains(i)).count() == 2)
+                .map(|q| {
+                    let (a, b) = (mean(poly), mean(q));
+                    let distance = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+                    (q, distance * if q == 4 { 2.5 } else { 1.0 })
+                })
+                .collect();
+            expected.sort_by_key(|&(q, _)| q);
+
+            let mut from_iter: Vec<_> = mesh.neighbors_iter(poly).collect();
+            let mut from_callback = Vec::new();
+            mesh.neighbors(&poly, |n, cost| from_callback.push((n, cost)));
+            from_iter.sort_by_key(|&(q, _)| q);
+            from_callback.sort_by_key(|&(q, _)| q);
+            for actual in [from_iter, from_callback] {
+                let polys = |list: &[(u32, f32)]| list.iter().map(|&(q, _)| q).collect::<Vec<_>>();
+                assert_eq!(polys(&actual), polys(&expected));
+                assert!(actual.iter().zip(&expected).all(|(a, e)| (a.1 - e.1).abs() < 1e-5), "{poly}: {actual:?}");
+            }
+        }
+        // Quad (1, 0)'s upper triangle borders its lower triangle and both quads beside it
+        let ns: Vec<u32> = mesh.neighbors_iter(3).map(|(n, _)| n).collect();
+        assert_eq!(ns.len(), 2);
+        assert!(ns.contains(&2) && ns.contains(&4));
+        // Out of range
+        assert_eq!(mesh.neighbors_iter(12).count(), 0);
+        assert_eq!(mesh.default_heuristic().estimate(&0, &12), 0.0);
+    }
+
+    #[test]
+    fn find_path_on_mesh_and_same_poly() {
+        let mesh = strip(10);
+        let config = AStarConfig::default();
+
+        let res = mesh.find_path([0.2, 0.0, 0.5], [9.8, 0.0, 0.5], config);
+        assert_eq!(res.status, PathStatus::Found);
+        assert!(!res.start_snapped && !res.end_snapped);
+        assert_eq!((res.corridor.first(), res.corridor.last()), (Some(&0), Some(&19)));
+        assert_eq!(res.portals.len(), res.corridor.len() + 1);
+        // Straight corridor: the funnel pulls the path tight
+        assert_eq!(res.points.len(), 2);
+        assert!((res.length - 9.6).abs() < 1e-4);
+        assert!(res.nodes_expanded > 0);
+
+        let same = mesh.find_path([0.1, 0.0, 0.1], [0.5, 0.0, 0.2], config);
+        assert_eq!(same.status, PathStatus::Found);
+        assert_eq!(same.corridor, vec![0]);
+        assert_eq!(same.nodes_expanded, 0);
+        assert_eq!(same.points, vec![[0.1, 0.0, 0.1], [0.5, 0.0, 0.2]]);
+    }
+
+    #[test]
+    fn find_path_snaps_off_mesh_points() {
+        let mesh = strip(4);
+        let res = mesh.find_path([1.5, 0.0, -2.0], [6.0, 0.0, 0.5], AStarConfig::default());
+        assert_eq!(res.status, PathStatus::Found);
+        assert!(res.start_snapped && res.end_snapped);
+        assert_eq!(res.points.first(), Some(&[1.5, 0.0, 0.0]));
+        assert_eq!(res.points.last(), Some(&[4.0, 0.0, 0.5]));
+        assert_eq!(mesh.find_nearest_poly([2.5, 0.0, 0.5]).map(|(_, p)| p), Some([2.5, 0.0, 0.5]));
+    }
+
+    #[test]
+    fn find_path_between_islands_is_not_found() {
+        // Two strips 10 units apart with no adjacency between them
+        let a = strip(3);
+        let b = strip(3);
+        let (nv, np) = ((a.vertices.len() / 3) as u32, (a.polygons.len() / 3) as i32);
+        let mut vertices = a.vertices.clone();
+        vertices.extend(b.vertices.chunks(3).flat_map(|v| [v[0] + 10.0, v[1], v[2]]));
+        let mut polygons = a.polygons.clone();
+        polygons.extend(b.polygons.iter().map(|&i| i + nv));
+        let mut neighbors = a.neighbors.clone();
+        neighbors.extend(b.neighbors.iter().map(|&n| if n == -1 { -1 } else { n + np }));
+        let mesh = NavMesh::new(vertices, polygons, neighbors);
+
+        let res = mesh.find_path([0.5, 0.0, 0.5], [11.5, 0.0, 0.5], AStarConfig::default());
+        assert_eq!(res.status, PathStatus::NotFound);
+        assert!(res.points.is_empty() && res.corridor.is_empty());
+        assert_eq!(res.nodes_expanded, 6);
+    }
+
+    #[test]
+    fn portal_cache_matches_uncached_lookup() {
+        let mut mesh = strip(20);
+        let path: Vec<u32> = (0..40).collect();
+        let (start, end) = ([0.2, 0.0, 0.2], [19.8, 0.0, 0.8]);
+
+        let uncached = mesh.get_portals(&path, start, end).unwrap();
+        assert_eq!(uncached.len(), path.len() + 1);
+
+        mesh.compute_portal_cache();
+        let cached = mesh.get_portals(&path, start, end).unwrap();
+        assert_eq!(cached.len(), uncached.len());
+        for (a, b) in cached.iter().zip(&uncached) {
+            assert_eq!((a.left, a.right), (b.left, b.right));
+        }
+
+        // After an adjacency edit the stale cache must be dropped
+        mesh.neighbors[1] = -1;
+        mesh.neighbors[5] = -1;
+        mesh.invalidate_portal_cache();
+        assert!(mesh.find_shared_edge(0, 1).is_none());
+    }
+
+    // Two 4-quad floors, the upper 3 units up and offset in z so they don't overlap in XZ,
+    // joined at their far ends by a staircase: one link up, one down
+    fn two_floors() -> NavMesh {
+        let floor = strip(4);
+        let (nv, np) = ((floor.vertices.len() / 3) as u32, (floor.polygons.len() / 3) as i32);
+        let mut vertices = floor.vertices.clone();
+        vertices.extend(floor.vertices.chunks(3).flat_map(|v| [v[0], v[1] + 3.0, v[2] + 2.0]));
+        let mut polygons = floor.polygons.clone();
+        polygons.extend(floor.polygons.iter().map(|&i| i + nv));
+        let mut neighbors = floor.neighbors.clone();
+        neighbors.extend(floor.neighbors.iter().map(|&n| if n == -1 { -1 } else { n + np }));
+        let mut mesh = NavMesh::new(vertices, polygons, neighbors);
+
+        let (bottom, top) = ([3.8, 0.0, 0.5], [3.8, 3.0, 2.5]);
+        let mut up = OffMeshLink { animation_id: 1, ..OffMeshLink::new(7, 15, bottom, top) };
+        up.metadata.insert("kind".to_string(), "stairs".to_string());
+        mesh.add_off_mesh_link(up);
+        mesh.add_off_mesh_link(OffMeshLink { animation_id: 2, ..OffMeshLink::new(15, 7, top, bottom) });
+        mesh
+    }
+
+    #[test]
+    fn staircase_link_is_reported_on_two_floor_path() {
+        let mesh = two_floors();
+        assert_eq!(mesh.off_mesh_links_from(7).len(), 1);
+        assert_eq!(mesh.off_mesh_links_from(15)[0].animation_id, 2);
+        assert!(mesh.off_mesh_links_from(3).is_empty());
+
+        let up = astar(&mesh, &mesh.default_heuristic(), 0, 8, AStarConfig::default());
+        assert_eq!(up.status, PathStatus::Found);
+        let used = up.off_mesh_links_used(&mesh);
+        assert_eq!(used.len(), 1);
+        assert_eq!((used[0].start_poly, used[0].end_poly, used[0].animation_id), (7, 15, 1));
+        assert_eq!(used[0].metadata.get("kind").map(String::as_str), Some("stairs"));
+
+        let down = astar(&mesh, &mesh.default_heuristic(), 9, 1, AStarConfig::default());
+        let used = down.off_mesh_links_used(&mesh);
+        assert_eq!(used.iter().map(|l| l.animation_id).collect::<Vec<_>>(), vec![2]);
+
+        // Staying on one floor takes no link
+        let flat = astar(&mesh, &mesh.default_heuristic(), 0, 6, AStarConfig::default());
+        assert!(flat.off_mesh_links_used(&mesh).is_empty());
+
+        // The pulled path walks to the bottom of the stairs and on from the top
+        let res = mesh.find_path([0.2, 0.0, 0.5], [0.2, 3.0, 2.5], AStarConfig::default());
+        assert_eq!(res.status, PathStatus::Found);
+        assert_eq!(res.points, vec![[0.2, 0.0, 0.5], [3.8, 0.0, 0.5], [3.8, 3.0, 2.5], [0.2, 3.0, 2.5]]);
+        assert!((res.length - (3.6 + 13f32.sqrt() + 3.6)).abs() < 1e-4);
+    }
+
+    // 6 x 6 quads rising 0.1 per unit of x, with the 2 x 2 quads in the middle walled off
+    fn ramp_with_pillar() -> NavMesh {
+        let mut mesh = quad_grid(6, 6);
+        for v in mesh.vertices.chunks_mut(3) {
+            v[1] = v[0] * 0.1;
+        }
+        let pillar: Vec<i32> = [(2, 2), (3, 2), (2, 3), (3, 3)].iter().flat_map(|&(i, j)| [2 * (j * 6 + i), 2 * (j * 6 + i) + 1]).collect();
+        for (slot, n) in mesh.neighbors.iter_mut().enumerate() {
+            if pillar.contains(n) != pillar.contains(&(slot as i32 / 3)) {
+                *n = -1;
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn raycast_stops_at_boundaries_and_walls() {
+        let mesh = ramp_with_pillar();
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-4);
+
+        // Along the bottom row to the mesh edge; dir's y and length don't matter
+        let edge = mesh.raycast([0.5, 0.05, 0.3], [2.0, 5.0, 0.0], 10.0);
+        assert!(edge.hit);
+        assert!((edge.distance - 5.5).abs() < 1e-4);
+        assert!(close(edge.position, [6.0, 0.6, 0.3]));
+        assert_eq!(edge.poly, Some(11));
+
+        let short = mesh.raycast([0.5, 0.05, 0.3], [1.0, 0.0, 0.0], 3.0);
+        assert!(!short.hit && short.distance == 3.0);
+        assert!(close(short.position, [3.5, 0.35, 0.3]));
+
+        // Into the pillar's side
+        let wall = mesh.raycast([0.5, 0.05, 2.5], [1.0, 0.0, 0.0], 10.0);
+        assert!(wall.hit);
+        assert!((wall.distance - 1.5).abs() < 1e-4);
+        assert!(close(wall.position, [2.0, 0.2, 2.5]));
+
+        assert_eq!(mesh.raycast([-1.0, 0.0, 0.5], [1.0, 0.0, 0.0], 3.0).poly, None);
+        assert!(mesh.is_visible([0.5, 0.0, 1.5], [5.5, 0.0, 1.5]));
+        assert!(!mesh.is_visible([0.5, 0.0, 3.1], [5.5, 0.0, 3.1]));
+        assert!(mesh.is_visible([1.5, 0.0, 0.5], [1.5, 0.0, 5.5]));
+    }
+
+    #[test]
+    fn raycast_batch_matches_sequential_raycasts() {
+        let mesh = ramp_with_pillar();
+        let rays: Vec<([f32; 3], [f32; 3], f32)> = (0..100)
+            .map(|i| {
+                let angle = i as f32 * 0.37;
+                let origin = [0.3 + (i % 5) as f32 * 1.1, 0.0, 0.2 + (i % 3) as f32 * 0.7];
+                (origin, [angle.cos(), 0.0, angle.sin()], 1.0 + (i % 7) as f32)
+            })
+            .collect();
+        let batch = mesh.raycast_batch(&rays);
+        assert_eq!(batch.len(), rays.len());
+        for (&(origin, dir, max_dist), hit) in rays.iter().zip(&batch) {
+            assert_eq!(*hit, mesh.raycast(origin, dir, max_dist));
+        }
+        // A mix of walls and open floor
+        assert!(batch.iter().any(|h| h.hit) && batch.iter().any(|h| !h.hit));
+    }
+
+    // 7 x 5 quads split into three lanes (rows 0, 2 and 4) by walled-off rows 1 and 3,
+    // except in the first and last columns
+    fn three_lanes() -> NavMesh {
+        let mut mesh = quad_grid(7, 5);
+        let walls: Vec<i32> = [1, 3].iter().flat_map(|&j| (1..6).flat_map(move |i| [2 * (j * 7 + i), 2 * (j * 7 + i) + 1])).collect();
+        for (slot, n) in mesh.neighbors.iter_mut().enumerate() {
+            if walls.contains(n) || walls.contains(&(slot as i32 / 3)) {
+                *n = -1;
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn painted_costs_divert_paths_until_they_decay() {
+        let mut mesh = three_lanes();
+        let (start, goal) = ([0.5, 0.0, 2.5], [6.5, 0.0, 2.5]);
+        let lane = |mesh: &NavMesh| {
+            let corridor = mesh.find_path(start, goal, AStarConfig::default()).corridor;
+            // Row of the polygon halfway along
+            corridor[corridor.len() / 2] / 14
+        };
+        assert_eq!(lane(&mesh), 2);
+
+        // Fighting in the middle lane
+        let before = mesh.revision();
+        mesh.add_cost_in_circle([3.5, 0.0, 2.5], 1.0, 9.0);
+        assert!(mesh.revision() > before);
+        let fight = 2 * (2 * 7 + 3); // Quad (3, 2)
+        assert_eq!(mesh.poly_cost_multiplier(fight), 10.0);
+        assert_eq!(mesh.poly_cost_multiplier(0), 1.0);
+        let detour = lane(&mesh);
+        assert!(detour == 0 || detour == 4, "still in lane {detour}");
+
+        // The filtered view sees the same costs
+        let filter = NavMeshQueryFilter::default();
+        let filtered = navmesh_astar_filtered(&mesh, &mesh.default_heuristic(), 28, 41, &filter, AStarConfig::default());
+        assert!(filtered.path.iter().all(|&p| p / 14 != 2 || p % 14 < 2 || p % 14 > 11));
+
+        // The fighting dies down
+        let mut steps = 0;
+        while lane(&mesh) != 2 {
+            mesh.decay_costs(1.0, 0.5);
+            steps += 1;
+            assert!(steps < 20, "multiplier still {}", mesh.poly_cost_multiplier(fight));
+        }
+        assert!(steps > 1);
+        for _ in 0..20 {
+            mesh.decay_costs(1.0, 0.5);
+        }
+        assert_eq!(mesh.poly_cost_multiplier(fight), 1.0);
+        let settled = mesh.revision();
+        mesh.decay_costs(1.0, 0.5);
+        assert_eq!(mesh.revision(), settled);
+
+        // Multipliers stay positive, and unknown polygons are ignored
+        mesh.add_cost_in_circle([3.5, 0.0, 2.5], 1.0, -5.0);
+        assert_eq!(mesh.poly_cost_multiplier(fight), MIN_COST_MULTIPLIER);
+        mesh.set_poly_cost_multiplier(0, -2.0);
+        assert_eq!(mesh.poly_cost_multiplier(0), MIN_COST_MULTIPLIER);
+        let before = mesh.revision();
+        mesh.set_poly_cost_multiplier(u32::MAX, 3.0);
+        assert_eq!((mesh.poly_cost_multiplier(u32::MAX), mesh.revision()), (1.0, before));
+        assert_eq!(lane(&mesh), 2);
+    }
+}
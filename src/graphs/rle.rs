@@ -0,0 +1,139 @@
+use crate::graphs::grid2d::{grid_steps, CellType, DiagonalMode, Grid2D, GridPos};
+use crate::heuristics::Euclidean;
+use crate::traits::{Graph, Heuristic};
+
+/// Run-length encoded `Grid2D` cells, for maps with long uniform stretches (procedural
+/// cities, large open areas). Searchable directly: each cell lookup is a binary search
+/// over the runs. Directional costs and change listeners are not kept.
+#[derive(Clone, Debug)]
+pub struct RleGrid {
+    pub width: usize,
+    pub height: usize,
+    pub diagonal: DiagonalMode,
+    pub diagonal_cost: f32,
+    /// (count, value) pairs covering the cells in row-major order.
+    pub runs: Vec<(u32, CellType)>,
+    // Exclusive end index of each run
+    run_ends: Vec<usize>,
+}
+
+impl RleGrid {
+    /// Panics if the runs don't cover exactly `width * height` cells.
+    pub fn new(width: usize, height: usize, diagonal: DiagonalMode, runs: Vec<(u32, CellType)>) -> Self {
+        let run_ends: Vec<usize> = runs
+            .iter()
+            .scan(0usize, |end, &(count, _)| {
+                *end += count as usize;
+                Some(*end)
+            })
+            .collect();
+        assert_eq!(run_ends.last().copied().unwrap_or(0), width * height, "runs must cover the grid");
+        Self { width, height, diagonal, diagonal_cost: std::f32::consts::SQRT_2, runs, run_ends }
+    }
+
+    /// Expands back into a `Grid2D` with the same cells, diagonal mode and diagonal cost.
+    pub fn decompress(&self) -> Grid2D {
+        let mut grid = Grid2D::new(self.width, self.height, self.diagonal).with_diagonal_cost(self.diagonal_cost);
+        grid.cells = self.runs.iter().flat_map(|&(count, cell)| std::iter::repeat_n(cell, count as usize)).collect();
+        grid
+    }
+
+    /// The cell at (x, y); None out of bounds. O(log runs).
+    pub fn cell(&self, x: i32, y: i32) -> Option<CellType> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        let idx = y as usize * self.width + x as usize;
+        Some(self.runs[self.run_ends.partition_point(|&end| end <= idx)].1)
+    }
+
+    /// Same semantics as `Grid2D::is_blocked` (out of bounds counts as blocked).
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        !matches!(self.cell(x, y), Some(CellType::Passable(_)))
+    }
+}
+
+impl Grid2D {
+    /// Run-length encodes the cells; see `RleGrid`.
+    pub fn compress_rle(&self) -> RleGrid {
+        let mut runs: Vec<(u32, CellType)> = Vec::new();
+        for &cell in &self.cells {
+            match runs.last_mut() {
+                Some((count, value)) if *value == cell && *count < u32::MAX => *count += 1,
+                _ => runs.push((1, cell)),
+            }
+        }
+        let mut rle = RleGrid::new(self.width, self.height, self.diagonal_movement, runs);
+        rle.diagonal_cost = self.diagonal_cost;
+        rle
+    }
+}
+
+impl Graph for RleGrid {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    // `Grid2D`'s steps and corner rules; costs as `Grid2D` without directional costs
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        let blocked = |x, y| self.is_blocked(x, y);
+        let cost = |next: GridPos, dx: i32, dy: i32| {
+            // `grid_steps` only prices cells `blocked` let through, so this is always Passable
+            let Some(CellType::Passable(cost)) = self.cell(next.x, next.y) else { return 0.0 };
+            cost * if dx != 0 && dy != 0 { self.diagonal_cost } else { 1.0 }
+        };
+        for (next, cost) in grid_steps(*node, self.diagonal, blocked, cost) {
+            visit(next, cost);
+        }
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        Some(self.width * self.height)
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        Some(Euclidean.estimate(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::heuristics::Diagonal;
+
+    #[test]
+    fn sparse_city_compresses_and_matches_grid() {
+        // Open 64x64 map with a few long walls and one expensive road
+        let mut grid = Grid2D::new(64, 64, DiagonalMode::IfNoObstacle);
+        grid.set_region_blocked((0, 20, 64, 2), true);
+        grid.set_blocked(30, 20, false);
+        grid.set_blocked(30, 21, false);
+        for x in 0..64 {
+            grid.set_cost(x, 40, 3.0);
+        }
+        let rle = grid.compress_rle();
+        assert!(rle.runs.len() < 10, "{} runs", rle.runs.len());
+
+        for y in -1..=64 {
+            for x in -1..=64 {
+                assert_eq!(rle.is_blocked(x, y), grid.is_blocked(x, y), "({x}, {y})");
+            }
+        }
+
+        let back = rle.decompress();
+        assert_eq!(back.cells, grid.cells);
+        assert_eq!(back.diagonal_movement, grid.diagonal_movement);
+
+        let (start, goal) = (GridPos { x: 2, y: 2 }, GridPos { x: 60, y: 60 });
+        let direct = astar(&grid, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default());
+        let compressed = astar(&rle, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default());
+        assert_eq!(compressed.path, direct.path);
+        assert_eq!(compressed.cost, direct.cost);
+    }
+}
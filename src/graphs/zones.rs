@@ -0,0 +1,287 @@
+use std::time::Instant;
+
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::traits::Graph;
+
+// Floor for zone cost multipliers: zero or negative edge costs break A*
+const MIN_COST_MULTIPLIER: f32 = 0.01;
+
+/// Area covered by a no-go zone, in cell coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoneShape {
+    /// (x, y, width, height) in cells.
+    Rect(i32, i32, i32, i32),
+    /// Cells whose centers lie within `radius` of `center`.
+    Circle { center: (f32, f32), radius: f32 },
+}
+
+impl ZoneShape {
+    pub fn contains(&self, pos: GridPos) -> bool {
+        match *self {
+            ZoneShape::Rect(x, y, w, h) => pos.x >= x && pos.x < x + w && pos.y >= y && pos.y < y + h,
+            ZoneShape::Circle { center, radius } => {
+                let (dx, dy) = (pos.x as f32 - center.0, pos.y as f32 - center.1);
+                dx * dx + dy * dy <= radius * radius
+            }
+        }
+    }
+
+    /// (x, y, width, height) covering every cell the shape contains.
+    pub fn bounds(&self) -> (i32, i32, i32, i32) {
+        match *self {
+            ZoneShape::Rect(x, y, w, h) => (x, y, w, h),
+            ZoneShape::Circle { center, radius } => {
+                let (x0, y0) = ((center.0 - radius).ceil() as i32, (center.1 - radius).ceil() as i32);
+                let (x1, y1) = ((center.0 + radius).floor() as i32, (center.1 + radius).floor() as i32);
+                (x0, y0, (x1 - x0 + 1).max(0), (y1 - y0 + 1).max(0))
+            }
+        }
+    }
+}
+
+/// What a no-go zone does to the cells it covers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoneEffect {
+    /// Steps into the zone cost `1 + penalty` times as much; overlapping penalties add up.
+    /// Negative penalties make steps cheaper, but never below 0.01 times the normal cost.
+    Penalty(f32),
+    /// The zone's cells count as blocked.
+    Block,
+}
+
+/// Handle for removing a zone before it expires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ZoneId(pub u64);
+
+#[derive(Clone, Debug)]
+struct Zone {
+    id: ZoneId,
+    shape: ZoneShape,
+    effect: ZoneEffect,
+    expires_at: Instant,
+}
+
+/// Temporary areas to avoid (a grenade about to land, a burning patch), laid over a
+/// `Grid2D` with `overlay`. Zones stay in force until `purge` is called at or after their
+/// expiry, so searches in the same frame agree on them. Every add, removal and expiry bumps
+/// `revision` and records the zone's bounds; feed `take_changed_rects` to
+/// `PathCache::invalidate_rect` to drop the cached paths they touch.
+///
+/// Each cell lookup checks every zone, which suits the handful of short-lived zones a
+/// game has at any time.
+#[derive(Clone, Debug, Default)]
+pub struct NoGoZones {
+    zones: Vec<Zone>,
+    next_id: u64,
+    revision: u64,
+    changed: Vec<(i32, i32, i32, i32)>,
+}
+
+impl NoGoZones {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_zone(&mut self, shape: ZoneShape, effect: ZoneEffect, expires_at: Instant) -> ZoneId {
+        let id = ZoneId(self.next_id);
+        self.next_id += 1;
+        self.zones.push(Zone { id, shape, effect, expires_at });
+        self.mark_changed(shape);
+        id
+    }
+
+    /// Removes a zone early. False if it isn't present (already expired and purged).
+    pub fn remove_zone(&mut self, id: ZoneId) -> bool {
+        let Some(i) = self.zones.iter().position(|z| z.id == id) else {
+            return false;
+        };
+        let zone = self.zones.remove(i);
+        self.mark_changed(zone.shape);
+        true
+    }
+
+    /// Drops the zones that have expired by `now`; returns how many.
+    pub fn purge(&mut self, now: Instant) -> usize {
+        let (expired, live): (Vec<Zone>, Vec<Zone>) =
+            std::mem::take(&mut self.zones).into_iter().partition(|z| z.expires_at <= now);
+        self.zones = live;
+        for zone in &expired {
+            self.mark_changed(zone.shape);
+        }
+        expired.len()
+    }
+
+    /// Bumped by every add, removal and expiry.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Bounds of the zones added, removed or expired since the last call.
+    pub fn take_changed_rects(&mut self) -> Vec<(i32, i32, i32, i32)> {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn is_blocked(&self, pos: GridPos) -> bool {
+        self.zones.iter().any(|z| z.effect == ZoneEffect::Block && z.shape.contains(pos))
+    }
+
+    /// Cost multiplier for stepping into `pos`: 1 plus the penalties of the zones over it,
+    /// floored at 0.01 so no step becomes free or negative.
+    pub fn cost_multiplier(&self, pos: GridPos) -> f32 {
+        let penalties = self.zones.iter().filter(|z| z.shape.contains(pos)).map(|z| match z.effect {
+            ZoneEffect::Penalty(penalty) => penalty,
+            ZoneEffect::Block => 0.0,
+        });
+        (1.0 + penalties.sum::<f32>()).max(MIN_COST_MULTIPLIER)
+    }
+
+    /// Zones (of either effect) covering any node of `path`, in the order they were added:
+    /// followers check their path after new zones land and replan if it's non-empty.
+    pub fn zones_overlapping(&self, path: &[GridPos]) -> Vec<ZoneId> {
+        self.zones.iter().filter(|z| path.iter().any(|&p| z.shape.contains(p))).map(|z| z.id).collect()
+    }
+
+    /// `grid` with these zones applied.
+    pub fn overlay<'a>(&'a self, grid: &'a Grid2D) -> ZonedGrid2D<'a> {
+        ZonedGrid2D { base: grid, zones: self }
+    }
+
+    fn mark_changed(&mut self, shape: ZoneShape) {
+        self.revision += 1;
+        self.changed.push(shape.bounds());
+    }
+}
+
+/// A `Grid2D` with `NoGoZones` laid over it: blocking zones count as walls (corner rules
+/// included), penalty zones raise the cost of stepping in. Built by `NoGoZones::overlay`.
+pub struct ZonedGrid2D<'a> {
+    pub base: &'a Grid2D,
+    pub zones: &'a NoGoZones,
+}
+
+impl ZonedGrid2D<'_> {
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.base.is_blocked(x, y) || self.zones.is_blocked(GridPos { x, y })
+    }
+}
+
+impl Graph for ZonedGrid2D<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        !self.is_blocked(node.x, node.y)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, mut visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        for (n, cost) in self.base.neighbors_where(*node, |x, y| self.is_blocked(x, y)) {
+            visit(n, cost * self.zones.cost_multiplier(n));
+        }
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        Grid2D::line_clear(from, to, |x, y| self.is_blocked(x, y))
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.base.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.base.geometric_distance(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::cache::PathCache;
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::Octile;
+    use crate::traits::PathStatus;
+
+    #[test]
+    fn blocking_zone_forces_a_detour_until_it_expires() {
+        let grid = Grid2D::new(30, 20, DiagonalMode::OnlyIfBothOpen);
+        let (start, goal) = (GridPos { x: 2, y: 10 }, GridPos { x: 27, y: 10 });
+        let mut zones = NoGoZones::new();
+        let mut cache = PathCache::new(16, Duration::from_secs(60));
+        let search = |zones: &NoGoZones| astar(&zones.overlay(&grid), &Octile, start, goal, AStarConfig::default());
+
+        let original = search(&zones);
+        cache.insert(start, goal, original.clone());
+        assert!(zones.zones_overlapping(&original.path).is_empty());
+
+        // A grenade lands on the straight route
+        let now = Instant::now();
+        let blast = ZoneShape::Circle { center: (14.0, 10.0), radius: 3.5 };
+        let id = zones.add_zone(blast, ZoneEffect::Block, now + Duration::from_secs(30));
+        assert_eq!(zones.revision(), 1);
+        assert_eq!(zones.zones_overlapping(&original.path), vec![id]);
+        for rect in zones.take_changed_rects() {
+            cache.invalidate_rect(rect);
+        }
+        assert!(cache.get(&start, &goal).is_none());
+
+        let detour = search(&zones);
+        assert_eq!(detour.status, PathStatus::Found);
+        assert!(detour.cost > original.cost);
+        assert!(detour.path.iter().all(|&p| !blast.contains(p)));
+        assert!(zones.zones_overlapping(&detour.path).is_empty());
+        cache.insert(start, goal, detour.clone());
+
+        // Not yet expired: purge keeps it
+        assert_eq!(zones.purge(now + Duration::from_secs(10)), 0);
+        assert!(zones.take_changed_rects().is_empty());
+        assert_eq!(search(&zones).path, detour.path);
+
+        // Expired and purged: the straight route comes back
+        assert_eq!(zones.purge(now + Duration::from_secs(30)), 1);
+        assert_eq!(zones.revision(), 2);
+        for rect in zones.take_changed_rects() {
+            cache.invalidate_rect(rect);
+        }
+        assert!(cache.get(&start, &goal).is_none());
+        assert_eq!(search(&zones).path, original.path);
+        assert!(!zones.remove_zone(id));
+    }
+
+    #[test]
+    fn penalty_zones_steer_without_blocking() {
+        let grid = Grid2D::new(20, 9, DiagonalMode::OnlyIfBothOpen);
+        let (start, goal) = (GridPos { x: 0, y: 4 }, GridPos { x: 19, y: 4 });
+        let mut zones = NoGoZones::new();
+        let later = Instant::now() + Duration::from_secs(5);
+        // A wall-to-wall band: too wide to go around, so it's crossed at the penalty
+        zones.add_zone(ZoneShape::Rect(9, 0, 2, 9), ZoneEffect::Penalty(4.0), later);
+        let crossed = astar(&zones.overlay(&grid), &Octile, start, goal, AStarConfig::default());
+        assert_eq!(crossed.cost, 19.0 + 2.0 * 4.0);
+        // A small patch on the route is walked around instead
+        let patch = zones.add_zone(ZoneShape::Rect(4, 3, 2, 3), ZoneEffect::Penalty(4.0), later);
+        assert_eq!(zones.overlay(&grid).zones.cost_multiplier(GridPos { x: 4, y: 4 }), 5.0);
+        let around = astar(&zones.overlay(&grid), &Octile, start, goal, AStarConfig::default());
+        assert!(zones.zones_overlapping(&around.path).iter().all(|&id| id != patch));
+        assert!(around.path.iter().all(|&p| !zones.is_blocked(p)));
+
+        // Penalties below -1 floor at a small positive multiplier rather than going negative
+        zones.add_zone(ZoneShape::Rect(0, 0, 2, 2), ZoneEffect::Penalty(-5.0), later);
+        zones.add_zone(ZoneShape::Rect(0, 0, 1, 1), ZoneEffect::Penalty(f32::NAN), later);
+        assert_eq!(zones.cost_multiplier(GridPos { x: 1, y: 1 }), MIN_COST_MULTIPLIER);
+        assert_eq!(zones.cost_multiplier(GridPos { x: 0, y: 0 }), MIN_COST_MULTIPLIER);
+        let overlay = zones.overlay(&grid);
+        overlay.neighbors(&GridPos { x: 0, y: 1 }, |_, cost| assert!(cost > 0.0));
+    }
+}
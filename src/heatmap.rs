@@ -0,0 +1,218 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::graphs::grid2d::{Grid2D, GridPos};
+
+/// How many recorded paths passed through each cell of a grid, for spotting the corridors
+/// traffic funnels through. Counters are atomic so parallel searches can share one heatmap
+/// through `record_shared` (see `find_paths_parallel_recorded` and `PathCache::record_into`).
+pub struct PathHeatmap {
+    pub width: usize,
+    pub height: usize,
+    counts: Vec<AtomicU32>,
+}
+
+impl PathHeatmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            counts: (0..width * height).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    /// Same dimensions as `grid`.
+    pub fn for_grid(grid: &Grid2D) -> Self {
+        Self::new(grid.width, grid.height)
+    }
+
+    /// Counts every cell of `path` once, however often the path revisits it (a unit waiting
+    /// in place or doubling back is still one path through the cell). Cells outside the map
+    /// are ignored.
+    pub fn record(&mut self, path: &[GridPos]) {
+        for i in self.distinct_indices(path) {
+            *self.counts[i].get_mut() += 1;
+        }
+    }
+
+    /// `record` through a shared reference, for recording from several threads at once.
+    pub fn record_shared(&self, path: &[GridPos]) {
+        for i in self.distinct_indices(path) {
+            self.counts[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The count at (x, y); 0 outside the map.
+    pub fn get(&self, x: i32, y: i32) -> u32 {
+        self.index(GridPos { x, y }).map_or(0, |i| self.counts[i].load(Ordering::Relaxed))
+    }
+
+    /// A snapshot of the counts, row-major.
+    pub fn counts(&self) -> Vec<u32> {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Counts divided by the highest count, row-major. All zeros when nothing was recorded.
+    pub fn normalized(&self) -> Vec<f32> {
+        normalize(&self.counts())
+    }
+
+    /// Multiplies every count by `factor` (rounding down), so old traffic fades out.
+    pub fn decay(&mut self, factor: f32) {
+        for count in &mut self.counts {
+            let c = count.get_mut();
+            *c = (*c as f32 * factor.max(0.0)) as u32;
+        }
+    }
+
+    /// The `n` busiest cells with their counts, busiest first (ties in row-major order).
+    /// Cells never visited are left out.
+    pub fn top_n_cells(&self, n: usize) -> Vec<(GridPos, u32)> {
+        top_n_heatmap_cells(&self.counts(), self.width, n)
+    }
+
+    fn distinct_indices(&self, path: &[GridPos]) -> Vec<usize> {
+        let mut indices: Vec<usize> = path.iter().filter_map(|&pos| self.index(pos)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    fn index(&self, pos: GridPos) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x as usize >= self.width || pos.y as usize >= self.height {
+            return None;
+        }
+        Some(pos.y as usize * self.width + pos.x as usize)
+    }
+}
+
+impl Grid2D {
+    /// How many of `paths` pass through each cell, row-major (`width * height` entries).
+    /// A one-shot `PathHeatmap` for when the paths are all at hand.
+    pub fn path_heatmap(&self, paths: &[&[GridPos]]) -> Vec<u32> {
+        let mut heatmap = PathHeatmap::for_grid(self);
+        for path in paths {
+            heatmap.record(path);
+        }
+        heatmap.counts()
+    }
+
+    /// `path_heatmap` divided by its highest count.
+    pub fn path_heatmap_normalized(&self, paths: &[&[GridPos]]) -> Vec<f32> {
+        normalize(&self.path_heatmap(paths))
+    }
+}
+
+/// The `n` highest cells of a row-major `heatmap`, busiest first (ties in row-major order).
+/// Zero cells are left out.
+pub fn top_n_heatmap_cells(heatmap: &[u32], grid_width: usize, n: usize) -> Vec<(GridPos, u32)> {
+    let mut cells: Vec<(usize, u32)> = heatmap.iter().copied().enumerate().filter(|&(_, c)| c > 0).collect();
+    cells.sort_by_key(|&(i, c)| (std::cmp::Reverse(c), i));
+    cells
+        .into_iter()
+        .take(n)
+        .map(|(i, c)| (GridPos { x: (i % grid_width) as i32, y: (i / grid_width) as i32 }, c))
+        .collect()
+}
+
+fn normalize(counts: &[u32]) -> Vec<f32> {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return vec![0.0; counts.len()];
+    }
+    counts.iter().map(|&c| c as f32 / max as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::algorithms::astar::AStarConfig;
+    use crate::algorithms::parallel::find_paths_parallel_recorded;
+    use crate::cache::{astar_with_cache, PathCache};
+    use crate::graphs::grid2d::DiagonalMode;
+    use crate::heuristics::Manhattan;
+
+    #[test]
+    fn two_paths_meet_in_the_shared_corridor() {
+        // Two horizontal paths that both squeeze through the gap at (2, 1)
+        let mut heatmap = PathHeatmap::new(5, 3);
+        let upper = [(0, 0), (1, 0), (2, 1), (3, 0), (4, 0)].map(|(x, y)| GridPos { x, y });
+        let lower = [(0, 2), (1, 2), (2, 1), (3, 2), (4, 2)].map(|(x, y)| GridPos { x, y });
+        heatmap.record(&upper);
+        heatmap.record(&lower);
+
+        assert_eq!(heatmap.get(2, 1), 2);
+        assert_eq!(heatmap.get(0, 0), 1);
+        assert_eq!(heatmap.get(4, 2), 1);
+        assert_eq!(heatmap.get(2, 0), 0);
+        assert_eq!(heatmap.get(-1, 0), 0);
+        assert_eq!(heatmap.top_n_cells(2), vec![(GridPos { x: 2, y: 1 }, 2), (GridPos { x: 0, y: 0 }, 1)]);
+        assert_eq!(heatmap.top_n_cells(100).len(), 9);
+
+        let normalized = heatmap.normalized();
+        assert_eq!(normalized[5 + 2], 1.0);
+        assert_eq!(normalized[0], 0.5);
+        assert_eq!(normalized[1], 0.5);
+        assert_eq!(normalized[2], 0.0);
+
+        heatmap.decay(0.5);
+        assert_eq!((heatmap.get(2, 1), heatmap.get(0, 0)), (1, 0));
+        assert_eq!(heatmap.top_n_cells(5), vec![(GridPos { x: 2, y: 1 }, 1)]);
+    }
+
+    #[test]
+    fn parallel_and_cached_searches_record_into_shared_heatmap() {
+        let mut grid = Grid2D::new(7, 5, DiagonalMode::Never);
+        grid.set_region_blocked((3, 0, 1, 5), true);
+        grid.set_blocked(3, 2, false); // The only way through
+        let gap = GridPos { x: 3, y: 2 };
+        let queries: Vec<(GridPos, GridPos)> = (0..5).map(|y| (GridPos { x: 0, y }, GridPos { x: 6, y: 4 - y })).collect();
+
+        let heatmap = Arc::new(PathHeatmap::for_grid(&grid));
+        let results = find_paths_parallel_recorded(&grid, &Manhattan, &queries, AStarConfig::default(), &heatmap);
+        assert_eq!(heatmap.get(gap.x, gap.y), results.len() as u32);
+        // The gap and the cells on either side of it carry all the traffic
+        let hottest: Vec<GridPos> = heatmap.top_n_cells(3).into_iter().map(|(pos, _)| pos).collect();
+        assert_eq!(hottest, vec![GridPos { x: 2, y: 2 }, gap, GridPos { x: 4, y: 2 }]);
+        assert!(heatmap.top_n_cells(4)[3].1 < 5);
+
+        // Every path handed out by the cache counts, hits included
+        let mut cache = PathCache::new(8, Duration::from_secs(60));
+        cache.record_into(Arc::clone(&heatmap));
+        for _ in 0..3 {
+            let _ = astar_with_cache(&grid, &Manhattan, queries[0].0, queries[0].1, AStarConfig::default(), &mut cache);
+        }
+        assert_eq!(heatmap.get(gap.x, gap.y), 8);
+    }
+
+    #[test]
+    fn grid_heatmap_counts_paths_through_a_cell() {
+        let grid = Grid2D::new(5, 5, DiagonalMode::Always);
+        let diagonal = [(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)].map(|(x, y)| GridPos { x, y });
+        let column = [(2, 0), (2, 1), (2, 2), (2, 3)].map(|(x, y)| GridPos { x, y });
+        let row = [(0, 2), (1, 2), (2, 2)].map(|(x, y)| GridPos { x, y });
+        let paths: [&[GridPos]; 3] = [&diagonal, &column, &row];
+
+        let heatmap = grid.path_heatmap(&paths);
+        assert_eq!(heatmap.len(), 25);
+        assert_eq!(heatmap[2 * 5 + 2], 3);
+        assert_eq!(heatmap[0], 1);
+        assert_eq!(heatmap.iter().sum::<u32>(), 12);
+
+        let normalized = grid.path_heatmap_normalized(&paths);
+        assert_eq!(normalized[2 * 5 + 2], 1.0);
+        assert_eq!(normalized[2 * 5 + 1], 1.0 / 3.0);
+        assert_eq!(normalized[4], 0.0);
+
+        assert_eq!(
+            top_n_heatmap_cells(&heatmap, grid.width, 2),
+            vec![(GridPos { x: 2, y: 2 }, 3), (GridPos { x: 0, y: 0 }, 1)]
+        );
+
+        // A path that doubles back through (2,2) still counts once there
+        let back_and_forth = [(1, 2), (2, 2), (3, 2), (2, 2), (1, 2)].map(|(x, y)| GridPos { x, y });
+        let heatmap = grid.path_heatmap(&[&back_and_forth, &row]);
+        assert_eq!((heatmap[2 * 5 + 2], heatmap[2 * 5 + 1], heatmap[2 * 5 + 3]), (2, 2, 1));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::traits::Heuristic;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::traits::{GoalBoundHeuristic, Heuristic};
+use crate::error::GridError;
+use crate::graphs::grid2d::{DiagonalMode, Grid2D, GridPos};
 
 pub trait Position {
     fn x(&self) -> f32;
@@ -6,6 +13,11 @@ pub trait Position {
     fn z(&self) -> f32 { 0.0 }  // Optional for 2D
 }
 
+impl Position for (f32, f32) {
+    fn x(&self) -> f32 { self.0 }
+    fn y(&self) -> f32 { self.1 }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Manhattan;
 
@@ -27,6 +39,116 @@ impl Default for Diagonal {
     }
 }
 
+impl Diagonal {
+    /// Matches the grid's step costs. Diagonals are capped at two cardinal steps
+    /// (and treated as such when diagonal movement is off) to stay admissible.
+    pub fn for_grid(grid: &Grid2D) -> Self {
+        let diagonal_cost = if grid.diagonal_movement == DiagonalMode::Never {
+            2.0
+        } else {
+            grid.diagonal_cost.min(2.0)
+        };
+        Self {
+            cardinal_cost: 1.0,
+            diagonal_cost,
+        }
+    }
+}
+
+/// Multiplies another heuristic by a constant factor. `is_admissible` is conservative: any
+/// `factor` above 1.0 reports false, even where `inner` under-estimates by at least that much.
+#[derive(Clone, Copy, Debug)]
+pub struct Scaled<H> {
+    pub inner: H,
+    pub factor: f32,
+}
+
+impl<H> Scaled<H> {
+    /// Scales `inner` by the grid's smallest directional multiplier so it stays
+    /// admissible when some directions are cheaper than the base cost.
+    pub fn for_directional_grid(grid: &Grid2D, inner: H) -> Self {
+        Self {
+            inner,
+            factor: grid.min_directional_multiplier(),
+        }
+    }
+}
+
+impl<N, H: Heuristic<N>> Heuristic<N> for Scaled<H> {
+    fn estimate(&self, from: &N, to: &N) -> f32 {
+        self.inner.estimate(from, to) * self.factor
+    }
+
+    // Conservative: a factor above 1 may still under-estimate (e.g. `Manhattan` scaled by the
+    // cheapest step cost), but `inner` can't say by how much, so it reports false
+    fn is_admissible(&self) -> bool {
+        self.inner.is_admissible() && self.factor <= 1.0
+    }
+}
+
+/// Memoizes an expensive heuristic: each node's estimate toward the current goal is
+/// computed once, then looked up. An estimate toward a different goal drops the memo, so
+/// nothing carries over between queries with different goals (searches that alternate
+/// goals, like bidirectional ones, gain nothing). Call `reset` if `inner` itself changes.
+/// Not `Sync`; give each thread its own.
+#[derive(Clone, Debug)]
+pub struct CachedHeuristic<H, N> {
+    inner: H,
+    // The goal the memo is for, and estimates from each node toward it
+    memo: RefCell<(Option<N>, HashMap<N, f32>)>,
+}
+
+impl<H, N> CachedHeuristic<H, N> {
+    pub fn new(inner: H) -> Self {
+        Self { inner, memo: RefCell::new((None, HashMap::new())) }
+    }
+
+    /// Forgets every memoized estimate.
+    pub fn reset(&self) {
+        let mut memo = self.memo.borrow_mut();
+        memo.0 = None;
+        memo.1.clear();
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Number of memoized estimates.
+    pub fn cached_len(&self) -> usize {
+        self.memo.borrow().1.len()
+    }
+}
+
+impl<N: Eq + Hash + Clone, H: Heuristic<N>> Heuristic<N> for CachedHeuristic<H, N> {
+    fn estimate(&self, from: &N, to: &N) -> f32 {
+        let mut memo = self.memo.borrow_mut();
+        let (goal, estimates) = &mut *memo;
+        if goal.as_ref() != Some(to) {
+            *goal = Some(to.clone());
+            estimates.clear();
+        }
+        if let Some(&h) = estimates.get(from) {
+            return h;
+        }
+        let h = self.inner.estimate(from, to);
+        estimates.insert(from.clone(), h);
+        h
+    }
+
+    fn is_admissible(&self) -> bool {
+        self.inner.is_admissible()
+    }
+}
+
+/// `Diagonal::default()` as a unit struct: unit cardinal steps, sqrt(2) diagonals.
+#[derive(Clone, Copy, Debug)]
+pub struct Octile;
+
+/// Largest axis distance; exact when diagonal steps cost the same as cardinal ones.
+#[derive(Clone, Copy, Debug)]
+pub struct Chebyshev;
+
 #[derive(Clone, Copy, Debug)]
 pub struct Zero;  // For Dijkstra behavior
 
@@ -61,8 +183,139 @@ impl<P: Position> Heuristic<P> for Diagonal {
     }
 }
 
+impl<P: Position> Heuristic<P> for Octile {
+    fn estimate(&self, from: &P, to: &P) -> f32 {
+        Diagonal::default().estimate(from, to)
+    }
+}
+
+impl<P: Position> Heuristic<P> for Chebyshev {
+    fn estimate(&self, from: &P, to: &P) -> f32 {
+        let dx = (from.x() - to.x()).abs();
+        let dy = (from.y() - to.y()).abs();
+        let dz = (from.z() - to.z()).abs();
+        dx.max(dy).max(dz)
+    }
+}
+
 impl<P> Heuristic<P> for Zero {
     fn estimate(&self, _from: &P, _to: &P) -> f32 {
         0.0
     }
 }
+
+/// Adapts a heuristic without per-goal setup to `GoalBoundHeuristic`.
+#[derive(Clone, Copy, Debug)]
+pub struct NoopPrecomputed<H>(pub H);
+
+impl<N, H: Heuristic<N>> Heuristic<N> for NoopPrecomputed<H> {
+    fn estimate(&self, from: &N, to: &N) -> f32 {
+        self.0.estimate(from, to)
+    }
+
+    fn is_admissible(&self) -> bool {
+        self.0.is_admissible()
+    }
+}
+
+impl<N, H: Heuristic<N> + Clone> GoalBoundHeuristic<N> for NoopPrecomputed<H> {
+    type Precomputed = H;
+
+    fn precompute(&self, _goal: &N) -> H {
+        self.0.clone()
+    }
+}
+
+/// Exact costs between one landmark cell and every cell of a grid, in both directions, for
+/// `AltHeuristic`. Built by `DistanceField::landmark`; a blocked or out-of-bounds landmark
+/// has no finite costs and contributes nothing.
+#[derive(Clone, Debug)]
+pub struct Landmark {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) to_landmark: Vec<f32>,   // d(cell, L)
+    pub(crate) from_landmark: Vec<f32>, // d(L, cell)
+}
+
+/// ALT (A*, landmarks, triangle inequality): exact costs between every cell and a few
+/// landmark cells, and the bound `max(d(from, L) - d(to, L), d(L, to) - d(L, from))`
+/// maximized over landmarks `L`. Both terms matter once costs are asymmetric (directional
+/// costs). Admissible for the grid as it was when the landmarks were built; rebuild after
+/// cost or obstacle edits. Landmarks spread around the map edges work best.
+#[derive(Clone, Debug)]
+pub struct AltHeuristic {
+    width: usize,
+    height: usize,
+    landmarks: usize,
+    // Per cell, (cost to, cost from) each landmark, landmarks innermost
+    distances: Arc<[f32]>,
+}
+
+impl AltHeuristic {
+    /// Fails with `DimensionMismatch` unless all `landmarks` were built on grids of one size.
+    pub fn new(landmarks: &[Landmark]) -> Result<Self, GridError> {
+        let (width, height) = landmarks.first().map_or((0, 0), |l| (l.width, l.height));
+        if landmarks.iter().any(|l| (l.width, l.height) != (width, height)) {
+            return Err(GridError::DimensionMismatch);
+        }
+        let distances = (0..width * height)
+            .flat_map(|cell| landmarks.iter().flat_map(move |l| [l.to_landmark[cell], l.from_landmark[cell]]))
+            .collect();
+        Ok(Self { width, height, landmarks: landmarks.len(), distances })
+    }
+
+    fn row(&self, pos: &GridPos) -> Option<&[f32]> {
+        if pos.x < 0 || pos.y < 0 || pos.x as usize >= self.width || pos.y as usize >= self.height {
+            return None;
+        }
+        let start = (pos.y as usize * self.width + pos.x as usize) * self.landmarks * 2;
+        Some(&self.distances[start..start + self.landmarks * 2])
+    }
+}
+
+// Landmarks unreachable from either end carry no information and are skipped
+fn alt_bound(from: &[f32], to: &[f32]) -> f32 {
+    let bound = |a: f32, b: f32| if a.is_finite() && b.is_finite() { a - b } else { 0.0 };
+    from.chunks_exact(2)
+        .zip(to.chunks_exact(2))
+        .map(|(f, t)| bound(f[0], t[0]).max(bound(t[1], f[1])))
+        .fold(0.0, f32::max)
+}
+
+impl Heuristic<GridPos> for AltHeuristic {
+    fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+        match (self.row(from), self.row(to)) {
+            (Some(from), Some(to)) => alt_bound(from, to),
+            _ => 0.0,
+        }
+    }
+}
+
+impl GoalBoundHeuristic<GridPos> for AltHeuristic {
+    type Precomputed = AltToGoal;
+
+    fn precompute(&self, goal: &GridPos) -> AltToGoal {
+        AltToGoal { goal: *goal, goal_row: self.row(goal).map(<[f32]>::to_vec), alt: self.clone() }
+    }
+}
+
+/// `AltHeuristic` with the goal's landmark distances looked up once. Estimates toward any
+/// other node fall back to the full lookup.
+#[derive(Clone, Debug)]
+pub struct AltToGoal {
+    alt: AltHeuristic,
+    goal: GridPos,
+    goal_row: Option<Vec<f32>>,
+}
+
+impl Heuristic<GridPos> for AltToGoal {
+    fn estimate(&self, from: &GridPos, to: &GridPos) -> f32 {
+        if *to != self.goal {
+            return self.alt.estimate(from, to);
+        }
+        match (self.alt.row(from), &self.goal_row) {
+            (Some(from), Some(goal)) => alt_bound(from, goal),
+            _ => 0.0,
+        }
+    }
+}
@@ -1,8 +1,20 @@
 pub mod traits;
 pub mod heuristics;
+pub mod cost;
 pub mod algorithms;
 pub mod graphs;
 pub mod cache;
+pub mod heatmap;
+pub mod crowd;
 pub mod smoothing;
+pub mod perturb;
 pub mod budget;
+pub mod geometry;
+pub mod error;
+pub mod trace;
+/// Re-exports of the types and functions most programs need: `use pathforge::prelude::*;`.
+pub mod prelude;
+#[cfg(feature = "svg-export")]
+pub mod export;
+mod rng;
 pub use algorithms::flowfield;
@@ -0,0 +1,219 @@
+use crate::graphs::grid2d::GridPos;
+use crate::heuristics::Position;
+use crate::rng::SplitMix64;
+use crate::traits::Graph;
+
+/// Waypoints that can be nudged sideways by `perturb_path`.
+pub trait Perturbable: Position + Clone + PartialEq {
+    /// Returns the waypoint moved by (dx, dy). Grid nodes snap to an adjacent cell.
+    fn offset(&self, dx: f32, dy: f32) -> Self;
+}
+
+impl Perturbable for GridPos {
+    fn offset(&self, dx: f32, dy: f32) -> Self {
+        GridPos {
+            x: self.x + (dx.round() as i32).clamp(-1, 1),
+            y: self.y + (dy.round() as i32).clamp(-1, 1),
+        }
+    }
+}
+
+impl Perturbable for (f32, f32) {
+    fn offset(&self, dx: f32, dy: f32) -> Self {
+        (self.0 + dx, self.1 + dy)
+    }
+}
+
+/// Offsets intermediate waypoints sideways so agents sharing a route don't walk in single file.
+///
+/// Each moved waypoint must be passable, have line of sight (`can_traverse`) to both
+/// neighbors and be joined to them by edges of `graph`, so a step-by-step path stays one.
+/// The path's cost (`graph.path_cost`, terrain included) may grow by at most
+/// `max_cost_factor` (e.g. 1.1 for 10%). With `keep_anchors`, waypoints where the path turns
+/// stay fixed. The endpoints never move. Deterministic for a given `rng_seed`.
+pub fn perturb_path<G>(
+    graph: &G,
+    path: &[G::Node],
+    rng_seed: u64,
+    lateral_spread: f32,
+    keep_anchors: bool,
+    max_cost_factor: f32,
+) -> Vec<G::Node>
+where
+    G: Graph,
+    G::Node: Perturbable,
+{
+    perturb_with(
+        path,
+        rng_seed,
+        lateral_spread,
+        keep_anchors,
+        max_cost_factor,
+        // An edge also means both ends are passable
+        |a, b| graph.can_traverse(a, b) && graph.path_cost(&[a.clone(), b.clone()]).is_finite(),
+        |p| graph.path_cost(p),
+    )
+}
+
+/// `perturb_path` for continuous paths; `is_clear(a, b)` reports whether the segment is walkable.
+/// The cost budget is the path's Euclidean length.
+pub fn perturb_points<F>(
+    path: &[(f32, f32)],
+    rng_seed: u64,
+    lateral_spread: f32,
+    keep_anchors: bool,
+    max_cost_factor: f32,
+    is_clear: F,
+) -> Vec<(f32, f32)>
+where
+    F: Fn(&(f32, f32), &(f32, f32)) -> bool,
+{
+    perturb_with(
+        path,
+        rng_seed,
+        lateral_spread,
+        keep_anchors,
+        max_cost_factor,
+        is_clear,
+        path_length,
+    )
+}
+
+fn perturb_with<N, C, K>(
+    path: &[N],
+    rng_seed: u64,
+    lateral_spread: f32,
+    keep_anchors: bool,
+    max_cost_factor: f32,
+    clear: C,
+    cost: K,
+) -> Vec<N>
+where
+    N: Perturbable,
+    C: Fn(&N, &N) -> bool,
+    K: Fn(&[N]) -> f32,
+{
+    let mut out = path.to_vec();
+    if path.len() < 3 || lateral_spread <= 0.0 {
+        return out;
+    }
+
+    let budget = cost(path) * max_cost_factor.max(1.0);
+    let mut rng = SplitMix64(rng_seed);
+
+    for i in 1..path.len() - 1 {
+        // Always draw, so the sequence for later waypoints doesn't depend on earlier rejections
+        let r = rng.next_f32() * 2.0 - 1.0;
+
+        if keep_anchors && is_turn(&path[i - 1], &path[i], &path[i + 1]) {
+            continue;
+        }
+
+        // Offset along the normal of the local direction of travel
+        let (tx, ty) = (path[i + 1].x() - path[i - 1].x(), path[i + 1].y() - path[i - 1].y());
+        let len = (tx * tx + ty * ty).sqrt();
+        if len <= f32::EPSILON {
+            continue;
+        }
+        let shift = r * lateral_spread;
+        let candidate = path[i].offset(-ty / len * shift, tx / len * shift);
+        if candidate == path[i]
+            || !clear(&out[i - 1], &candidate)
+            || !clear(&candidate, &path[i + 1])
+        {
+            continue;
+        }
+
+        let previous = std::mem::replace(&mut out[i], candidate);
+        if cost(&out) > budget {
+            out[i] = previous;
+        }
+    }
+
+    out
+}
+
+fn path_length<N: Position>(path: &[N]) -> f32 {
+    path.windows(2)
+        .map(|w| ((w[1].x() - w[0].x()).powi(2) + (w[1].y() - w[0].y()).powi(2)).sqrt())
+        .sum()
+}
+
+fn is_turn<N: Position>(a: &N, b: &N, c: &N) -> bool {
+    let cross = (b.x() - a.x()) * (c.y() - b.y()) - (b.y() - a.y()) * (c.x() - b.x());
+    cross.abs() > 1e-4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D};
+
+    fn corridor_path() -> Vec<GridPos> {
+        (0..12).map(|x| GridPos { x, y: 3 }).collect()
+    }
+
+    #[test]
+    fn zero_spread_leaves_path_unchanged() {
+        let grid = Grid2D::new(12, 7, DiagonalMode::Always);
+        let path = corridor_path();
+        assert_eq!(perturb_path(&grid, &path, 42, 0.0, false, 1.5), path);
+
+        let points = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)];
+        assert_eq!(perturb_points(&points, 42, 0.0, false, 1.5, |_, _| true), points);
+    }
+
+    #[test]
+    fn fixed_seed_is_reproducible_and_stays_passable() {
+        let mut grid = Grid2D::new(12, 7, DiagonalMode::Always);
+        grid.set_region_blocked((0, 5, 12, 2), true);
+        grid.set_blocked(6, 2, true);
+        let path = corridor_path();
+
+        let a = perturb_path(&grid, &path, 7, 1.0, false, 1.3);
+        let b = perturb_path(&grid, &path, 7, 1.0, false, 1.3);
+        assert_eq!(a, b);
+        assert_ne!(a, path, "seed 7 should move at least one waypoint");
+        assert_eq!(a.first(), path.first());
+        assert_eq!(a.last(), path.last());
+        assert!(a.iter().all(|p| grid.is_passable(p)));
+        assert!(a.windows(2).all(|w| grid.can_traverse(&w[0], &w[1])));
+        assert!(a.windows(2).all(|w| grid.path_cost(w).is_finite()), "still a step-by-step path");
+        assert!(grid.path_cost(&a) <= grid.path_cost(&path) * 1.3 + 1e-4);
+    }
+
+    #[test]
+    fn weighted_grid_budget_counts_terrain_cost() {
+        // Cheap corridor row between expensive side rows
+        let mut grid = Grid2D::new(12, 7, DiagonalMode::Always);
+        let path = corridor_path();
+        let uniform = perturb_path(&grid, &path, 7, 1.0, false, 1.3);
+        assert_ne!(uniform, path);
+
+        for x in 0..12 {
+            for y in [0, 1, 2, 4, 5, 6] {
+                grid.set_cost(x, y, 4.0);
+            }
+        }
+        // Any sideways step now costs more than the 30% budget; the length-only check allowed it
+        assert_eq!(perturb_path(&grid, &path, 7, 1.0, false, 1.3), path);
+
+        let loose = perturb_path(&grid, &path, 7, 1.0, false, 3.0);
+        assert_ne!(loose, path);
+        assert!(grid.path_cost(&loose) <= grid.path_cost(&path) * 3.0 + 1e-4);
+        assert!(loose.windows(2).all(|w| grid.path_cost(w).is_finite()));
+    }
+
+    #[test]
+    fn float_paths_respect_cost_factor_and_anchors() {
+        let points: Vec<(f32, f32)> = vec![(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (4.0, 2.0), (4.0, 4.0)];
+        let out = perturb_points(&points, 3, 0.5, true, 1.05, |_, _| true);
+
+        assert_eq!(out.len(), points.len());
+        assert_eq!(out[2], points[2], "corner is an anchor");
+        assert!(path_length(&out) <= path_length(&points) * 1.05 + 1e-4);
+
+        // Nothing can move if every segment is reported blocked
+        assert_eq!(perturb_points(&points, 3, 0.5, false, 2.0, |_, _| false), points);
+    }
+}
@@ -0,0 +1,16 @@
+pub use crate::algorithms::astar::{astar, AStarConfig, TieBreaking};
+pub use crate::algorithms::dispatch::{find_path_with, Algorithm, ErasedHeuristic};
+pub use crate::algorithms::flowfield::{Direction, Direction3D, FlowField, FlowField3D};
+pub use crate::algorithms::jps::jps;
+pub use crate::algorithms::nearest::nearest_targets;
+pub use crate::algorithms::parallel::find_paths_parallel;
+pub use crate::algorithms::theta::theta_star;
+pub use crate::budget::BudgetedPathfinder;
+pub use crate::cache::{astar_with_cache, PathCache};
+pub use crate::graphs::grid2d::{CellType, DiagonalMode, Grid2D, GridPos};
+pub use crate::graphs::grid3d::{Grid3D, GridPos3D};
+pub use crate::graphs::hierarchical::HierarchicalGrid;
+pub use crate::graphs::navmesh::NavMesh;
+pub use crate::heuristics::{Chebyshev, Diagonal, Euclidean, Manhattan, Octile, Zero};
+pub use crate::smoothing::{smooth_path, SmoothingMethod};
+pub use crate::traits::{Graph, Heuristic, PathResult, PathStatus};
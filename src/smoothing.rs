@@ -1,41 +1,476 @@
-use crate::traits::Graph;
+use crate::algorithms::funnel::{string_pull, Portal};
+use crate::error::PathforgeError;
+use crate::geometry::point_segment_distance;
+use crate::graphs::grid2d::{Grid2D, GridPos};
+use crate::traits::{path_length, Graph, PathResult, PathStatus};
 
+// Upper bound on waypoint radii, in cells, so followers still track the route in open areas
+const MAX_WAYPOINT_RADIUS: f32 = 4.0;
+
+/// A steering target: agents may move on to the next waypoint once within `radius`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Waypoint {
+    pub pos: (f32, f32), // World units, cell (x, y) centered at (x * cell_size, y * cell_size)
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SmoothingMethod {
     None,
     RemoveRedundant, // String pulling / Line-of-sight shortening
+    /// Repeats `RemoveRedundant` until a pass removes fewer than `min_improvement` nodes
+    /// (or none), or after `max_iterations` passes.
+    IterativeShortening { max_iterations: usize, min_improvement: f32 },
+    /// Shortest any-angle path through the corridor of cells a grid path visits; see
+    /// `funnel_smooth`. Its corners are cell corners, not nodes, so `smooth_path` falls back to
+    /// `RemoveRedundant` and `postprocess` returns the funneled points as `waypoints`.
+    Funnel,
+}
+
+/// Output of `smooth_path`.
+#[derive(Debug, Clone)]
+pub struct SmoothedPathResult<N> {
+    pub path: Vec<N>,
+    pub length: f32,                // Geometric length of `path` (`traits::path_length`)
+    pub iterations: usize,          // Shortening passes performed
+    pub original_node_count: usize, // Node count of the input path
 }
 
+/// Shortens `path` by `method`. `SmoothingMethod::Funnel` yields points rather than nodes, so
+/// here it runs `RemoveRedundant` (the node path the funnel starts from); call
+/// `funnel_smooth` or `postprocess` for the funneled points.
 pub fn smooth_path<G: Graph>(
     graph: &G,
     path: &[G::Node],
     method: SmoothingMethod,
-) -> Vec<G::Node> {
-    if path.len() < 3 { return path.to_vec(); }
+) -> SmoothedPathResult<G::Node> {
+    let mut result = SmoothedPathResult {
+        path: path.to_vec(),
+        length: path_length(graph, path),
+        iterations: 0,
+        original_node_count: path.len(),
+    };
+    if path.len() < 3 { return result; }
     
     match method {
-        SmoothingMethod::None => path.to_vec(),
-        SmoothingMethod::RemoveRedundant => {
-             let mut smooth = vec![path[0].clone()];
-             let mut current_idx = 0;
-             
-             while current_idx < path.len() - 1 {
-                 let mut next_idx = current_idx + 1;
-                 // Look ahead as far as possible
-                 // We scan from the END backwards to find the furthest visible node
-                 // optimization: check reverse
-                 for i in (current_idx + 2..path.len()).rev() {
-                     if graph.can_traverse(&path[current_idx], &path[i]) {
-                         next_idx = i;
-                         break;
-                     }
-                 }
-                 smooth.push(path[next_idx].clone());
-                 current_idx = next_idx;
-             }
-             
-             smooth
+        SmoothingMethod::None => {}
+        SmoothingMethod::RemoveRedundant | SmoothingMethod::Funnel => {
+            result.path = remove_redundant(graph, path);
+            result.iterations = 1;
+        }
+        SmoothingMethod::IterativeShortening { max_iterations, min_improvement } => {
+            while result.iterations < max_iterations {
+                let next = remove_redundant(graph, &result.path);
+                let removed = result.path.len() - next.len();
+                result.path = next;
+                result.iterations += 1;
+                if removed == 0 || (removed as f32) < min_improvement {
+                    break;
+                }
+            }
+        }
+    }
+    result.length = path_length(graph, &result.path);
+    result
+}
+
+fn remove_redundant<G: Graph>(graph: &G, path: &[G::Node]) -> Vec<G::Node> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+    line_of_sight_indices(graph, path).into_iter().map(|i| path[i].clone()).collect()
+}
+
+// Indices of the nodes `remove_redundant` keeps, for a path of at least 2 nodes
+fn line_of_sight_indices<G: Graph>(graph: &G, path: &[G::Node]) -> Vec<usize> {
+    let mut kept = vec![0];
+    let mut current_idx = 0;
+
+    while current_idx < path.len() - 1 {
+        let mut next_idx = current_idx + 1;
+        // Look ahead as far as possible
+        // We scan from the END backwards to find the furthest visible node
+        for i in (current_idx + 2..path.len()).rev() {
+            if graph.can_traverse(&path[current_idx], &path[i]) {
+                next_idx = i;
+                break;
+            }
+        }
+        kept.push(next_idx);
+        current_idx = next_idx;
+    }
+
+    kept
+}
+
+/// At most `max_points` nodes of `path`, first and last included, with line of sight
+/// (`can_traverse`) between consecutive ones, e.g. to fit a path into a fixed-size network
+/// message. Starts from the fewest nodes line-of-sight shortening needs (the corners it
+/// can't see past), then spends what's left of the budget on the sharpest remaining
+/// corners, as measured by the detour through them (`Graph::geometric_distance`; graphs
+/// without geometry get no extra nodes).
+///
+/// Fails with `EmptyPath` on an empty path, and with `WaypointBudgetExceeded` if even the
+/// mandatory corners don't fit.
+pub fn downsample_path<G: Graph>(graph: &G, path: &[G::Node], max_points: usize) -> Result<Vec<G::Node>, PathforgeError> {
+    if path.is_empty() {
+        return Err(PathforgeError::EmptyPath);
+    }
+    if path.len() <= max_points {
+        return Ok(path.to_vec());
+    }
+    let mut kept = if path.len() < 2 { vec![0] } else { line_of_sight_indices(graph, path) };
+    if kept.len() > max_points {
+        return Err(PathforgeError::WaypointBudgetExceeded { required: kept.len(), budget: max_points });
+    }
+
+    let distance = |a: usize, b: usize| graph.geometric_distance(&path[a], &path[b]).unwrap_or(0.0);
+    while kept.len() < max_points {
+        // (detour, position in `kept` to insert at, path index)
+        let mut best: Option<(f32, usize, usize)> = None;
+        for (slot, pair) in kept.windows(2).enumerate() {
+            let (a, b) = (pair[0], pair[1]);
+            for k in a + 1..b {
+                let detour = distance(a, k) + distance(k, b) - distance(a, b);
+                if detour <= 1e-3 || best.is_some_and(|(d, _, _)| detour <= d) {
+                    continue;
+                }
+                if graph.can_traverse(&path[a], &path[k]) && graph.can_traverse(&path[k], &path[b]) {
+                    best = Some((detour, slot + 1, k));
+                }
+            }
+        }
+        let Some((_, slot, k)) = best else { break };
+        kept.insert(slot, k);
+    }
+    Ok(kept.into_iter().map(|i| path[i].clone()).collect())
+}
+
+/// At most `max_points` of `points` (never fewer than the two ends), chosen by
+/// Ramer-Douglas-Peucker with a point budget instead of a tolerance: the point farthest
+/// from the simplified line is added until the budget runs out or the rest lie on it. For
+/// float paths such as `funnel_smooth` output, which have no obstacles to respect.
+pub fn downsample_points(points: &[(f32, f32)], max_points: usize) -> Vec<(f32, f32)> {
+    if points.len() <= max_points.max(2) {
+        return points.to_vec();
+    }
+    let mut kept = vec![0, points.len() - 1];
+    while kept.len() < max_points {
+        let farthest = kept
+            .windows(2)
+            .enumerate()
+            .flat_map(|(slot, pair)| {
+                let (a, b) = (points[pair[0]], points[pair[1]]);
+                (pair[0] + 1..pair[1]).map(move |k| (point_segment_distance(points[k], a, b), slot + 1, k))
+            })
+            .max_by(|x, y| x.0.total_cmp(&y.0));
+        match farthest {
+            Some((dist, slot, k)) if dist > 0.0 => kept.insert(slot, k),
+            _ => break,
+        }
+    }
+    kept.into_iter().map(|i| points[i]).collect()
+}
+
+/// Pulls a grid path taut, bending only on the corners of blocked cells. The path is first
+/// shortened with line of sight (`RemoveRedundant`); the funnel then runs through the
+/// corridor of cells those segments cross, widened by one cell on either side where both
+/// cells across a step are open, so the result is never longer than the line-of-sight path.
+/// Returns points in cell units (cell (x, y) centered at (x, y)).
+/// Diagonal steps are split through an open cardinal neighbor, or squeezed through the
+/// shared corner when the grid allowed cutting between two blocked cells.
+/// Fails on an empty path or one that leaves the grid.
+pub fn funnel_smooth(grid: &Grid2D, path: &[GridPos]) -> Result<Vec<(f32, f32)>, PathforgeError> {
+    let (Some(first), Some(last)) = (path.first(), path.last()) else { return Err(PathforgeError::EmptyPath) };
+    let outside = |p: &&GridPos| p.x < 0 || p.y < 0 || p.x as usize >= grid.width || p.y as usize >= grid.height;
+    if let Some(p) = path.iter().find(outside) {
+        return Err(PathforgeError::PositionOutOfBounds { x: p.x, y: p.y });
+    }
+    let lift = |x: f32, y: f32| [x, 0.0, y];
+    let open = |x: i32, y: i32| !grid.is_blocked(x, y);
+
+    let shortened = smooth_path(grid, path, SmoothingMethod::RemoveRedundant).path;
+    let mut cells = vec![*first];
+    for pair in supercover(&shortened).windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a.x != b.x && a.y != b.y {
+            if open(b.x, a.y) {
+                cells.push(GridPos { x: b.x, y: a.y });
+            } else if open(a.x, b.y) {
+                cells.push(GridPos { x: a.x, y: b.y });
+            }
+        }
+        cells.push(b);
+    }
+
+    let mut edges = Vec::with_capacity(cells.len());
+    for pair in cells.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let mid = ((a.x + b.x) as f32 / 2.0, (a.y + b.y) as f32 / 2.0);
+        if dx != 0 && dy != 0 {
+            edges.push(CellEdge { mid, normal: (0.0, 0.0), left: 0.0, right: 0.0 });
+            continue;
+        }
+        // Left of the direction of travel, with y pointing down the grid
+        let (nx, ny) = (-dy, dx);
+        let widen = |s: i32| open(a.x + nx * s, a.y + ny * s) && open(b.x + nx * s, b.y + ny * s);
+        edges.push(CellEdge {
+            mid,
+            normal: (nx as f32, ny as f32),
+            left: if widen(1) { 1.5 } else { 0.5 },
+            right: if widen(-1) { 1.5 } else { 0.5 },
+        });
+    }
+    // A portal sharing an endpoint with its neighbor (the inside of a turn) must not be
+    // widened past it, or the two would cross
+    let shared = |i: usize, side: f32| {
+        let p = edges[i].at(side * 0.5);
+        let touches = |j: usize| edges[j].at(0.5) == p || edges[j].at(-0.5) == p;
+        (i > 0 && touches(i - 1)) || (i + 1 < edges.len() && touches(i + 1))
+    };
+
+    let mut portals = vec![Portal { left: lift(first.x as f32, first.y as f32), right: lift(first.x as f32, first.y as f32) }];
+    for (i, edge) in edges.iter().enumerate() {
+        let left = if shared(i, 1.0) { 0.5 } else { edge.left };
+        let right = if shared(i, -1.0) { 0.5 } else { edge.right };
+        let (l, r) = (edge.at(left), edge.at(-right));
+        portals.push(Portal { left: lift(l.0, l.1), right: lift(r.0, r.1) });
+    }
+    portals.push(Portal { left: lift(last.x as f32, last.y as f32), right: lift(last.x as f32, last.y as f32) });
+
+    Ok(string_pull(&portals).into_iter().map(|p| (p[0], p[2])).collect())
+}
+
+// The edge between two consecutive corridor cells, widened by `left` and `right` half-cells
+// along `normal`. A corner squeeze has a zero normal.
+struct CellEdge {
+    mid: (f32, f32),
+    normal: (f32, f32),
+    left: f32,
+    right: f32,
+}
+
+impl CellEdge {
+    fn at(&self, offset: f32) -> (f32, f32) {
+        (self.mid.0 + self.normal.0 * offset, self.mid.1 + self.normal.1 * offset)
+    }
+}
+
+/// Converts a grid path into sparse waypoints for physics-driven agents. The path is first
+/// shortened with line of sight (`RemoveRedundant`), then each waypoint gets an acceptance
+/// radius equal to its clearance from obstacles, capped at `MAX_WAYPOINT_RADIUS` cells.
+/// `max_segment_length` (world units) inserts evenly spaced waypoints along longer segments.
+pub fn to_waypoints(
+    grid: &Grid2D,
+    path: &[GridPos],
+    cell_size: f32,
+    max_segment_length: Option<f32>,
+) -> Vec<Waypoint> {
+    let smoothed = smooth_path(grid, path, SmoothingMethod::RemoveRedundant).path;
+    let Some(first) = smoothed.first() else { return Vec::new() };
+    let clearance = grid.clearance_field();
+    // Clearance at the cell under a point given in cell units
+    let radius_at = |x: f32, y: f32| {
+        clearance.get(x.round() as i32, y.round() as i32).min(MAX_WAYPOINT_RADIUS) * cell_size
+    };
+    let waypoint = |x: f32, y: f32| Waypoint {
+        pos: (x * cell_size, y * cell_size),
+        radius: radius_at(x, y),
+    };
+
+    let mut out = vec![waypoint(first.x as f32, first.y as f32)];
+    for pair in smoothed.windows(2) {
+        let (ax, ay) = (pair[0].x as f32, pair[0].y as f32);
+        let (bx, by) = (pair[1].x as f32, pair[1].y as f32);
+        let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt() * cell_size;
+        let pieces = match max_segment_length {
+            Some(max) if max > 0.0 => (length / max).ceil().max(1.0) as usize,
+            _ => 1,
+        };
+        for i in 1..=pieces {
+            let t = i as f32 / pieces as f32;
+            out.push(waypoint(ax + (bx - ax) * t, ay + (by - ay) * t));
+        }
+    }
+    out
+}
+
+/// Stages of `postprocess`, in the order they run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostProcessStage {
+    ExpandJumps,
+    Smooth,
+    Simplify,
+    FloatWaypoints,
+}
+
+/// What `postprocess` should do with a grid path. The default does nothing.
+#[derive(Clone, Copy, Debug)]
+pub struct PostProcessOptions {
+    /// Fill in the cells between sparse nodes (JPS jump points) with unit steps.
+    pub expand_jumps: bool,
+    pub smoothing: SmoothingMethod,
+    /// Drop nodes within this distance (cells) of the simplified line, if it stays clear.
+    pub simplify_epsilon: Option<f32>,
+    pub to_float_waypoints: bool,
+}
+
+impl Default for PostProcessOptions {
+    fn default() -> Self {
+        Self {
+            expand_jumps: false,
+            smoothing: SmoothingMethod::None,
+            simplify_epsilon: None,
+            to_float_waypoints: false,
+        }
+    }
+}
+
+/// Output of `postprocess`.
+#[derive(Debug, Clone)]
+pub struct ProcessedPath {
+    pub path: Vec<GridPos>,
+    pub cost: f32, // Recomputed along `path` whenever a stage moved it off the grid's edges
+    pub length: f32, // Euclidean, in cells; recomputed along with `cost`
+    pub status: PathStatus,
+    pub waypoints: Option<Vec<(f32, f32)>>, // Cell centers, in cell units
+    pub stages: Vec<PostProcessStage>,      // Stages that ran, in order
+}
+
+/// Runs the requested stages over any grid search result (A*, JPS, hierarchical): expand
+/// jumps, smooth, simplify, then convert to float waypoints. Stages that would not change
+/// the path (e.g. expanding a path that is already dense) are not recorded.
+pub fn postprocess(grid: &Grid2D, result: PathResult<GridPos>, opts: PostProcessOptions) -> ProcessedPath {
+    let mut out = ProcessedPath {
+        path: result.path,
+        cost: result.cost,
+        length: result.length,
+        status: result.status,
+        waypoints: None,
+        stages: Vec::new(),
+    };
+
+    if opts.expand_jumps && out.path.windows(2).any(|w| (w[1].x - w[0].x).abs().max((w[1].y - w[0].y).abs()) > 1) {
+        out.path = expand_jumps(&out.path);
+        out.stages.push(PostProcessStage::ExpandJumps);
+    }
+    // Smoothing needs a dense path to find every shortcut
+    let mut funneled = None;
+    if opts.smoothing != SmoothingMethod::None && out.path.len() > 2 {
+        if opts.smoothing == SmoothingMethod::Funnel {
+            // A path that leaves the grid gets the plain smoothing below only
+            funneled = funnel_smooth(grid, &out.path).ok();
+        }
+        out.path = smooth_path(grid, &out.path, opts.smoothing).path;
+        out.stages.push(PostProcessStage::Smooth);
+    }
+    if let Some(epsilon) = opts.simplify_epsilon {
+        if out.path.len() > 2 {
+            out.path = simplify(grid, &out.path, epsilon);
+            out.stages.push(PostProcessStage::Simplify);
+        }
+    }
+    if let Some(points) = &funneled {
+        out.cost = points.windows(2).map(|w| line_cost(grid, w[0], w[1])).sum();
+        out.length = points.windows(2).map(|w| ((w[1].0 - w[0].0).powi(2) + (w[1].1 - w[0].1).powi(2)).sqrt()).sum();
+    } else if out.stages.iter().any(|s| matches!(s, PostProcessStage::Smooth | PostProcessStage::Simplify)) {
+        out.cost = out.path.windows(2).map(|w| segment_cost(grid, w[0], w[1])).sum();
+        out.length = path_length(grid, &out.path);
+    }
+    if opts.to_float_waypoints {
+        out.stages.push(PostProcessStage::FloatWaypoints);
+    }
+    if funneled.is_some() || opts.to_float_waypoints {
+        out.waypoints = funneled.or_else(|| Some(out.path.iter().map(|p| (p.x as f32, p.y as f32)).collect()));
+    }
+    out
+}
+
+// Every cell the segments between consecutive nodes pass through, in order. Crossing exactly
+// through a corner is a diagonal step.
+fn supercover(path: &[GridPos]) -> Vec<GridPos> {
+    let mut out: Vec<GridPos> = path.first().into_iter().copied().collect();
+    for pair in path.windows(2) {
+        let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        let (nx, ny) = (dx.abs(), dy.abs());
+        let (mut ix, mut iy, mut p) = (0, 0, pair[0]);
+        while ix < nx || iy < ny {
+            // Compares where the segment leaves the current cell: side, top/bottom, or corner
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if decision <= 0 {
+                p.x += dx.signum();
+                ix += 1;
+            }
+            if decision >= 0 {
+                p.y += dy.signum();
+                iy += 1;
+            }
+            out.push(p);
         }
     }
+    out
+}
+
+// Straight and diagonal runs between consecutive nodes, as JPS produces them
+fn expand_jumps(path: &[GridPos]) -> Vec<GridPos> {
+    let mut out: Vec<GridPos> = path.first().into_iter().copied().collect();
+    for pair in path.windows(2) {
+        let mut p = pair[0];
+        while p != pair[1] {
+            p = GridPos { x: p.x + (pair[1].x - p.x).signum(), y: p.y + (pair[1].y - p.y).signum() };
+            out.push(p);
+        }
+    }
+    out
+}
+
+// Ramer-Douglas-Peucker, only shortcutting where the grid has line of sight
+fn simplify(grid: &Grid2D, path: &[GridPos], epsilon: f32) -> Vec<GridPos> {
+    let point = |p: &GridPos| (p.x as f32, p.y as f32);
+    let mut keep = vec![false; path.len()];
+    keep[0] = true;
+    keep[path.len() - 1] = true;
+    let mut stack = vec![(0, path.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        if last - first < 2 {
+            continue;
+        }
+        let (a, b) = (point(&path[first]), point(&path[last]));
+        let (far, dist) = (first + 1..last)
+            .map(|i| (i, point_segment_distance(point(&path[i]), a, b)))
+            .fold((first + 1, -1.0), |best, c| if c.1 > best.1 { c } else { best });
+        if dist > epsilon || !grid.can_traverse(&path[first], &path[last]) {
+            keep[far] = true;
+            stack.push((first, far));
+            stack.push((far, last));
+        }
+    }
+    path.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| *p).collect()
+}
+
+// Grid step cost between neighbors; longer (any-angle) segments cost their length times the
+// mean cost of the cells sampled along them
+fn segment_cost(grid: &Grid2D, a: GridPos, b: GridPos) -> f32 {
+    if let Some((_, cost)) = grid.neighbors_iter(a).find(|(n, _)| *n == b) {
+        return cost;
+    }
+    line_cost(grid, (a.x as f32, a.y as f32), (b.x as f32, b.y as f32))
+}
+
+fn line_cost(grid: &Grid2D, a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let samples = length.ceil().max(1.0) as usize;
+    // Funneled segments run along the edges of blocked cells; samples landing on one are skipped
+    let (sum, count) = (0..samples)
+        .map(|i| {
+            let t = (i as f32 + 0.5) / samples as f32;
+            grid.get_cost((a.0 + dx * t).round() as i32, (a.1 + dy * t).round() as i32)
+        })
+        .filter(|c| c.is_finite())
+        .fold((0.0, 0), |(sum, count), c| (sum + c, count + 1));
+    if count == 0 { length } else { length * sum / count as f32 }
 }
 
 #[cfg(test)]
@@ -54,7 +489,7 @@ mod tests {
             GridPos { x: 4, y: 3 },
         ];
 
-        let smoothed = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant);
+        let smoothed = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant).path;
         assert_eq!(smoothed, vec![GridPos { x: 0, y: 0 }, GridPos { x: 4, y: 3 }]);
     }
 
@@ -74,11 +509,286 @@ mod tests {
             GridPos { x: 4, y: 2 },
         ];
 
-        let smoothed = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant);
+        let smoothed = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant).path;
         assert!(
             smoothed.len() > 2,
             "Obstacle should prevent full straightening; got {:?}",
             smoothed
         );
     }
+
+    #[test]
+    fn open_field_gives_two_wide_waypoints() {
+        let grid = Grid2D::new(20, 20, DiagonalMode::Always);
+        let raw: Vec<GridPos> = (0..10).map(|i| GridPos { x: 5 + i, y: 5 + i / 2 }).collect();
+
+        let waypoints = to_waypoints(&grid, &raw, 2.0, None);
+        assert_eq!(waypoints.len(), 2);
+        assert_eq!(waypoints[0].pos, (10.0, 10.0));
+        assert_eq!(waypoints[1].pos, (28.0, 18.0));
+        assert!(waypoints.iter().all(|w| w.radius >= 8.0), "{waypoints:?}");
+
+        // Capping segments at 5 units splits the ~19.7 unit leg into four
+        let capped = to_waypoints(&grid, &raw, 2.0, Some(5.0));
+        assert_eq!(capped.len(), 5);
+        assert_eq!(capped.last(), waypoints.last());
+    }
+
+    #[test]
+    fn corridor_waypoints_shrink_to_half_a_cell() {
+        let mut grid = Grid2D::new(12, 3, DiagonalMode::Always);
+        grid.set_region_blocked((0, 0, 12, 1), true);
+        grid.set_region_blocked((0, 2, 12, 1), true);
+        let raw: Vec<GridPos> = (1..11).map(|x| GridPos { x, y: 1 }).collect();
+
+        let waypoints = to_waypoints(&grid, &raw, 1.0, Some(3.0));
+        assert_eq!(waypoints.len(), 4);
+        assert!(waypoints.iter().all(|w| (w.radius - 0.5).abs() < 1e-5), "{waypoints:?}");
+    }
+
+    #[test]
+    fn iterative_shortening_converges_and_counts_passes() {
+        // Zig-zag maze: walls hang alternately from the top and bottom
+        let mut grid = Grid2D::new(13, 7, DiagonalMode::Never);
+        for x in [3, 9] {
+            grid.set_region_blocked((x, 0, 1, 5), true);
+        }
+        grid.set_region_blocked((6, 2, 1, 5), true);
+        let raw = crate::algorithms::astar::astar(
+            &grid,
+            &crate::heuristics::Manhattan,
+            GridPos { x: 0, y: 0 },
+            GridPos { x: 12, y: 0 },
+            crate::algorithms::astar::AStarConfig::default(),
+        )
+        .path;
+
+        let once = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant);
+        let iterative = smooth_path(
+            &grid,
+            &raw,
+            SmoothingMethod::IterativeShortening { max_iterations: 5, min_improvement: 0.0 },
+        );
+        assert_eq!(iterative.path, once.path);
+        assert_eq!(smooth_path(&grid, &raw, SmoothingMethod::Funnel).path, once.path);
+        assert!(iterative.path.len() < raw.len());
+        assert!(iterative.path.windows(2).all(|w| grid.can_traverse(&w[0], &w[1])));
+        // One pass that shortens, one that confirms nothing is left to remove
+        assert_eq!(iterative.iterations, 2);
+        assert_eq!(iterative.original_node_count, raw.len());
+        assert_eq!(iterative.length, path_length(&grid, &iterative.path));
+        assert!(iterative.length <= path_length(&grid, &raw));
+
+        let capped = smooth_path(
+            &grid,
+            &raw,
+            SmoothingMethod::IterativeShortening { max_iterations: 0, min_improvement: 0.0 },
+        );
+        assert_eq!(capped.path, raw);
+        assert_eq!(capped.iterations, 0);
+    }
+
+    #[test]
+    fn postprocessed_jps_matches_smoothed_astar_on_maze() {
+        use crate::algorithms::astar::{astar, AStarConfig};
+        use crate::algorithms::jps::jps;
+        use crate::graphs::hierarchical::HierarchicalGrid;
+        use crate::heuristics::Diagonal;
+
+        let build = || {
+            let mut grid = Grid2D::new(64, 64, DiagonalMode::Always);
+            for x in (4..64).step_by(4) {
+                grid.set_region_blocked((x, 0, 1, 54), true);
+            }
+            grid
+        };
+        let grid = build();
+        let heuristic = Diagonal::for_grid(&grid);
+        let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 63, y: 0 });
+        let smooth = PostProcessOptions {
+            expand_jumps: true,
+            smoothing: SmoothingMethod::IterativeShortening { max_iterations: 4, min_improvement: 0.0 },
+            ..Default::default()
+        };
+
+        let jps_result = jps(&grid, &heuristic, start, goal, AStarConfig::default());
+        let processed_jps = postprocess(&grid, jps_result, smooth);
+        assert_eq!(processed_jps.stages, vec![PostProcessStage::ExpandJumps, PostProcessStage::Smooth]);
+
+        let astar_result = astar(&grid, &heuristic, start, goal, AStarConfig::default());
+        let grid_cost = astar_result.cost;
+        let processed_astar = postprocess(&grid, astar_result, smooth);
+        assert_eq!(processed_astar.stages, vec![PostProcessStage::Smooth]);
+        assert!(processed_astar.cost < grid_cost);
+        assert!((processed_jps.cost - processed_astar.cost).abs() < processed_astar.cost * 0.02);
+        for processed in [&processed_jps, &processed_astar] {
+            assert!(processed.path.windows(2).all(|w| grid.can_traverse(&w[0], &w[1])));
+        }
+
+        let funneled = postprocess(
+            &grid,
+            jps(&grid, &heuristic, start, goal, AStarConfig::default()),
+            PostProcessOptions { smoothing: SmoothingMethod::Funnel, ..smooth },
+        );
+        assert!(funneled.cost <= processed_jps.cost + 1e-3);
+        assert_eq!(funneled.waypoints.as_ref().map(|w| w[0]), Some((0.0, 0.0)));
+
+        // Simplifying and converting keeps the endpoints and never raises the cost
+        let simplified = postprocess(
+            &grid,
+            jps(&grid, &heuristic, start, goal, AStarConfig::default()),
+            PostProcessOptions { simplify_epsilon: Some(0.5), to_float_waypoints: true, ..smooth },
+        );
+        assert!(simplified.cost <= processed_jps.cost + 1e-3);
+        let waypoints = simplified.waypoints.unwrap();
+        assert_eq!(waypoints.len(), simplified.path.len());
+        assert_eq!((waypoints[0], waypoints[waypoints.len() - 1]), ((0.0, 0.0), (63.0, 0.0)));
+
+        // Hierarchical paths are already dense; with nothing requested they pass through as-is
+        let hierarchical = HierarchicalGrid::new(build(), 8).find_path(start, goal);
+        let (path, cost) = (hierarchical.path.clone(), hierarchical.cost);
+        let passthrough = postprocess(&grid, hierarchical, PostProcessOptions { expand_jumps: true, ..Default::default() });
+        assert!(passthrough.stages.is_empty());
+        assert_eq!((passthrough.path, passthrough.cost), (path, cost));
+    }
+
+    // Fails if any segment passes through the interior of a blocked cell; touching a corner
+    // or running along an edge is fine
+    fn assert_segments_clear(grid: &Grid2D, points: &[(f32, f32)]) {
+        const MARGIN: f32 = 1e-3;
+        for w in points.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let samples = (((b.0 - a.0).abs() + (b.1 - a.1).abs()) * 100.0).ceil() as usize + 1;
+            for i in 0..=samples {
+                let t = i as f32 / samples as f32;
+                let (x, y) = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+                // Every cell whose shrunken interior contains the sample
+                for cy in [(y - 0.5 + MARGIN).round(), (y + 0.5 - MARGIN).round()] {
+                    for cx in [(x - 0.5 + MARGIN).round(), (x + 0.5 - MARGIN).round()] {
+                        let inside = (x - cx).abs() < 0.5 - MARGIN && (y - cy).abs() < 0.5 - MARGIN;
+                        assert!(
+                            !(inside && grid.is_blocked(cx as i32, cy as i32)),
+                            "segment {a:?} -> {b:?} crosses blocked cell ({cx}, {cy})"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn polyline_length(points: &[(f32, f32)]) -> f32 {
+        points.windows(2).map(|w| ((w[1].0 - w[0].0).powi(2) + (w[1].1 - w[0].1).powi(2)).sqrt()).sum()
+    }
+
+    #[test]
+    fn funnel_turns_on_wall_corners() {
+        // Corridor along the top and left edges, bending around the corner of the block
+        let mut grid = Grid2D::new(6, 6, DiagonalMode::Never);
+        grid.set_region_blocked((1, 1, 5, 5), true);
+        let raw: Vec<GridPos> = (0..6).rev().map(|x| GridPos { x, y: 0 })
+            .chain((1..6).map(|y| GridPos { x: 0, y }))
+            .collect();
+
+        let points = funnel_smooth(&grid, &raw).unwrap();
+        assert_eq!(points, vec![(5.0, 0.0), (0.5, 0.5), (0.0, 5.0)]);
+        assert_segments_clear(&grid, &points);
+    }
+
+    #[test]
+    fn funnel_is_no_longer_than_line_of_sight_on_maze() {
+        use crate::algorithms::astar::{astar, AStarConfig};
+        use crate::heuristics::Diagonal;
+
+        for mode in [DiagonalMode::Always, DiagonalMode::Never] {
+            let mut grid = Grid2D::new(64, 64, mode);
+            for x in (4..64).step_by(4) {
+                grid.set_region_blocked((x, 0, 1, 54), true);
+            }
+            let heuristic = Diagonal::for_grid(&grid);
+            for (start, goal) in [
+                (GridPos { x: 0, y: 0 }, GridPos { x: 63, y: 0 }),
+                (GridPos { x: 2, y: 30 }, GridPos { x: 61, y: 10 }),
+            ] {
+                let raw = astar(&grid, &heuristic, start, goal, AStarConfig::default()).path;
+                let funneled = funnel_smooth(&grid, &raw).unwrap();
+                assert_segments_clear(&grid, &funneled);
+                assert_eq!(funneled.first(), Some(&(start.x as f32, start.y as f32)));
+                assert_eq!(funneled.last(), Some(&(goal.x as f32, goal.y as f32)));
+
+                let los: Vec<(f32, f32)> = smooth_path(&grid, &raw, SmoothingMethod::RemoveRedundant)
+                    .path
+                    .iter()
+                    .map(|p| (p.x as f32, p.y as f32))
+                    .collect();
+                assert!(
+                    polyline_length(&funneled) <= polyline_length(&los) + 1e-3,
+                    "{mode:?}: funnel {} > line of sight {}",
+                    polyline_length(&funneled),
+                    polyline_length(&los)
+                );
+            }
+        }
+    }
+
+    // Serpentine of `lanes` corridors `lane_height` tall, separated by walls with a one-cell
+    // gap at alternating ends
+    fn serpentine(width: usize, lanes: usize, lane_height: usize) -> (Grid2D, Vec<GridPos>) {
+        use crate::algorithms::astar::{astar, AStarConfig};
+        use crate::heuristics::Manhattan;
+
+        let height = lanes * (lane_height + 1) - 1;
+        let mut grid = Grid2D::new(width, height, DiagonalMode::Never);
+        for lane in 1..lanes {
+            let y = lane * (lane_height + 1) - 1;
+            grid.set_region_blocked((0, y, width, 1), true);
+            grid.set_blocked(if lane % 2 == 1 { width - 1 } else { 0 }, y, false);
+        }
+        let goal = GridPos { x: if lanes % 2 == 1 { width as i32 - 1 } else { 0 }, y: height as i32 - 1 };
+        let path = astar(&grid, &Manhattan, GridPos { x: 0, y: 0 }, goal, AStarConfig::default()).path;
+        (grid, path)
+    }
+
+    #[test]
+    fn downsampled_maze_path_fits_the_budget_with_line_of_sight() {
+        let (grid, path) = serpentine(100, 8, 3);
+        assert!(path.len() >= 800, "{}", path.len());
+
+        let points = downsample_path(&grid, &path, 32).unwrap();
+        assert!(points.len() <= 32);
+        assert_eq!((points[0], points.last()), (path[0], path.last()));
+        assert!(points.windows(2).all(|w| grid.can_traverse(&w[0], &w[1])));
+        // A subsequence of the path
+        let mut rest = path.iter();
+        assert!(points.iter().all(|p| rest.any(|q| q == p)));
+
+        // In the open, spare budget goes to the sharpest corner first
+        let open = Grid2D::new(12, 12, DiagonalMode::Never);
+        let ell: Vec<GridPos> = (0..10).map(|x| GridPos { x, y: 0 }).chain((0..10).map(|y| GridPos { x: 10, y })).collect();
+        assert_eq!(downsample_path(&open, &ell, 2).unwrap(), vec![ell[0], ell[19]]);
+        assert_eq!(downsample_path(&open, &ell, 3).unwrap(), vec![ell[0], ell[10], ell[19]]);
+
+        assert_eq!(downsample_path(&grid, &path[..20], 32).unwrap(), path[..20]);
+        assert_eq!(downsample_path(&grid, &[] as &[GridPos], 32), Err(PathforgeError::EmptyPath));
+    }
+
+    #[test]
+    fn too_many_mandatory_corners_is_an_error() {
+        let (grid, path) = serpentine(10, 20, 1);
+        let Err(PathforgeError::WaypointBudgetExceeded { required, budget }) = downsample_path(&grid, &path, 32) else {
+            panic!("expected a budget error");
+        };
+        assert_eq!(budget, 32);
+        assert!(required >= 40, "{required}");
+        assert_eq!(downsample_path(&grid, &path, required).unwrap().len(), required);
+    }
+
+    #[test]
+    fn point_budget_keeps_the_farthest_points_first() {
+        let zigzag: Vec<(f32, f32)> = (0..50).map(|i| (i as f32, if i == 20 { 5.0 } else { (i % 2) as f32 * 0.1 })).collect();
+        assert_eq!(downsample_points(&zigzag, 3), vec![(0.0, 0.0), (20.0, 5.0), (49.0, 0.1)]);
+        assert_eq!(downsample_points(&zigzag, 0).len(), 2);
+        assert_eq!(downsample_points(&zigzag, 100), zigzag);
+        let line: Vec<(f32, f32)> = (0..10).map(|i| (i as f32, 2.0 * i as f32)).collect();
+        assert_eq!(downsample_points(&line, 5), vec![(0.0, 0.0), (9.0, 18.0)]);
+    }
 }
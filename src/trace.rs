@@ -0,0 +1,370 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::TraceParseError;
+use crate::graphs::grid2d::GridPos;
+use crate::graphs::grid3d::GridPos3D;
+use crate::traits::{Graph, PathResult, PathStatus};
+
+/// Environment variable that makes `assert_trace_matches!` rewrite goldens instead of comparing.
+pub const UPDATE_GOLDENS_ENV: &str = "PATHFORGE_UPDATE_GOLDENS";
+
+/// Nodes that can appear in a trace. Tokens must not contain whitespace.
+pub trait TraceNode {
+    fn trace_token(&self) -> String;
+}
+
+impl TraceNode for GridPos {
+    fn trace_token(&self) -> String {
+        format!("{},{}", self.x, self.y)
+    }
+}
+
+impl TraceNode for GridPos3D {
+    fn trace_token(&self) -> String {
+        format!("{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+impl TraceNode for u32 {
+    fn trace_token(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Everything observable about one search: expansion order, outcome and stats.
+/// Costs are stored exactly (shortest round-trip formatting), so traces compare bit for bit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTrace {
+    pub name: String,
+    pub status: PathStatus,
+    pub cost: f32,
+    pub nodes_expanded: usize,
+    pub nodes_in_open_set: usize,
+    pub best_g_score: f32,
+    pub path: Vec<String>,
+    /// Nodes in the order the search expanded them. Empty for algorithms that don't expand
+    /// through `Graph::neighbors` (e.g. JPS, which works on `JpsGrid`).
+    pub expansions: Vec<String>,
+}
+
+impl SearchTrace {
+    /// A trace with only the outcome and stats of `result`.
+    pub fn from_result<N: TraceNode>(name: &str, result: &PathResult<N>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: result.status,
+            cost: result.cost,
+            nodes_expanded: result.nodes_expanded,
+            nodes_in_open_set: result.nodes_in_open_set,
+            best_g_score: result.best_g_score,
+            path: result.path.iter().map(TraceNode::trace_token).collect(),
+            expansions: Vec::new(),
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        use std::fmt::Write;
+        // Writing to a String can't fail
+        let _ = writeln!(out, "trace {}", self.name);
+        let _ = writeln!(out, "status {:?}", self.status);
+        let _ = writeln!(out, "cost {}", self.cost);
+        let _ = writeln!(out, "stats {} {} {}", self.nodes_expanded, self.nodes_in_open_set, self.best_g_score);
+        let _ = writeln!(out, "path {}", self.path.join(" "));
+        let _ = writeln!(out, "expansions {}", self.expansions.join(" "));
+    }
+}
+
+/// Serializes traces to the golden file format: one `key value...` line per field, with
+/// each trace opened by a `trace <name>` line.
+pub fn traces_to_text(traces: &[SearchTrace]) -> String {
+    let mut out = String::new();
+    for trace in traces {
+        trace.write(&mut out);
+    }
+    out
+}
+
+/// Parses the output of `traces_to_text`. Blank lines are ignored.
+pub fn parse_traces(text: &str) -> Result<Vec<SearchTrace>, TraceParseError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.is_empty())
+        .peekable();
+    let mut traces = Vec::new();
+
+    while let Some((line_no, line)) = lines.next() {
+        let name = line.strip_prefix("trace ").ok_or(TraceParseError::MalformedLine(line_no))?;
+        let mut field = |key: &str| -> Result<(usize, &str), TraceParseError> {
+            let (line_no, line) = lines.next().ok_or(TraceParseError::Truncated)?;
+            let rest = line.strip_prefix(key).ok_or(TraceParseError::MalformedLine(line_no))?;
+            Ok((line_no, rest.trim_start()))
+        };
+
+        let (line_no, status) = field("status")?;
+        let status = parse_status(status).ok_or(TraceParseError::MalformedLine(line_no))?;
+        let (line_no, cost) = field("cost")?;
+        let cost = cost.parse().map_err(|_| TraceParseError::MalformedLine(line_no))?;
+        let (line_no, stats) = field("stats")?;
+        let stats: Vec<&str> = stats.split_whitespace().collect();
+        let (nodes_expanded, nodes_in_open_set, best_g_score) = match stats[..] {
+            [expanded, open, best_g] => (
+                expanded.parse().map_err(|_| TraceParseError::MalformedLine(line_no))?,
+                open.parse().map_err(|_| TraceParseError::MalformedLine(line_no))?,
+                best_g.parse().map_err(|_| TraceParseError::MalformedLine(line_no))?,
+            ),
+            _ => return Err(TraceParseError::MalformedLine(line_no)),
+        };
+        let (_, path) = field("path")?;
+        let path = path.split_whitespace().map(str::to_string).collect();
+        let (_, expansions) = field("expansions")?;
+        let expansions = expansions.split_whitespace().map(str::to_string).collect();
+
+        traces.push(SearchTrace {
+            name: name.to_string(),
+            status,
+            cost,
+            nodes_expanded,
+            nodes_in_open_set,
+            best_g_score,
+            path,
+            expansions,
+        });
+    }
+    Ok(traces)
+}
+
+fn parse_status(s: &str) -> Option<PathStatus> {
+    Some(match s {
+        "Found" => PathStatus::Found,
+        "NotFound" => PathStatus::NotFound,
+        "PartialTimeout" => PathStatus::PartialTimeout,
+        "PartialMaxIter" => PathStatus::PartialMaxIter,
+        "PartialCostLimit" => PathStatus::PartialCostLimit,
+        "UnsupportedGraph" => PathStatus::UnsupportedGraph,
+        "NoAbstractConnection" => PathStatus::NoAbstractConnection,
+        _ => return None,
+    })
+}
+
+/// Where two traces first disagree. Expansions are compared first, since a search that
+/// diverges there usually differs everywhere after.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceDiff {
+    Identical,
+    /// `None` means that trace ended before `step`.
+    Expansion { step: usize, expected: Option<String>, actual: Option<String> },
+    Path { step: usize, expected: Option<String>, actual: Option<String> },
+    Status { expected: PathStatus, actual: PathStatus },
+    Cost { expected: f32, actual: f32 },
+    Stats { field: &'static str, expected: String, actual: String },
+}
+
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node = |n: &Option<String>| n.clone().unwrap_or_else(|| "<end>".to_string());
+        match self {
+            TraceDiff::Identical => write!(f, "traces are identical"),
+            TraceDiff::Expansion { step, expected, actual } => {
+                write!(f, "expansion {step} differs: expected {}, got {}", node(expected), node(actual))
+            }
+            TraceDiff::Path { step, expected, actual } => {
+                write!(f, "path node {step} differs: expected {}, got {}", node(expected), node(actual))
+            }
+            TraceDiff::Status { expected, actual } => write!(f, "status differs: expected {expected:?}, got {actual:?}"),
+            TraceDiff::Cost { expected, actual } => write!(f, "cost differs: expected {expected}, got {actual}"),
+            TraceDiff::Stats { field, expected, actual } => write!(f, "{field} differs: expected {expected}, got {actual}"),
+        }
+    }
+}
+
+/// First divergence of `actual` from `expected`. Names are not compared.
+pub fn compare_traces(expected: &SearchTrace, actual: &SearchTrace) -> TraceDiff {
+    let first_mismatch = |a: &[String], b: &[String]| {
+        (0..a.len().max(b.len()))
+            .find(|&i| a.get(i) != b.get(i))
+            .map(|i| (i, a.get(i).cloned(), b.get(i).cloned()))
+    };
+    if let Some((step, expected, actual)) = first_mismatch(&expected.expansions, &actual.expansions) {
+        return TraceDiff::Expansion { step, expected, actual };
+    }
+    if let Some((step, expected, actual)) = first_mismatch(&expected.path, &actual.path) {
+        return TraceDiff::Path { step, expected, actual };
+    }
+    if expected.status != actual.status {
+        return TraceDiff::Status { expected: expected.status, actual: actual.status };
+    }
+    if expected.cost.to_bits() != actual.cost.to_bits() {
+        return TraceDiff::Cost { expected: expected.cost, actual: actual.cost };
+    }
+    let stats = [
+        ("nodes_expanded", expected.nodes_expanded.to_string(), actual.nodes_expanded.to_string()),
+        ("nodes_in_open_set", expected.nodes_in_open_set.to_string(), actual.nodes_in_open_set.to_string()),
+        ("best_g_score", expected.best_g_score.to_string(), actual.best_g_score.to_string()),
+    ];
+    for (field, expected, actual) in stats {
+        if expected != actual {
+            return TraceDiff::Stats { field, expected, actual };
+        }
+    }
+    TraceDiff::Identical
+}
+
+/// A `Graph` wrapper that logs the order in which a search expands nodes (each call to
+/// `neighbors`). Not `Sync`; record one query at a time.
+pub struct TraceRecorder<'a, G: Graph> {
+    graph: &'a G,
+    expansions: RefCell<Vec<G::Node>>,
+}
+
+impl<'a, G: Graph> TraceRecorder<'a, G> {
+    pub fn new(graph: &'a G) -> Self {
+        Self {
+            graph,
+            expansions: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The wrapped graph, for algorithms that need the concrete type (their trace then has
+    /// no expansions).
+    pub fn inner(&self) -> &'a G {
+        self.graph
+    }
+
+    /// Combines the recorded expansions with the search's outcome.
+    pub fn finish(self, name: &str, result: &PathResult<G::Node>) -> SearchTrace
+    where
+        G::Node: TraceNode,
+    {
+        let mut trace = SearchTrace::from_result(name, result);
+        trace.expansions = self.expansions.into_inner().iter().map(TraceNode::trace_token).collect();
+        trace
+    }
+}
+
+impl<G: Graph> Graph for TraceRecorder<'_, G> {
+    type Node = G::Node;
+
+    fn is_passable(&self, node: &Self::Node) -> bool {
+        self.graph.is_passable(node)
+    }
+
+    fn neighbors<F>(&self, node: &Self::Node, visit: F)
+    where
+        F: FnMut(Self::Node, f32),
+    {
+        self.expansions.borrow_mut().push(node.clone());
+        self.graph.neighbors(node, visit);
+    }
+
+    fn can_traverse(&self, from: &Self::Node, to: &Self::Node) -> bool {
+        self.graph.can_traverse(from, to)
+    }
+
+    fn layer_count(&self) -> usize {
+        self.graph.layer_count()
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.graph.node_count_hint()
+    }
+
+    fn geometric_distance(&self, from: &Self::Node, to: &Self::Node) -> Option<f32> {
+        self.graph.geometric_distance(from, to)
+    }
+}
+
+/// Runs `search` on each query through a fresh `TraceRecorder`, naming traces `name#i`.
+pub fn record_queries<G, F>(name: &str, graph: &G, queries: &[(G::Node, G::Node)], mut search: F) -> Vec<SearchTrace>
+where
+    G: Graph,
+    G::Node: TraceNode,
+    F: FnMut(&TraceRecorder<'_, G>, G::Node, G::Node) -> PathResult<G::Node>,
+{
+    queries
+        .iter()
+        .enumerate()
+        .map(|(i, (start, goal))| {
+            let recorder = TraceRecorder::new(graph);
+            let result = search(&recorder, start.clone(), goal.clone());
+            recorder.finish(&format!("{name}#{i}"), &result)
+        })
+        .collect()
+}
+
+/// Compares `traces` with the golden file at `path`, or rewrites the file when
+/// `PATHFORGE_UPDATE_GOLDENS` is set. The error describes the first divergence.
+pub fn check_golden(path: impl AsRef<Path>, traces: &[SearchTrace]) -> Result<(), String> {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDENS_ENV).is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        return std::fs::write(path, traces_to_text(traces)).map_err(|e| format!("{}: {e}", path.display()));
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("{}: {e} (rerun with {UPDATE_GOLDENS_ENV}=1 to create it)", path.display()))?;
+    let golden = parse_traces(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    for (expected, actual) in golden.iter().zip(traces) {
+        if expected.name != actual.name {
+            return Err(format!("{}: expected trace `{}`, got `{}`", path.display(), expected.name, actual.name));
+        }
+        let diff = compare_traces(expected, actual);
+        if diff != TraceDiff::Identical {
+            return Err(format!("{}: trace `{}`: {diff}", path.display(), expected.name));
+        }
+    }
+    if golden.len() != traces.len() {
+        return Err(format!("{}: expected {} traces, got {}", path.display(), golden.len(), traces.len()));
+    }
+    Ok(())
+}
+
+/// Asserts that traces (a slice or `Vec` of `SearchTrace`) match a golden file, reporting the
+/// first divergence. Set `PATHFORGE_UPDATE_GOLDENS=1` to regenerate the file instead.
+#[macro_export]
+macro_rules! assert_trace_matches {
+    ($golden:expr, $traces:expr) => {
+        if let Err(message) = $crate::trace::check_golden($golden, &$traces) {
+            panic!("{}", message);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::astar::{astar, AStarConfig};
+    use crate::graphs::grid2d::{DiagonalMode, Grid2D};
+    use crate::heuristics::Manhattan;
+
+    #[test]
+    fn round_trip_and_first_divergence() {
+        let mut grid = Grid2D::new(8, 8, DiagonalMode::Never);
+        grid.set_region_blocked((3, 0, 1, 6), true);
+        let queries = [(GridPos { x: 0, y: 0 }, GridPos { x: 7, y: 0 }), (GridPos { x: 0, y: 0 }, GridPos { x: 3, y: 3 })];
+        let traces = record_queries("wall", &grid, &queries, |g, s, e| astar(g, &Manhattan, s, e, AStarConfig::default()));
+        assert_eq!(traces[0].status, PathStatus::Found);
+        assert_eq!(traces[0].expansions.len(), traces[0].nodes_expanded);
+        assert_eq!(traces[1].status, PathStatus::NotFound);
+
+        let parsed = parse_traces(&traces_to_text(&traces)).unwrap();
+        assert_eq!(parsed, traces);
+        assert_eq!(compare_traces(&parsed[0], &traces[0]), TraceDiff::Identical);
+
+        // Opening a shortcut changes the search from the point it is first seen
+        grid.set_blocked(3, 1, false);
+        let changed = record_queries("wall", &grid, &queries[..1], |g, s, e| astar(g, &Manhattan, s, e, AStarConfig::default()));
+        match compare_traces(&traces[0], &changed[0]) {
+            TraceDiff::Expansion { step, .. } => assert!(step > 0),
+            other => panic!("expected an expansion diff, got {other:?}"),
+        }
+
+        assert_eq!(parse_traces("status Found\n"), Err(TraceParseError::MalformedLine(1)));
+        assert_eq!(parse_traces("trace x\nstatus Found\n"), Err(TraceParseError::Truncated));
+    }
+}
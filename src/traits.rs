@@ -1,4 +1,8 @@
+use std::collections::HashMap;
 use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::error::{MergeError, PathforgeError};
 
 /// Represents a searchable graph structure.
 /// Implementors define topology; algorithms handle search strategy.
@@ -18,6 +22,61 @@ pub trait Graph {
     fn can_traverse(&self, _from: &Self::Node, _to: &Self::Node) -> bool {
         true
     }
+
+    /// Number of stacked layers (floors) this graph spans. Flat graphs have one.
+    fn layer_count(&self) -> usize {
+        1
+    }
+
+    /// Total number of nodes, if known. Searches use it to size their maps up front
+    /// (see `search_capacity`); it never changes results.
+    fn node_count_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// Straight-line distance between two nodes, ignoring costs, for `PathResult::length`.
+    /// None for graphs without geometry.
+    fn geometric_distance(&self, _from: &Self::Node, _to: &Self::Node) -> Option<f32> {
+        None
+    }
+
+    /// Total edge cost along `path`, each step priced by searching the node's `neighbors`
+    /// for its successor (O(path length * degree)); `f32::INFINITY` if a step isn't an edge.
+    /// 0 for paths of fewer than two nodes.
+    fn path_cost(&self, path: &[Self::Node]) -> f32 {
+        path.windows(2)
+            .map(|w| {
+                let mut edge = f32::INFINITY;
+                self.neighbors(&w[0], |n, cost| {
+                    if edge.is_infinite() && n == w[1] {
+                        edge = cost;
+                    }
+                });
+                edge
+            })
+            .sum()
+    }
+}
+
+/// Sum of `geometric_distance` over consecutive nodes of `path`; steps without a distance
+/// count as 0.
+pub fn path_length<G: Graph>(graph: &G, path: &[G::Node]) -> f32 {
+    path.windows(2).map(|w| graph.geometric_distance(&w[0], &w[1]).unwrap_or(0.0)).sum()
+}
+
+// Searches never preallocate more entries than this, however large the graph
+const MAX_PREALLOCATED_NODES: usize = 1 << 12;
+
+/// Initial capacity for a search's maps and open set: the graph's node count, but no more
+/// than 4x the squared start-to-goal estimate (short searches on big maps touch few nodes)
+/// and never more than 4096 entries. 0 without a hint.
+pub fn search_capacity(node_count_hint: Option<usize>, estimate: f32) -> usize {
+    let Some(hint) = node_count_hint else {
+        return 0;
+    };
+    let local = 4.0 * estimate * estimate;
+    let local = if local.is_finite() { local as usize } else { hint };
+    hint.min(local).min(MAX_PREALLOCATED_NODES)
 }
 
 /// Heuristic function for informed search algorithms.
@@ -29,19 +88,389 @@ pub trait Heuristic<N> {
     fn is_admissible(&self) -> bool { true }
 }
 
-/// Result of a pathfinding query
+/// A graph with a natural heuristic for its own nodes, so callers don't have to pick one.
+/// See `find_path_default`.
+pub trait GraphWithHeuristic: Graph {
+    type DefaultHeuristic: Heuristic<Self::Node>;
+
+    fn default_heuristic(&self) -> Self::DefaultHeuristic;
+}
+
+/// A heuristic with per-goal setup (e.g. looking up the goal's landmark distances) that
+/// `astar_precomputed` does once per search instead of on every estimate.
+pub trait GoalBoundHeuristic<N>: Heuristic<N> {
+    type Precomputed: Heuristic<N>;
+
+    fn precompute(&self, goal: &N) -> Self::Precomputed;
+}
+
+/// Result of a pathfinding query.
+/// On `NotFound`, `path` is empty and `cost` is 0.0.
 #[derive(Debug, Clone)]
+#[must_use = "PathResult contains the computed path; ignoring it means no path was obtained"]
 pub struct PathResult<N> {
     pub path: Vec<N>,
     pub cost: f32,
+    /// Geometric length of `path` (see `path_length`), as opposed to the effort in `cost`:
+    /// 1 or sqrt(2) per grid step, straight-line for any-angle steps. 0.0 without geometry.
+    pub length: f32,
+    /// Cost from the start to each node of `path` (same length, first element 0.0), so
+    /// `cumulative_costs[i]` belongs to `path[i]`. Entries follow the path, not the cells it
+    /// crosses: JPS paths list only jump points and Theta* paths only corners, with one entry
+    /// each. Use `astar` (or Dijkstra) where a cost for every cell along the way is needed.
+    /// Empty when the producing algorithm does not track per-node costs.
+    pub cumulative_costs: Vec<f32>,
     pub nodes_expanded: usize,
+    /// Highest g-score among the nodes the search settled (including the goal when found).
+    /// On `NotFound` this tells how far the search got, since `cost` is then 0.0.
+    pub best_g_score: f32,
+    /// Open-set size when the search terminated (0 when the graph was exhausted).
+    pub nodes_in_open_set: usize,
     pub status: PathStatus,
+    /// Wall-clock time the query took, for logging slow ones. Set by `astar`, `jps`,
+    /// `theta_star` and `HierarchicalGrid::find_path` when the `timing` feature (on by
+    /// default) is enabled; None otherwise.
+    pub elapsed: Option<Duration>,
+}
+
+/// `PathResult` whose path is rebuilt node by node as it is iterated, from the search's
+/// own parent links (see `astar_stream`). Yields the same nodes in the same order as
+/// `PathResult::path`; nothing on `NotFound`.
+pub struct PathResultStream<N> {
+    pub cost: f32,
+    pub nodes_expanded: usize,
+    pub best_g_score: f32,
+    pub nodes_in_open_set: usize,
+    pub status: PathStatus,
+    // The search's `came_from` until the first read; then each path node maps to the next
+    links: HashMap<N, N>,
+    last: Option<N>, // End of the path while `links` still point backwards
+    next: Option<N>,
+}
+
+impl<N: Clone + Eq + Hash> PathResultStream<N> {
+    pub(crate) fn new(last: Option<N>, came_from: HashMap<N, N>, cost: f32, stats: SearchStats, status: PathStatus) -> Self {
+        Self {
+            cost,
+            nodes_expanded: stats.nodes_expanded,
+            best_g_score: stats.best_g_score,
+            nodes_in_open_set: stats.nodes_in_open_set,
+            status,
+            links: came_from,
+            last,
+            next: None,
+        }
+    }
+
+    /// The remaining nodes as a `Vec`.
+    pub fn collect_path(self) -> Vec<N> {
+        self.collect()
+    }
+
+    /// Drops the next `n` nodes, e.g. for an agent already `n` nodes along the path. Unlike
+    /// `Iterator::skip`, this returns the stream itself, so its fields stay readable.
+    pub fn skip_nodes(mut self, n: usize) -> Self {
+        for _ in 0..n {
+            if self.next().is_none() {
+                break;
+            }
+        }
+        self
+    }
+
+    // Turns the parent links along the path around in place (one pass, no allocation),
+    // so the path can be walked from the start
+    fn link_forward(&mut self) {
+        let Some(mut node) = self.last.take() else { return };
+        let mut child: Option<N> = None;
+        loop {
+            let parent = match child {
+                Some(child) => self.links.insert(node.clone(), child),
+                None => self.links.remove(&node),
+            };
+            let Some(parent) = parent else {
+                self.next = Some(node);
+                return;
+            };
+            child = Some(node);
+            node = parent;
+        }
+    }
+}
+
+impl<N: Clone + Eq + Hash> Iterator for PathResultStream<N> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        self.link_forward();
+        let node = self.next.take()?;
+        self.next = self.links.get(&node).cloned();
+        Some(node)
+    }
+}
+
+/// Bookkeeping shared by the search loops when building a `PathResult`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SearchStats {
+    pub nodes_expanded: usize,
+    pub best_g_score: f32,
+    pub nodes_in_open_set: usize,
 }
 
+impl SearchStats {
+    pub(crate) fn new(nodes_expanded: usize, best_g_score: f32, nodes_in_open_set: usize) -> Self {
+        Self { nodes_expanded, best_g_score, nodes_in_open_set }
+    }
+}
+
+impl<N> PathResult<N> {
+    /// Sets `elapsed` to the time since `started`, the instant the search's limits are
+    /// counted from. None without the `timing` feature, where searches don't read the clock.
+    pub(crate) fn timed(self, started: Option<Instant>) -> Self {
+        Self { elapsed: started.map(|s| s.elapsed()), ..self }
+    }
+
+    /// The path if one was found; panics with `msg` and the status otherwise.
+    #[track_caller]
+    pub fn expect(self, msg: &str) -> Vec<N> {
+        match self.status {
+            PathStatus::Found => self.path,
+            status => panic!("{msg}: {status:?}"),
+        }
+    }
+
+    /// The path if one was found; panics otherwise.
+    #[track_caller]
+    pub fn unwrap(self) -> Vec<N> {
+        match self.status {
+            PathStatus::Found => self.path,
+            status => panic!("called `PathResult::unwrap()` on a {status:?} result"),
+        }
+    }
+
+    /// The path if one was found. Partial paths (timeouts, limits) count as not found.
+    pub fn ok(self) -> Option<Vec<N>> {
+        (self.status == PathStatus::Found).then_some(self.path)
+    }
+
+    /// `f` applied to the path if one was found.
+    pub fn map_path<U, F: FnOnce(Vec<N>) -> U>(self, f: F) -> Option<U> {
+        self.ok().map(f)
+    }
+
+    /// Cross-checks the internal invariants of the result.
+    /// Useful in tests and debug assertions after post-processing a path.
+    pub fn validate(&self) -> bool {
+        if self.status == PathStatus::Found && self.path.is_empty() {
+            return false;
+        }
+        if self.cumulative_costs.is_empty() {
+            return true;
+        }
+        if self.cumulative_costs.len() != self.path.len() || self.cumulative_costs[0] != 0.0 {
+            return false;
+        }
+        if self.cumulative_costs.windows(2).any(|w| w[1] < w[0]) {
+            return false;
+        }
+        let last = self.cumulative_costs[self.cumulative_costs.len() - 1];
+        (last - self.cost).abs() <= 1e-3 * self.cost.abs().max(1.0)
+    }
+}
+
+impl<N: Clone + PartialEq> PathResult<N> {
+    /// Prefix of the path reachable within `max_cost`, with segment costs looked up via
+    /// `graph.neighbors`. Cost, length and `cumulative_costs` are always measured on `graph`,
+    /// as in `truncate`. The status depends only on whether nodes were dropped: a cut path is
+    /// `PartialCostLimit`, and one that fits whole keeps the status it had (`Found` for a
+    /// complete path, the original partial status for a path that was already partial).
+    pub fn truncate_to_cost<G: Graph<Node = N>>(&self, max_cost: f32, graph: &G) -> PathResult<N> {
+        let costs = self.segment_cumulative_costs(graph);
+        let len = costs.iter().rposition(|&c| c <= max_cost).map_or(1, |last| last + 1);
+        let mut result = self.truncate(len, graph);
+        if result.path.len() < self.path.len() {
+            result.status = PathStatus::PartialCostLimit;
+        }
+        result
+    }
+
+    /// The first `len` nodes (all of them if `len` is larger), with cost, length and
+    /// `cumulative_costs` measured on `graph` as in `truncate_to_cost`. Status and search
+    /// counters are kept, as for `split_at_cost`'s front half, so a replan from the last node
+    /// `merge`s back on. See `truncate_at_first_blocked` for where to cut.
+    pub fn truncate<G: Graph<Node = N>>(&self, len: usize, graph: &G) -> PathResult<N> {
+        let len = len.min(self.path.len());
+        let costs = self.segment_cumulative_costs(graph);
+        PathResult {
+            length: path_length(graph, &self.path[..len]),
+            path: self.path[..len].to_vec(),
+            cost: if len > 0 { costs[len - 1] } else { 0.0 },
+            cumulative_costs: costs[..len].to_vec(),
+            nodes_expanded: self.nodes_expanded,
+            best_g_score: self.best_g_score,
+            nodes_in_open_set: self.nodes_in_open_set,
+            status: self.status,
+            elapsed: self.elapsed,
+        }
+    }
+
+    /// Fraction of the path's total cost (0.0 to 1.0) covered by spending `max_cost`.
+    pub fn progress_at_cost<G: Graph<Node = N>>(&self, max_cost: f32, graph: &G) -> f32 {
+        let total = self.segment_cumulative_costs(graph).last().copied().unwrap_or(0.0);
+        if total <= 0.0 {
+            return if self.path.is_empty() { 0.0 } else { 1.0 };
+        }
+        (max_cost / total).clamp(0.0, 1.0)
+    }
+
+    /// Splits at the last node reachable within `cost`. That node ends the front half and
+    /// starts the back half. Beyond the total cost the back half is empty (`NotFound`).
+    pub fn split_at_cost<G: Graph<Node = N>>(&self, graph: &G, cost: f32) -> (PathResult<N>, PathResult<N>) {
+        let costs = self.segment_cumulative_costs(graph);
+        let idx = costs.iter().rposition(|&c| c <= cost).unwrap_or(0);
+        self.split_at_index(graph, idx, &costs)
+    }
+
+    /// Splits at the first occurrence of `node`, which ends the front half and starts the back.
+    /// Returns None if the node isn't on the path or the result has no `cumulative_costs`.
+    /// Each half's `length` is measured on `graph`.
+    pub fn split_at_node<G: Graph<Node = N>>(&self, graph: &G, node: &N) -> Option<(PathResult<N>, PathResult<N>)> {
+        if self.cumulative_costs.is_empty() {
+            return None;
+        }
+        let idx = self.path.iter().position(|n| n == node)?;
+        Some(self.split_at_index(graph, idx, &self.cumulative_costs))
+    }
+
+    /// Joins two segments whose junction node matches (the back's first node is dropped).
+    /// Costs and search counters are summed; an empty segment merges as a no-op.
+    pub fn merge(front: PathResult<N>, back: PathResult<N>) -> Result<PathResult<N>, MergeError> {
+        if back.path.is_empty() {
+            return Ok(front);
+        }
+        if front.path.is_empty() {
+            return Ok(back);
+        }
+        if front.path.last() != back.path.first() {
+            return Err(MergeError::JunctionMismatch);
+        }
+
+        let cumulative_costs = if front.cumulative_costs.is_empty() || back.cumulative_costs.is_empty() {
+            Vec::new()
+        } else {
+            let mut costs = front.cumulative_costs;
+            costs.extend(back.cumulative_costs[1..].iter().map(|c| c + front.cost));
+            costs
+        };
+        let mut path = front.path;
+        path.extend(back.path.into_iter().skip(1));
+
+        Ok(PathResult {
+            path,
+            cost: front.cost + back.cost,
+            length: front.length + back.length,
+            cumulative_costs,
+            nodes_expanded: front.nodes_expanded + back.nodes_expanded,
+            best_g_score: front.best_g_score.max(back.best_g_score),
+            nodes_in_open_set: back.nodes_in_open_set,
+            status: if front.status == PathStatus::Found { back.status } else { front.status },
+            elapsed: front.elapsed.into_iter().chain(back.elapsed).reduce(|a, b| a + b),
+        })
+    }
+
+    // `costs` are per-node costs from the start. Search counters stay with the front half.
+    fn split_at_index<G>(&self, graph: &G, idx: usize, costs: &[f32]) -> (PathResult<N>, PathResult<N>)
+    where
+        G: Graph<Node = N>,
+    {
+        let junction = costs.get(idx).copied().unwrap_or(0.0);
+        let total = costs.last().copied().unwrap_or(0.0);
+        let front_path = &self.path[..(idx + 1).min(self.path.len())];
+        let front = PathResult {
+            path: front_path.to_vec(),
+            cost: junction,
+            length: path_length(graph, front_path),
+            cumulative_costs: costs[..(idx + 1).min(costs.len())].to_vec(),
+            nodes_expanded: self.nodes_expanded,
+            best_g_score: self.best_g_score,
+            nodes_in_open_set: self.nodes_in_open_set,
+            status: self.status,
+            elapsed: self.elapsed,
+        };
+
+        let back = if idx + 1 >= self.path.len() {
+            PathResult {
+                path: Vec::new(),
+                cost: 0.0,
+                length: 0.0,
+                cumulative_costs: Vec::new(),
+                nodes_expanded: 0,
+                best_g_score: 0.0,
+                nodes_in_open_set: 0,
+                status: PathStatus::NotFound,
+                elapsed: None,
+            }
+        } else {
+            PathResult {
+                path: self.path[idx..].to_vec(),
+                cost: total - junction,
+                length: path_length(graph, &self.path[idx..]),
+                cumulative_costs: costs[idx..].iter().map(|c| c - junction).collect(),
+                nodes_expanded: 0,
+                best_g_score: 0.0,
+                nodes_in_open_set: 0,
+                status: self.status,
+                elapsed: None,
+            }
+        };
+        (front, back)
+    }
+
+    // Per-node cost from the start, re-derived from the graph's edge costs.
+    // Falls back to `cumulative_costs` for non-adjacent steps (any-angle paths).
+    fn segment_cumulative_costs<G: Graph<Node = N>>(&self, graph: &G) -> Vec<f32> {
+        let mut costs = Vec::with_capacity(self.path.len());
+        let mut total = 0.0;
+        for (i, node) in self.path.iter().enumerate() {
+            if i > 0 {
+                let prev = &self.path[i - 1];
+                let mut edge = None;
+                graph.neighbors(prev, |n, c| {
+                    if edge.is_none() && n == *node {
+                        edge = Some(c);
+                    }
+                });
+                total += edge.unwrap_or_else(|| match self.cumulative_costs.get(i) {
+                    Some(&c) => c - self.cumulative_costs[i - 1],
+                    None => f32::INFINITY,
+                });
+            }
+            costs.push(total);
+        }
+        costs
+    }
+}
+
+/// Outcome of a search. Only `UnsupportedGraph` means the call itself was wrong; every other
+/// status is a legitimate run-time result. See `as_error`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathStatus {
     Found,
     NotFound,
     PartialTimeout,  // Hit frame budget, returning best partial
     PartialMaxIter,  // Hit iteration limit
+    PartialCostLimit, // Truncated to a travel budget (see `PathResult::truncate_to_cost`)
+    UnsupportedGraph, // The algorithm can't handle this graph's cost model
+    NoAbstractConnection, // Hierarchical search: start or goal can't reach its cluster's entrances
+}
+
+impl PathStatus {
+    /// The `PathforgeError` for statuses that signal misuse: `UnsupportedGraph` maps to
+    /// `PathforgeError::UnsupportedGraph`. None for found, partial and not-found outcomes.
+    pub fn as_error(self) -> Option<PathforgeError> {
+        match self {
+            PathStatus::UnsupportedGraph => Some(PathforgeError::UnsupportedGraph),
+            _ => None,
+        }
+    }
 }
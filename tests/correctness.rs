@@ -1,8 +1,19 @@
+use std::collections::HashSet;
+
 use pathforge::{
-    algorithms::{astar::{astar, AStarConfig}, jps::jps},
-    graphs::grid2d::{Grid2D, GridPos, DiagonalMode},
-    heuristics::Diagonal,
-    traits::PathStatus,
+    algorithms::{
+        astar::{astar, astar_int, AStarConfig},
+        flowfield::{Direction, FlowField},
+        jps::jps,
+        parallel::find_paths_parallel,
+        theta::theta_star,
+    },
+    cost::{IntDiagonal, Milli},
+    graphs::{grid2d::{Grid2D, GridPos, DiagonalMode, IntGrid2D, MaskedGrid2D}, hierarchical::HierarchicalGrid},
+    heuristics::{Diagonal, Euclidean, Manhattan, Scaled},
+    traits::{search_capacity, Graph, PathResult, PathStatus},
+    error::MergeError,
+    smoothing::{postprocess, PostProcessOptions},
 };
 
 #[test]
@@ -39,3 +50,384 @@ fn test_astar_vs_jps_correctness() {
         assert!((res_astar.cost - res_jps.cost).abs() < 1e-4, "Costs differ: A*={}, JPS={}", res_astar.cost, res_jps.cost);
     }
 }
+
+#[test]
+fn test_astar_vs_jps_parity_with_custom_diagonal_cost() {
+    for diagonal_cost in [1.0, 1.5] {
+        let mut grid = Grid2D::new(50, 50, DiagonalMode::Always).with_diagonal_cost(diagonal_cost);
+        for i in 0..(50 * 50 / 3) {
+            grid.set_blocked((i * 123 + 5) % 50, (i * 456 + 7) % 50, true);
+        }
+        let heuristic = Diagonal::for_grid(&grid);
+        let config = AStarConfig::default();
+
+        for (start, goal) in [((0, 0), (49, 49)), ((3, 40), (45, 2)), ((25, 0), (20, 49))] {
+            grid.set_blocked(start.0, start.1, false);
+            grid.set_blocked(goal.0, goal.1, false);
+            let start = GridPos { x: start.0 as i32, y: start.1 as i32 };
+            let goal = GridPos { x: goal.0 as i32, y: goal.1 as i32 };
+
+            let res_astar = astar(&grid, &heuristic, start, goal, config);
+            let res_jps = jps(&grid, &heuristic, start, goal, config);
+            assert_eq!(res_astar.status, res_jps.status);
+            if res_astar.status == PathStatus::Found {
+                assert!(
+                    (res_astar.cost - res_jps.cost).abs() < 1e-3,
+                    "diagonal {}: A*={}, JPS={}", diagonal_cost, res_astar.cost, res_jps.cost
+                );
+            }
+        }
+
+        // Open diagonal run: cost is exactly 10 diagonal steps
+        let open = Grid2D::new(20, 20, DiagonalMode::Always).with_diagonal_cost(diagonal_cost);
+        let res = jps(&open, &Diagonal::for_grid(&open), GridPos { x: 0, y: 0 }, GridPos { x: 10, y: 10 }, AStarConfig::default());
+        assert!((res.cost - 10.0 * diagonal_cost).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_cumulative_costs_on_weighted_grid() {
+    let mut grid = Grid2D::new(20, 20, DiagonalMode::Always);
+    grid.set_region_blocked((10, 0, 1, 15), true);
+    for y in 0..20 {
+        grid.set_cost(5, y, 4.0);
+    }
+
+    let start = GridPos { x: 0, y: 2 };
+    let goal = GridPos { x: 19, y: 2 };
+    let res = astar(&grid, &Diagonal::default(), start, goal, AStarConfig::default());
+
+    assert_eq!(res.status, PathStatus::Found);
+    assert_eq!(res.cumulative_costs.len(), res.path.len());
+    assert_eq!(res.cumulative_costs[0], 0.0);
+    assert!((res.cumulative_costs.last().unwrap() - res.cost).abs() < 1e-4);
+    assert!(res.cumulative_costs.windows(2).all(|w| w[1] > w[0]));
+    assert!(res.validate());
+
+    let res_jps = jps(&Grid2D::new(20, 20, DiagonalMode::Always), &Diagonal::default(), start, goal, AStarConfig::default());
+    assert!((res_jps.cumulative_costs.last().unwrap() - res_jps.cost).abs() < 1e-4);
+    assert!(res_jps.cumulative_costs.windows(2).all(|w| w[1] > w[0]));
+    assert!(res_jps.validate());
+}
+
+#[test]
+fn test_not_found_reports_search_progress() {
+    let mut grid = Grid2D::new(10, 10, DiagonalMode::Always);
+    // Seal the goal in the corner
+    grid.set_region_blocked((7, 7, 3, 1), true);
+    grid.set_region_blocked((7, 8, 1, 2), true);
+
+    let res = astar(&grid, &Diagonal::default(), GridPos { x: 0, y: 0 }, GridPos { x: 9, y: 9 }, AStarConfig::default());
+    assert_eq!(res.status, PathStatus::NotFound);
+    assert_eq!(res.cost, 0.0);
+    assert!(res.best_g_score > 0.0);
+    assert_eq!(res.nodes_in_open_set, 0);
+}
+
+#[test]
+fn test_truncate_to_cost_budget() {
+    let mut grid = Grid2D::new(6, 1, DiagonalMode::Never);
+    for (x, cost) in [(1, 2.0), (2, 3.0), (3, 1.0), (4, 4.0), (5, 1.0)] {
+        grid.set_cost(x, 0, cost);
+    }
+    let res = astar(&grid, &Manhattan, GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 0 }, AStarConfig::default());
+    assert!((res.cost - 11.0).abs() < 1e-4);
+
+    // Cumulative costs along the path: 0, 2, 5, 6, 10, 11
+    let t = res.truncate_to_cost(6.0, &grid);
+    assert_eq!(t.status, PathStatus::PartialCostLimit);
+    assert_eq!(t.path.last(), Some(&GridPos { x: 3, y: 0 }));
+    assert!((t.cost - 6.0).abs() < 1e-4);
+    assert!(t.validate());
+
+    let t = res.truncate_to_cost(9.5, &grid);
+    assert_eq!(t.path.len(), 4);
+
+    let t = res.truncate_to_cost(1.0, &grid);
+    assert_eq!(t.path, vec![GridPos { x: 0, y: 0 }]);
+    assert_eq!(t.cost, 0.0);
+
+    let full = res.truncate_to_cost(11.0, &grid);
+    assert_eq!(full.status, PathStatus::Found);
+    assert_eq!(full.path, res.path);
+
+    // Fitting the budget never upgrades a partial result; cutting it always reports the cut.
+    let mut partial = res.truncate(4, &grid);
+    partial.status = PathStatus::PartialMaxIter;
+    assert_eq!(partial.truncate_to_cost(100.0, &grid).status, PathStatus::PartialMaxIter);
+    assert_eq!(partial.truncate_to_cost(5.0, &grid).status, PathStatus::PartialCostLimit);
+
+    assert!((res.progress_at_cost(5.5, &grid) - 0.5).abs() < 1e-6);
+    assert_eq!(res.progress_at_cost(20.0, &grid), 1.0);
+    assert_eq!(res.progress_at_cost(0.0, &grid), 0.0);
+}
+
+#[test]
+fn test_split_and_merge_weighted_path() {
+    let mut grid = Grid2D::new(6, 1, DiagonalMode::Never);
+    for (x, cost) in [(1, 2.0), (2, 3.0), (3, 1.0), (4, 4.0), (5, 1.0)] {
+        grid.set_cost(x, 0, cost);
+    }
+    let res = astar(&grid, &Manhattan, GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 0 }, AStarConfig::default());
+
+    // 40% of 11 = 4.4 -> the front ends at x = 1 (cumulative cost 2)
+    let (front, back) = res.split_at_cost(&grid, res.cost * 0.4);
+    assert_eq!(front.path, vec![GridPos { x: 0, y: 0 }, GridPos { x: 1, y: 0 }]);
+    assert!((front.cost - 2.0).abs() < 1e-4);
+    assert_eq!(back.path.first(), Some(&GridPos { x: 1, y: 0 }));
+    assert!((back.cost - 9.0).abs() < 1e-4);
+    assert!(front.validate() && back.validate());
+
+    let merged = PathResult::merge(front, back).unwrap();
+    assert_eq!(merged.path, res.path);
+    assert!((merged.cost - res.cost).abs() < 1e-4);
+    assert_eq!(merged.nodes_expanded, res.nodes_expanded);
+    assert_eq!(merged.status, PathStatus::Found);
+    assert!(merged.validate());
+
+    // Beyond the total cost: whole path plus an empty remainder
+    let (whole, rest) = res.split_at_cost(&grid, 100.0);
+    assert_eq!(whole.path, res.path);
+    assert!(rest.path.is_empty());
+    assert_eq!(rest.cost, 0.0);
+
+    let (front, back) = res.split_at_node(&grid, &GridPos { x: 3, y: 0 }).unwrap();
+    assert!((front.cost - 6.0).abs() < 1e-4);
+    assert!((back.cost - 5.0).abs() < 1e-4);
+    // Length is geometric, not shared out by cost
+    assert_eq!((front.length, back.length), (3.0, 2.0));
+    assert!(res.split_at_node(&grid, &GridPos { x: 9, y: 9 }).is_none());
+
+    // Swapped halves don't share a junction
+    assert_eq!(PathResult::merge(back, front).unwrap_err(), MergeError::JunctionMismatch);
+}
+
+#[test]
+fn test_directional_costs_make_routes_asymmetric() {
+    // Two lanes around a central wall: the north current flows east, the south one west
+    let mut grid = Grid2D::new(20, 5, DiagonalMode::Always);
+    grid.set_region_blocked((2, 2, 16, 1), true);
+    for x in 0..20 {
+        for y in [0, 1] {
+            grid.set_directional_cost(x, y, Direction::E, 0.5);
+            grid.set_directional_cost(x, y, Direction::W, 2.0);
+        }
+        for y in [3, 4] {
+            grid.set_directional_cost(x, y, Direction::E, 2.0);
+            grid.set_directional_cost(x, y, Direction::W, 0.8);
+        }
+    }
+    let heuristic = Scaled::for_directional_grid(&grid, Diagonal::for_grid(&grid));
+    assert!((heuristic.factor - 0.5).abs() < 1e-6);
+
+    let west = GridPos { x: 0, y: 2 };
+    let east = GridPos { x: 19, y: 2 };
+    let outbound = astar(&grid, &heuristic, west, east, AStarConfig::default());
+    let inbound = astar(&grid, &heuristic, east, west, AStarConfig::default());
+    assert_eq!(outbound.status, PathStatus::Found);
+    assert_eq!(inbound.status, PathStatus::Found);
+
+    // Each direction rides its own current
+    assert!(outbound.path.iter().any(|p| p.y < 2));
+    assert!(inbound.path.iter().all(|p| p.y >= 2));
+    let mut mirrored = inbound.path.clone();
+    mirrored.reverse();
+    assert_ne!(outbound.path, mirrored);
+    assert!(outbound.cost < inbound.cost, "{} vs {}", outbound.cost, inbound.cost);
+
+    // JPS can't prune symmetric paths here
+    let res_jps = jps(&grid, &Diagonal::default(), west, east, AStarConfig::default());
+    assert_eq!(res_jps.status, PathStatus::UnsupportedGraph);
+}
+
+#[test]
+fn test_jps_on_masked_grid() {
+    // Wall down the middle with a single gap at y = 5
+    let mut grid = Grid2D::new(12, 12, DiagonalMode::IfNoObstacle);
+    grid.set_region_blocked((6, 0, 1, 12), true);
+    grid.set_blocked(6, 5, false);
+    let (start, goal) = (GridPos { x: 1, y: 1 }, GridPos { x: 10, y: 10 });
+    let config = AStarConfig::default();
+
+    let open = jps(&grid, &Diagonal::default(), start, goal, config);
+    assert_eq!(open.status, PathStatus::Found);
+    assert!(open.path.contains(&GridPos { x: 6, y: 5 }));
+
+    let extra: HashSet<GridPos> = [GridPos { x: 6, y: 5 }].into_iter().collect();
+    let masked = grid.masked(&extra);
+    assert_eq!(jps(&masked, &Diagonal::default(), start, goal, config).status, PathStatus::NotFound);
+    assert_eq!(astar(&masked, &Diagonal::default(), start, goal, config).status, PathStatus::NotFound);
+    // The base grid is untouched
+    assert!(!grid.is_blocked(6, 5));
+
+    // A mask off the route changes nothing
+    let elsewhere: HashSet<GridPos> = [GridPos { x: 0, y: 11 }].into_iter().collect();
+    let res = jps(&grid.masked(&elsewhere), &Diagonal::default(), start, goal, config);
+    assert_eq!(res.status, PathStatus::Found);
+    assert!((res.cost - open.cost).abs() < 1e-4);
+}
+
+// Grid that reports whatever node count it's told to
+struct MisreportedGrid<'a> {
+    grid: &'a Grid2D,
+    hint: Option<usize>,
+}
+
+impl Graph for MisreportedGrid<'_> {
+    type Node = GridPos;
+
+    fn is_passable(&self, node: &GridPos) -> bool {
+        self.grid.is_passable(node)
+    }
+
+    fn neighbors<F: FnMut(GridPos, f32)>(&self, node: &GridPos, visit: F) {
+        self.grid.neighbors(node, visit);
+    }
+
+    fn node_count_hint(&self) -> Option<usize> {
+        self.hint
+    }
+}
+
+#[test]
+fn test_wrong_node_count_hints_only_change_allocation() {
+    let mut grid = Grid2D::new(64, 64, DiagonalMode::IfNoObstacle);
+    grid.set_region_blocked((30, 0, 2, 60), true);
+    let (start, goal) = (GridPos { x: 2, y: 5 }, GridPos { x: 60, y: 7 });
+    let expected = astar(&grid, &Diagonal::default(), start, goal, AStarConfig::default());
+    assert_eq!(expected.status, PathStatus::Found);
+
+    for hint in [None, Some(0), Some(1), Some(64 * 64), Some(usize::MAX)] {
+        let graph = MisreportedGrid { grid: &grid, hint };
+        let result = astar(&graph, &Diagonal::default(), start, goal, AStarConfig::default());
+        assert_eq!((result.path, result.cost), (expected.path.clone(), expected.cost), "{hint:?}");
+    }
+    // Huge hints and estimates stay capped
+    assert_eq!(search_capacity(Some(usize::MAX), f32::INFINITY), 4096);
+    assert_eq!(search_capacity(Some(usize::MAX), f32::NAN), 4096);
+    assert_eq!(search_capacity(Some(0), 100.0), 0);
+    assert_eq!(search_capacity(None, 100.0), 0);
+}
+
+#[test]
+fn test_path_cost_matches_reported_search_cost() {
+    let mut grid = Grid2D::new(40, 40, DiagonalMode::IfNoObstacle).with_diagonal_cost(1.5);
+    for i in 0..(40 * 40 / 4) {
+        grid.set_blocked((i * 123 + 5) % 40, (i * 456 + 7) % 40, true);
+    }
+    let (start, goal) = (GridPos { x: 0, y: 0 }, GridPos { x: 39, y: 39 });
+    grid.set_blocked(0, 0, false);
+    grid.set_blocked(39, 39, false);
+
+    // JPS needs uniform costs, so it runs before the terrain goes in
+    let res_jps = jps(&grid, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default());
+    assert_eq!(res_jps.status, PathStatus::Found);
+    // Its path is jump points; price the cell-by-cell route
+    let expanded = postprocess(&grid, res_jps.clone(), PostProcessOptions { expand_jumps: true, ..Default::default() });
+    assert_eq!(grid.path_cost(&res_jps.path), f32::INFINITY);
+    assert!((grid.path_cost(&expanded.path) - res_jps.cost).abs() < 1e-4);
+
+    for y in 0..40 {
+        for x in 10..30 {
+            grid.set_cost(x, y, 1.0 + ((x * 7 + y * 3) % 5) as f32);
+        }
+    }
+    grid.set_directional_cost(20, 20, Direction::E, 3.0);
+    let res = astar(&grid, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default());
+    assert_eq!(res.status, PathStatus::Found);
+    assert!((grid.path_cost(&res.path) - res.cost).abs() < 1e-4, "{} vs {}", grid.path_cost(&res.path), res.cost);
+
+    // The provided method (via a view that doesn't override it) agrees with Grid2D's own
+    let no_extra = HashSet::new();
+    let masked = MaskedGrid2D { base: &grid, blocked_extra: &no_extra };
+    assert!((masked.path_cost(&res.path) - res.cost).abs() < 1e-4);
+
+    // Skipping a cell or cutting a blocked corner isn't an edge
+    let gap = [res.path[0], res.path[2]];
+    assert_eq!(grid.path_cost(&gap), f32::INFINITY);
+    assert_eq!(masked.path_cost(&gap), f32::INFINITY);
+    assert_eq!(grid.path_cost(&res.path[..1]), 0.0);
+    let mut corner = Grid2D::new(3, 3, DiagonalMode::OnlyIfBothOpen);
+    corner.set_blocked(1, 0, true);
+    assert_eq!(corner.path_cost(&[GridPos { x: 0, y: 0 }, GridPos { x: 1, y: 1 }]), f32::INFINITY);
+}
+
+#[test]
+fn test_integer_costs_match_float_model_on_fixture_maps() {
+    // Scattered obstacles, and walls with alternating gaps (the trace fixtures), plus
+    // whole-number terrain costs both models price exactly
+    let mut scattered = Grid2D::new(24, 24, DiagonalMode::Always).with_diagonal_cost(1.4);
+    for i in 0..150 {
+        scattered.set_blocked((i * 123 + 5) % 24, (i * 456 + 7) % 24, true);
+    }
+    scattered.set_blocked(0, 0, false);
+    scattered.set_blocked(23, 23, false);
+    let mut serpentine = Grid2D::new(21, 21, DiagonalMode::Always).with_diagonal_cost(1.4);
+    for x in (2..20).step_by(4) {
+        let gap = if x % 8 == 2 { 20 } else { 0 };
+        serpentine.set_region_blocked((x, 0, 1, 21), true);
+        serpentine.set_blocked(x, gap, false);
+    }
+    let mut terrain = Grid2D::new(32, 32, DiagonalMode::IfNoObstacle).with_diagonal_cost(1.4);
+    for y in 0..32 {
+        for x in 0..32 {
+            terrain.set_cost(x, y, 1.0 + ((x * 7 + y * 3) % 4) as f32);
+        }
+    }
+    terrain.set_region_blocked((16, 2, 1, 28), true);
+
+    let float_heuristic = Diagonal { cardinal_cost: 1.0, diagonal_cost: 1.4 };
+    let int_heuristic = IntDiagonal { cardinal: Milli(10), diagonal: Milli(14) };
+    let queries = [
+        (&scattered, GridPos { x: 0, y: 0 }, GridPos { x: 23, y: 23 }),
+        (&scattered, GridPos { x: 23, y: 23 }, GridPos { x: 0, y: 0 }),
+        (&serpentine, GridPos { x: 0, y: 0 }, GridPos { x: 20, y: 20 }),
+        (&serpentine, GridPos { x: 0, y: 10 }, GridPos { x: 11, y: 3 }),
+        (&terrain, GridPos { x: 2, y: 16 }, GridPos { x: 30, y: 5 }),
+    ];
+    for (grid, start, goal) in queries {
+        let float = astar(grid, &float_heuristic, start, goal, AStarConfig::default());
+        let int_grid = IntGrid2D::new(grid, Milli(10), Milli(14));
+        let int = astar_int(&int_grid, &int_heuristic, start, goal, AStarConfig::default());
+        assert_eq!(float.status, PathStatus::Found);
+        assert_eq!(int.status, PathStatus::Found);
+        assert_eq!(int.cost, Milli((float.cost * 10.0).round() as u32), "{start:?} -> {goal:?}");
+        // Equal-cost routes may place their diagonals differently (float rounding breaks the
+        // ties), but take the same steps
+        let diagonals = |path: &[GridPos]| path.windows(2).filter(|w| w[0].x != w[1].x && w[0].y != w[1].y).count();
+        assert_eq!(int.path.len(), float.path.len(), "{start:?} -> {goal:?}");
+        assert_eq!(diagonals(&int.path), diagonals(&float.path), "{start:?} -> {goal:?}");
+        assert_eq!((int.path[0], int.path.last()), (start, Some(&goal)));
+    }
+}
+
+#[test]
+fn test_results_carry_their_own_elapsed_time() {
+    let (grid, rooms) = Grid2D::generate_room_layout(96, 96, 6, 16, 3, DiagonalMode::Always);
+    let center = |r: (usize, usize, usize, usize)| GridPos { x: (r.0 + r.2 / 2) as i32, y: (r.1 + r.3 / 2) as i32 };
+    let (start, goal) = (center(rooms[0]), center(rooms[rooms.len() - 1]));
+    let (config, heuristic) = (AStarConfig::default(), Diagonal::for_grid(&grid));
+
+    let mut results = vec![
+        astar(&grid, &heuristic, start, goal, config),
+        jps(&grid, &heuristic, start, goal, config),
+        theta_star(&grid, &Euclidean, start, goal, config),
+    ];
+    let queries = vec![(start, goal), (goal, start), (start, center(rooms[rooms.len() / 2]))];
+    results.extend(find_paths_parallel(&grid, &heuristic, &queries, config));
+    let field = FlowField::compute(&grid, goal);
+    let hierarchical = HierarchicalGrid::new(grid, 16).find_path(start, goal);
+    results.push(hierarchical);
+    assert!(results.iter().all(|r| r.status == PathStatus::Found));
+
+    #[cfg(feature = "timing")]
+    {
+        assert!(results.iter().all(|r| r.elapsed.is_some_and(|t| t > std::time::Duration::ZERO)));
+        assert!(field.elapsed.is_some_and(|t| t > std::time::Duration::ZERO));
+    }
+    #[cfg(not(feature = "timing"))]
+    {
+        assert!(results.iter().all(|r| r.elapsed.is_none()));
+        assert!(field.elapsed.is_none());
+    }
+}
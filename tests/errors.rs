@@ -0,0 +1,135 @@
+use std::error::Error;
+
+use pathforge::error::{ConfigError, DeltaError, GridError, PathforgeError};
+use pathforge::graphs::grid2d::GridDelta;
+use pathforge::graphs::navmesh::OffMeshLink;
+use pathforge::prelude::*;
+use pathforge::smoothing::funnel_smooth;
+
+// Unit quad split into two triangles in the XZ plane
+fn quad() -> NavMesh {
+    NavMesh::try_new(
+        vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+        vec![0, 1, 2, 0, 2, 3],
+        vec![-1, -1, 1, 0, -1, -1],
+    )
+    .unwrap()
+}
+
+#[test]
+fn funnel_rejects_empty_and_off_grid_paths() {
+    let grid = Grid2D::new(4, 4, DiagonalMode::Never);
+    assert_eq!(funnel_smooth(&grid, &[]), Err(PathforgeError::EmptyPath));
+    let path = [GridPos { x: 3, y: 0 }, GridPos { x: 4, y: 0 }];
+    assert_eq!(funnel_smooth(&grid, &path), Err(PathforgeError::PositionOutOfBounds { x: 4, y: 0 }));
+}
+
+#[test]
+fn portals_reject_empty_unknown_and_disconnected_polygons() {
+    let mut mesh = quad();
+    let (a, b) = ([0.8, 0.0, 0.1], [0.1, 0.0, 0.8]);
+    assert_eq!(mesh.get_portals(&[0, 1], a, b).unwrap().len(), 3);
+    assert_eq!(mesh.get_portals(&[], a, b).unwrap_err(), PathforgeError::EmptyPath);
+    assert_eq!(mesh.get_portals(&[0, 2], a, b).unwrap_err(), PathforgeError::PolygonOutOfRange(2));
+    assert_eq!(mesh.get_portals(&[0, 0], a, b).unwrap_err(), PathforgeError::NotAdjacent { from: 0, to: 0 });
+
+    // A step along an off-mesh link is allowed and gets no portal
+    mesh.neighbors = vec![-1; 6];
+    assert_eq!(mesh.get_portals(&[0, 1], a, b).unwrap_err(), PathforgeError::NotAdjacent { from: 0, to: 1 });
+    mesh.add_off_mesh_link(OffMeshLink::new(0, 1, a, b));
+    assert_eq!(mesh.get_portals(&[0, 1], a, b).unwrap().len(), 2);
+}
+
+#[test]
+fn mesh_construction_rejects_malformed_and_degenerate_input() {
+    let vertices = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    let malformed = [
+        (vertices[..11].to_vec(), vec![0, 1, 2], vec![-1, -1, -1]),
+        (vertices.clone(), vec![0, 1], vec![-1, -1]),
+        (vertices.clone(), vec![0, 1, 2], vec![-1, -1]),
+        (vertices.clone(), vec![0, 1, 4], vec![-1, -1, -1]),
+        (vertices.clone(), vec![0, 1, 2], vec![-1, 1, -1]),
+    ];
+    for (v, p, n) in malformed {
+        assert_eq!(NavMesh::try_new(v, p, n).err(), Some(PathforgeError::MalformedMesh));
+    }
+    // Second triangle repeats a vertex
+    let degenerate = NavMesh::try_new(vertices, vec![0, 1, 2, 0, 2, 2], vec![-1; 6]);
+    assert_eq!(degenerate.err(), Some(PathforgeError::DegenerateMesh(1)));
+}
+
+#[test]
+fn rejected_deltas_and_narrow_errors_convert() {
+    let mut source = Grid2D::new(4, 4, DiagonalMode::Never);
+    let mut copy = Grid2D::new(4, 4, DiagonalMode::Never);
+    source.set_blocked(1, 1, true);
+    let first = source.drain_changes_as_delta();
+    source.set_blocked(2, 2, true);
+    let second: GridDelta = source.drain_changes_as_delta();
+    let err = copy.apply_delta(&second).unwrap_err();
+    let mismatch = DeltaError::RevisionMismatch { grid_revision: 0, delta_base_revision: 1 };
+    assert_eq!(err, PathforgeError::Delta(mismatch));
+    assert!(err.source().is_some());
+    assert_eq!(copy.apply_delta(&first), Ok(()));
+
+    let build = || -> Result<AStarConfig, PathforgeError> { Ok(AStarConfig::builder().max_iterations(0).build()?) };
+    assert_eq!(build().unwrap_err(), PathforgeError::Config(ConfigError::ZeroMaxIterations));
+    let small = Grid3D::new(2, 2, 2);
+    let union = || -> Result<Grid3D, PathforgeError> { Ok(Grid3D::new(1, 1, 1).union(&small)?) };
+    assert_eq!(union().err(), Some(PathforgeError::Grid(GridError::DimensionMismatch)));
+    assert_eq!(PathforgeError::DegenerateMesh(3).to_string(), "polygon 3 has zero area");
+}
+
+#[test]
+fn only_unsupported_graph_status_maps_to_an_error() {
+    let mut grid = Grid2D::new(4, 4, DiagonalMode::Always);
+    grid.set_directional_cost(1, 1, Direction::E, 2.0);
+    let result = jps(&grid, &Octile, GridPos { x: 0, y: 0 }, GridPos { x: 3, y: 3 }, AStarConfig::default());
+    assert_eq!(result.status.as_error(), Some(PathforgeError::UnsupportedGraph));
+    // Called directly, not just through the dispatcher, `jps` refuses diagonal modes its
+    // pruning doesn't cover
+    let strict = Grid2D::new(4, 4, DiagonalMode::OnlyIfBothOpen);
+    let cardinal = Grid2D::new(4, 4, DiagonalMode::Never);
+    for grid in [&strict, &cardinal] {
+        let result = jps(grid, &Octile, GridPos { x: 0, y: 0 }, GridPos { x: 3, y: 3 }, AStarConfig::default());
+        assert_eq!(result.status, PathStatus::UnsupportedGraph);
+    }
+    for status in [PathStatus::Found, PathStatus::NotFound, PathStatus::PartialTimeout, PathStatus::NoAbstractConnection] {
+        assert_eq!(status.as_error(), None);
+    }
+}
+
+fn walled_off() -> (Grid2D, GridPos, GridPos) {
+    let mut grid = Grid2D::new(6, 6, DiagonalMode::Always);
+    grid.set_region_blocked((3, 0, 1, 6), true);
+    (grid, GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 5 })
+}
+
+#[test]
+#[should_panic(expected = "called `PathResult::unwrap()` on a NotFound result")]
+fn unwrap_panics_without_a_path() {
+    let (grid, start, goal) = walled_off();
+    let _ = astar(&grid, &Octile, start, goal, AStarConfig::default()).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "the exit must be reachable: NotFound")]
+fn expect_panics_with_the_callers_message() {
+    let (grid, start, goal) = walled_off();
+    let _ = theta_star(&grid, &Euclidean, start, goal, AStarConfig::default()).expect("the exit must be reachable");
+}
+
+#[test]
+fn found_paths_unwrap_and_map() {
+    let (mut grid, start, goal) = walled_off();
+    assert_eq!(astar(&grid, &Octile, start, goal, AStarConfig::default()).ok(), None);
+    grid.set_blocked(3, 5, false);
+    let result = astar(&grid, &Octile, start, goal, AStarConfig::default());
+    assert_eq!(result.clone().map_path(|p| p.len()), Some(result.path.len()));
+    assert_eq!(result.clone().ok().as_ref(), Some(&result.path));
+    assert_eq!(jps(&grid, &Octile, start, goal, AStarConfig::default()).expect("open").last(), Some(&goal));
+
+    let partial = astar(&grid, &Octile, start, goal, AStarConfig::builder().max_iterations(2).build().unwrap());
+    assert_eq!(partial.status, PathStatus::PartialMaxIter);
+    assert_eq!(partial.ok(), None);
+}
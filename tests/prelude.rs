@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use pathforge::prelude::*;
+
+fn walled_grid() -> Grid2D {
+    let mut grid = Grid2D::new(16, 16, DiagonalMode::IfNoObstacle);
+    grid.set_region_blocked((8, 0, 1, 12), true);
+    grid
+}
+
+#[test]
+fn grid_searches_with_prelude_only() {
+    let grid = walled_grid();
+    let (start, goal) = (GridPos { x: 1, y: 1 }, GridPos { x: 14, y: 2 });
+    let config = AStarConfig::builder().tie_breaking(TieBreaking::PreferHigherG).build().unwrap();
+
+    let result: PathResult<GridPos> = astar(&grid, &Octile, start, goal, config);
+    assert_eq!(result.status, PathStatus::Found);
+    assert_eq!(jps(&grid, &Diagonal::for_grid(&grid), start, goal, AStarConfig::default()).status, PathStatus::Found);
+    assert_eq!(theta_star(&grid, &Euclidean, start, goal, AStarConfig::default()).status, PathStatus::Found);
+    for found in [
+        astar(&grid, &Chebyshev, start, goal, AStarConfig::default()),
+        astar(&grid, &Zero, start, goal, AStarConfig::default()),
+    ] {
+        assert_eq!(found.cost, result.cost);
+    }
+
+    let smoothed = smooth_path(&grid, &result.path, SmoothingMethod::RemoveRedundant);
+    assert!(smoothed.path.len() <= result.path.len());
+    assert_eq!(astar(&grid, &Manhattan, start, goal, AStarConfig::default()).status, PathStatus::Found);
+    assert!(grid.is_passable(&start));
+    assert_eq!(grid.cells[3 * 16 + 3], CellType::Passable(1.0));
+
+    let mut cache = PathCache::new(4, Duration::from_secs(60));
+    let cached = astar_with_cache(&grid, &Octile, start, goal, AStarConfig::default(), &mut cache);
+    assert_eq!(cached.cost, result.cost);
+
+    let batch = find_paths_parallel(&grid, &Octile, &[(start, goal), (goal, start)], AStarConfig::default());
+    assert!(batch.iter().all(|r| r.cost == result.cost));
+
+    let mut budgeted = BudgetedPathfinder::new(AStarConfig::default());
+    budgeted.start(start, goal, &Octile);
+    while !budgeted.step(&grid, &Octile, Duration::from_millis(5)) {}
+    assert_eq!(budgeted.take_result().unwrap().cost, result.cost);
+
+    let field = FlowField::compute(&grid, goal);
+    assert_ne!(field.get_direction(start), Direction::None);
+
+    let hierarchical = HierarchicalGrid::new(grid, 4);
+    assert_eq!(hierarchical.find_path(start, goal).status, PathStatus::Found);
+}
+
+#[test]
+fn grid3d_and_navmesh_with_prelude_only() {
+    let grid = Grid3D::new(4, 4, 4);
+    let (start, goal) = (GridPos3D { x: 0, y: 0, z: 0 }, GridPos3D { x: 3, y: 3, z: 3 });
+    assert_eq!(astar(&grid, &Manhattan, start, goal, AStarConfig::default()).status, PathStatus::Found);
+
+    // One unit quad split into two triangles in the XZ plane
+    let mesh = NavMesh::new(
+        vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+        vec![0, 1, 2, 0, 2, 3],
+        vec![-1, -1, 1, 0, -1, -1],
+    );
+    let path = mesh.find_path([0.8, 0.0, 0.1], [0.1, 0.0, 0.8], AStarConfig::default());
+    assert_eq!(path.status, PathStatus::Found);
+    assert_eq!(path.corridor, vec![0, 1]);
+}
@@ -1,8 +1,8 @@
 use pathforge::{
     algorithms::{theta::theta_star, astar::AStarConfig},
-    graphs::grid2d::{Grid2D, GridPos, DiagonalMode},
+    graphs::{grid2d::{Grid2D, GridPos, DiagonalMode}, visibility::VisibilityGraph},
     heuristics::Euclidean,
-    traits::PathStatus,
+    traits::{path_length, Graph, PathStatus},
 };
 
 #[test]
@@ -63,3 +63,55 @@ fn test_theta_star_around_obstacle() {
         // The point of Theta* is they might be far apart.
     }
 }
+
+#[test]
+fn test_visibility_graph_never_longer_than_theta_star() {
+    // Scattered obstacles, rooms, and walls with alternating gaps (the trace fixtures)
+    let mut scattered = Grid2D::new(24, 24, DiagonalMode::Always);
+    for i in 0..150 {
+        scattered.set_blocked((i * 123 + 5) % 24, (i * 456 + 7) % 24, true);
+    }
+    scattered.set_blocked(0, 0, false);
+    scattered.set_blocked(23, 23, false);
+    let (rooms, layout) = Grid2D::generate_room_layout(32, 32, 4, 10, 7, DiagonalMode::Always);
+    let cells = |(a, b): ((i32, i32), (i32, i32))| (GridPos { x: a.0, y: a.1 }, GridPos { x: b.0, y: b.1 });
+    let center = |r: (usize, usize, usize, usize)| GridPos { x: (r.0 + r.2 / 2) as i32, y: (r.1 + r.3 / 2) as i32 };
+    let mut serpentine = Grid2D::new(21, 21, DiagonalMode::Always);
+    for x in (2..20).step_by(4) {
+        let gap = if x % 8 == 2 { 20 } else { 0 };
+        serpentine.set_region_blocked((x, 0, 1, 21), true);
+        serpentine.set_blocked(x, gap, false);
+    }
+
+    let fixtures = [
+        (&scattered, vec![((0, 0), (23, 23)), ((23, 23), (0, 0))].into_iter().map(cells).collect()),
+        (&rooms, vec![
+            (center(layout[0]), center(layout[layout.len() - 1])),
+            (center(layout[1]), center(layout[layout.len() / 2])),
+        ]),
+        (&serpentine, vec![((0, 0), (20, 20)), ((0, 10), (11, 3))].into_iter().map(cells).collect()),
+    ];
+    for (grid, queries) in fixtures {
+        let visibility = VisibilityGraph::build(grid);
+        assert!(!visibility.corners.is_empty() && visibility.edge_count() > 0);
+        for (start, goal) in queries {
+            let theta = theta_star(grid, &Euclidean, start, goal, AStarConfig::default());
+            let found = visibility.find_path(start, goal);
+            assert_eq!(theta.status, PathStatus::Found);
+            assert_eq!(found.status, PathStatus::Found);
+            assert!(found.cost <= theta.cost + 1e-4, "{start:?} -> {goal:?}: {} vs Theta* {}", found.cost, theta.cost);
+            assert_eq!((found.path[0], found.path.last()), (start, Some(&goal)));
+            assert!(found.path.windows(2).all(|w| grid.can_traverse(&w[0], &w[1])));
+            assert!((found.cost - path_length(grid, &found.path)).abs() < 1e-3);
+        }
+    }
+
+    // Straight shots need no corners; walled-in goals aren't found
+    let mut open = Grid2D::new(10, 10, DiagonalMode::Always);
+    open.set_blocked(5, 5, true);
+    let visibility = VisibilityGraph::build(&open);
+    // Every cell around a lone block can round it
+    assert_eq!(visibility.corners.len(), 8);
+    assert_eq!(visibility.find_path(GridPos { x: 0, y: 0 }, GridPos { x: 9, y: 2 }).path.len(), 2);
+    assert_eq!(visibility.find_path(GridPos { x: 0, y: 0 }, GridPos { x: 5, y: 5 }).status, PathStatus::NotFound);
+}
@@ -0,0 +1,91 @@
+// Golden traces for A*, JPS and Theta* on three fixture maps. An intentional behavior change
+// is recorded with `PATHFORGE_UPDATE_GOLDENS=1 cargo test --test trace_golden`.
+
+use pathforge::{
+    algorithms::{astar::{astar, AStarConfig}, jps::jps, theta::theta_star},
+    assert_trace_matches,
+    graphs::grid2d::{Grid2D, GridPos, DiagonalMode},
+    heuristics::{Diagonal, Euclidean},
+    trace::{record_queries, SearchTrace},
+};
+
+type Fixture = (&'static str, Grid2D, Vec<(GridPos, GridPos)>);
+
+fn golden(name: &str) -> String {
+    format!("{}/tests/golden/{name}.trace", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn fixtures() -> Vec<Fixture> {
+    // Scattered obstacles, same pattern as the A*/JPS correctness test
+    let mut scattered = Grid2D::new(24, 24, DiagonalMode::Always);
+    for i in 0..150 {
+        scattered.set_blocked((i * 123 + 5) % 24, (i * 456 + 7) % 24, true);
+    }
+    scattered.set_blocked(0, 0, false);
+    scattered.set_blocked(23, 23, false);
+    let scattered_queries = vec![
+        (GridPos { x: 0, y: 0 }, GridPos { x: 23, y: 23 }),
+        (GridPos { x: 23, y: 23 }, GridPos { x: 0, y: 0 }),
+    ];
+
+    let (rooms, layout) = Grid2D::generate_room_layout(32, 32, 4, 10, 7, DiagonalMode::Always);
+    let center = |r: (usize, usize, usize, usize)| GridPos { x: (r.0 + r.2 / 2) as i32, y: (r.1 + r.3 / 2) as i32 };
+    let rooms_queries = vec![
+        (center(layout[0]), center(layout[layout.len() - 1])),
+        (center(layout[1]), center(layout[layout.len() / 2])),
+    ];
+
+    // Walls with alternating gaps at the top and bottom
+    let mut serpentine = Grid2D::new(21, 21, DiagonalMode::Always);
+    for x in (2..20).step_by(4) {
+        let gap = if x % 8 == 2 { 20 } else { 0 };
+        serpentine.set_region_blocked((x, 0, 1, 21), true);
+        serpentine.set_blocked(x, gap, false);
+    }
+    let serpentine_queries = vec![
+        (GridPos { x: 0, y: 0 }, GridPos { x: 20, y: 20 }),
+        (GridPos { x: 0, y: 10 }, GridPos { x: 11, y: 3 }),
+    ];
+
+    vec![
+        ("scattered", scattered, scattered_queries),
+        ("rooms", rooms, rooms_queries),
+        ("serpentine", serpentine, serpentine_queries),
+    ]
+}
+
+#[test]
+fn astar_matches_golden_traces() {
+    let traces: Vec<SearchTrace> = fixtures()
+        .iter()
+        .flat_map(|(name, grid, queries)| {
+            let heuristic = Diagonal::for_grid(grid);
+            record_queries(name, grid, queries, |g, s, e| astar(g, &heuristic, s, e, AStarConfig::default()))
+        })
+        .collect();
+    assert_trace_matches!(golden("astar"), traces);
+}
+
+#[test]
+fn jps_matches_golden_traces() {
+    let traces: Vec<SearchTrace> = fixtures()
+        .iter()
+        .flat_map(|(name, grid, queries)| {
+            let heuristic = Diagonal::for_grid(grid);
+            // JPS works on the grid directly, so its traces carry no expansions
+            record_queries(name, grid, queries, |g, s, e| jps(g.inner(), &heuristic, s, e, AStarConfig::default()))
+        })
+        .collect();
+    assert_trace_matches!(golden("jps"), traces);
+}
+
+#[test]
+fn theta_star_matches_golden_traces() {
+    let traces: Vec<SearchTrace> = fixtures()
+        .iter()
+        .flat_map(|(name, grid, queries)| {
+            record_queries(name, grid, queries, |g, s, e| theta_star(g, &Euclidean, s, e, AStarConfig::default()))
+        })
+        .collect();
+    assert_trace_matches!(golden("theta"), traces);
+}